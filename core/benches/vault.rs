@@ -0,0 +1,104 @@
+use std::path::{Path, PathBuf};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use kimun_core::{NoteVault, NotesValidation};
+
+const NOTES: usize = 5_000;
+const DIRECTORIES: usize = 50;
+const VOCABULARY: usize = 2_000;
+const SYLLABLES: &[&str] = &[
+    "ka", "mi", "run", "to", "le", "sa", "no", "vi", "da", "re", "pu", "xo",
+];
+
+/// A word of the generated vocabulary
+fn word(index: usize) -> String {
+    let mut index = index % VOCABULARY;
+    let mut word = String::new();
+    loop {
+        word.push_str(SYLLABLES[index % SYLLABLES.len()]);
+        index /= SYLLABLES.len();
+        if index == 0 {
+            return word;
+        }
+    }
+}
+
+/// The temporary directory with the generated vault and its index DB
+fn bench_dir() -> PathBuf {
+    std::env::temp_dir().join("kimun_bench_v3")
+}
+
+/// Generates a vault with notes made of pseudo random words, so the
+/// benchmarks always run against the same content
+fn generate_vault() -> PathBuf {
+    let path = bench_dir().join("vault");
+    if path.exists() {
+        return path;
+    }
+    let mut seed: u64 = 42;
+    let mut next = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (seed >> 33) as usize
+    };
+    for n in 0..NOTES {
+        let dir = path.join(format!("dir_{}", n % DIRECTORIES));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut text = format!("# Note {}\n\n", n);
+        for section in 0..4 {
+            text.push_str(&format!("## Section {}\n\n", section));
+            for _ in 0..60 {
+                text.push_str(&word(next()));
+                text.push(' ');
+            }
+            text.push_str("\n\n");
+        }
+        std::fs::write(dir.join(format!("note_{}.md", n)), text).unwrap();
+    }
+    path
+}
+
+fn open_vault(path: &Path) -> NoteVault {
+    // Not in the default location, it would be left in the user's cache
+    let vault = NoteVault::with_db_path(path, bench_dir().join("db/notes.sqlite")).unwrap();
+    vault.init_and_validate().unwrap();
+    vault
+}
+
+fn search(c: &mut Criterion) {
+    let vault = open_vault(&generate_vault());
+    c.bench_function("search one term", |b| {
+        b.iter(|| vault.search_notes(word(42)).unwrap())
+    });
+    c.bench_function("search terms and heading", |b| {
+        b.iter(|| {
+            vault
+                .search_notes(format!("{} {} >section", word(7), word(1234)))
+                .unwrap()
+        })
+    });
+    // Search as you type sends a query for every key pressed
+    c.bench_function("search as you type", |b| {
+        b.iter(|| {
+            let query = word(1500);
+            for end in 1..=query.len() {
+                vault.search_notes(&query[..end]).unwrap();
+            }
+        })
+    });
+}
+
+fn index(c: &mut Criterion) {
+    let vault = open_vault(&generate_vault());
+    let mut group = c.benchmark_group("index");
+    group.sample_size(10);
+    group.bench_function("index notes fast validation", |b| {
+        b.iter(|| vault.index_notes(NotesValidation::Fast).unwrap())
+    });
+    group.bench_function("recreate index", |b| {
+        b.iter(|| vault.recreate_index().unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, search, index);
+criterion_main!(benches);
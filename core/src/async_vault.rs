@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use log::{debug, info};
+
+use crate::{
+    db::{self, async_db::AsyncConnection},
+    error::VaultError,
+    indexer::{self, IndexReport},
+    nfs::{NoteEntryData, VaultPath},
+    write_note, NoteDetails, NoteVault, NotesValidation,
+};
+
+/// Async version of the Note Vault, the DB and disk work runs in a
+/// background thread so it can be used from an async runtime without
+/// blocking it.
+/// The index must be initialized with `NoteVault::init_and_validate`
+/// before using it, the sync vault is available with `vault()`.
+#[derive(Debug, Clone)]
+pub struct AsyncNoteVault {
+    vault: NoteVault,
+    connection: AsyncConnection,
+}
+
+impl AsyncNoteVault {
+    pub async fn new<P: AsRef<Path>>(workspace_path: P) -> Result<Self, VaultError> {
+        Self::from_vault(NoteVault::new(workspace_path)?).await
+    }
+
+    /// Creates the async vault using the same workspace and DB of a vault
+    pub async fn from_vault(vault: NoteVault) -> Result<Self, VaultError> {
+        debug!("Creating new async vault Instance");
+        let connection = AsyncConnection::open(vault.get_db_path()).await?;
+        Ok(Self { vault, connection })
+    }
+
+    /// The sync vault sharing the same workspace
+    pub fn vault(&self) -> &NoteVault {
+        &self.vault
+    }
+
+    /// Async version of `NoteVault::index_notes`
+    pub async fn index_notes(
+        &self,
+        validation_mode: NotesValidation,
+    ) -> Result<IndexReport, VaultError> {
+        info!("Start indexing files");
+        let workspace_path = self.vault.workspace_path.clone();
+        let config = self.vault.get_config().clone();
+        self.connection
+            .call(move |conn| {
+                indexer::index_vault(&workspace_path, &config, conn, validation_mode, &|_| true)
+            })
+            .await
+    }
+
+    /// Async version of `NoteVault::search_notes`
+    pub async fn search_notes<S: AsRef<str>>(
+        &self,
+        terms: S,
+    ) -> Result<Vec<NoteDetails>, VaultError> {
+        let terms = terms.as_ref().to_owned();
+        let notes = self
+            .connection
+            .call(move |conn| db::search_terms(conn, terms))
+            .await?;
+        Ok(notes.into_iter().map(|(_data, details)| details).collect())
+    }
+
+    /// Async version of `NoteVault::get_notes`
+    pub async fn get_notes(
+        &self,
+        path: &VaultPath,
+        recursive: bool,
+    ) -> Result<Vec<NoteDetails>, VaultError> {
+        let path = path.to_owned();
+        let case = self.vault.get_config().path_case;
+        let notes = self
+            .connection
+            .call(move |conn| db::get_notes(conn, &path, recursive, case))
+            .await?;
+        Ok(notes.into_iter().map(|(_data, details)| details).collect())
+    }
+
+    /// Async version of `NoteVault::save_note`
+    pub async fn save_note<S: AsRef<str>>(
+        &self,
+        path: &VaultPath,
+        text: S,
+    ) -> Result<(NoteEntryData, NoteDetails), VaultError> {
+        let workspace_path = self.vault.workspace_path.clone();
+        let path = path.to_owned();
+        let text = text.as_ref().to_owned();
+        let types = self.vault.get_config().notes.clone();
+        self.connection
+            .call(move |conn| {
+                let (entry_data, details) = write_note(&workspace_path, &path, text, &types)?;
+                db::save_note(conn, &entry_data, &details)?;
+                Ok((entry_data, details))
+            })
+            .await
+    }
+
+    /// Closes the async connection, any call after this returns an error
+    pub async fn close(&self) -> Result<(), VaultError> {
+        self.connection.close().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{nfs::VaultPath, test_utils::indexed_vault, NotesValidation};
+
+    use super::AsyncNoteVault;
+
+    #[tokio::test]
+    async fn save_index_and_search() {
+        let (_dir, sync_vault) = indexed_vault(&[("sub/existing.md", "# Existing\n\nOld text")]);
+        let workspace = sync_vault.workspace_path.clone();
+        let vault = AsyncNoteVault::from_vault(sync_vault).await.unwrap();
+
+        let path = VaultPath::file_from("async_note");
+        vault
+            .save_note(&path, "# Async\n\nSaved from async code")
+            .await
+            .unwrap();
+        let found = vault.search_notes("async").await.unwrap();
+        assert_eq!(vec![path.clone()], dedup_paths(found));
+
+        std::fs::write(workspace.join("added.md"), "# Added\n\nAdded outside").unwrap();
+        vault.index_notes(NotesValidation::Fast).await.unwrap();
+        let notes = vault.get_notes(&VaultPath::root(), true).await.unwrap();
+        assert_eq!(3, notes.len());
+        let found = vault.search_notes("outside").await.unwrap();
+        assert_eq!(vec![VaultPath::file_from("added")], dedup_paths(found));
+
+        vault.close().await.unwrap();
+        assert!(vault.search_notes("async").await.is_err());
+    }
+
+    fn dedup_paths(notes: Vec<crate::NoteDetails>) -> Vec<VaultPath> {
+        let mut paths = notes.into_iter().map(|n| n.path).collect::<Vec<_>>();
+        paths.dedup();
+        paths
+    }
+}
@@ -0,0 +1,99 @@
+//! Command line tool to work with a vault without the desktop app
+
+use std::{error::Error, process::ExitCode};
+
+use kimun_core::{import::ImportSource, nfs::VaultPath, NoteVault};
+
+const USAGE: &str = "Usage:
+  kimun export <workspace> <destination> [path]
+      Exports the notes in the path, or the whole vault, to HTML files
+  kimun import <obsidian|notion|evernote> <workspace> <from> [path]
+      Imports the notes exported from another tool into the path, or the vault's root
+  kimun serve <workspace>
+      Serves the vault over HTTP on localhost, needs the server feature";
+
+fn main() -> ExitCode {
+    env_logger::init();
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let result = match args.first().map(String::as_str) {
+        Some("export") => export(&args[1..]),
+        Some("import") => import(&args[1..]),
+        Some("serve") => serve(&args[1..]),
+        _ => Err(USAGE.into()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn export(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (workspace, destination, path) = match args {
+        [workspace, destination] => (workspace, destination, VaultPath::root()),
+        [workspace, destination, path] => (workspace, destination, VaultPath::from(path)),
+        _ => return Err(USAGE.into()),
+    };
+    let vault = NoteVault::new(workspace)?;
+    // The notes to export are taken from the index
+    vault.init_and_validate()?;
+    let report = vault.export_html(&path, destination)?;
+    println!(
+        "Exported {} notes, {} attachments and {} tags to {}",
+        report.notes, report.attachments, report.tags, destination
+    );
+    for link in &report.broken_links {
+        println!("Broken link in {}", link);
+    }
+    for file in &report.skipped {
+        println!("Skipped {}", file);
+    }
+    Ok(())
+}
+
+fn import(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (source, workspace, from, path) = match args {
+        [source, workspace, from] => (source, workspace, from, VaultPath::root()),
+        [source, workspace, from, path] => (source, workspace, from, VaultPath::from(path)),
+        _ => return Err(USAGE.into()),
+    };
+    let Some(source) = ImportSource::all()
+        .into_iter()
+        .find(|import_source| import_source.to_string().eq_ignore_ascii_case(source))
+    else {
+        return Err(USAGE.into());
+    };
+    let vault = NoteVault::new(workspace)?;
+    vault.init_and_validate()?;
+    let report = vault.import_notes(source, from, &path)?;
+    println!(
+        "Imported {} notes and {} attachments from {}",
+        report.notes, report.attachments, source
+    );
+    for issue in &report.issues {
+        println!("{}", issue);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+fn serve(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let [workspace] = args else {
+        return Err(USAGE.into());
+    };
+    let vault = NoteVault::new(workspace)?;
+    vault.init_and_validate()?;
+    let server = kimun_core::server::VaultServer::new(vault)?;
+    if let Some(address) = server.address() {
+        println!("Serving the vault at http://{}", address);
+    }
+    server.run();
+    Ok(())
+}
+
+#[cfg(not(feature = "server"))]
+fn serve(_args: &[String]) -> Result<(), Box<dyn Error>> {
+    Err("kimun was built without the server, build it with `--features server`".into())
+}
@@ -0,0 +1,190 @@
+use std::path::Path;
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{FSError, VaultError},
+    nfs::{ignore_rules::IgnoreRules, note_types::NoteTypes, PathCase, VaultPath},
+};
+
+const CONFIG_FILE: &str = "config.toml";
+const DEFAULT_SERVER_PORT: u16 = 7417;
+
+/// The vault settings that travel with the notes, stored in the
+/// vault's config directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultConfig {
+    pub ignore: IgnoreRules,
+    /// The extensions of the notes, each one with its format
+    pub notes: NoteTypes,
+    /// How the paths to notes are matched
+    pub path_case: PathCase,
+    pub server: ServerConfig,
+    /// Searches kept by name, shown as folders when browsing
+    pub saved_searches: Vec<SavedSearch>,
+    /// Notes and saved searches kept at hand, in order
+    pub pins: Vec<Pin>,
+}
+
+/// Something pinned in the vault
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Pin {
+    Note {
+        path: VaultPath,
+    },
+    /// A saved search, by its name
+    Search {
+        name: String,
+    },
+}
+
+/// A query with the search syntax kept under a name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+}
+
+/// The settings of the local HTTP server, `kimun serve`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// The clients send it as a bearer token, the server doesn't start without one
+    pub token: Option<String>,
+    pub port: u16,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            token: None,
+            port: DEFAULT_SERVER_PORT,
+        }
+    }
+}
+
+impl VaultConfig {
+    /// Loads the config from the directory, or the default
+    /// one if there is no config file
+    pub(crate) fn load<P: AsRef<Path>>(config_dir: P) -> Result<Self, VaultError> {
+        let config_file = config_dir.as_ref().join(CONFIG_FILE);
+        if !config_file.exists() {
+            debug!("No vault config found, using the default");
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(config_file).map_err(FSError::ReadFileError)?;
+        toml::from_str(&text).map_err(|e| VaultError::InvalidConfig(e.to_string()))
+    }
+
+    pub(crate) fn save<P: AsRef<Path>>(&self, config_dir: P) -> Result<(), VaultError> {
+        let text = toml::to_string(self).map_err(|e| VaultError::InvalidConfig(e.to_string()))?;
+        std::fs::write(config_dir.as_ref().join(CONFIG_FILE), text)
+            .map_err(FSError::ReadFileError)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::VaultError,
+        nfs::{
+            note_types::{NoteFormat, NoteTypes},
+            PathCase, VaultPath,
+        },
+        test_utils::{db_path, indexed_vault},
+        NoteVault,
+    };
+
+    use super::{Pin, SavedSearch, VaultConfig};
+
+    #[test]
+    fn config_round_trip() {
+        let mut config = VaultConfig::default();
+        config.ignore.use_gitignore = true;
+        config.ignore.excluded_folders = vec![VaultPath::from("templates")];
+        config.notes = NoteTypes::new([("md", NoteFormat::Markdown), ("txt", NoteFormat::Text)]);
+        config.path_case = PathCase::Insensitive;
+        config.server.token = Some("secret".to_string());
+        config.saved_searches = vec![SavedSearch {
+            name: "Work".to_string(),
+            query: ">work #todo".to_string(),
+        }];
+        config.pins = vec![
+            Pin::Note {
+                path: VaultPath::from("projects/plan.md"),
+            },
+            Pin::Search {
+                name: "Work".to_string(),
+            },
+        ];
+
+        let text = toml::to_string(&config).unwrap();
+        assert_eq!(config, toml::from_str(&text).unwrap());
+        // Missing values take the default
+        assert_eq!(VaultConfig::default(), toml::from_str("").unwrap());
+    }
+
+    #[test]
+    fn saved_searches() {
+        let (dir, mut vault) = indexed_vault(&[
+            ("todo.md", "# Todo\n\n## Work\n\nCall #client"),
+            ("home.md", "# Home\n\nCall mum"),
+        ]);
+
+        vault.save_search("Calls", "call").unwrap();
+        vault.save_search("Work", ">work").unwrap();
+        assert_eq!(2, vault.run_saved_search("calls").unwrap().len());
+        // The same name replaces the query
+        vault.save_search("calls", "#client").unwrap();
+        assert_eq!(2, vault.get_saved_searches().len());
+        let results = vault.run_saved_search("Calls").unwrap();
+        assert_eq!(1, results.len());
+        assert_eq!(VaultPath::from("todo.md"), results[0].path);
+        assert!(vault.save_search(" ", "call").is_err());
+
+        // Saved in the vault config
+        let reopened = NoteVault::with_db_path(&vault.workspace_path, db_path(&dir)).unwrap();
+        assert_eq!(vault.get_saved_searches(), reopened.get_saved_searches());
+
+        vault.delete_saved_search("Work").unwrap();
+        assert!(matches!(
+            vault.run_saved_search("Work"),
+            Err(VaultError::SavedSearchNotFound { .. })
+        ));
+        assert!(vault.delete_saved_search("Work").is_err());
+    }
+
+    #[test]
+    fn pins() {
+        let (dir, mut vault) = indexed_vault(&[]);
+
+        let plan = Pin::Note {
+            path: VaultPath::from("plan.md"),
+        };
+        let work = Pin::Search {
+            name: "Work".to_string(),
+        };
+        vault.pin(plan.clone()).unwrap();
+        // Only saved searches can be pinned
+        assert!(vault.pin(work.clone()).is_err());
+        vault.save_search("Work", ">work").unwrap();
+        vault.pin(work.clone()).unwrap();
+        vault.pin(plan.clone()).unwrap();
+        assert_eq!(&[plan.clone(), work.clone()], vault.get_pins());
+
+        vault.move_pin(&work, 0).unwrap();
+        assert_eq!(&[work.clone(), plan.clone()], vault.get_pins());
+        let reopened = NoteVault::with_db_path(&vault.workspace_path, db_path(&dir)).unwrap();
+        assert_eq!(vault.get_pins(), reopened.get_pins());
+
+        // Deleting the saved search unpins it
+        vault.delete_saved_search("work").unwrap();
+        assert_eq!(std::slice::from_ref(&plan), vault.get_pins());
+        vault.unpin(&plan).unwrap();
+        assert!(!vault.is_pinned(&plan));
+    }
+}
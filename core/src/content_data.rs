@@ -1,16 +1,34 @@
-use std::{cmp::min, fmt::Display};
+use std::{cmp::min, fmt::Display, sync::OnceLock};
 
 use log::error;
-use pulldown_cmark::{Event, Parser, Tag};
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use regex::Regex;
 
-use crate::nfs;
+use crate::{
+    links::{extract_links, NoteLink},
+    nfs,
+    tasks::{extract_tasks, NoteTask},
+};
 
 const MAX_TITLE_LENGTH: usize = 40;
+/// Words read in a minute, for the reading time
+const WORDS_PER_MINUTE: u64 = 200;
+/// A tag starts with `#` after a whitespace or at the start of the text,
+/// and needs at least one non numeric character, so `#1` is not a tag
+const TAG_REGEX: &str = r"(?:^|\s)#([\p{L}\p{N}_\-/]*[\p{L}_\-/][\p{L}\p{N}_\-/]*)";
 
 pub fn extract_data<S: AsRef<str>>(md_text: S) -> NoteContentData {
     let (frontmatter, text) = remove_frontmatter(md_text.as_ref());
 
     let mut note_content = parse_text(&text);
+    // The front matter is part of the note, changing it changes the hash
+    note_content.hash = nfs::hash_text(md_text.as_ref());
+    note_content.tags = extract_tags(&frontmatter, &text);
+    note_content.aliases = frontmatter_list(&frontmatter, "aliases");
+    note_content.tasks = extract_tasks(md_text.as_ref());
+    note_content.links = extract_links(md_text.as_ref());
+    note_content.headings = extract_outline(md_text.as_ref());
+    note_content.stats = note_stats(&text);
     if !frontmatter.is_empty() {
         note_content.content_chunks.push(ContentChunk {
             breadcrumb: vec!["FrontMatter".to_string()],
@@ -20,6 +38,43 @@ pub fn extract_data<S: AsRef<str>>(md_text: S) -> NoteContentData {
     note_content
 }
 
+/// Plain text notes have no structure, the whole text is a single
+/// chunk and the first line is the title
+pub fn extract_text_data<S: AsRef<str>>(text: S) -> NoteContentData {
+    let text = text.as_ref();
+    let title = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(|line| line.chars().take(MAX_TITLE_LENGTH).collect());
+    let mut tags = text_tags(text).collect::<Vec<String>>();
+    tags.sort();
+    tags.dedup();
+    let content_chunks = if text.trim().is_empty() {
+        vec![]
+    } else {
+        vec![ContentChunk {
+            breadcrumb: vec![],
+            text: super::utilities::remove_diacritics(text),
+        }]
+    };
+    NoteContentData {
+        title,
+        hash: nfs::hash_text(text),
+        content_chunks,
+        tags,
+        aliases: vec![],
+        tasks: vec![],
+        links: vec![],
+        headings: vec![],
+        stats: NoteStats {
+            words: count_words(text),
+            characters: text.chars().count() as u64,
+            headings: 0,
+        },
+    }
+}
+
 fn parse_text(md_text: &str) -> NoteContentData {
     let hash = nfs::hash_text(md_text);
     let mut title = None;
@@ -31,9 +86,9 @@ fn parse_text(md_text: &str) -> NoteContentData {
     while let Some(event) = parser.next() {
         let tt = match event {
             Event::Start(tag) => parse_start_tag(tag, &mut parser),
-            Event::End(_tag_end) => {
-                panic!("Non Matching Tags")
-            }
+            // The start tags consume their content up to their end, a
+            // stray end tag is not worth failing the whole note
+            Event::End(_tag_end) => TextType::None,
             Event::Text(cow_str) => TextType::Text(cow_str.to_string()),
             Event::Code(cow_str) => TextType::Text(cow_str.to_string()),
             Event::InlineMath(cow_str) => TextType::Text(cow_str.to_string()),
@@ -44,7 +99,8 @@ fn parse_text(md_text: &str) -> NoteContentData {
             Event::SoftBreak => TextType::None,
             Event::HardBreak => TextType::None,
             Event::Rule => TextType::None,
-            Event::TaskListMarker(result) => TextType::Text(result.to_string()),
+            // The tasks are extracted on their own
+            Event::TaskListMarker(_checked) => TextType::None,
         };
 
         if title.is_none() {
@@ -101,10 +157,172 @@ fn parse_text(md_text: &str) -> NoteContentData {
         title,
         hash,
         content_chunks,
+        tags: vec![],
+        aliases: vec![],
+        tasks: vec![],
+        links: vec![],
+        headings: vec![],
+        stats: NoteStats::default(),
+    }
+}
+
+/// Counts the words and characters of the text shown, without the
+/// markdown syntax, and the headings. The front matter is not counted.
+fn note_stats(md_text: &str) -> NoteStats {
+    let mut stats = NoteStats::default();
+    for event in Parser::new(md_text) {
+        match event {
+            Event::Start(Tag::Heading { .. }) => stats.headings += 1,
+            Event::Text(text) | Event::Code(text) => {
+                stats.words += count_words(&text);
+                stats.characters += text.chars().count() as u64;
+            }
+            Event::SoftBreak | Event::HardBreak => stats.characters += 1,
+            _ => {}
+        }
+    }
+    stats
+}
+
+fn count_words(text: &str) -> u64 {
+    text.split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count() as u64
+}
+
+/// Returns the headings of the note in the order they appear, the
+/// offsets are in bytes from the start of the text, front matter included
+pub fn extract_outline<S: AsRef<str>>(md_text: S) -> Vec<OutlineHeading> {
+    let md_text = md_text.as_ref();
+    let options = Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+    let mut outline = vec![];
+    let mut current: Option<OutlineHeading> = None;
+    for (event, range) in Parser::new_ext(md_text, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                current = Some(OutlineHeading {
+                    level: heading_level(level),
+                    text: String::new(),
+                    offset: range.start,
+                });
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some(mut heading) = current.take() {
+                    heading.text = heading.text.trim().to_string();
+                    outline.push(heading);
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(heading) = current.as_mut() {
+                    heading.text.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+    }
+    outline
+}
+
+pub(crate) fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
     }
 }
 
-fn remove_frontmatter(text: &str) -> (String, String) {
+fn tag_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(TAG_REGEX).unwrap())
+}
+
+/// Gets the tags from the `tags` key in the front matter and the `#tags`
+/// in the text, ignoring the ones inside code. The result is sorted and
+/// without duplicates.
+fn extract_tags(frontmatter: &str, md_text: &str) -> Vec<String> {
+    let mut tags = frontmatter_tags(frontmatter);
+
+    let mut in_code = false;
+    let mut current_text = String::new();
+    for event in Parser::new(md_text) {
+        match event {
+            Event::Text(text) if !in_code => {
+                current_text.push_str(&text);
+                continue;
+            }
+            Event::Start(Tag::CodeBlock(_)) => in_code = true,
+            Event::End(TagEnd::CodeBlock) => in_code = false,
+            _ => {}
+        }
+        // Text events are split on some characters, like `_`, so we only
+        // look for tags once we have the whole text
+        tags.extend(text_tags(&current_text));
+        current_text.clear();
+    }
+    tags.extend(text_tags(&current_text));
+
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+fn text_tags(text: &str) -> impl Iterator<Item = String> + '_ {
+    tag_regex()
+        .captures_iter(text)
+        .map(|caps| caps[1].to_string())
+}
+
+/// Tags can be separated by commas or spaces
+fn frontmatter_tags(frontmatter: &str) -> Vec<String> {
+    frontmatter_list(frontmatter, "tags")
+        .iter()
+        .flat_map(|value| value.split_whitespace())
+        .map(|tag| tag.trim_matches('#').to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// The values of a front matter key, supports both `key: [one, two]`
+/// and the list form, with one `- value` per line
+fn frontmatter_list(frontmatter: &str, key: &str) -> Vec<String> {
+    let yaml_key = format!("{}:", key);
+    let toml_key = format!("{} =", key);
+    let mut values = vec![];
+    let mut lines = frontmatter.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some(value) = line
+            .strip_prefix(&yaml_key)
+            .or_else(|| line.strip_prefix(&toml_key))
+        else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            while let Some(item) = lines.peek().and_then(|l| l.trim().strip_prefix("- ")) {
+                values.push(item.to_string());
+                lines.next();
+            }
+        } else {
+            values.extend(
+                value
+                    .trim_matches(['[', ']'])
+                    .split(',')
+                    .map(|v| v.to_string()),
+            );
+        }
+    }
+    values
+        .into_iter()
+        .map(|v| v.trim().trim_matches(['"', '\'']).trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+pub(crate) fn remove_frontmatter(text: &str) -> (String, String) {
     let mut lines = text.lines();
     let first_line = lines.next();
     if let Some(line) = first_line {
@@ -149,14 +367,7 @@ fn parse_start_tag(tag: Tag, parser: &mut Parser) -> TextType {
             classes: _,
             attrs: _,
         } => {
-            let level = match level {
-                pulldown_cmark::HeadingLevel::H1 => 1,
-                pulldown_cmark::HeadingLevel::H2 => 2,
-                pulldown_cmark::HeadingLevel::H3 => 3,
-                pulldown_cmark::HeadingLevel::H4 => 4,
-                pulldown_cmark::HeadingLevel::H5 => 5,
-                pulldown_cmark::HeadingLevel::H6 => 6,
-            };
+            let level = heading_level(level);
             let text = get_text_till_end(parser);
             TextType::Header(level, text)
         }
@@ -257,6 +468,29 @@ pub struct NoteContentData {
     pub(super) title: Option<String>,
     pub hash: u64,
     pub content_chunks: Vec<ContentChunk>,
+    pub tags: Vec<String>,
+    /// Other names of the note, from the `aliases` key in the front matter
+    pub aliases: Vec<String>,
+    pub tasks: Vec<NoteTask>,
+    pub links: Vec<NoteLink>,
+    pub headings: Vec<OutlineHeading>,
+    pub stats: NoteStats,
+}
+
+/// Counts of the note's content, stored in the index to sort and
+/// filter the notes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoteStats {
+    pub words: u64,
+    pub characters: u64,
+    pub headings: u64,
+}
+
+impl NoteStats {
+    /// The minutes it takes to read the note, rounded up
+    pub fn reading_minutes(&self) -> u64 {
+        self.words.div_ceil(WORDS_PER_MINUTE)
+    }
 }
 
 impl Display for NoteContentData {
@@ -275,6 +509,15 @@ impl Display for NoteContentData {
     }
 }
 
+/// A heading in the note's outline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineHeading {
+    pub level: u8,
+    pub text: String,
+    /// Where the heading starts in the note's text, in bytes
+    pub offset: usize,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ContentChunk {
     breadcrumb: Vec<String>,
@@ -286,14 +529,14 @@ impl ContentChunk {
         self.breadcrumb.join(">")
     }
 
-    fn get_text(&self) -> &str {
+    pub fn get_text(&self) -> &str {
         &self.text
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::content_data::extract_data;
+    use crate::content_data::{extract_data, extract_outline, extract_text_data, NoteStats};
 
     #[test]
     fn check_title_yaml_frontmatter() {
@@ -563,6 +806,97 @@ Some text"#;
         assert_eq!("Some text", ch.content_chunks[0].get_text());
     }
 
+    #[test]
+    fn check_tags() {
+        let markdown = r#"# Title #heading_tag
+Some text with #a_tag, #nested/tag and #a_tag again
+
+Not tags: #123, issue#4, `#code`
+
+```
+#in_code_block
+```"#;
+        let ch = extract_data(markdown);
+
+        assert_eq!(vec!["a_tag", "heading_tag", "nested/tag"], ch.tags);
+    }
+
+    #[test]
+    fn check_frontmatter_tags() {
+        let markdown = r#"---
+tags: [one, "two"]
+other:
+  - not a tag
+---
+Text with #three"#;
+        let ch = extract_data(markdown);
+        assert_eq!(vec!["one", "three", "two"], ch.tags);
+
+        let markdown = r#"---
+tags:
+  - one
+  - two
+---
+Text"#;
+        let ch = extract_data(markdown);
+        assert_eq!(vec!["one", "two"], ch.tags);
+    }
+
+    #[test]
+    fn check_frontmatter_aliases() {
+        let markdown = r#"---
+aliases: [First Name, "Second"]
+---
+Text"#;
+        let ch = extract_data(markdown);
+        assert_eq!(vec!["First Name", "Second"], ch.aliases);
+
+        let markdown = r#"---
+aliases:
+  - First Name
+  - 'Second'
+---
+Text"#;
+        let ch = extract_data(markdown);
+        assert_eq!(vec!["First Name", "Second"], ch.aliases);
+        assert!(extract_data("aliases: [Not front matter]")
+            .aliases
+            .is_empty());
+    }
+
+    #[test]
+    fn check_outline() {
+        let markdown = r#"---
+title: not a heading
+---
+# Title
+Some text
+
+## Sub `title`
+
+```
+# Not a heading
+```
+
+Setext heading
+--------------"#;
+        let outline = extract_outline(markdown);
+
+        assert_eq!(3, outline.len());
+        assert_eq!((1, "Title"), (outline[0].level, outline[0].text.as_str()));
+        assert!(markdown[outline[0].offset..].starts_with("# Title"));
+        assert_eq!(
+            (2, "Sub title"),
+            (outline[1].level, outline[1].text.as_str())
+        );
+        assert!(markdown[outline[1].offset..].starts_with("## Sub"));
+        assert_eq!(
+            (2, "Setext heading"),
+            (outline[2].level, outline[2].text.as_str())
+        );
+        assert!(markdown[outline[2].offset..].starts_with("Setext heading"));
+    }
+
     #[test]
     fn check_content_without_title() {
         let markdown = r#"Intro text
@@ -579,4 +913,38 @@ Some text"#;
         assert_eq!("Title", ch.content_chunks[1].get_breadcrumb());
         assert_eq!("Some text", ch.content_chunks[1].get_text());
     }
+
+    #[test]
+    fn plain_text_single_chunk() {
+        let text = "\n  Shopping list  \n# not a header\nmilk #groceries\n";
+        let data = extract_text_data(text);
+
+        assert_eq!(Some("Shopping list".to_string()), data.title);
+        assert_eq!(1, data.content_chunks.len());
+        assert!(data.content_chunks[0].breadcrumb.is_empty());
+        assert_eq!(text, data.content_chunks[0].text);
+        assert_eq!(vec!["groceries".to_string()], data.tags);
+        assert_eq!(7, data.stats.words);
+        assert_eq!(0, data.stats.headings);
+    }
+
+    #[test]
+    fn check_stats() {
+        let md = r#"---
+title: Not counted
+---
+# Groceries
+
+Buy **milk** and [bread](bread.md) - today
+
+## Later
+
+`cheese`"#;
+        let stats = extract_data(md).stats;
+
+        assert_eq!(8, stats.words);
+        assert_eq!(2, stats.headings);
+        assert_eq!(1, stats.reading_minutes());
+        assert_eq!(0, NoteStats::default().reading_minutes());
+    }
 }
@@ -4,168 +4,111 @@
 //!
 #![forbid(unsafe_code)]
 
-use std::{path::Path, thread};
+use std::{
+    fmt::Debug,
+    path::Path,
+    sync::mpsc::{Receiver, Sender},
+    thread,
+};
 
-// use crossbeam::channel::{Receiver, Sender};
-use crossbeam_channel::{Receiver, Sender};
-// use tokio::sync::oneshot::{self};
 use futures_channel::oneshot;
+use log::{debug, error};
 
-use crate::core_notes::error::DBError;
+use crate::error::DBError;
 
-const DB_FILE: &str = "note.sqlite";
-const BUG_TEXT: &str = "bug in tokio-rusqlite, please report";
+use super::ConnectionBuilder;
 
-/// The result returned on method calls in this crate.
-pub type Result<T> = std::result::Result<T, DBError>;
 /// The function called executing against the SQLite connection
 type CallFn = Box<dyn FnOnce(&mut rusqlite::Connection) + Send + 'static>;
 
 enum Message {
     Execute(CallFn),
-    Close(oneshot::Sender<std::result::Result<(), rusqlite::Error>>),
+    Close(oneshot::Sender<Result<(), rusqlite::Error>>),
 }
 
-/// A handle to call functions in background thread.
+/// A handle to call functions in a background thread that owns
+/// the connection, so async callers never block on the DB.
 #[derive(Clone)]
 pub struct AsyncConnection {
     sender: Sender<Message>,
 }
 
+impl Debug for AsyncConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncConnection").finish()
+    }
+}
+
 impl AsyncConnection {
-    pub async fn open<P: AsRef<Path>>(workspace_path: P) -> Result<Self> {
-        let db_path = workspace_path.as_ref().join(DB_FILE);
-        let connection = rusqlite::Connection::open(db_path)?;
-        let _c = connection.set_db_config(
-            rusqlite::config::DbConfig::SQLITE_DBCONFIG_ENABLE_FTS3_TOKENIZER,
-            true,
-        )?;
-        start(move || Ok(connection))
-            .await
-            .map_err(DBError::DBError)
+    /// Opens the vault's DB with the same settings as the sync connections
+    pub async fn open<P: AsRef<Path>>(db_path: P) -> Result<Self, DBError> {
+        let db_path = db_path.as_ref().to_path_buf();
+        start(move || ConnectionBuilder::build(db_path)).await
     }
 
     /// Open a new connection to an in-memory SQLite database.
-    ///
-    /// # Failure
-    ///
-    /// Will return `Err` if the underlying SQLite open call fails.
-    pub async fn open_in_memory() -> Result<Self> {
-        start(rusqlite::Connection::open_in_memory)
-            .await
-            .map_err(DBError::DBError)
+    #[cfg(test)]
+    pub async fn open_in_memory() -> Result<Self, DBError> {
+        start(|| rusqlite::Connection::open_in_memory().map_err(DBError::DBError)).await
     }
 
-    /// Call a function in background thread and get the result
+    /// Call a function in the background thread and get the result
     /// asynchronously.
     ///
     /// # Failure
     ///
-    /// Will return `Err` if the database connection has been closed.
-    pub async fn call<F, R>(&self, function: F) -> Result<R>
+    /// Will return `Err` with `DBError::DBConnectionClosed` if the connection has been closed.
+    pub async fn call<F, R, E>(&self, function: F) -> Result<R, E>
     where
-        F: FnOnce(&mut rusqlite::Connection) -> Result<R> + 'static + Send,
+        F: FnOnce(&mut rusqlite::Connection) -> Result<R, E> + Send + 'static,
         R: Send + 'static,
+        E: From<DBError> + Send + 'static,
     {
-        let (sender, receiver) = oneshot::channel::<Result<R>>();
+        let (sender, receiver) = oneshot::channel::<Result<R, E>>();
 
         self.sender
             .send(Message::Execute(Box::new(move |conn| {
                 let value = function(conn);
-                println!("> Executed function");
+                // The caller may have been dropped before getting the result
                 let _ = sender.send(value);
-                println!("> Sent");
             })))
             .map_err(|_| DBError::DBConnectionClosed)?;
 
-        println!("< Waiting for value");
-        let value = receiver.await.map_err(|_| DBError::DBConnectionClosed)?;
-        println!("< Received value");
-        value
-    }
-
-    /// Call a function in background thread and get the result
-    /// asynchronously.
-    ///
-    /// This method can cause a `panic` if the underlying database connection is closed.
-    /// it is a more user-friendly alternative to the [`Connection::call`] method.
-    /// It should be safe if the connection is never explicitly closed (using the [`Connection::close`] call).
-    ///
-    /// Calling this on a closed connection will cause a `panic`.
-    pub async fn call_unwrap<F, R>(&self, function: F) -> R
-    where
-        F: FnOnce(&mut rusqlite::Connection) -> R + Send + 'static,
-        R: Send + 'static,
-    {
-        let (sender, receiver) = oneshot::channel::<R>();
-
-        self.sender
-            .send(Message::Execute(Box::new(move |conn| {
-                let value = function(conn);
-                let _ = sender.send(value);
-            })))
-            .expect("database connection should be open");
-
-        receiver.await.expect(BUG_TEXT)
-    }
-
-    pub async fn execute<F>(&self, function: F) -> Result<()>
-    where
-        F: FnOnce(&rusqlite::Transaction) -> Result<()> + Send + 'static,
-    {
-        self.call(|conn| {
-            let tx = conn.transaction()?;
-            function(&tx)?;
-            tx.commit()?;
-            Ok(())
-        })
-        .await
+        receiver.await.map_err(|_| DBError::DBConnectionClosed)?
     }
 
     /// Close the database connection.
     ///
-    /// This is functionally equivalent to the `Drop` implementation for
-    /// `Connection`. It consumes the `Connection`, but on error returns it
-    /// to the caller for retry purposes.
-    ///
     /// If successful, any following `close` operations performed
-    /// on `Connection` copies will succeed immediately.
-    ///
-    /// On the other hand, any calls to [`Connection::call`] will return a [`Error::ConnectionClosed`],
-    /// and any calls to [`Connection::call_unwrap`] will cause a `panic`.
+    /// on clones of the connection will succeed immediately, and any
+    /// calls to [`AsyncConnection::call`] will return a [`DBError::DBConnectionClosed`].
     ///
     /// # Failure
     ///
     /// Will return `Err` if the underlying SQLite close call fails.
-    pub async fn close(self) -> Result<()> {
-        let (sender, receiver) = oneshot::channel::<std::result::Result<(), rusqlite::Error>>();
+    pub async fn close(&self) -> Result<(), DBError> {
+        let (sender, receiver) = oneshot::channel::<Result<(), rusqlite::Error>>();
 
-        if let Err(crossbeam_channel::SendError(_)) = self.sender.send(Message::Close(sender)) {
-            // If the channel is closed on the other side, it means the connection closed successfully
-            // This is a safeguard against calling close on a `Copy` of the connection
+        if self.sender.send(Message::Close(sender)).is_err() {
+            // If the channel is closed on the other side, the connection closed successfully
             return Ok(());
         }
 
-        let result = receiver.await;
-
-        if result.is_err() {
-            // If we get a RecvError at this point, it also means the channel closed in the meantime
-            // we can assume the connection is closed
-            return Ok(());
+        match receiver.await {
+            Ok(result) => result.map_err(DBError::DBError),
+            // The channel closed in the meantime, so did the connection
+            Err(_) => Ok(()),
         }
-
-        result.unwrap().map_err(DBError::DBError)
     }
 }
 
-async fn start<F>(open: F) -> rusqlite::Result<AsyncConnection>
+async fn start<F>(open: F) -> Result<AsyncConnection, DBError>
 where
-    F: FnOnce() -> rusqlite::Result<rusqlite::Connection> + Send + 'static,
+    F: FnOnce() -> Result<rusqlite::Connection, DBError> + Send + 'static,
 {
-    let (sender, receiver) = crossbeam_channel::unbounded::<Message>();
+    let (sender, receiver) = std::sync::mpsc::channel::<Message>();
     let (result_sender, result_receiver) = oneshot::channel();
 
-    println!("Started thread");
     thread::spawn(move || {
         let conn = match open() {
             Ok(c) => c,
@@ -175,40 +118,36 @@ where
             }
         };
 
-        if let Err(_e) = result_sender.send(Ok(())) {
+        if result_sender.send(Ok(())).is_err() {
             return;
         }
 
         event_loop(conn, receiver);
     });
-    println!("Running thread");
 
     result_receiver
         .await
-        .expect(BUG_TEXT)
+        .map_err(|_| DBError::DBConnectionClosed)?
         .map(|_| AsyncConnection { sender })
 }
 
 fn event_loop(mut conn: rusqlite::Connection, receiver: Receiver<Message>) {
+    debug!("Async DB connection started");
     while let Ok(message) = receiver.recv() {
-        println!("Message Received");
         match message {
             Message::Execute(f) => f(&mut conn),
-            Message::Close(s) => {
-                let result = conn.close();
-
-                match result {
-                    Ok(v) => {
-                        s.send(Ok(v)).expect(BUG_TEXT);
-                        break;
-                    }
-                    Err((c, e)) => {
-                        conn = c;
-                        s.send(Err(e)).expect(BUG_TEXT);
-                    }
+            Message::Close(s) => match conn.close() {
+                Ok(()) => {
+                    let _ = s.send(Ok(()));
+                    break;
                 }
-            }
+                Err((c, e)) => {
+                    error!("Error closing the async DB connection: {}", e);
+                    conn = c;
+                    let _ = s.send(Err(e));
+                }
+            },
         }
     }
-    println!("We are done here");
+    debug!("Async DB connection finished");
 }
@@ -0,0 +1,446 @@
+use log::{debug, info};
+use rusqlite::{Connection, OptionalExtension, Transaction};
+
+use crate::{
+    error::DBError,
+    nfs::{PathCase, VaultPath},
+};
+
+/// The `appData` version of the schema before migrations were introduced,
+/// DBs with this version are at the first migration
+const LEGACY_VERSION: &str = "0.3";
+
+/// A step that upgrades the schema from `version - 1` to `version`.
+/// Steps should only drop data that can be derived from the notes, as
+/// the vault is indexed after migrating. If a step needs the notes to be
+/// parsed again, it can delete their rows from the `notes` table so the
+/// next index adds them back.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    up: fn(&Transaction) -> Result<(), DBError>,
+}
+
+/// The migrations in order, never change or remove an existing one,
+/// add a new step instead
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Notes, content and tags tables",
+        up: initial_schema,
+    },
+    Migration {
+        version: 2,
+        description: "Normalized note paths",
+        up: path_keys,
+    },
+    Migration {
+        version: 3,
+        description: "Note aliases",
+        up: aliases,
+    },
+    Migration {
+        version: 4,
+        description: "Note tasks",
+        up: tasks,
+    },
+    Migration {
+        version: 5,
+        description: "Note links",
+        up: links,
+    },
+    Migration {
+        version: 6,
+        description: "Note statistics",
+        up: stats,
+    },
+    Migration {
+        version: 7,
+        description: "Note headings",
+        up: headings,
+    },
+    Migration {
+        version: 8,
+        description: "Terms of the content index",
+        up: content_terms,
+    },
+    Migration {
+        version: 9,
+        description: "Task breadcrumbs",
+        up: task_headings,
+    },
+];
+
+/// The schema version once all the migrations are applied
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Where a DB is in the migrations
+#[derive(Debug, PartialEq, Eq)]
+pub enum SchemaStatus {
+    UpToDate,
+    /// There are migrations to apply
+    Pending,
+    /// The DB was created by an older version without migrations or by
+    /// a newer version of the app, it has to be recreated
+    Incompatible,
+}
+
+pub fn schema_status(connection: &Connection) -> Result<SchemaStatus, DBError> {
+    let version = user_version(connection)?;
+    let status = if version == 0 {
+        match legacy_version(connection)? {
+            // Empty DB, the migrations create everything
+            None => SchemaStatus::Pending,
+            Some(legacy) if legacy == LEGACY_VERSION => SchemaStatus::Pending,
+            Some(_) => SchemaStatus::Incompatible,
+        }
+    } else if version < latest_version() {
+        SchemaStatus::Pending
+    } else if version == latest_version() {
+        SchemaStatus::UpToDate
+    } else {
+        SchemaStatus::Incompatible
+    };
+    debug!(
+        "DB schema version {}, latest {}: {:?}",
+        version,
+        latest_version(),
+        status
+    );
+    Ok(status)
+}
+
+/// Applies the pending migrations, each one in its own transaction
+/// so a failing step leaves the DB in the previous version.
+/// Returns the number of migrations applied.
+pub fn migrate(connection: &mut Connection) -> Result<usize, DBError> {
+    migrate_to(connection, latest_version())
+}
+
+/// Applies the migrations up to the version, to have the DBs of older
+/// versions in the tests
+pub(crate) fn migrate_to(connection: &mut Connection, target: u32) -> Result<usize, DBError> {
+    let mut version = user_version(connection)?;
+    if version == 0 && legacy_version(connection)?.as_deref() == Some(LEGACY_VERSION) {
+        // Same schema as the first migration, we just start tracking it
+        debug!("Adopting legacy schema {}", LEGACY_VERSION);
+        version = 1;
+        set_user_version(connection, version)?;
+    }
+    if version > latest_version() {
+        return Err(DBError::Other(format!(
+            "DB schema version {} is newer than the supported {}",
+            version,
+            latest_version()
+        )));
+    }
+
+    let mut applied = 0;
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|m| m.version > version && m.version <= target)
+    {
+        info!(
+            "Migrating DB to version {}: {}",
+            migration.version, migration.description
+        );
+        let tx = connection.transaction()?;
+        (migration.up)(&tx)?;
+        set_user_version(&tx, migration.version)?;
+        tx.commit()?;
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+pub fn user_version(connection: &Connection) -> Result<u32, DBError> {
+    let version = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    Ok(version)
+}
+
+fn set_user_version(connection: &Connection, version: u32) -> Result<(), DBError> {
+    connection.pragma_update(None, "user_version", version)?;
+    Ok(())
+}
+
+fn legacy_version(connection: &Connection) -> Result<Option<String>, DBError> {
+    let has_app_data = connection
+        .query_row(
+            "SELECT name FROM sqlite_schema WHERE type = 'table' AND name = 'appData'",
+            [],
+            |_row| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if !has_app_data {
+        return Ok(None);
+    }
+    let version = connection
+        .query_row(
+            "SELECT value FROM appData WHERE name = 'version'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(version)
+}
+
+fn initial_schema(tx: &Transaction) -> Result<(), DBError> {
+    tx.execute(
+        "CREATE TABLE appData (
+            name TEXT PRIMARY KEY,
+            value TEXT
+        )",
+        (), // empty list of parameters.
+    )?;
+    tx.execute(
+        "INSERT INTO appData (name, value) VALUES (?1, ?2)",
+        ["version", LEGACY_VERSION],
+    )?;
+
+    // Storing hash as a string, as SQlite doesn't like
+    // unsigned 64bit integers, alternatively we could
+    // have used signed numbers by substracting the half
+    // of the max value, but that looks like a worse conversion
+    tx.execute(
+        "CREATE TABLE notes (
+            path TEXT PRIMARY KEY,
+            title TEXT,
+            size INTEGER,
+            modified INTEGER,
+            hash TEXT,
+            basePath TEXT,
+            noteName TEXT
+        )",
+        (), // empty list of parameters.
+    )?;
+    tx.execute(
+        "CREATE VIRTUAL TABLE notesContent USING fts4(
+            path,
+            breadcrumb,
+            text
+        )",
+        (), // empty list of parameters.
+    )?;
+    tx.execute(
+        "CREATE TABLE tags (
+            path TEXT,
+            tag TEXT
+        )",
+        (), // empty list of parameters.
+    )?;
+    tx.execute("CREATE INDEX tagsPath ON tags (path)", ())?;
+    Ok(())
+}
+
+/// Paths are looked up by their normalized, case insensitive key, so
+/// the same name written with a different case or Unicode form is found
+fn path_keys(tx: &Transaction) -> Result<(), DBError> {
+    tx.execute("ALTER TABLE notes ADD COLUMN pathKey TEXT", ())?;
+    tx.execute("ALTER TABLE notes ADD COLUMN basePathKey TEXT", ())?;
+    let paths = tx
+        .prepare("SELECT path FROM notes")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+    let mut update =
+        tx.prepare("UPDATE notes SET pathKey = ?2, basePathKey = ?3 WHERE path = ?1")?;
+    for path in paths {
+        let vault_path = VaultPath::from(&path);
+        let (base_path, _name) = vault_path.get_parent_path();
+        update.execute((
+            &path,
+            vault_path.key(PathCase::Insensitive),
+            base_path.key(PathCase::Insensitive),
+        ))?;
+    }
+    tx.execute("CREATE INDEX notesPathKey ON notes (pathKey)", ())?;
+    tx.execute("CREATE INDEX notesBasePathKey ON notes (basePathKey)", ())?;
+    Ok(())
+}
+
+/// The aliases come from the front matter, the notes are marked as
+/// changed so the next index parses them again
+fn aliases(tx: &Transaction) -> Result<(), DBError> {
+    tx.execute(
+        "CREATE TABLE aliases (
+            path TEXT,
+            alias TEXT
+        )",
+        (),
+    )?;
+    tx.execute("CREATE INDEX aliasesPath ON aliases (path)", ())?;
+    tx.execute("UPDATE notes SET modified = 0, hash = '0'", ())?;
+    Ok(())
+}
+
+/// The tasks are extracted from the notes, so they are parsed again
+fn tasks(tx: &Transaction) -> Result<(), DBError> {
+    tx.execute(
+        "CREATE TABLE tasks (
+            path TEXT,
+            line INTEGER,
+            text TEXT,
+            done BOOL,
+            breadcrumb TEXT,
+            due TEXT
+        )",
+        (),
+    )?;
+    tx.execute("CREATE INDEX tasksPath ON tasks (path)", ())?;
+    tx.execute("UPDATE notes SET modified = 0, hash = '0'", ())?;
+    Ok(())
+}
+
+fn links(tx: &Transaction) -> Result<(), DBError> {
+    tx.execute(
+        "CREATE TABLE links (
+            path TEXT,
+            target TEXT,
+            name TEXT
+        )",
+        (),
+    )?;
+    tx.execute("CREATE INDEX linksPath ON links (path)", ())?;
+    tx.execute("CREATE INDEX linksName ON links (name)", ())?;
+    tx.execute("UPDATE notes SET modified = 0, hash = '0'", ())?;
+    Ok(())
+}
+
+/// The counts are taken when parsing, so the notes are parsed again
+fn stats(tx: &Transaction) -> Result<(), DBError> {
+    tx.execute("ALTER TABLE notes ADD COLUMN created INTEGER", ())?;
+    tx.execute("ALTER TABLE notes ADD COLUMN words INTEGER", ())?;
+    tx.execute("ALTER TABLE notes ADD COLUMN characters INTEGER", ())?;
+    tx.execute("ALTER TABLE notes ADD COLUMN headings INTEGER", ())?;
+    tx.execute("UPDATE notes SET modified = 0, hash = '0'", ())?;
+    Ok(())
+}
+
+/// The headings were only kept in the breadcrumbs of the content, joined
+/// by `>`, so a heading containing it couldn't be told apart
+fn headings(tx: &Transaction) -> Result<(), DBError> {
+    tx.execute(
+        "CREATE TABLE headings (
+            path TEXT,
+            level INTEGER,
+            text TEXT
+        )",
+        (),
+    )?;
+    tx.execute("CREATE INDEX headingsPath ON headings (path)", ())?;
+    tx.execute("UPDATE notes SET modified = 0, hash = '0'", ())?;
+    Ok(())
+}
+
+/// The words in the content index, to look up the ones containing
+/// a part of a word
+fn content_terms(tx: &Transaction) -> Result<(), DBError> {
+    tx.execute(
+        "CREATE VIRTUAL TABLE notesTerms USING fts4aux(notesContent)",
+        (),
+    )?;
+    Ok(())
+}
+
+/// The breadcrumb of a task was kept joined by `>`, so a heading
+/// containing it couldn't be told apart
+fn task_headings(tx: &Transaction) -> Result<(), DBError> {
+    tx.execute(
+        "CREATE TABLE taskHeadings (
+            path TEXT,
+            line INTEGER,
+            position INTEGER,
+            text TEXT
+        )",
+        (),
+    )?;
+    tx.execute("CREATE INDEX taskHeadingsPath ON taskHeadings (path)", ())?;
+    tx.execute("ALTER TABLE tasks DROP COLUMN breadcrumb", ())?;
+    tx.execute("UPDATE notes SET modified = 0, hash = '0'", ())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::{latest_version, migrate, schema_status, user_version, SchemaStatus, MIGRATIONS};
+
+    #[test]
+    fn migrations_are_ordered() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(index as u32 + 1, migration.version);
+        }
+    }
+
+    #[test]
+    fn migrate_empty_db() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(SchemaStatus::Pending, schema_status(&conn).unwrap());
+
+        assert_eq!(MIGRATIONS.len(), migrate(&mut conn).unwrap());
+        assert_eq!(latest_version(), user_version(&conn).unwrap());
+        assert_eq!(SchemaStatus::UpToDate, schema_status(&conn).unwrap());
+        // Nothing left to apply
+        assert_eq!(0, migrate(&mut conn).unwrap());
+    }
+
+    #[test]
+    fn adopt_legacy_db_keeping_data() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        {
+            let tx = conn.transaction().unwrap();
+            super::initial_schema(&tx).unwrap();
+            // An NFD name, as written by macOS
+            tx.execute(
+                "INSERT INTO notes (path) VALUES (?1)",
+                ["/Notes/Cafe\u{301}.md"],
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+        assert_eq!(0, user_version(&conn).unwrap());
+        assert_eq!(SchemaStatus::Pending, schema_status(&conn).unwrap());
+
+        assert_eq!(MIGRATIONS.len() - 1, migrate(&mut conn).unwrap());
+        assert_eq!(latest_version(), user_version(&conn).unwrap());
+        let notes: Vec<(String, String)> = conn
+            .prepare("SELECT path, pathKey FROM notes")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![(
+                "/Notes/Cafe\u{301}.md".to_string(),
+                "/notes/caf\u{e9}.md".to_string()
+            )],
+            notes
+        );
+    }
+
+    #[test]
+    fn older_legacy_db_is_incompatible() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE appData (name TEXT PRIMARY KEY, value TEXT)",
+            [],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO appData VALUES ('version', '0.2')", [])
+            .unwrap();
+        assert_eq!(SchemaStatus::Incompatible, schema_status(&conn).unwrap());
+    }
+
+    #[test]
+    fn newer_db_is_incompatible() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+        assert_eq!(SchemaStatus::Incompatible, schema_status(&conn).unwrap());
+        assert!(migrate(&mut conn).is_err());
+    }
+}
@@ -1,56 +1,168 @@
-// pub mod async_db;
+#[cfg(feature = "async")]
+pub(crate) mod async_db;
+mod migrations;
+#[cfg(test)]
+pub(crate) use migrations::migrate_to;
 mod search_terms;
+#[cfg(all(test, feature = "async"))]
+mod tests;
 
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
 
-use log::{debug, error};
+use gxhash::gxhash64;
+use log::{debug, error, info, warn};
+use migrations::SchemaStatus;
 use rusqlite::{config::DbConfig, params, Connection, Transaction};
 use rusqlite::{params_from_iter, OpenFlags, OptionalExtension};
 use search_terms::SearchTerms;
 
 use super::error::DBError;
 
-use super::NoteDetails;
-use super::{nfs::NoteEntryData, VaultPath};
+use super::{
+    find_replace::IndexTerm,
+    links::NoteLink,
+    nfs::{NoteEntryData, PathCase},
+    tasks::{format_due_date, parse_due_date, NoteTask, TaskFilter},
+    VaultPath,
+};
+use super::{NoteDetails, NoteStats, OutlineHeading};
 
-const VERSION: &str = "0.2";
 const DB_FILE: &str = "notes.sqlite";
+/// The tables with a row per note, or more, keyed by its path
+const TABLES_BY_PATH: [&str; 8] = [
+    "notes",
+    "notesContent",
+    "tags",
+    "aliases",
+    "tasks",
+    "taskHeadings",
+    "links",
+    "headings",
+];
+/// The columns read for each note, see [`note_entry_data`]
+const NOTE_COLUMNS: &str = "path, title, size, modified, hash, noteName, created";
+const CACHE_DIR: &str = "kimun";
+/// Files SQLite keeps next to the DB in WAL mode
+const DB_SIDE_FILES: [&str; 2] = ["-wal", "-shm"];
+const READ_POOL_SIZE: usize = 4;
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// A part of a word found in more words than this doesn't narrow the
+/// candidates enough to be worth looking up
+const MAX_PART_TERMS: usize = 64;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub(super) struct VaultDB {
-    workspace_path: PathBuf,
+    db_path: PathBuf,
+    pool: Arc<ConnectionPool>,
+}
+
+impl PartialEq for VaultDB {
+    fn eq(&self, other: &Self) -> bool {
+        self.db_path == other.db_path
+    }
+}
+
+/// Keeps the connections to the DB open between calls. There is a single
+/// writer, as SQLite allows only one at a time, and a pool of readers that
+/// thanks to WAL are not blocked by the writer. Connections are opened
+/// lazily, so the DB file is not created until it's used.
+#[derive(Debug, Default)]
+struct ConnectionPool {
+    writer: Mutex<Option<Connection>>,
+    readers: Mutex<Vec<Connection>>,
 }
 
 pub enum DBStatus {
     Ready,
+    /// There are schema migrations to apply
     Outdated,
+    /// The schema can't be migrated, the DB has to be recreated
+    Incompatible,
     NotValid,
     FileNotFound,
 }
 
 impl VaultDB {
-    pub(super) fn new<P: AsRef<Path>>(workspace_path: P) -> Self {
+    pub(super) fn new<P: AsRef<Path>>(db_path: P) -> Self {
         Self {
-            workspace_path: workspace_path.as_ref().to_owned(),
+            db_path: db_path.as_ref().to_owned(),
+            pool: Arc::new(ConnectionPool::default()),
         }
     }
 
-    /// Executes a function with a connection, the connection is closed right
-    /// after the funciton closes
-    pub fn call<F, R>(&self, function: F) -> Result<R, DBError>
+    /// Executes a function with the writer connection, calls are
+    /// serialized as there is only one writer
+    pub fn call<F, R, E>(&self, function: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut rusqlite::Connection) -> Result<R, E>,
+        E: From<DBError>,
+    {
+        let mut writer = self
+            .pool
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let conn = match writer.as_mut() {
+            Some(conn) => conn,
+            None => writer.insert(ConnectionBuilder::build(&self.db_path)?),
+        };
+
+        function(conn)
+    }
+
+    /// Executes a function that only reads from the DB with a connection
+    /// from the read pool, so it can run while something else is written
+    pub fn call_read<F, R>(&self, function: F) -> Result<R, DBError>
     where
         F: FnOnce(&mut rusqlite::Connection) -> Result<R, DBError> + 'static + Send,
     {
-        let mut conn = ConnectionBuilder::build(&self.workspace_path)?;
+        let reader = self
+            .pool
+            .readers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+        let mut conn = match reader {
+            Some(conn) => conn,
+            None => ConnectionBuilder::build_reader(&self.db_path)?,
+        };
 
         let res = function(&mut conn);
-        conn.close().map_err(|(_conn, e)| e)?;
 
+        let mut readers = self
+            .pool
+            .readers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if readers.len() < READ_POOL_SIZE {
+            readers.push(conn);
+        }
         res
     }
 
+    /// Closes all the open connections, needed before removing the DB file
+    pub fn close_connections(&self) {
+        debug!("Closing DB connections");
+        self.pool
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        self.pool
+            .readers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
     pub fn get_db_path(&self) -> PathBuf {
-        self.workspace_path.join(DB_FILE)
+        self.db_path.clone()
     }
 
     pub fn check_db(&self) -> Result<DBStatus, DBError> {
@@ -60,15 +172,17 @@ impl VaultDB {
             OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_URI,
         );
         match conn_res {
-            Ok(mut conn) => {
-                let ver = self.current_schema_version(&mut conn)?.unwrap_or_default();
-                debug!("DB Version: {}, current DB Version: {}", ver, VERSION);
-                let status = if ver == VERSION {
-                    debug!("DB up to date");
-                    DBStatus::Ready
-                } else {
-                    debug!("DB outdated");
-                    DBStatus::Outdated
+            Ok(conn) => {
+                let status = match migrations::schema_status(&conn) {
+                    Ok(SchemaStatus::UpToDate) => DBStatus::Ready,
+                    Ok(SchemaStatus::Pending) => DBStatus::Outdated,
+                    Ok(SchemaStatus::Incompatible) => DBStatus::Incompatible,
+                    Err(DBError::DBError(e))
+                        if e.sqlite_error_code() == Some(rusqlite::ErrorCode::NotADatabase) =>
+                    {
+                        DBStatus::NotValid
+                    }
+                    Err(e) => return Err(e),
                 };
                 conn.close().map_err(|(_conn, e)| e)?;
                 Ok(status)
@@ -86,18 +200,6 @@ impl VaultDB {
             }
         }
     }
-
-    fn current_schema_version(&self, conn: &mut Connection) -> Result<Option<String>, DBError> {
-        let mut stmt = conn.prepare("SELECT value FROM appData WHERE name = 'version'")?;
-        let ver = stmt
-            .query_row([], |row| {
-                let ver: String = row.get(0)?;
-                Ok(ver)
-            })
-            .optional()?;
-
-        Ok(ver)
-    }
 }
 
 /// Deletes all tables and recreates them
@@ -105,7 +207,14 @@ pub fn init_db(connection: &mut Connection) -> Result<(), DBError> {
     debug!("Deleting DB");
     delete_db(connection)?;
     debug!("Creating Tables");
-    create_tables(connection)
+    migrate(connection)?;
+    Ok(())
+}
+
+/// Upgrades the schema in place applying the pending migrations,
+/// returns how many were applied
+pub fn migrate(connection: &mut Connection) -> Result<usize, DBError> {
+    migrations::migrate(connection)
 }
 
 fn _close_connection(connection: Connection) -> Result<(), DBError> {
@@ -140,62 +249,20 @@ fn delete_db(connection: &mut Connection) -> Result<(), DBError> {
             })?;
     }
 
+    connection.pragma_update(None, "user_version", 0)?;
     connection.execute("VACUUM", [])?;
     Ok(())
 }
 
-fn create_tables(connection: &mut Connection) -> Result<(), DBError> {
-    let tx = connection.transaction()?;
-
-    tx.execute(
-        "CREATE TABLE appData (
-            name TEXT PRIMARY KEY,
-            value TEXT
-        )",
-        (), // empty list of parameters.
-    )?;
-    tx.execute(
-        "INSERT INTO appData (name, value) VALUES (?1, ?2)",
-        ["version", VERSION],
-    )?;
-
-    // Storing hash as a string, as SQlite doesn't like
-    // unsigned 64bit integers, alternatively we could
-    // have used signed numbers by substracting the half
-    // of the max value, but that looks like a worse conversion
-    tx.execute(
-        "CREATE TABLE notes (
-            path TEXT PRIMARY KEY,
-            title TEXT,
-            size INTEGER,
-            modified INTEGER,
-            hash TEXT,
-            basePath TEXT,
-            noteName TEXT
-        )",
-        (), // empty list of parameters.
-    )?;
-    tx.execute(
-        "CREATE VIRTUAL TABLE notesContent USING fts4(
-            path,
-            breadcrumb,
-            text
-        )",
-        (), // empty list of parameters.
-    )?;
-
-    tx.commit()?;
-
-    Ok(())
-}
-
 pub fn search_terms<S: AsRef<str>>(
     connection: &mut Connection,
     query: S,
 ) -> Result<Vec<(NoteEntryData, NoteDetails)>, DBError> {
     let search_terms = SearchTerms::from_query_string(query);
     let mut var_num = 1;
-    let base_sql = "SELECT notesContent.path, title, size, modified, hash, noteName FROM notesContent JOIN notes ON notesContent.path = notes.path";
+    // Each part of the query finds the paths matching it, the notes
+    // are the ones found by all of them
+    let base_sql = "SELECT path FROM notesContent";
     let mut params = vec![];
     let mut queries = vec![];
     if !search_terms.terms.is_empty() {
@@ -217,6 +284,33 @@ pub fn search_terms<S: AsRef<str>>(
         let terms_sql = format!("{} WHERE notesContent.path MATCH ?{}", base_sql, var_num);
         queries.push(terms_sql);
         params.push(search_terms.path.join(" "));
+        var_num += 1;
+    }
+    for task in &search_terms.tasks {
+        let done = match task.as_str() {
+            "open" => "0",
+            "done" => "1",
+            _ => {
+                debug!("Unknown task state: {}", task);
+                continue;
+            }
+        };
+        let terms_sql = format!("SELECT path FROM tasks WHERE done = ?{}", var_num);
+        queries.push(terms_sql);
+        params.push(done.to_string());
+        var_num += 1;
+    }
+    for filter in &search_terms.stats {
+        // The value is a param, so it's cast to compare it as a number
+        let terms_sql = format!(
+            "SELECT path FROM notes WHERE {} {} CAST(?{} AS INTEGER)",
+            filter.stat.sql(),
+            filter.operator,
+            var_num
+        );
+        queries.push(terms_sql);
+        params.push(filter.value.to_string());
+        var_num += 1;
     }
 
     if queries.is_empty() {
@@ -224,24 +318,26 @@ pub fn search_terms<S: AsRef<str>>(
         return Ok(vec![]);
     }
 
-    let sql = queries.join(" INTERSECT ");
+    let mut sql = format!(
+        "SELECT {} FROM notes WHERE path IN ({})",
+        NOTE_COLUMNS,
+        queries.join(" INTERSECT ")
+    );
+    if let Some(sort) = &search_terms.sort {
+        let order = if sort.descending { "DESC" } else { "ASC" };
+        sql = format!("{} ORDER BY {} {}", sql, sort.column, order);
+    }
     debug!("QUERY: {}", sql);
 
     let params = params_from_iter(params);
-    let mut stmt = connection.prepare(&sql)?;
+    let mut stmt = connection.prepare_cached(&sql)?;
     let res = stmt
         .query_map(params, |row| {
             let path: String = row.get(0)?;
             let title = row.get(1)?;
-            let size = row.get(2)?;
-            let modified = row.get(3)?;
             let hash: String = row.get(4)?;
             let note_path = VaultPath::from(&path);
-            let data = NoteEntryData {
-                path: note_path.clone(),
-                size,
-                modified_secs: modified,
-            };
+            let data = note_entry_data(&note_path, row)?;
             let det = NoteDetails::new(note_path, hash.parse().unwrap(), title, None);
             Ok((data, det))
         })?
@@ -250,9 +346,52 @@ pub fn search_terms<S: AsRef<str>>(
     Ok(res)
 }
 
+/// Reads the columns in [`NOTE_COLUMNS`]
+fn note_entry_data(
+    path: &VaultPath,
+    row: &rusqlite::Row,
+) -> Result<NoteEntryData, rusqlite::Error> {
+    let modified = row.get(3)?;
+    // The notes migrated from an older index have no creation date
+    // until they are parsed again
+    let created: Option<u64> = row.get(6)?;
+    Ok(NoteEntryData {
+        path: path.to_owned(),
+        size: row.get(2)?,
+        modified_secs: modified,
+        created_secs: created.unwrap_or(modified),
+    })
+}
+
+/// The stored counts of the note, with its outgoing links, if it's
+/// in the index
+pub fn get_note_stats(
+    connection: &mut Connection,
+    path: &VaultPath,
+) -> Result<Option<(NoteEntryData, NoteStats, usize)>, DBError> {
+    let sql = format!(
+        "SELECT {}, words, characters, headings, (SELECT count(*) FROM links WHERE links.path = notes.path) FROM notes WHERE path = ?1",
+        NOTE_COLUMNS
+    );
+    let mut stmt = connection.prepare_cached(&sql)?;
+    let stats = stmt
+        .query_row([path.to_string()], |row| {
+            let data = note_entry_data(path, row)?;
+            let stats = NoteStats {
+                words: row.get(7)?,
+                characters: row.get(8)?,
+                headings: row.get(9)?,
+            };
+            let links: usize = row.get(10)?;
+            Ok((data, stats, links))
+        })
+        .optional()?;
+    Ok(stats)
+}
+
 fn note_exists(connection: &mut Connection, path: &VaultPath) -> Result<bool, DBError> {
     let sql = "SELECT count(*) FROM notes where path = ?1";
-    let mut stmt = connection.prepare(sql)?;
+    let mut stmt = connection.prepare_cached(sql)?;
     let res = stmt.query_row([path.to_string()], |row| row.get(0))?;
     match res {
         0 => Ok(false),
@@ -264,41 +403,157 @@ fn note_exists(connection: &mut Connection, path: &VaultPath) -> Result<bool, DB
     }
 }
 
+/// The path of the note as stored in the index, if there is a note
+/// matching the path
+pub fn find_note_path(
+    connection: &mut Connection,
+    path: &VaultPath,
+    case: PathCase,
+) -> Result<Option<VaultPath>, DBError> {
+    let sql = "SELECT path FROM notes WHERE pathKey = ?1";
+    let mut stmt = connection.prepare_cached(sql)?;
+    let paths = stmt
+        .query_map([path.key(PathCase::Insensitive)], |row| {
+            row.get::<_, String>(0)
+        })?
+        .map(|el| el.map_err(DBError::DBError))
+        .collect::<Result<Vec<String>, DBError>>()?;
+    let candidates = paths
+        .iter()
+        .map(VaultPath::from)
+        .filter(|stored| stored.matches(path, case))
+        .collect::<Vec<VaultPath>>();
+    // Prefer the exact name if there's more than one
+    let exact = path.to_string();
+    Ok(candidates
+        .iter()
+        .find(|stored| stored.to_string() == exact)
+        .or(candidates.first())
+        .cloned())
+}
+
+/// The directory as it's written in the index, matching its path with
+/// `case`. Only the directories with notes are in the index.
+pub fn find_directory_path(
+    connection: &mut Connection,
+    path: &VaultPath,
+    case: PathCase,
+) -> Result<Option<VaultPath>, DBError> {
+    let sql = format!(
+        "SELECT DISTINCT basePath FROM notes WHERE basePathKey = ?1 OR {}",
+        in_directory("basePathKey", "?1")
+    );
+    let mut stmt = connection.prepare_cached(&sql)?;
+    let base_paths = stmt
+        .query_map([path.key(PathCase::Insensitive)], |row| {
+            row.get::<_, String>(0)
+        })?
+        .map(|el| el.map_err(DBError::DBError))
+        .collect::<Result<Vec<String>, DBError>>()?;
+    let depth = path.get_slices().len();
+    let mut candidates = vec![];
+    for base_path in base_paths {
+        let slices = VaultPath::from(base_path).get_slices();
+        let Some(directory) = slices.get(..depth) else {
+            continue;
+        };
+        let directory = VaultPath::from(directory.join("/"));
+        if directory.matches(path, case) && !candidates.contains(&directory) {
+            candidates.push(directory);
+        }
+    }
+    // Prefer the exact name if there's more than one
+    let exact = path.to_string();
+    Ok(candidates
+        .iter()
+        .find(|stored| stored.to_string() == exact)
+        .or(candidates.first())
+        .cloned())
+}
+
+/// The notes in the directory, matching its path with `case`
 pub fn get_notes(
     connection: &mut Connection,
     path: &VaultPath,
     recursive: bool,
+    case: PathCase,
 ) -> Result<Vec<(NoteEntryData, NoteDetails)>, DBError> {
+    // The keys are case insensitive and `LIKE` also matches other
+    // directories starting with the same name, we check the exact
+    // path once loaded
     let sql = if recursive {
-        "SELECT path, title, size, modified, hash, noteName FROM notes where basePath LIKE (?1 || '%')"
+        format!(
+            "SELECT {} FROM notes where basePathKey LIKE (?1 || '%')",
+            NOTE_COLUMNS
+        )
     } else {
-        "SELECT path, title, size, modified, hash, noteName FROM notes where basePath = ?1"
+        format!("SELECT {} FROM notes where basePathKey = ?1", NOTE_COLUMNS)
     };
-    let mut stmt = connection.prepare(sql)?;
+    let mut stmt = connection.prepare_cached(&sql)?;
     let res = stmt
-        .query_map([path.to_string()], |row| {
+        .query_map([path.key(PathCase::Insensitive)], |row| {
             let path: String = row.get(0)?;
             let title = row.get(1)?;
-            let size = row.get(2)?;
-            let modified = row.get(3)?;
             let hash: String = row.get(4)?;
             let note_path = VaultPath::from(&path);
-            let data = NoteEntryData {
-                path: note_path.clone(),
-                size,
-                modified_secs: modified,
-            };
+            let data = note_entry_data(&note_path, row)?;
             let det = NoteDetails::new(note_path, hash.parse().unwrap(), title, None);
             Ok((data, det))
         })?
         .map(|el| el.map_err(DBError::DBError))
+        .filter(|note| {
+            note.as_ref().map_or(true, |(data, _details)| {
+                if recursive {
+                    data.path.is_in(path, case)
+                } else {
+                    data.path.get_parent_path().0.matches(path, case)
+                }
+            })
+        })
         .collect::<Result<Vec<(NoteEntryData, NoteDetails)>, DBError>>()?;
     Ok(res)
 }
 
+/// The directories right inside the directory with notes in them, at
+/// any depth, sorted by name
+pub fn get_directories(
+    connection: &mut Connection,
+    path: &VaultPath,
+    case: PathCase,
+) -> Result<Vec<VaultPath>, DBError> {
+    let sql = "SELECT DISTINCT basePath FROM notes WHERE basePathKey LIKE (?1 || '%')";
+    let mut stmt = connection.prepare_cached(sql)?;
+    let base_paths = stmt
+        .query_map([path.key(PathCase::Insensitive)], |row| {
+            let base_path: String = row.get(0)?;
+            Ok(VaultPath::from(&base_path))
+        })?
+        .collect::<Result<Vec<VaultPath>, rusqlite::Error>>()?;
+
+    let depth = path.get_slices().len();
+    let mut directories: Vec<VaultPath> = vec![];
+    for base_path in base_paths {
+        if !base_path.is_in(path, case) {
+            continue;
+        }
+        let Some(name) = base_path.get_slices().into_iter().nth(depth) else {
+            continue;
+        };
+        let directory = path.append(&VaultPath::from(name));
+        if !directories
+            .iter()
+            .any(|listed| listed.matches(&directory, case))
+        {
+            directories.push(directory);
+        }
+    }
+    directories.sort_by_key(|directory| directory.key(PathCase::Insensitive));
+    Ok(directories)
+}
+
 pub fn insert_notes(
     tx: &Transaction,
-    notes: &Vec<(NoteEntryData, NoteDetails)>,
+    notes: &[(NoteEntryData, NoteDetails)],
 ) -> Result<(), DBError> {
     if !notes.is_empty() {
         debug!("Inserting {} notes", notes.len());
@@ -311,7 +566,7 @@ pub fn insert_notes(
 
 pub fn update_notes(
     tx: &Transaction,
-    notes: &Vec<(NoteEntryData, NoteDetails)>,
+    notes: &[(NoteEntryData, NoteDetails)],
 ) -> Result<(), DBError> {
     if !notes.is_empty() {
         debug!("Updating {} notes", notes.len());
@@ -322,7 +577,7 @@ pub fn update_notes(
     Ok(())
 }
 
-pub fn delete_notes(tx: &Transaction, paths: &Vec<VaultPath>) -> Result<(), DBError> {
+pub fn delete_notes(tx: &Transaction, paths: &[VaultPath]) -> Result<(), DBError> {
     if !paths.is_empty() {
         for path in paths {
             delete_note(tx, path)?;
@@ -353,21 +608,37 @@ fn insert_note(
     details: &NoteDetails,
 ) -> Result<(), DBError> {
     let (parent_path, name) = details.path.get_parent_path();
-    if let Err(e) = tx.execute(
-        "INSERT INTO notes (path, title, size, modified, hash, basePath, noteName) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![details.path.to_string(), details.get_title(), data.size, data.modified_secs, details.data.hash.to_string(), parent_path.to_string(), name],
-    ){
+    let stats = &details.data.stats;
+    let sql = "INSERT INTO notes (path, title, size, modified, hash, basePath, noteName, pathKey, basePathKey, created, words, characters, headings) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)";
+    if let Err(e) = tx.prepare_cached(sql)?.execute(params![
+        details.path.to_string(),
+        details.get_title(),
+        data.size,
+        data.modified_secs,
+        details.data.hash.to_string(),
+        parent_path.to_string(),
+        name,
+        details.path.key(PathCase::Insensitive),
+        parent_path.key(PathCase::Insensitive),
+        data.created_secs,
+        stats.words,
+        stats.characters,
+        stats.headings,
+    ]) {
         error!("Error inserting note: {}\nDetails: {}", e, details);
     }
     let content_data = &details.data;
     for chunk in &content_data.content_chunks {
         let breadcrumb = chunk.get_breadcrumb();
         let chunk_text = &chunk.text;
-        tx.execute(
-            "INSERT INTO notesContent (path, breadcrumb, text) VALUES (?1, ?2, ?3)",
-            params![details.path.to_string(), breadcrumb, chunk_text],
-        )?;
+        tx.prepare_cached("INSERT INTO notesContent (path, breadcrumb, text) VALUES (?1, ?2, ?3)")?
+            .execute(params![details.path.to_string(), breadcrumb, chunk_text])?;
     }
+    insert_tags(tx, &details.path, &content_data.tags)?;
+    insert_aliases(tx, &details.path, &content_data.aliases)?;
+    insert_tasks(tx, &details.path, &content_data.tasks)?;
+    insert_links(tx, &details.path, &content_data.links)?;
+    insert_headings(tx, &details.path, &content_data.headings)?;
 
     Ok(())
 }
@@ -380,40 +651,425 @@ fn update_note(
     let title = details.get_title();
     let hash = details.data.hash.to_string();
     let path = details.path.clone();
-    tx.execute(
-        "UPDATE notes SET title = ?2, size = ?3, modified = ?4, hash = ?5 WHERE path = ?1",
-        params![path.to_string(), title, data.size, data.modified_secs, hash],
-    )?;
+    let stats = &details.data.stats;
+    tx.prepare_cached(
+        "UPDATE notes SET title = ?2, size = ?3, modified = ?4, hash = ?5, created = ?6, words = ?7, characters = ?8, headings = ?9 WHERE path = ?1",
+    )?
+    .execute(params![
+        path.to_string(),
+        title,
+        data.size,
+        data.modified_secs,
+        hash,
+        data.created_secs,
+        stats.words,
+        stats.characters,
+        stats.headings,
+    ])?;
     let content_data = &details.data;
-    tx.execute(
-        "DELETE FROM notesContent WHERE path = ?1",
-        params![path.to_string()],
-    )?;
+    tx.prepare_cached("DELETE FROM notesContent WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM tags WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM aliases WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM tasks WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM taskHeadings WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM links WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM headings WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
     for chunk in &content_data.content_chunks {
         let breadcrumb = chunk.get_breadcrumb();
         let chunk_text = &chunk.text;
-        tx.execute(
-            "INSERT INTO notesContent (path, breadcrumb, text) VALUES (?1, ?2, ?3)",
-            params![details.path.to_string(), breadcrumb, chunk_text],
-        )?;
+        tx.prepare_cached("INSERT INTO notesContent (path, breadcrumb, text) VALUES (?1, ?2, ?3)")?
+            .execute(params![details.path.to_string(), breadcrumb, chunk_text])?;
     }
+    insert_tags(tx, &details.path, &content_data.tags)?;
+    insert_aliases(tx, &details.path, &content_data.aliases)?;
+    insert_tasks(tx, &details.path, &content_data.tasks)?;
+    insert_links(tx, &details.path, &content_data.links)?;
+    insert_headings(tx, &details.path, &content_data.headings)?;
 
     Ok(())
 }
 
 fn delete_note(tx: &Transaction, path: &VaultPath) -> Result<(), DBError> {
-    tx.execute(
-        "DELETE FROM notes WHERE path = ?1",
-        params![path.to_string()],
-    )?;
-    tx.execute(
-        "DELETE FROM notesContent WHERE path = ?1",
-        params![path.to_string()],
-    )?;
+    tx.prepare_cached("DELETE FROM notes WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM notesContent WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM tags WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM aliases WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM tasks WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM taskHeadings WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM links WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+    tx.prepare_cached("DELETE FROM headings WHERE path = ?1")?
+        .execute(params![path.to_string()])?;
+
+    Ok(())
+}
+
+fn insert_tags(tx: &Transaction, path: &VaultPath, tags: &Vec<String>) -> Result<(), DBError> {
+    for tag in tags {
+        tx.prepare_cached("INSERT INTO tags (path, tag) VALUES (?1, ?2)")?
+            .execute(params![path.to_string(), tag])?;
+    }
+    Ok(())
+}
 
+fn insert_aliases(
+    tx: &Transaction,
+    path: &VaultPath,
+    aliases: &Vec<String>,
+) -> Result<(), DBError> {
+    for alias in aliases {
+        tx.prepare_cached("INSERT INTO aliases (path, alias) VALUES (?1, ?2)")?
+            .execute(params![path.to_string(), alias])?;
+    }
+    Ok(())
+}
+
+fn insert_tasks(tx: &Transaction, path: &VaultPath, tasks: &Vec<NoteTask>) -> Result<(), DBError> {
+    for task in tasks {
+        tx.prepare_cached(
+            "INSERT INTO tasks (path, line, text, done, due) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?
+        .execute(params![
+            path.to_string(),
+            task.line,
+            task.text,
+            task.done,
+            task.due.as_ref().map(format_due_date),
+        ])?;
+        for (position, heading) in task.breadcrumb.iter().enumerate() {
+            tx.prepare_cached(
+                "INSERT INTO taskHeadings (path, line, position, text) VALUES (?1, ?2, ?3, ?4)",
+            )?
+            .execute(params![path.to_string(), task.line, position, heading])?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_links(tx: &Transaction, path: &VaultPath, links: &Vec<NoteLink>) -> Result<(), DBError> {
+    for link in links {
+        // Links to the same note are not stored
+        if link.target.is_empty() {
+            continue;
+        }
+        tx.prepare_cached("INSERT INTO links (path, target, name) VALUES (?1, ?2, ?3)")?
+            .execute(params![path.to_string(), link.target, link.name()])?;
+    }
+    Ok(())
+}
+
+fn insert_headings(
+    tx: &Transaction,
+    path: &VaultPath,
+    headings: &Vec<OutlineHeading>,
+) -> Result<(), DBError> {
+    for heading in headings {
+        tx.prepare_cached("INSERT INTO headings (path, level, text) VALUES (?1, ?2, ?3)")?
+            .execute(params![path.to_string(), heading.level, heading.text])?;
+    }
     Ok(())
 }
 
+/// The notes with links to a file with any of the names, which have
+/// to be in lowercase
+pub fn get_linking_notes(
+    connection: &mut Connection,
+    names: &[String],
+) -> Result<Vec<VaultPath>, DBError> {
+    let placeholders = vec!["?"; names.len()].join(", ");
+    let sql = format!(
+        "SELECT DISTINCT path FROM links WHERE name IN ({}) ORDER BY path",
+        placeholders
+    );
+    let mut stmt = connection.prepare(&sql)?;
+    let paths = stmt
+        .query_map(params_from_iter(names), |row| row.get::<_, String>(0))?
+        .map(|el| el.map(VaultPath::from).map_err(DBError::DBError))
+        .collect::<Result<Vec<VaultPath>, DBError>>()?;
+    Ok(paths)
+}
+
+/// The notes named as the link, with or without the extension,
+/// ignoring the case
+pub fn find_notes_by_name(
+    connection: &mut Connection,
+    name: &str,
+) -> Result<Vec<VaultPath>, DBError> {
+    let name = name.to_lowercase();
+    let pattern = format!(
+        "{}.%",
+        name.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+    let sql = "SELECT path, noteName FROM notes WHERE noteName = ?1 COLLATE NOCASE OR noteName LIKE ?2 ESCAPE '\\' ORDER BY path";
+    let mut stmt = connection.prepare_cached(sql)?;
+    let notes = stmt
+        .query_map(params![name, pattern], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .map(|el| el.map_err(DBError::DBError))
+        .collect::<Result<Vec<(String, String)>, DBError>>()?;
+    Ok(notes
+        .into_iter()
+        .filter(|(_path, note_name)| {
+            let note_name = note_name.to_lowercase();
+            note_name == name
+                || note_name
+                    .rsplit_once('.')
+                    .is_some_and(|(stem, _extension)| stem == name)
+        })
+        .map(|(path, _note_name)| VaultPath::from(path))
+        .collect())
+}
+
+/// The notes that may contain all the terms, from the content index.
+/// The parts of a word are looked up in the words of the index.
+/// Returns `None` if no term can be looked up, so all notes may match.
+pub fn find_text_candidates(
+    connection: &mut Connection,
+    terms: &[IndexTerm],
+) -> Result<Option<Vec<VaultPath>>, DBError> {
+    let mut query = vec![];
+    for term in terms {
+        let pattern = match term {
+            IndexTerm::Word(word) => {
+                query.push(format!("\"{}\"", word));
+                continue;
+            }
+            IndexTerm::Prefix(word) => {
+                query.push(format!("\"{}*\"", word));
+                continue;
+            }
+            IndexTerm::Suffix(word) => format!("%{}", word),
+            IndexTerm::Part(word) => format!("%{}%", word),
+        };
+        let sql = "SELECT term FROM notesTerms WHERE col = '*' AND term LIKE ?1 LIMIT ?2";
+        let mut stmt = connection.prepare_cached(sql)?;
+        let words = stmt
+            .query_map(params![pattern, MAX_PART_TERMS + 1], |row| {
+                row.get::<_, String>(0)
+            })?
+            .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+        if words.is_empty() {
+            // No note has it
+            return Ok(Some(vec![]));
+        }
+        if words.len() <= MAX_PART_TERMS {
+            let words = words
+                .iter()
+                .map(|word| format!("\"{}\"", word))
+                .collect::<Vec<String>>();
+            query.push(format!("({})", words.join(" OR ")));
+        }
+    }
+    if query.is_empty() {
+        return Ok(None);
+    }
+
+    let sql = "SELECT DISTINCT path FROM notesContent WHERE notesContent MATCH ?1";
+    let mut stmt = connection.prepare_cached(sql)?;
+    let paths = stmt
+        .query_map([query.join(" ")], |row| row.get::<_, String>(0))?
+        .map(|path| path.map(VaultPath::from).map_err(DBError::DBError))
+        .collect::<Result<Vec<VaultPath>, DBError>>()?;
+    Ok(Some(paths))
+}
+
+/// The headings with the text, with the note they are in and their breadcrumb
+pub fn search_headings(
+    connection: &mut Connection,
+    text: &str,
+    limit: usize,
+) -> Result<Vec<(VaultPath, Vec<String>)>, DBError> {
+    // All the headings of the notes with a match, to know the parents
+    // `instr` instead of `LIKE`, so `%` and `_` are not wildcards
+    let sql = "SELECT path, level, text, instr(lower(text), lower(?1)) > 0 FROM headings WHERE path IN (SELECT path FROM headings WHERE instr(lower(text), lower(?1)) > 0) ORDER BY path, rowid";
+    let mut stmt = connection.prepare_cached(sql)?;
+    let rows = stmt
+        .query_map(params![text], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, u8>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, bool>(3)?,
+            ))
+        })?
+        .map(|el| el.map_err(DBError::DBError));
+
+    let mut headings = vec![];
+    let mut current_path = String::new();
+    let mut parents: Vec<(u8, String)> = vec![];
+    for row in rows {
+        let (path, level, heading, matches) = row?;
+        if path != current_path {
+            current_path = path.clone();
+            parents.clear();
+        }
+        while parents
+            .last()
+            .is_some_and(|(parent_level, _)| *parent_level >= level)
+        {
+            parents.pop();
+        }
+        parents.push((level, heading));
+        if matches {
+            let breadcrumb = parents
+                .iter()
+                .map(|(_level, text)| text.to_owned())
+                .collect();
+            headings.push((VaultPath::from(path), breadcrumb));
+            if headings.len() >= limit {
+                break;
+            }
+        }
+    }
+    Ok(headings)
+}
+
+/// Returns the tasks matching the filter, the ones with a due date
+/// first, sorted by date, and then by note and line
+pub fn query_tasks(
+    connection: &mut Connection,
+    filter: &TaskFilter,
+    case: PathCase,
+) -> Result<Vec<(VaultPath, NoteTask)>, DBError> {
+    let mut conditions = vec![];
+    let mut params = vec![];
+    if let Some(done) = filter.done {
+        params.push(if done { "1" } else { "0" }.to_string());
+        conditions.push(format!("done = ?{}", params.len()));
+    }
+    if let Some(due_before) = &filter.due_before {
+        params.push(format_due_date(due_before));
+        conditions.push(format!("due <= ?{}", params.len()));
+    }
+    if let Some(directory) = &filter.directory {
+        // Checked once loaded, like in `get_notes`
+        params.push(directory.key(PathCase::Insensitive));
+        conditions.push(format!(
+            "path IN (SELECT path FROM notes WHERE basePathKey LIKE (?{} || '%'))",
+            params.len()
+        ));
+    }
+    let where_sql = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    let sql = format!(
+        "SELECT path, line, text, done, due FROM tasks {} ORDER BY due IS NULL, due, path, line",
+        where_sql
+    );
+    let mut stmt = connection.prepare_cached(&sql)?;
+    let mut res = stmt
+        .query_map(params_from_iter(params), |row| {
+            let path: String = row.get(0)?;
+            let due: Option<String> = row.get(4)?;
+            let task = NoteTask {
+                line: row.get(1)?,
+                text: row.get(2)?,
+                done: row.get(3)?,
+                breadcrumb: vec![],
+                due: due.as_deref().and_then(parse_due_date),
+            };
+            Ok((VaultPath::from(&path), task))
+        })?
+        .map(|el| el.map_err(DBError::DBError))
+        .filter(|task| {
+            task.as_ref().map_or(true, |(path, _task)| {
+                filter
+                    .directory
+                    .as_ref()
+                    .is_none_or(|directory| path.is_in(directory, case))
+            })
+        })
+        .collect::<Result<Vec<(VaultPath, NoteTask)>, DBError>>()?;
+
+    let sql = "SELECT text FROM taskHeadings WHERE path = ?1 AND line = ?2 ORDER BY position";
+    let mut stmt = connection.prepare_cached(sql)?;
+    for (path, task) in &mut res {
+        task.breadcrumb = stmt
+            .query_map(params![path.to_string(), task.line], |row| {
+                row.get::<_, String>(0)
+            })?
+            .map(|el| el.map_err(DBError::DBError))
+            .collect::<Result<Vec<String>, DBError>>()?;
+    }
+    Ok(res)
+}
+
+/// Returns all the notes in the vault with their title and aliases,
+/// the rest of their data is not loaded
+pub fn get_note_names(connection: &mut Connection) -> Result<Vec<NoteDetails>, DBError> {
+    let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+    let mut stmt = connection.prepare_cached("SELECT path, alias FROM aliases ORDER BY rowid")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    for row in rows {
+        let (path, alias): (String, String) = row?;
+        aliases.entry(path).or_default().push(alias);
+    }
+
+    let mut stmt = connection.prepare_cached("SELECT path, title, hash FROM notes")?;
+    let res = stmt
+        .query_map([], |row| {
+            let path: String = row.get(0)?;
+            let title = row.get(1)?;
+            let hash: String = row.get(2)?;
+            let mut details =
+                NoteDetails::new(VaultPath::from(&path), hash.parse().unwrap(), title, None);
+            details.data.aliases = aliases.remove(&path).unwrap_or_default();
+            Ok(details)
+        })?
+        .map(|el| el.map_err(DBError::DBError))
+        .collect::<Result<Vec<NoteDetails>, DBError>>()?;
+    Ok(res)
+}
+
+/// Returns all the tags in the vault, with the number of notes using them,
+/// the most used first
+pub fn get_tags(connection: &mut Connection) -> Result<Vec<(String, usize)>, DBError> {
+    let sql = "SELECT tag, count(*) AS uses FROM tags GROUP BY tag ORDER BY uses DESC, tag";
+    let mut stmt = connection.prepare_cached(sql)?;
+    let res = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .map(|el| el.map_err(DBError::DBError))
+        .collect::<Result<Vec<(String, usize)>, DBError>>()?;
+    Ok(res)
+}
+
+/// Returns the headings of a note in order, from the headings stored
+/// when indexing it
+pub fn get_headings(
+    connection: &mut Connection,
+    path: &VaultPath,
+    case: PathCase,
+) -> Result<Vec<String>, DBError> {
+    let Some(path) = find_note_path(connection, path, case)? else {
+        return Ok(vec![]);
+    };
+    let sql = "SELECT text FROM headings WHERE path = ?1 ORDER BY rowid";
+    let mut stmt = connection.prepare_cached(sql)?;
+    let headings = stmt
+        .query_map([path.to_string()], |row| row.get::<_, String>(0))?
+        .map(|el| el.map_err(DBError::DBError))
+        .collect::<Result<Vec<String>, DBError>>()?;
+    Ok(headings)
+}
+
 pub fn delete_directories(tx: &Transaction, directories: &Vec<VaultPath>) -> Result<(), DBError> {
     if !directories.is_empty() {
         for directory in directories {
@@ -425,23 +1081,386 @@ pub fn delete_directories(tx: &Transaction, directories: &Vec<VaultPath>) -> Res
 
 fn delete_directory(tx: &Transaction, directory_path: &VaultPath) -> Result<(), DBError> {
     let path_string = directory_path.to_string();
-    let sql1 = "DELETE FROM notes WHERE path LIKE (?1 || '%')";
-    let sql2 = "DELETE FROM notesContent WHERE path LIKE (?1 || '%')";
+    for table in TABLES_BY_PATH {
+        let sql = format!("DELETE FROM {} WHERE {}", table, in_directory("path", "?1"));
+        tx.execute(&sql, params![path_string])?;
+    }
 
-    tx.execute(sql1, params![path_string])?;
-    tx.execute(sql2, params![path_string])?;
+    Ok(())
+}
+
+/// Changes the paths of the notes in the directory to the new one
+pub fn rename_directory(tx: &Transaction, from: &VaultPath, to: &VaultPath) -> Result<(), DBError> {
+    let from_path = from.to_string();
+    let to_path = to.to_string();
+    let from_key = from.key(PathCase::Insensitive);
+    let to_key = to.key(PathCase::Insensitive);
+    for table in TABLES_BY_PATH {
+        let sql = format!(
+            "UPDATE {} SET path = ?2 || substr(path, length(?1) + 1) WHERE {}",
+            table,
+            in_directory("path", "?1")
+        );
+        tx.execute(&sql, params![from_path, to_path])?;
+    }
+    // The notes were moved above, the keys are changed by the paths
+    // they have now
+    tx.execute(
+        &format!(
+            "UPDATE notes SET pathKey = ?2 || substr(pathKey, length(?1) + 1) WHERE {}",
+            in_directory("path", "?3")
+        ),
+        params![from_key, to_key, to_path],
+    )?;
+    tx.execute(
+        &format!(
+            "UPDATE notes SET basePath = ?2 || substr(basePath, length(?1) + 1), basePathKey = ?4 || substr(basePathKey, length(?3) + 1) WHERE basePath = ?1 OR {}",
+            in_directory("basePath", "?1")
+        ),
+        params![from_path, to_path, from_key, to_key],
+    )?;
 
     Ok(())
 }
 
+/// A condition for the paths in the column inside the directory in the
+/// param. Compares the prefix as is, `LIKE` would take `_` and `%` in
+/// the name as wildcards and ignore the case.
+fn in_directory(column: &str, param: &str) -> String {
+    format!(
+        "substr({0}, 1, length({1}) + 1) = ({1} || '/')",
+        column, param
+    )
+}
+
+/// The default location of the index for a vault, in the user's cache
+/// directory so it is not synced with the notes. Each vault gets its own
+/// directory, keyed by a hash of the vault path.
+pub fn default_db_path<P: AsRef<Path>>(workspace_path: P) -> PathBuf {
+    let workspace_path = workspace_path.as_ref();
+    let workspace_path = workspace_path
+        .canonicalize()
+        .unwrap_or_else(|_| workspace_path.to_path_buf());
+    let hash = gxhash64(workspace_path.to_string_lossy().as_bytes(), 0);
+    let name = workspace_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(CACHE_DIR)
+        .join(format!("{}-{:016x}", name, hash))
+        .join(DB_FILE)
+}
+
+/// Older versions kept the DB in the root of the vault, moves it to the
+/// new location so the index doesn't need to be rebuilt.
+/// Returns true if there was a DB to move.
+pub fn move_legacy_db<P: AsRef<Path>, Q: AsRef<Path>>(
+    workspace_path: P,
+    db_path: Q,
+) -> Result<bool, DBError> {
+    let legacy_path = workspace_path.as_ref().join(DB_FILE);
+    let db_path = db_path.as_ref();
+    if !legacy_path.is_file() || legacy_path == db_path {
+        return Ok(false);
+    }
+    if db_path.exists() {
+        warn!(
+            "There is a DB at {} and another one in the vault at {}, keeping both",
+            db_path.display(),
+            legacy_path.display()
+        );
+        return Ok(false);
+    }
+    info!(
+        "Moving the DB from {} to {}",
+        legacy_path.display(),
+        db_path.display()
+    );
+    let move_file = |from: &Path, to: &Path| -> std::io::Result<()> {
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Rename fails between file systems, so we copy instead
+        if std::fs::rename(from, to).is_err() {
+            std::fs::copy(from, to)?;
+            std::fs::remove_file(from)?;
+        }
+        Ok(())
+    };
+    let map_error = |e: std::io::Error| DBError::Other(format!("Can't move the DB: {}", e));
+    // The WAL may contain data not yet in the DB file, moved first so an
+    // interrupted move doesn't leave a DB without it
+    for suffix in DB_SIDE_FILES {
+        let from = side_file(&legacy_path, suffix);
+        if from.is_file() {
+            move_file(&from, &side_file(db_path, suffix)).map_err(map_error)?;
+        }
+    }
+    move_file(&legacy_path, db_path).map_err(map_error)?;
+    Ok(true)
+}
+
+/// Removes the DB with the files SQLite keeps next to it, a WAL left
+/// behind would be replayed into a new DB at the same path
+pub fn remove_db_files<P: AsRef<Path>>(db_path: P) -> std::io::Result<()> {
+    let db_path = db_path.as_ref();
+    for suffix in DB_SIDE_FILES {
+        let side_file = side_file(db_path, suffix);
+        if side_file.is_file() {
+            std::fs::remove_file(side_file)?;
+        }
+    }
+    std::fs::remove_file(db_path)
+}
+
+fn side_file(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
 pub struct ConnectionBuilder {}
 
 impl ConnectionBuilder {
-    pub fn build<P: AsRef<Path>>(workspace_path: P) -> Result<Connection, DBError> {
-        // debug!("Opening Database");
-        let db_path = workspace_path.as_ref().join(DB_FILE);
-        let connection = Connection::open(&db_path)?;
+    pub fn build<P: AsRef<Path>>(db_path: P) -> Result<Connection, DBError> {
+        debug!("Opening Database");
+        let db_path = db_path.as_ref();
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| DBError::Other(format!("Can't create the DB directory: {}", e)))?;
+        }
+        let connection = Connection::open(db_path)?;
         let _c = connection.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FTS3_TOKENIZER, true)?;
+        // With WAL the readers don't block the writer and the other way around
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
         Ok(connection)
     }
+
+    /// Builds a connection that can't modify the DB
+    pub fn build_reader<P: AsRef<Path>>(db_path: P) -> Result<Connection, DBError> {
+        let connection = Self::build(db_path)?;
+        connection.pragma_update(None, "query_only", true)?;
+        Ok(connection)
+    }
+}
+
+#[cfg(test)]
+mod vault_db_tests {
+    use std::path::Path;
+
+    use super::{
+        default_db_path, move_legacy_db, remove_db_files, VaultDB, DB_FILE, DB_SIDE_FILES,
+    };
+    use crate::{error::DBError, test_utils::TestDir};
+
+    fn count_rows(db: &VaultDB) -> Result<i64, DBError> {
+        db.call_read(|conn| Ok(conn.query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))?))
+    }
+
+    fn write_db_files(db_path: &Path, content: &str) {
+        std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+        std::fs::write(db_path, content).unwrap();
+        std::fs::write(format!("{}-wal", db_path.display()), "wal").unwrap();
+        std::fs::write(format!("{}-shm", db_path.display()), "shm").unwrap();
+    }
+
+    #[test]
+    fn default_db_path_is_outside_the_vault() {
+        let base = TestDir::new("db_default_path");
+        let workspace = base.join("my vault");
+        std::fs::create_dir_all(workspace.join("inner")).unwrap();
+
+        let db_path = default_db_path(&workspace);
+        assert!(!db_path.starts_with(&workspace));
+        assert!(db_path.ends_with(DB_FILE));
+        let dir_name = db_path.parent().unwrap().file_name().unwrap();
+        assert!(dir_name.to_string_lossy().starts_with("my vault-"));
+        // The same vault written another way gets the same DB
+        assert_eq!(db_path, default_db_path(workspace.join("inner/..")));
+        assert_ne!(db_path, default_db_path(workspace.join("inner")));
+    }
+
+    #[test]
+    fn legacy_db_is_moved_with_the_side_files() {
+        let base = TestDir::new("db_move_legacy");
+        let workspace = base.join("vault");
+        let legacy_path = workspace.join(DB_FILE);
+        write_db_files(&legacy_path, "db");
+        let db_path = base.join("cache/vault/notes.sqlite");
+
+        assert!(move_legacy_db(&workspace, &db_path).unwrap());
+        assert_eq!("db", std::fs::read_to_string(&db_path).unwrap());
+        for (suffix, content) in [("-wal", "wal"), ("-shm", "shm")] {
+            let moved = format!("{}{}", db_path.display(), suffix);
+            assert_eq!(content, std::fs::read_to_string(moved).unwrap());
+            assert!(!Path::new(&format!("{}{}", legacy_path.display(), suffix)).exists());
+        }
+        assert!(!legacy_path.exists());
+        // Nothing left to move
+        assert!(!move_legacy_db(&workspace, &db_path).unwrap());
+    }
+
+    #[test]
+    fn legacy_db_is_kept_if_there_is_a_db() {
+        let base = TestDir::new("db_keep_legacy");
+        let workspace = base.join("vault");
+        let legacy_path = workspace.join(DB_FILE);
+        write_db_files(&legacy_path, "legacy");
+        let db_path = base.join("cache/vault/notes.sqlite");
+        write_db_files(&db_path, "current");
+
+        assert!(!move_legacy_db(&workspace, &db_path).unwrap());
+        assert_eq!("legacy", std::fs::read_to_string(&legacy_path).unwrap());
+        assert_eq!("current", std::fs::read_to_string(&db_path).unwrap());
+    }
+
+    #[test]
+    fn legacy_db_at_the_db_path_is_not_moved() {
+        let base = TestDir::new("db_legacy_in_place");
+        let workspace = base.join("vault");
+        let legacy_path = workspace.join(DB_FILE);
+        write_db_files(&legacy_path, "db");
+
+        assert!(!move_legacy_db(&workspace, &legacy_path).unwrap());
+        assert_eq!("db", std::fs::read_to_string(&legacy_path).unwrap());
+        assert!(Path::new(&format!("{}-wal", legacy_path.display())).exists());
+    }
+
+    #[test]
+    fn remove_db_with_the_side_files() {
+        let base = TestDir::new("db_remove");
+        let db_path = base.join("notes.sqlite");
+        write_db_files(&db_path, "db");
+
+        remove_db_files(&db_path).unwrap();
+        assert!(!db_path.exists());
+        for suffix in DB_SIDE_FILES {
+            assert!(!Path::new(&format!("{}{}", db_path.display(), suffix)).exists());
+        }
+    }
+
+    #[test]
+    fn read_while_writing() {
+        let base = TestDir::new("db_read_while_writing");
+        let db = VaultDB::new(base.join("notes.sqlite"));
+        db.call(|conn| {
+            conn.execute_batch("CREATE TABLE items (name TEXT); INSERT INTO items VALUES ('a');")?;
+            Ok::<(), DBError>(())
+        })
+        .unwrap();
+
+        db.call(|conn| {
+            let tx = conn.transaction()?;
+            tx.execute("INSERT INTO items VALUES ('b')", [])?;
+            // The reader doesn't wait for the writer, and sees the
+            // last commit
+            assert_eq!(1, count_rows(&db)?);
+            tx.commit()?;
+            Ok::<(), DBError>(())
+        })
+        .unwrap();
+        assert_eq!(2, count_rows(&db).unwrap());
+    }
+
+    #[test]
+    fn reconnect_after_closing() {
+        let base = TestDir::new("db_reconnect");
+        let db_path = base.join("notes.sqlite");
+        let db = VaultDB::new(&db_path);
+        db.call(|conn| {
+            conn.execute_batch("CREATE TABLE items (name TEXT); INSERT INTO items VALUES ('a');")?;
+            Ok::<(), DBError>(())
+        })
+        .unwrap();
+        assert_eq!(1, count_rows(&db).unwrap());
+
+        db.close_connections();
+        std::fs::remove_file(&db_path).unwrap();
+        for suffix in DB_SIDE_FILES {
+            let _ = std::fs::remove_file(format!("{}{}", db_path.display(), suffix));
+        }
+
+        // The connections are opened again, on the new file
+        db.call(|conn| {
+            conn.execute_batch("CREATE TABLE items (name TEXT);")?;
+            Ok::<(), DBError>(())
+        })
+        .unwrap();
+        assert!(db_path.exists());
+        assert_eq!(0, count_rows(&db).unwrap());
+    }
+}
+
+#[cfg(test)]
+mod query_tests {
+    use rusqlite::Connection;
+
+    use super::{find_text_candidates, search_headings};
+    use crate::{
+        find_replace::{FindOptions, TextFinder},
+        nfs::VaultPath,
+        test_utils::{db_path, indexed_vault},
+    };
+
+    #[test]
+    fn text_candidates_from_the_index() {
+        let (dir, _vault) = indexed_vault(&[
+            ("hello.md", "Hello big world"),
+            ("yellow.md", "# Yellow\n\nA color"),
+            ("other.md", "Nothing here"),
+        ]);
+        let mut conn = Connection::open(db_path(&dir)).unwrap();
+        let candidates = |conn: &mut Connection, pattern: &str| {
+            let terms = TextFinder::new(pattern, FindOptions::default())
+                .unwrap()
+                .index_terms()
+                .unwrap();
+            find_text_candidates(conn, &terms)
+                .unwrap()
+                .map(|mut paths| {
+                    paths.sort_by_key(|path| path.to_string());
+                    paths
+                })
+        };
+
+        assert_eq!(
+            Some(vec![
+                VaultPath::from("hello.md"),
+                VaultPath::from("yellow.md")
+            ]),
+            candidates(&mut conn, "ELLO")
+        );
+        assert_eq!(
+            Some(vec![VaultPath::from("hello.md")]),
+            candidates(&mut conn, "lo big wo")
+        );
+        assert_eq!(Some(vec![]), candidates(&mut conn, "xyz"));
+    }
+
+    #[test]
+    fn headings_with_wildcards() {
+        let (dir, _vault) = indexed_vault(&[
+            ("a.md", "# My_notes\n\n## 100% done"),
+            ("b.md", "# Other notes"),
+        ]);
+        let mut conn = Connection::open(db_path(&dir)).unwrap();
+
+        assert_eq!(
+            vec![(VaultPath::from("a.md"), vec!["My_notes".to_string()])],
+            search_headings(&mut conn, "_", 10).unwrap()
+        );
+        assert_eq!(
+            vec![(
+                VaultPath::from("a.md"),
+                vec!["My_notes".to_string(), "100% done".to_string()]
+            )],
+            search_headings(&mut conn, "0%", 10).unwrap()
+        );
+        assert_eq!(2, search_headings(&mut conn, "NOTES", 10).unwrap().len());
+    }
 }
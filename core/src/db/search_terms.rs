@@ -4,12 +4,106 @@ const IN_CHAR: &str = ">";
 const IN_LETTER: &str = "in";
 const AT_CHAR: &str = "@";
 const AT_LETTER: &str = "at";
+const TASK_LETTER: &str = "task";
+const SORT_LETTER: &str = "sort";
+/// Sorting by these goes from the highest value
+const DESCENDING_CHAR: &str = "-";
 
 enum ElementType {
     Invalid,
     Term,
     In,
     At,
+    Task,
+    Stat(Stat),
+    Sort,
+}
+
+/// The counts of the note that can be filtered, as in `words:>500`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stat {
+    Words,
+    Characters,
+    Headings,
+    Links,
+}
+
+impl Stat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "words" => Some(Stat::Words),
+            "characters" | "chars" => Some(Stat::Characters),
+            "headings" => Some(Stat::Headings),
+            "links" => Some(Stat::Links),
+            _ => None,
+        }
+    }
+
+    /// The value in a query over the `notes` table
+    pub fn sql(&self) -> &'static str {
+        match self {
+            Stat::Words => "words",
+            Stat::Characters => "characters",
+            Stat::Headings => "headings",
+            Stat::Links => "(SELECT count(*) FROM links WHERE links.path = notes.path)",
+        }
+    }
+}
+
+/// A count compared with a number, `>500`, `<=10` or just `3`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatFilter {
+    pub stat: Stat,
+    pub operator: &'static str,
+    pub value: u64,
+}
+
+impl StatFilter {
+    fn parse(stat: Stat, comparison: &str) -> Option<Self> {
+        let (operator, value) = [">=", "<=", ">", "<", "="]
+            .into_iter()
+            .find_map(|operator| {
+                comparison
+                    .strip_prefix(operator)
+                    .map(|value| (operator, value))
+            })
+            .unwrap_or(("=", comparison));
+        let value = value.trim().parse().ok()?;
+        Some(Self {
+            stat,
+            operator,
+            value,
+        })
+    }
+}
+
+/// How to sort the results, `sort:words` or `sort:-modified`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortBy {
+    /// The column of the `notes` table
+    pub column: &'static str,
+    pub descending: bool,
+}
+
+impl SortBy {
+    fn parse(sort: &str) -> Option<Self> {
+        let (descending, name) = match sort.strip_prefix(DESCENDING_CHAR) {
+            Some(name) => (true, name),
+            None => (false, sort),
+        };
+        let column = match name {
+            "title" => "title COLLATE NOCASE",
+            "path" => "pathKey",
+            "modified" => "modified",
+            "created" => "created",
+            "size" => "size",
+            "words" => "words",
+            "characters" | "chars" => "characters",
+            "headings" => "headings",
+            _ => return None,
+        };
+        Some(Self { column, descending })
+    }
 }
 
 struct QueryTermExtractor {
@@ -23,6 +117,11 @@ impl QueryTermExtractor {
         let query = query.as_ref().trim();
         let in_prefix = format!("{}:", IN_LETTER);
         let at_prefix = format!("{}:", AT_LETTER);
+        let task_prefix = format!("{}:", TASK_LETTER);
+        let sort_prefix = format!("{}:", SORT_LETTER);
+        let stat_prefix = query
+            .split_once(':')
+            .and_then(|(name, remaining)| Stat::from_name(name).map(|stat| (stat, remaining)));
 
         let (element_type, remaining) = if query.starts_with(&in_prefix) {
             (
@@ -52,6 +151,22 @@ impl QueryTermExtractor {
                     .strip_prefix(AT_CHAR)
                     .map_or_else(|| query.to_string(), |s| s.to_string()),
             )
+        } else if query.starts_with(&task_prefix) {
+            (
+                ElementType::Task,
+                query
+                    .strip_prefix(&task_prefix)
+                    .map_or_else(|| query.to_string(), |s| s.to_string()),
+            )
+        } else if query.starts_with(&sort_prefix) {
+            (
+                ElementType::Sort,
+                query
+                    .strip_prefix(&sort_prefix)
+                    .map_or_else(|| query.to_string(), |s| s.to_string()),
+            )
+        } else if let Some((stat, remaining)) = stat_prefix {
+            (ElementType::Stat(stat), remaining.to_string())
         } else {
             (ElementType::Term, query.to_string())
         };
@@ -105,6 +220,11 @@ pub struct SearchTerms {
     pub terms: Vec<String>,
     pub breadcrumb: Vec<String>,
     pub path: Vec<String>,
+    /// The state of the tasks the notes have, `open` or `done`
+    pub tasks: Vec<String>,
+    pub stats: Vec<StatFilter>,
+    /// The last sort in the query is the one used
+    pub sort: Option<SortBy>,
 }
 
 impl SearchTerms {
@@ -113,6 +233,9 @@ impl SearchTerms {
         let mut breadcrumb = vec![];
         let mut terms = vec![];
         let mut path = vec![];
+        let mut tasks = vec![];
+        let mut stats = vec![];
+        let mut sort = None;
         while !query.is_empty() {
             let qp = QueryTermExtractor::extract_and_consume(query);
             query = qp.remainder;
@@ -120,6 +243,15 @@ impl SearchTerms {
                 ElementType::Term => terms.push(qp.term),
                 ElementType::In => breadcrumb.push(qp.term),
                 ElementType::At => path.push(qp.term),
+                ElementType::Task => tasks.push(qp.term.to_lowercase()),
+                ElementType::Stat(stat) => match StatFilter::parse(stat, &qp.term) {
+                    Some(filter) => stats.push(filter),
+                    None => debug!("Invalid filter: {}", qp.term),
+                },
+                ElementType::Sort => match SortBy::parse(&qp.term.to_lowercase()) {
+                    Some(sort_by) => sort = Some(sort_by),
+                    None => debug!("Unknown sort: {}", qp.term),
+                },
                 ElementType::Invalid => {}
             }
         }
@@ -128,13 +260,16 @@ impl SearchTerms {
             breadcrumb,
             path,
             terms,
+            tasks,
+            stats,
+            sort,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::SearchTerms;
+    use super::{SearchTerms, SortBy, Stat, StatFilter};
 
     #[test]
     fn search_terms() {
@@ -227,6 +362,53 @@ mod tests {
         assert!(path.contains(&"file name".to_string()));
     }
 
+    #[test]
+    fn search_tasks() {
+        let query = "task:open meeting task:Done";
+        let search_terms = SearchTerms::from_query_string(query);
+
+        assert_eq!(vec!["meeting".to_string()], search_terms.terms);
+        assert_eq!(
+            vec!["open".to_string(), "done".to_string()],
+            search_terms.tasks
+        );
+    }
+
+    #[test]
+    fn search_stats() {
+        let query = "words:>500 chars:<=40 links:2 headings:many sort:-words recipe";
+        let search_terms = SearchTerms::from_query_string(query);
+
+        assert_eq!(vec!["recipe".to_string()], search_terms.terms);
+        assert_eq!(
+            vec![
+                StatFilter {
+                    stat: Stat::Words,
+                    operator: ">",
+                    value: 500
+                },
+                StatFilter {
+                    stat: Stat::Characters,
+                    operator: "<=",
+                    value: 40
+                },
+                StatFilter {
+                    stat: Stat::Links,
+                    operator: "=",
+                    value: 2
+                },
+            ],
+            search_terms.stats
+        );
+        assert_eq!(
+            Some(SortBy {
+                column: "words",
+                descending: true
+            }),
+            search_terms.sort
+        );
+    }
+
     #[test]
     fn search_combined() {
         let query = "searchterm    @file otherterm at:directory in:title >text      \"some text\"";
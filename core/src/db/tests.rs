@@ -1,5 +1,7 @@
 use std::fmt::Display;
 
+use rusqlite::{ffi, ErrorCode};
+
 use crate::{db::async_db::AsyncConnection, error::DBError};
 
 #[tokio::test]
@@ -19,7 +21,7 @@ async fn call_success_test() -> Result<(), DBError> {
                 "CREATE TABLE person(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL);",
                 [],
             )
-            .map_err(|e| e.into())
+            .map_err(DBError::from)
         })
         .await;
 
@@ -29,20 +31,23 @@ async fn call_success_test() -> Result<(), DBError> {
 }
 
 #[tokio::test]
-async fn call_unwrap_success_test() -> Result<(), DBError> {
+async fn call_with_value_test() -> Result<(), DBError> {
     let conn = AsyncConnection::open_in_memory().await?;
 
     let result = conn
-        .call_unwrap(|conn| {
+        .call(|conn| {
             conn.execute(
                 "CREATE TABLE person(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL);",
                 [],
-            )
-            .unwrap()
+            )?;
+            conn.execute("INSERT INTO person (name) VALUES ('Ana'), ('Bill')", [])?;
+            let count: usize =
+                conn.query_row("SELECT COUNT(*) FROM person", [], |row| row.get(0))?;
+            Ok::<usize, DBError>(count)
         })
-        .await;
+        .await?;
 
-    assert_eq!(0, result);
+    assert_eq!(2, result);
 
     Ok(())
 }
@@ -52,11 +57,11 @@ async fn call_failure_test() -> Result<(), DBError> {
     let conn = AsyncConnection::open_in_memory().await?;
 
     let result = conn
-        .call(|conn| conn.execute("Invalid sql", []).map_err(|e| e.into()))
+        .call(|conn| conn.execute("Invalid sql", []).map_err(DBError::from))
         .await;
 
     assert!(match result.unwrap_err() {
-        crate::Error::Rusqlite(e) => {
+        DBError::DBError(e) => {
             e == rusqlite::Error::SqlInputError {
                 error: ffi::Error {
                     code: ErrorCode::Unknown,
@@ -103,30 +108,35 @@ async fn close_call_test() -> Result<(), DBError> {
     assert!(conn.close().await.is_ok());
 
     let result = conn2
-        .call(|conn| conn.execute("SELECT 1;", []).map_err(|e| e.into()))
+        .call(|conn| conn.execute("SELECT 1;", []).map_err(DBError::from))
         .await;
 
-    assert!(matches!(
-        result.unwrap_err(),
-        crate::Error::ConnectionClosed
-    ));
+    assert!(matches!(result.unwrap_err(), DBError::DBConnectionClosed));
 
     Ok(())
 }
 
 #[tokio::test]
-#[should_panic]
-async fn close_call_unwrap_test() {
-    let conn = AsyncConnection::open_in_memory().await.unwrap();
+async fn close_call_custom_error_test() -> Result<(), DBError> {
+    let conn = AsyncConnection::open_in_memory().await?;
 
     let conn2 = conn.clone();
 
     assert!(conn.close().await.is_ok());
 
-    conn2
-        .call_unwrap(|conn| conn.execute("SELECT 1;", []))
-        .await
-        .unwrap();
+    let result = conn2
+        .call(|conn| {
+            conn.execute("SELECT 1;", []).map_err(DBError::from)?;
+            Ok::<(), MyError>(())
+        })
+        .await;
+
+    assert!(matches!(
+        result.unwrap_err(),
+        MyError::DB(DBError::DBConnectionClosed)
+    ));
+
+    Ok(())
 }
 
 #[tokio::test]
@@ -138,7 +148,7 @@ async fn close_failure_test() -> Result<(), DBError> {
             "CREATE TABLE person(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL);",
             [],
         )
-        .map_err(|e| e.into())
+        .map_err(DBError::from)
     })
     .await?;
 
@@ -147,12 +157,12 @@ async fn close_failure_test() -> Result<(), DBError> {
         // See https://www.sqlite.org/c3ref/close.html for details regarding this behaviour
         let stmt = Box::new(conn.prepare("INSERT INTO person VALUES (1, ?1);").unwrap());
         Box::leak(stmt);
-        Ok(())
+        Ok::<(), DBError>(())
     })
     .await?;
 
     assert!(match conn.close().await.unwrap_err() {
-        crate::Error::Close((_, e)) => {
+        DBError::DBError(e) => {
             e == rusqlite::Error::SqliteFailure(
                 ffi::Error {
                     code: ErrorCode::DatabaseBusy,
@@ -174,58 +184,33 @@ async fn close_failure_test() -> Result<(), DBError> {
 async fn debug_format_test() -> Result<(), DBError> {
     let conn = AsyncConnection::open_in_memory().await?;
 
-    assert_eq!("Connection".to_string(), format!("{conn:?}"));
+    assert_eq!("AsyncConnection".to_string(), format!("{conn:?}"));
 
     Ok(())
 }
 
-// #[tokio::test]
-// async fn test_error_display() -> Result<(), DBError> {
-//     let conn = AsyncConnection::open_in_memory().await?;
-//
-//     let error = crate::Error::Close((conn, rusqlite::Error::InvalidQuery));
-//     assert_eq!(
-//         "Close((Connection, \"Query is not read-only\"))",
-//         format!("{error}")
-//     );
-//
-//     let error = crate::Error::ConnectionClosed;
-//     assert_eq!("ConnectionClosed", format!("{error}"));
-//
-//     let error = crate::Error::Rusqlite(rusqlite::Error::InvalidQuery);
-//     assert_eq!("Rusqlite(\"Query is not read-only\")", format!("{error}"));
-//
-//     Ok(())
-// }
-
-// #[tokio::test]
-// async fn test_error_source() -> Result<(), DBError> {
-//     let conn = AsyncConnection::open_in_memory().await?;
-//
-//     let error = crate::Error::Close((conn, rusqlite::Error::InvalidQuery));
-//     assert_eq!(
-//         std::error::Error::source(&error)
-//             .and_then(|e| e.downcast_ref::<rusqlite::Error>())
-//             .unwrap(),
-//         &rusqlite::Error::InvalidQuery,
-//     );
-//
-//     let error = crate::Error::ConnectionClosed;
-//     assert_eq!(
-//         std::error::Error::source(&error).and_then(|e| e.downcast_ref::<rusqlite::Error>()),
-//         None,
-//     );
-//
-//     let error = crate::Error::Rusqlite(rusqlite::Error::InvalidQuery);
-//     assert_eq!(
-//         std::error::Error::source(&error)
-//             .and_then(|e| e.downcast_ref::<rusqlite::Error>())
-//             .unwrap(),
-//         &rusqlite::Error::InvalidQuery,
-//     );
-//
-//     Ok(())
-// }
+#[test]
+fn error_display_test() {
+    let error = DBError::DBConnectionClosed;
+    assert_eq!("Error DB Connection Closed", format!("{error}"));
+
+    let error = DBError::DBError(rusqlite::Error::InvalidQuery);
+    assert_eq!("Database Error: Query is not read-only", format!("{error}"));
+}
+
+#[test]
+fn error_source_test() {
+    let error = DBError::DBError(rusqlite::Error::InvalidQuery);
+    assert_eq!(
+        std::error::Error::source(&error)
+            .and_then(|e| e.downcast_ref::<rusqlite::Error>())
+            .unwrap(),
+        &rusqlite::Error::InvalidQuery,
+    );
+
+    let error = DBError::DBConnectionClosed;
+    assert!(std::error::Error::source(&error).is_none());
+}
 
 fn failable_func(_: &rusqlite::Connection) -> std::result::Result<(), MyError> {
     Err(MyError::MySpecificError)
@@ -235,16 +220,9 @@ fn failable_func(_: &rusqlite::Connection) -> std::result::Result<(), MyError> {
 async fn test_ergonomic_errors() -> Result<(), DBError> {
     let conn = AsyncConnection::open_in_memory().await?;
 
-    let res = conn
-        .call(|conn| failable_func(conn).map_err(|e| DBError::Other(format!("{}", e))))
-        .await
-        .unwrap_err();
-
-    let err = std::error::Error::source(&res)
-        .and_then(|e| e.downcast_ref::<MyError>())
-        .unwrap();
+    let res = conn.call(|conn| failable_func(conn)).await.unwrap_err();
 
-    assert!(matches!(err, MyError::MySpecificError));
+    assert!(matches!(res, MyError::MySpecificError));
 
     Ok(())
 }
@@ -254,16 +232,22 @@ async fn test_ergonomic_errors() -> Result<(), DBError> {
 #[derive(Debug)]
 enum MyError {
     MySpecificError,
+    DB(DBError),
 }
 
-impl Display for MyError {
-    fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        Ok(())
+impl From<DBError> for MyError {
+    fn from(value: DBError) -> Self {
+        MyError::DB(value)
     }
 }
 
-impl std::error::Error for MyError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+impl Display for MyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MyError::MySpecificError => write!(f, "My specific error"),
+            MyError::DB(e) => write!(f, "{}", e),
+        }
     }
 }
+
+impl std::error::Error for MyError {}
@@ -2,8 +2,6 @@ use thiserror::Error;
 
 use crate::nfs::VaultPath;
 
-// use super::db::async_sqlite::Command;
-
 #[derive(Error, Debug)]
 pub enum VaultError {
     #[error("Path {path} doesn't exist")]
@@ -16,6 +14,22 @@ pub enum VaultError {
     FSError(#[from] FSError),
     #[error("Note already exists at: {path}")]
     NoteExists { path: VaultPath },
+    #[error("Directory already exists at: {path}")]
+    DirectoryExists { path: VaultPath },
+    #[error("Invalid search pattern: {0}")]
+    InvalidPattern(#[from] regex::Error),
+    #[error("Indexing cancelled")]
+    IndexCancelled,
+    #[error("Invalid vault config: {0}")]
+    InvalidConfig(String),
+    #[error("The task is not in {path} anymore")]
+    TaskNotFound { path: VaultPath },
+    #[error("Can't import the notes: {0}")]
+    InvalidImport(String),
+    #[error("There is no saved search named {name}")]
+    SavedSearchNotFound { name: String },
+    #[error("Server Error: {0}")]
+    ServerError(String),
 }
 
 #[derive(Error, Debug)]
@@ -0,0 +1,810 @@
+//! Renders notes to HTML files that can be shared or published as a
+//! static site, with the links between notes pointing to their pages
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use log::{debug, warn};
+use pulldown_cmark::{html, CowStr, Event, LinkType, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+
+use crate::{
+    content_data::remove_frontmatter,
+    error::{FSError, VaultError},
+    indexer::SkippedFile,
+    nfs::{
+        load_note,
+        note_types::{NoteFormat, NoteTypes},
+        PathCase, VaultPath,
+    },
+    NoteDetails,
+};
+
+const INDEX_PAGE: &str = "index.html";
+const TAGS_DIR: &str = "tags";
+const HTML_EXTENSION: &str = "html";
+/// `[[note]]`, `[[note#heading]]`, `[[note|label]]` or `[[note#heading|label]]`
+const WIKI_LINK_REGEX: &str = r"\[\[([^\[\]|#]*)(?:#([^\[\]|]*))?(?:\|([^\[\]]*))?\]\]";
+const STYLE: &str = "body{max-width:48rem;margin:0 auto;padding:1rem 1.5rem;\
+font-family:system-ui,sans-serif;line-height:1.6;color:#222}\
+nav,footer{font-size:.9rem;color:#666}a{color:#0b62a4}\
+pre,code{background:#f4f4f4;border-radius:3px}pre{padding:.75rem;overflow:auto}\
+img{max-width:100%}table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:.25rem .5rem}\
+.broken{color:#a33}.path{color:#888;font-size:.85rem}";
+
+/// What was exported, and what couldn't be
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    pub notes: usize,
+    pub attachments: usize,
+    pub tags: usize,
+    /// Links to notes or files that are not part of the export, they are
+    /// kept as they are in the note
+    pub broken_links: Vec<BrokenLink>,
+    /// Notes and attachments that couldn't be read
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// A link in a note that doesn't point to an exported note or attachment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    pub note: VaultPath,
+    pub link: String,
+}
+
+impl Display for BrokenLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.note, self.link)
+    }
+}
+
+/// Where the export writes its files
+pub(crate) enum ExportTarget {
+    /// A single page for the note
+    Note(VaultPath),
+    /// A page for each note in the directory and its subdirectories,
+    /// with an index page and a page for each tag
+    Site {
+        directory: VaultPath,
+        notes: Vec<VaultPath>,
+    },
+}
+
+struct ExportNote {
+    details: NoteDetails,
+    format: NoteFormat,
+    text: String,
+    /// The page of the note, relative to the destination
+    page: VaultPath,
+}
+
+struct Exporter<'a> {
+    workspace_path: &'a Path,
+    destination: PathBuf,
+    types: &'a NoteTypes,
+    case: PathCase,
+    /// Paths are relative to this directory in the destination
+    base: VaultPath,
+    site: bool,
+    /// The page of each note, by the note's path key
+    pages: HashMap<String, VaultPath>,
+    /// The notes by their lowercase name, with and without extension,
+    /// to resolve the wiki links
+    names: HashMap<String, Vec<VaultPath>>,
+    /// The attachments to copy, by path key
+    attachments: BTreeMap<String, VaultPath>,
+    /// The page of each tag in a site
+    tag_pages: HashMap<String, VaultPath>,
+    report: ExportReport,
+}
+
+pub(crate) fn export_html<P: AsRef<Path>, D: AsRef<Path>>(
+    workspace_path: P,
+    types: &NoteTypes,
+    case: PathCase,
+    target: ExportTarget,
+    destination: D,
+) -> Result<ExportReport, VaultError> {
+    let (base, paths, site) = match target {
+        ExportTarget::Note(path) => (path.get_parent_path().0, vec![path], false),
+        ExportTarget::Site { directory, notes } => (directory, notes, true),
+    };
+    let mut exporter = Exporter {
+        workspace_path: workspace_path.as_ref(),
+        destination: destination.as_ref().to_path_buf(),
+        types,
+        case,
+        base,
+        site,
+        pages: HashMap::new(),
+        names: HashMap::new(),
+        attachments: BTreeMap::new(),
+        tag_pages: HashMap::new(),
+        report: ExportReport::default(),
+    };
+    let notes = exporter.load_notes(paths);
+    exporter.write_notes(&notes)?;
+    if site {
+        exporter.write_index(&notes)?;
+        exporter.write_tags(&notes)?;
+    }
+    exporter.copy_attachments()?;
+    debug!(
+        "Exported {} notes and {} attachments",
+        exporter.report.notes, exporter.report.attachments
+    );
+    Ok(exporter.report)
+}
+
+impl Exporter<'_> {
+    /// Reads the notes and chooses the page of each one, sorted by path.
+    /// In a site, the index and the tag pages are chosen first, so the
+    /// notes with the same page name get another one.
+    fn load_notes(&mut self, mut paths: Vec<VaultPath>) -> Vec<ExportNote> {
+        paths.sort_by_cached_key(|path| path.to_string());
+        let mut loaded = vec![];
+        for path in paths {
+            let Some(format) = self.types.format(&path) else {
+                continue;
+            };
+            let text = match load_note(self.workspace_path, &path) {
+                Ok(text) => text,
+                Err(e) => {
+                    warn!("Can't export the note {}: {}", path, e);
+                    self.report.skipped.push(SkippedFile {
+                        path,
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let details = NoteDetails::from_content(&text, &path, format);
+            loaded.push((details, format, text));
+        }
+
+        let mut taken = HashSet::new();
+        if self.site {
+            taken.insert(page_key(&VaultPath::from(INDEX_PAGE)));
+            let tags = loaded
+                .iter()
+                .flat_map(|(details, _format, _text)| details.data.tags.iter())
+                .collect::<BTreeSet<&String>>();
+            for tag in tags {
+                let page = tag_page(tag, &taken);
+                taken.insert(page_key(&page));
+                self.tag_pages.insert(tag.to_owned(), page);
+            }
+        }
+        let mut notes = vec![];
+        for (details, format, text) in loaded {
+            let path = details.path.clone();
+            let page = self.page_for(&path, &mut taken);
+            self.pages.insert(path.key(self.case), page.clone());
+            let name = path.get_name().to_lowercase();
+            let stem = strip_extension(&name).to_string();
+            self.names.entry(name).or_default().push(path.clone());
+            self.names.entry(stem).or_default().push(path);
+            notes.push(ExportNote {
+                details,
+                format,
+                text,
+                page,
+            });
+        }
+        notes
+    }
+
+    /// The note's name with the `html` extension, or its full name if
+    /// there's already a page with that name, like for `note.md` and `note.txt`
+    fn page_for(&self, path: &VaultPath, taken: &mut HashSet<String>) -> VaultPath {
+        let relative = VaultPath::from(path.relative_to(&self.base));
+        let (directory, name) = relative.get_parent_path();
+        let mut page = directory.append(&VaultPath::from(format!(
+            "{}.{}",
+            strip_extension(&name),
+            HTML_EXTENSION
+        )));
+        if taken.contains(&page_key(&page)) {
+            page = directory.append(&VaultPath::from(format!("{}.{}", name, HTML_EXTENSION)));
+        }
+        taken.insert(page_key(&page));
+        page
+    }
+
+    fn write_notes(&mut self, notes: &[ExportNote]) -> Result<(), VaultError> {
+        for note in notes {
+            let content = match note.format {
+                NoteFormat::Markdown => self.render_markdown(note),
+                NoteFormat::Text => format!("<pre>{}</pre>", escape_html(&note.text)),
+            };
+            let mut body = String::new();
+            if self.site {
+                body.push_str(&format!(
+                    "<nav><a href=\"{}\">Index</a></nav>\n",
+                    link_between(&note.page, &VaultPath::from(INDEX_PAGE))
+                ));
+            }
+            body.push_str(&content);
+            let tags = &note.details.data.tags;
+            if !tags.is_empty() {
+                let tags = tags
+                    .iter()
+                    .map(|tag| {
+                        if self.site {
+                            format!(
+                                "<a href=\"{}\">#{}</a>",
+                                link_between(&note.page, &self.tag_pages[tag]),
+                                escape_html(tag)
+                            )
+                        } else {
+                            format!("#{}", escape_html(tag))
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                body.push_str(&format!("\n<footer>{}</footer>", tags));
+            }
+            self.write_page(&note.page, &note.details.get_title(), &body)?;
+            self.report.notes += 1;
+        }
+        Ok(())
+    }
+
+    fn render_markdown(&mut self, note: &ExportNote) -> String {
+        let (_frontmatter, text) = remove_frontmatter(&note.text);
+        let path = &note.details.path;
+        let options = Options::ENABLE_TABLES
+            | Options::ENABLE_STRIKETHROUGH
+            | Options::ENABLE_TASKLISTS
+            | Options::ENABLE_FOOTNOTES;
+        let mut events = vec![];
+        let mut pending_text = String::new();
+        let mut in_code = false;
+        for event in Parser::new_ext(&text, options) {
+            if let Event::Text(text) = &event {
+                if !in_code {
+                    // Text is split on brackets, so the wiki links are
+                    // looked up once we have the whole text
+                    pending_text.push_str(text);
+                    continue;
+                }
+            }
+            self.push_text(note, &mut pending_text, &mut events);
+            let event = match event {
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    in_code = true;
+                    Event::Start(Tag::CodeBlock(kind))
+                }
+                Event::End(TagEnd::CodeBlock) => {
+                    in_code = false;
+                    Event::End(TagEnd::CodeBlock)
+                }
+                Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }) => Event::Start(Tag::Link {
+                    link_type,
+                    dest_url: self.rewrite_link(note, path, &dest_url).into(),
+                    title,
+                    id,
+                }),
+                Event::Start(Tag::Image {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }) => Event::Start(Tag::Image {
+                    link_type,
+                    dest_url: self.rewrite_link(note, path, &dest_url).into(),
+                    title,
+                    id,
+                }),
+                event => event,
+            };
+            events.push(event);
+        }
+        self.push_text(note, &mut pending_text, &mut events);
+        add_heading_ids(&mut events);
+
+        let mut content = String::new();
+        html::push_html(&mut content, events.into_iter());
+        content
+    }
+
+    /// Adds the text to the events, with its wiki links as HTML links
+    fn push_text(&mut self, note: &ExportNote, text: &mut String, events: &mut Vec<Event>) {
+        if text.is_empty() {
+            return;
+        }
+        let mut last = 0;
+        for caps in wiki_link_regex().captures_iter(text) {
+            let whole = caps.get(0).unwrap();
+            if whole.start() > last {
+                events.push(Event::Text(text[last..whole.start()].to_string().into()));
+            }
+            last = whole.end();
+            let target = caps[1].trim();
+            let heading = caps.get(2).map(|h| h.as_str().trim());
+            let label = match (caps.get(3), heading) {
+                (Some(label), _) => label.as_str().to_string(),
+                (None, Some(heading)) if target.is_empty() => heading.to_string(),
+                (None, Some(heading)) => format!("{} > {}", target, heading),
+                (None, None) => target.to_string(),
+            };
+            let fragment = heading.map(slug);
+            let page = if target.is_empty() {
+                Some(note.page.clone())
+            } else {
+                self.resolve_wiki(&note.details.path, target)
+                    .and_then(|path| self.pages.get(&path.key(self.case)).cloned())
+            };
+            match page {
+                Some(page) => {
+                    let mut href = link_between(&note.page, &page);
+                    if let Some(fragment) = fragment {
+                        href = format!("{}#{}", href, fragment);
+                    }
+                    events.push(Event::Start(Tag::Link {
+                        link_type: LinkType::Inline,
+                        dest_url: href.into(),
+                        title: CowStr::from(""),
+                        id: CowStr::from(""),
+                    }));
+                    events.push(Event::Text(label.into()));
+                    events.push(Event::End(TagEnd::Link));
+                }
+                None => {
+                    self.report.broken_links.push(BrokenLink {
+                        note: note.details.path.clone(),
+                        link: whole.as_str().to_string(),
+                    });
+                    events.push(Event::InlineHtml(
+                        format!("<span class=\"broken\">{}</span>", escape_html(&label)).into(),
+                    ));
+                }
+            }
+        }
+        if last < text.len() {
+            events.push(Event::Text(text[last..].to_string().into()));
+        }
+        text.clear();
+    }
+
+    /// The link to the note's page or the copied attachment, or the same
+    /// link if it's external or there's nothing exported at the path
+    fn rewrite_link(&mut self, note: &ExportNote, from: &VaultPath, destination: &str) -> String {
+        if is_external(destination) {
+            return destination.to_string();
+        }
+        let (link_path, fragment) = match destination.split_once('#') {
+            Some((link_path, fragment)) => (link_path, Some(fragment)),
+            None => (destination, None),
+        };
+        let directory = from.get_parent_path().0;
+        let target = join_link(&directory, &percent_decode(link_path));
+        let page = target.as_ref().and_then(|target| {
+            self.pages.get(&target.key(self.case)).or_else(|| {
+                // Links can leave out the extension
+                target
+                    .get_extension()
+                    .is_none()
+                    .then(|| {
+                        self.pages
+                            .get(&VaultPath::file_from(target.to_string()).key(self.case))
+                    })
+                    .flatten()
+            })
+        });
+        if let Some(page) = page {
+            let href = link_between(&note.page, page);
+            return match fragment {
+                Some(fragment) => format!("{}#{}", href, fragment),
+                None => href,
+            };
+        }
+        if let Some(target) = target.filter(|target| self.is_attachment(target)) {
+            let copy = VaultPath::from(target.relative_to(&self.base));
+            self.attachments.insert(target.key(self.case), target);
+            return link_between(&note.page, &copy);
+        }
+        self.report.broken_links.push(BrokenLink {
+            note: from.clone(),
+            link: destination.to_string(),
+        });
+        destination.to_string()
+    }
+
+    /// Files in the exported directory that are not notes are copied
+    fn is_attachment(&self, path: &VaultPath) -> bool {
+        !self.types.is_note(path)
+            && path.is_in(&self.base, self.case)
+            && path.to_pathbuf(self.workspace_path).is_file()
+    }
+
+    /// Wiki links with a `/` are paths from the root of the vault,
+    /// otherwise the note is looked up by name, preferring the one
+    /// in the same directory
+    fn resolve_wiki(&self, from: &VaultPath, target: &str) -> Option<VaultPath> {
+        if target.contains('/') {
+            let path = join_link(&VaultPath::root(), target)?;
+            return [path.clone(), VaultPath::file_from(path.to_string())]
+                .into_iter()
+                .find(|path| self.pages.contains_key(&path.key(self.case)));
+        }
+        let candidates = self.names.get(&target.to_lowercase())?;
+        let directory = from.get_parent_path().0;
+        candidates
+            .iter()
+            .find(|path| path.get_parent_path().0.matches(&directory, self.case))
+            .or(candidates.first())
+            .cloned()
+    }
+
+    fn write_index(&mut self, notes: &[ExportNote]) -> Result<(), VaultError> {
+        let index = VaultPath::from(INDEX_PAGE);
+        let mut body = String::from("<h1>Notes</h1>\n<ul>\n");
+        for note in notes {
+            body.push_str(&format!(
+                "<li><a href=\"{}\">{}</a> <span class=\"path\">{}</span></li>\n",
+                link_between(&index, &note.page),
+                escape_html(&note.details.get_title()),
+                escape_html(&note.details.path.relative_to(&self.base))
+            ));
+        }
+        body.push_str("</ul>\n");
+        let tags = tag_notes(notes);
+        if !tags.is_empty() {
+            body.push_str("<h2>Tags</h2>\n<ul>\n");
+            for (tag, tagged) in &tags {
+                body.push_str(&format!(
+                    "<li><a href=\"{}\">#{}</a> ({})</li>\n",
+                    link_between(&index, &self.tag_pages[*tag]),
+                    escape_html(tag),
+                    tagged.len()
+                ));
+            }
+            body.push_str("</ul>\n");
+        }
+        let title = match self.base.get_name() {
+            name if name.is_empty() => "Notes".to_string(),
+            name => name,
+        };
+        self.write_page(&index, &title, &body)
+    }
+
+    fn write_tags(&mut self, notes: &[ExportNote]) -> Result<(), VaultError> {
+        let tags = tag_notes(notes);
+        for (tag, tagged) in &tags {
+            let page = self.tag_pages[*tag].clone();
+            let mut body = format!(
+                "<nav><a href=\"{}\">Index</a></nav>\n<h1>#{}</h1>\n<ul>\n",
+                link_between(&page, &VaultPath::from(INDEX_PAGE)),
+                escape_html(tag)
+            );
+            for note in tagged {
+                body.push_str(&format!(
+                    "<li><a href=\"{}\">{}</a></li>\n",
+                    link_between(&page, &note.page),
+                    escape_html(&note.details.get_title())
+                ));
+            }
+            body.push_str("</ul>\n");
+            self.write_page(&page, &format!("#{}", tag), &body)?;
+        }
+        self.report.tags = tags.len();
+        Ok(())
+    }
+
+    fn copy_attachments(&mut self) -> Result<(), VaultError> {
+        for attachment in std::mem::take(&mut self.attachments).into_values() {
+            let copy =
+                VaultPath::from(attachment.relative_to(&self.base)).to_pathbuf(&self.destination);
+            if let Some(parent) = copy.parent() {
+                std::fs::create_dir_all(parent).map_err(FSError::ReadFileError)?;
+            }
+            match std::fs::copy(attachment.to_pathbuf(self.workspace_path), copy) {
+                Ok(_) => self.report.attachments += 1,
+                Err(e) => {
+                    warn!("Can't copy the attachment {}: {}", attachment, e);
+                    self.report.skipped.push(SkippedFile {
+                        path: attachment,
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_page(&self, page: &VaultPath, title: &str, body: &str) -> Result<(), VaultError> {
+        let html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+            escape_html(title),
+            STYLE,
+            body
+        );
+        let file = page.to_pathbuf(&self.destination);
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(FSError::ReadFileError)?;
+        }
+        std::fs::write(file, html).map_err(FSError::ReadFileError)?;
+        Ok(())
+    }
+}
+
+fn wiki_link_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(WIKI_LINK_REGEX).unwrap())
+}
+
+/// The notes with each tag, sorted by tag
+fn tag_notes(notes: &[ExportNote]) -> BTreeMap<&str, Vec<&ExportNote>> {
+    let mut tags: BTreeMap<&str, Vec<&ExportNote>> = BTreeMap::new();
+    for note in notes {
+        for tag in &note.details.data.tags {
+            tags.entry(tag).or_default().push(note);
+        }
+    }
+    tags
+}
+
+/// The page of a tag from its slug, with a number if another tag with
+/// the same slug took it, like `c` and `c++`
+fn tag_page(tag: &str, taken: &HashSet<String>) -> VaultPath {
+    let slug = slug(tag);
+    let mut name = slug.clone();
+    let mut count = 0;
+    loop {
+        let page = VaultPath::from(TAGS_DIR)
+            .append(&VaultPath::from(format!("{}.{}", name, HTML_EXTENSION)));
+        if !taken.contains(&page_key(&page)) {
+            return page;
+        }
+        count += 1;
+        name = format!("{}-{}", slug, count);
+    }
+}
+
+fn page_key(page: &VaultPath) -> String {
+    page.key(PathCase::Insensitive)
+}
+
+/// The link from a page to another one, both relative to the destination
+fn link_between(from_page: &VaultPath, to_page: &VaultPath) -> String {
+    to_page.relative_to(&from_page.get_parent_path().0)
+}
+
+fn strip_extension(name: &str) -> &str {
+    name.rsplit_once('.')
+        .filter(|(stem, _extension)| !stem.is_empty())
+        .map_or(name, |(stem, _extension)| stem)
+}
+
+/// Links with a scheme, like `https:` or `mailto:`, and the ones to a
+/// heading in the same note
+pub(crate) fn is_external(destination: &str) -> bool {
+    destination.is_empty()
+        || destination.starts_with('#')
+        || destination
+            .split('/')
+            .next()
+            .is_some_and(|first| first.contains(':'))
+}
+
+/// The path of a link from a note in `directory`, none if it goes
+/// out of the vault
+pub(crate) fn join_link(directory: &VaultPath, link: &str) -> Option<VaultPath> {
+    let mut slices = if link.starts_with('/') {
+        vec![]
+    } else {
+        directory.get_slices()
+    };
+    for part in link.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                slices.pop()?;
+            }
+            name => slices.push(name.to_string()),
+        }
+    }
+    Some(VaultPath::from(slices.join("/")))
+}
+
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Gives the headings an id from their text, so they can be linked
+fn add_heading_ids(events: &mut [Event]) {
+    let mut used: HashMap<String, usize> = HashMap::new();
+    for index in 0..events.len() {
+        let Event::Start(Tag::Heading { id: None, .. }) = &events[index] else {
+            continue;
+        };
+        let text = events[index + 1..]
+            .iter()
+            .take_while(|event| !matches!(event, Event::End(TagEnd::Heading(_))))
+            .filter_map(|event| match event {
+                Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect::<String>();
+        let mut id = slug(&text);
+        let count = used.entry(id.clone()).or_default();
+        if *count > 0 {
+            id = format!("{}-{}", id, count);
+        }
+        *count += 1;
+        if let Event::Start(Tag::Heading { id: heading_id, .. }) = &mut events[index] {
+            *heading_id = Some(id.into());
+        }
+    }
+}
+
+/// Lowercase letters and numbers, with dashes instead of spaces
+fn slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '_' => Some(c),
+            ' ' | '-' | '/' => Some('-'),
+            _ => None,
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nfs::{note_types::NoteTypes, PathCase, VaultPath},
+        test_utils::{vault_files, workspace},
+    };
+
+    use super::{export_html, BrokenLink, ExportTarget};
+
+    #[test]
+    fn export_site_with_links() {
+        let dir = vault_files(&[
+            (
+                "notes/a.md",
+                "---\ntags: [work]\n---\n# Alpha\n\nSee [[b]], [[b#Second Part|the part]], [b](b.md), \
+                 [gone](gone.md), [web](https://x.org) and ![pic](img/pic.png)\n\n`[[b]]` in code",
+            ),
+            ("notes/b.md", "# Beta\n\n## Second Part\n\nBack to [[a]] #work"),
+            ("notes/b.txt", "Plain <b>text</b>"),
+        ]);
+        let workspace = workspace(&dir);
+        let destination = dir.join("site");
+        std::fs::create_dir_all(workspace.join("notes/img")).unwrap();
+        std::fs::write(workspace.join("notes/img/pic.png"), [0u8, 1, 2]).unwrap();
+
+        let target = ExportTarget::Site {
+            directory: VaultPath::root(),
+            notes: ["notes/a.md", "notes/b.md", "notes/b.txt"]
+                .into_iter()
+                .map(VaultPath::from)
+                .collect(),
+        };
+        let report = export_html(
+            &workspace,
+            &NoteTypes::default(),
+            PathCase::Sensitive,
+            target,
+            &destination,
+        )
+        .unwrap();
+        assert_eq!(3, report.notes);
+        assert_eq!(1, report.attachments);
+        assert_eq!(1, report.tags);
+        assert_eq!(
+            vec![BrokenLink {
+                note: VaultPath::from("notes/a.md"),
+                link: "gone.md".to_string()
+            }],
+            report.broken_links
+        );
+
+        let read = |page: &str| std::fs::read_to_string(destination.join(page)).unwrap();
+        let a = read("notes/a.html");
+        assert!(a.contains("<a href=\"b.html\">b</a>"));
+        assert!(a.contains("<a href=\"b.html#second-part\">the part</a>"));
+        assert!(a.contains("href=\"https://x.org\""));
+        assert!(a.contains("src=\"img/pic.png\""));
+        assert!(a.contains("<code>[[b]]</code>"));
+        assert!(a.contains("<a href=\"../tags/work.html\">#work</a>"));
+        let b = read("notes/b.html");
+        assert!(b.contains("<h2 id=\"second-part\">"));
+        assert!(b.contains("<a href=\"a.html\">a</a>"));
+        assert!(read("notes/b.txt.html").contains("Plain &lt;b&gt;text&lt;/b&gt;"));
+        assert!(read("index.html").contains("href=\"notes/a.html\">Alpha</a>"));
+        let tag = read("tags/work.html");
+        assert!(
+            tag.contains("href=\"../notes/a.html\"") && tag.contains("href=\"../notes/b.html\"")
+        );
+        assert!(destination.join("notes/img/pic.png").is_file());
+    }
+
+    #[test]
+    fn tag_pages_keep_their_place() {
+        let dir = vault_files(&[
+            ("a.md", "---\ntags: [c++, work]\n---\n# Alpha"),
+            ("b.md", "---\ntags: [c]\n---\n# Beta"),
+            ("tags/work.md", "# Work note"),
+        ]);
+        let workspace = workspace(&dir);
+        let destination = dir.join("site");
+
+        let target = ExportTarget::Site {
+            directory: VaultPath::root(),
+            notes: ["a.md", "b.md", "tags/work.md"]
+                .into_iter()
+                .map(VaultPath::from)
+                .collect(),
+        };
+        let report = export_html(
+            &workspace,
+            &NoteTypes::default(),
+            PathCase::Sensitive,
+            target,
+            &destination,
+        )
+        .unwrap();
+        assert_eq!(3, report.notes);
+        assert_eq!(3, report.tags);
+
+        let read = |page: &str| std::fs::read_to_string(destination.join(page)).unwrap();
+        // The tags are sorted, `c` comes before `c++`
+        assert!(read("tags/c.html").contains("<h1>#c</h1>"));
+        assert!(read("tags/c-1.html").contains("<h1>#c++</h1>"));
+        assert!(read("tags/work.html").contains("<h1>#work</h1>"));
+        assert!(read("tags/work.md.html").contains("Work note"));
+        let index = read("index.html");
+        assert!(index.contains("href=\"tags/c-1.html\">#c++</a>"));
+        assert!(index.contains("href=\"tags/work.md.html\">Work note</a>"));
+        assert!(read("a.html").contains("<a href=\"tags/c-1.html\">#c++</a>"));
+    }
+}
@@ -0,0 +1,305 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    error::VaultError, indexer::SkippedFile, nfs::VaultPath, utilities::remove_diacritics,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FindOptions {
+    pub case_sensitive: bool,
+    /// The pattern is a regular expression, the replacement
+    /// can then reference groups with `$1` or `${name}`
+    pub regex: bool,
+    pub whole_word: bool,
+}
+
+/// Finds and replaces a pattern in a text with the given options
+#[derive(Debug, Clone)]
+pub struct TextFinder {
+    pattern: String,
+    options: FindOptions,
+    regex: Regex,
+}
+
+impl TextFinder {
+    pub fn new<S: AsRef<str>>(pattern: S, options: FindOptions) -> Result<Self, VaultError> {
+        let pattern = pattern.as_ref().to_owned();
+        let expression = if options.regex {
+            pattern.clone()
+        } else {
+            regex::escape(&pattern)
+        };
+        let expression = if options.whole_word {
+            format!(r"\b(?:{})\b", expression)
+        } else {
+            expression
+        };
+        let regex = RegexBuilder::new(&expression)
+            .case_insensitive(!options.case_sensitive)
+            .multi_line(true)
+            .build()?;
+        Ok(Self {
+            pattern,
+            options,
+            regex,
+        })
+    }
+
+    pub fn get_pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn get_options(&self) -> &FindOptions {
+        &self.options
+    }
+
+    /// Returns the byte ranges of all the matches, empty matches are
+    /// skipped as they can't be selected nor replaced
+    pub fn find_all(&self, text: &str) -> Vec<Range<usize>> {
+        if self.pattern.is_empty() {
+            return vec![];
+        }
+        self.regex
+            .find_iter(text)
+            .filter(|m| !m.is_empty())
+            .map(|m| m.range())
+            .collect()
+    }
+
+    /// Returns the text that replaces the match at the range
+    pub fn replacement_for(&self, text: &str, range: &Range<usize>, replacement: &str) -> String {
+        if !self.options.regex {
+            return replacement.to_owned();
+        }
+        let mut expanded = String::new();
+        if let Some(captures) = self
+            .regex
+            .captures_at(text, range.start)
+            .filter(|c| c.get(0).is_some_and(|m| m.range() == *range))
+        {
+            captures.expand(replacement, &mut expanded);
+        }
+        expanded
+    }
+
+    /// Replaces all the matches in the text, returns the new text
+    /// and the number of replacements
+    pub fn replace_all(&self, text: &str, replacement: &str) -> (String, usize) {
+        let matches = self.find_all(text);
+        let mut new_text = String::with_capacity(text.len());
+        let mut last = 0;
+        for range in &matches {
+            new_text.push_str(&text[last..range.start]);
+            new_text.push_str(&self.replacement_for(text, range, replacement));
+            last = range.end;
+        }
+        new_text.push_str(&text[last..]);
+        (new_text, matches.len())
+    }
+
+    /// Words of the pattern to find candidate notes in the search index.
+    /// The words at the ends of the pattern may be part of a longer word
+    /// in the note, unless searching for whole words. The index doesn't
+    /// keep the markdown syntax, like the destination of a link, so only
+    /// a pattern made of words can be looked up. For a regex or any other
+    /// pattern `None` is returned and all notes should be checked.
+    pub(crate) fn index_terms(&self) -> Option<Vec<IndexTerm>> {
+        if self.options.regex
+            || self
+                .pattern
+                .chars()
+                .any(|c| !c.is_alphanumeric() && !c.is_whitespace())
+        {
+            return None;
+        }
+        let pattern = remove_diacritics(&self.pattern).to_lowercase();
+        // The first and last parts are empty if the pattern starts or
+        // ends with something else than a word
+        let parts = pattern
+            .split(|c: char| !c.is_alphanumeric())
+            .collect::<Vec<&str>>();
+        let last = parts.len() - 1;
+        let terms = parts
+            .iter()
+            .enumerate()
+            .filter(|(_index, word)| !word.is_empty())
+            .map(|(index, word)| {
+                let word = word.to_string();
+                let open_start = index == 0 && !self.options.whole_word;
+                let open_end = index == last && !self.options.whole_word;
+                match (open_start, open_end) {
+                    (false, false) => IndexTerm::Word(word),
+                    (false, true) => IndexTerm::Prefix(word),
+                    (true, false) => IndexTerm::Suffix(word),
+                    (true, true) => IndexTerm::Part(word),
+                }
+            })
+            .collect::<Vec<IndexTerm>>();
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms)
+        }
+    }
+
+    /// Previews the changes of replacing all the matches in a text,
+    /// one change per match with the lines it touches
+    pub fn preview(&self, text: &str, replacement: &str) -> Vec<ReplaceChange> {
+        self.find_all(text)
+            .into_iter()
+            .map(|range| {
+                let line_start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+                let line_end = text[range.end..]
+                    .find('\n')
+                    .map_or(text.len(), |i| range.end + i);
+                let before = &text[line_start..line_end];
+                let after = format!(
+                    "{}{}{}",
+                    &text[line_start..range.start],
+                    self.replacement_for(text, &range, replacement),
+                    &text[range.end..line_end]
+                );
+                ReplaceChange {
+                    line: text[..line_start].matches('\n').count() + 1,
+                    before: before.to_owned(),
+                    after,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A word of the pattern to look up in the search index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum IndexTerm {
+    Word(String),
+    /// The start of a word
+    Prefix(String),
+    /// The end of a word
+    Suffix(String),
+    /// Any part of a word
+    Part(String),
+}
+
+/// A replacement of a match, with the lines before and after it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplaceChange {
+    /// The line where the match starts, starting at 1
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// The changes a replacement would do in a note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteReplacement {
+    pub path: VaultPath,
+    pub changes: Vec<ReplaceChange>,
+}
+
+/// The changes a replacement would do in the vault
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplacePreview {
+    pub notes: Vec<NoteReplacement>,
+    /// Notes that couldn't be read, they are not changed
+    pub skipped: Vec<SkippedFile>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FindOptions, IndexTerm, TextFinder};
+
+    #[test]
+    fn find_literal_ignoring_case() {
+        let finder = TextFinder::new("a.b", FindOptions::default()).unwrap();
+        let text = "A.B axb a.b";
+
+        assert_eq!(vec![0..3, 8..11], finder.find_all(text));
+        assert_eq!(("$1 axb $1".to_string(), 2), finder.replace_all(text, "$1"));
+    }
+
+    #[test]
+    fn find_case_sensitive_whole_word() {
+        let options = FindOptions {
+            case_sensitive: true,
+            whole_word: true,
+            ..Default::default()
+        };
+        let finder = TextFinder::new("note", options).unwrap();
+        let text = "note notes Note note";
+
+        assert_eq!(vec![0..4, 16..20], finder.find_all(text));
+        assert_eq!(
+            Some(vec![IndexTerm::Word("note".to_string())]),
+            finder.index_terms()
+        );
+    }
+
+    #[test]
+    fn index_terms_of_substrings() {
+        let terms = |pattern: &str| {
+            TextFinder::new(pattern, FindOptions::default())
+                .unwrap()
+                .index_terms()
+        };
+
+        assert_eq!(Some(vec![IndexTerm::Part("ell".to_string())]), terms("ell"));
+        assert_eq!(
+            Some(vec![
+                IndexTerm::Suffix("ello".to_string()),
+                IndexTerm::Word("big".to_string()),
+                IndexTerm::Prefix("wor".to_string())
+            ]),
+            terms("Ello big wor")
+        );
+        assert_eq!(
+            Some(vec![
+                IndexTerm::Word("cafe".to_string()),
+                IndexTerm::Prefix("end".to_string())
+            ]),
+            terms(" Café  end")
+        );
+        // The markdown syntax is not in the index
+        assert_eq!(None, terms("**Café** end."));
+        assert_eq!(None, terms("roadmap.md"));
+        assert_eq!(None, terms("**"));
+    }
+
+    #[test]
+    fn replace_regex_with_groups() {
+        let options = FindOptions {
+            regex: true,
+            ..Default::default()
+        };
+        let finder = TextFinder::new(r"(\w+)@(\w+)", options).unwrap();
+        let text = "first line\nmail me@home now\nlast";
+
+        assert_eq!(None, finder.index_terms());
+        assert_eq!(
+            ("first line\nmail home@me now\nlast".to_string(), 1),
+            finder.replace_all(text, "$2@$1")
+        );
+        let changes = finder.preview(text, "$2@$1");
+        assert_eq!(1, changes.len());
+        assert_eq!(2, changes[0].line);
+        assert_eq!("mail me@home now", changes[0].before);
+        assert_eq!("mail home@me now", changes[0].after);
+    }
+
+    #[test]
+    fn invalid_regex() {
+        let options = FindOptions {
+            regex: true,
+            ..Default::default()
+        };
+        assert!(TextFinder::new("(unclosed", options).is_err());
+    }
+
+    #[test]
+    fn empty_pattern_finds_nothing() {
+        let finder = TextFinder::new("", FindOptions::default()).unwrap();
+        assert!(finder.find_all("some text").is_empty());
+    }
+}
@@ -0,0 +1,577 @@
+use std::{collections::HashMap, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::NaiveDateTime;
+use log::debug;
+use quick_xml::{
+    events::{BytesStart, BytesText, Event},
+    Reader,
+};
+
+use crate::{
+    error::{FSError, VaultError},
+    nfs::VaultPath,
+};
+
+use super::{
+    file_name, markdown_link, FileData, ImportIssue, ImportPlan, PlannedFile, PlannedNote,
+    UsedPaths,
+};
+
+const ATTACHMENTS_DIR: &str = "attachments";
+const ENEX_DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+#[derive(Default)]
+struct EnexNote {
+    title: String,
+    content: String,
+    created: String,
+    tags: Vec<String>,
+    resources: Vec<EnexResource>,
+}
+
+#[derive(Default)]
+struct EnexResource {
+    data: String,
+    mime: String,
+    file_name: String,
+}
+
+/// An attachment of the note being converted
+struct Attachment {
+    path: VaultPath,
+    is_image: bool,
+}
+
+/// Imports the notes in an Evernote `.enex` file, converting their
+/// content to Markdown. The attachments go to a directory next to them.
+pub(super) fn plan(from: &Path) -> Result<ImportPlan, VaultError> {
+    let text = std::fs::read_to_string(from).map_err(FSError::ReadFileError)?;
+    let notes = parse_enex(&text)?;
+    debug!("Found {} notes in {}", notes.len(), from.to_string_lossy());
+
+    let mut plan = ImportPlan::default();
+    let mut used_paths = UsedPaths::default();
+    for note in notes {
+        let title = note.title.trim().to_string();
+        let path = used_paths.take(VaultPath::file_from(file_name(&title)));
+        let source = if title.is_empty() {
+            path.to_string()
+        } else {
+            title.clone()
+        };
+
+        let mut attachments = HashMap::new();
+        for resource in note.resources {
+            let cleaned = resource
+                .data
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .concat();
+            let data = match STANDARD.decode(cleaned) {
+                Ok(data) => data,
+                Err(e) => {
+                    plan.issues.push(ImportIssue::new(
+                        &source,
+                        format!("Can't read an attachment: {}", e),
+                    ));
+                    continue;
+                }
+            };
+            let hash = format!("{:x}", md5::compute(&data));
+            let name = if resource.file_name.trim().is_empty() {
+                let extension = resource.mime.rsplit('/').next().unwrap_or("bin");
+                format!("{}.{}", hash, extension)
+            } else {
+                file_name(&resource.file_name)
+            };
+            let attachment_path =
+                used_paths.take(VaultPath::from(ATTACHMENTS_DIR).append(&VaultPath::from(name)));
+            plan.files.push(PlannedFile {
+                source: format!("{} ({})", source, resource.file_name),
+                path: attachment_path.clone(),
+                data: FileData::Bytes(data),
+            });
+            attachments.insert(
+                hash,
+                Attachment {
+                    path: attachment_path,
+                    is_image: resource.mime.starts_with("image/"),
+                },
+            );
+        }
+
+        let mut converter = Converter::new(&path, &attachments);
+        let result = converter.convert(&note.content);
+        let (body, issues) = converter.finish();
+        if let Err(e) = result {
+            plan.issues.push(ImportIssue::new(
+                &source,
+                format!("The content is not valid, it may be incomplete: {}", e),
+            ));
+        }
+        plan.issues.extend(
+            issues
+                .into_iter()
+                .map(|reason| ImportIssue::new(&source, reason)),
+        );
+
+        let text = note_text(&title, &note.tags, &note.created, &body);
+        plan.notes.push(PlannedNote { source, path, text });
+    }
+    Ok(plan)
+}
+
+/// The note with the tags and creation date in the front matter
+fn note_text(title: &str, tags: &[String], created: &str, body: &str) -> String {
+    let mut front_matter = vec![];
+    if !tags.is_empty() {
+        let tags = tags
+            .iter()
+            .map(|tag| tag.split_whitespace().collect::<Vec<&str>>().join("-"))
+            .collect::<Vec<String>>();
+        front_matter.push(format!("tags: [{}]", tags.join(", ")));
+    }
+    if let Ok(created) = NaiveDateTime::parse_from_str(created.trim(), ENEX_DATE_FORMAT) {
+        front_matter.push(format!("created: {}", created.format(DATE_FORMAT)));
+    }
+    let mut text = String::new();
+    if !front_matter.is_empty() {
+        text.push_str(&format!("---\n{}\n---\n", front_matter.join("\n")));
+    }
+    if !title.is_empty() {
+        text.push_str(&format!("# {}\n", title));
+    }
+    if !body.is_empty() {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(body);
+        text.push('\n');
+    }
+    text
+}
+
+fn parse_enex(text: &str) -> Result<Vec<EnexNote>, VaultError> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().check_end_names = false;
+    let mut notes = vec![];
+    let mut note: Option<EnexNote> = None;
+    let mut resource: Option<EnexResource> = None;
+    let mut elements: Vec<Vec<u8>> = vec![];
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| VaultError::InvalidImport(e.to_string()))?;
+        let text = match event {
+            Event::Start(start) => {
+                match start.name().as_ref() {
+                    b"note" => note = Some(EnexNote::default()),
+                    b"resource" => resource = Some(EnexResource::default()),
+                    _ => {}
+                }
+                elements.push(start.name().as_ref().to_vec());
+                continue;
+            }
+            Event::End(end) => {
+                match end.name().as_ref() {
+                    b"resource" => {
+                        if let (Some(note), Some(resource)) = (note.as_mut(), resource.take()) {
+                            note.resources.push(resource);
+                        }
+                    }
+                    b"note" => notes.extend(note.take()),
+                    _ => {}
+                }
+                elements.pop();
+                continue;
+            }
+            Event::Text(text) => unescape(&text),
+            Event::CData(data) => data
+                .decode()
+                .map_err(|e| VaultError::InvalidImport(e.to_string()))?
+                .to_string(),
+            Event::Eof => break,
+            _ => continue,
+        };
+        let (Some(note), Some(element)) = (note.as_mut(), elements.last()) else {
+            continue;
+        };
+        let field = match (element.as_slice(), resource.as_mut()) {
+            (b"data", Some(resource)) => &mut resource.data,
+            (b"mime", Some(resource)) => &mut resource.mime,
+            (b"file-name", Some(resource)) => &mut resource.file_name,
+            (b"title", None) => &mut note.title,
+            (b"content", None) => &mut note.content,
+            (b"created", None) => &mut note.created,
+            (b"tag", None) => {
+                note.tags.push(text);
+                continue;
+            }
+            _ => continue,
+        };
+        field.push_str(&text);
+    }
+    Ok(notes)
+}
+
+/// The text with the entities replaced, as it is if any is unknown
+fn unescape(text: &BytesText) -> String {
+    text.unescape().map_or_else(
+        |_e| String::from_utf8_lossy(text).to_string(),
+        |text| text.to_string(),
+    )
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(|value| value.to_string())
+}
+
+/// Converts the ENML content of a note, the HTML Evernote uses, to Markdown
+struct Converter<'a> {
+    path: &'a VaultPath,
+    attachments: &'a HashMap<String, Attachment>,
+    out: String,
+    /// New lines to add before the next text
+    pending: usize,
+    line_has_text: bool,
+    /// A list item just started, so blocks don't add new lines
+    after_marker: bool,
+    /// The open lists, with the next number for the ordered ones
+    lists: Vec<Option<usize>>,
+    links: Vec<Option<String>>,
+    quote: usize,
+    pre: bool,
+    /// The rows and cells of the table being converted
+    table: Option<Vec<Vec<String>>>,
+    /// Inside elements whose content is not imported
+    skip: usize,
+    issues: Vec<String>,
+}
+
+impl<'a> Converter<'a> {
+    fn new(path: &'a VaultPath, attachments: &'a HashMap<String, Attachment>) -> Self {
+        Self {
+            path,
+            attachments,
+            out: String::new(),
+            pending: 0,
+            line_has_text: false,
+            after_marker: false,
+            lists: vec![],
+            links: vec![],
+            quote: 0,
+            pre: false,
+            table: None,
+            skip: 0,
+            issues: vec![],
+        }
+    }
+
+    fn convert(&mut self, content: &str) -> Result<(), quick_xml::Error> {
+        let mut reader = Reader::from_str(content);
+        reader.config_mut().check_end_names = false;
+        loop {
+            match reader.read_event()? {
+                Event::Start(start) => {
+                    if self.skip > 0 {
+                        self.skip += 1;
+                    } else {
+                        self.start(&start, false);
+                    }
+                }
+                Event::Empty(start) if self.skip == 0 => self.start(&start, true),
+                Event::End(end) => {
+                    if self.skip > 0 {
+                        self.skip -= 1;
+                    } else {
+                        self.end(end.name().as_ref());
+                    }
+                }
+                Event::Text(text) => self.text(&unescape(&text)),
+                Event::CData(data) => self.text(&String::from_utf8_lossy(&data)),
+                Event::Eof => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// The Markdown, with what couldn't be converted
+    fn finish(self) -> (String, Vec<String>) {
+        (self.out.trim_end().to_string(), self.issues)
+    }
+
+    fn start(&mut self, element: &BytesStart, empty: bool) {
+        let name = element.name();
+        match name.as_ref() {
+            b"p" | b"div" => self.block(),
+            b"br" => {
+                self.block();
+                self.line_has_text = false;
+            }
+            b"h1" | b"h2" | b"h3" | b"h4" | b"h5" | b"h6" => {
+                self.block();
+                let level = (name.as_ref()[1] - b'0') as usize;
+                self.push(&format!("{} ", "#".repeat(level)));
+            }
+            b"ul" => {
+                self.block();
+                self.lists.push(None);
+            }
+            b"ol" => {
+                self.block();
+                self.lists.push(Some(1));
+            }
+            b"li" => {
+                self.after_marker = false;
+                self.block();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.push(&format!("{}{}", indent, marker));
+                self.after_marker = true;
+            }
+            b"en-todo" => {
+                if !self.line_has_text && !self.after_marker {
+                    self.block();
+                    self.push("- ");
+                }
+                let checked = attribute(element, "checked").is_some_and(|value| value == "true");
+                self.push(if checked { "[x] " } else { "[ ] " });
+                self.after_marker = true;
+            }
+            b"b" | b"strong" => self.push("**"),
+            b"i" | b"em" => self.push("*"),
+            b"s" | b"strike" | b"del" => self.push("~~"),
+            b"code" if !self.pre => self.push("`"),
+            b"pre" => {
+                self.block();
+                self.push("```\n");
+                self.pre = true;
+            }
+            b"a" => {
+                let href = attribute(element, "href");
+                if href.is_some() {
+                    self.push("[");
+                }
+                self.links.push(href);
+            }
+            b"blockquote" => {
+                self.block();
+                self.quote += 1;
+            }
+            b"hr" => {
+                self.block();
+                self.push("---");
+                self.line_has_text = true;
+                self.block();
+            }
+            b"table" => {
+                self.block();
+                self.table = Some(vec![]);
+            }
+            b"tr" => {
+                if let Some(rows) = self.table.as_mut() {
+                    rows.push(vec![]);
+                }
+            }
+            b"td" | b"th" => {
+                if let Some(row) = self.table.as_mut().and_then(|rows| rows.last_mut()) {
+                    row.push(String::new());
+                }
+            }
+            b"en-media" => self.media(element),
+            b"img" => match attribute(element, "src") {
+                Some(src) if src.starts_with("http") => self.inline(&format!("![]({})", src)),
+                _ => self
+                    .issues
+                    .push("An embedded image was not imported".to_string()),
+            },
+            b"en-crypt" => {
+                self.issues
+                    .push("Encrypted content can't be imported".to_string());
+                self.skip_content(empty);
+            }
+            b"object" | b"embed" | b"iframe" => {
+                self.issues.push(format!(
+                    "Unsupported element not imported: {}",
+                    String::from_utf8_lossy(name.as_ref())
+                ));
+                self.skip_content(empty);
+            }
+            b"script" | b"style" | b"title" => self.skip_content(empty),
+            _ => {}
+        }
+        // The empty elements don't have an end event
+        if empty
+            && !matches!(
+                name.as_ref(),
+                b"br" | b"hr" | b"en-todo" | b"en-media" | b"img"
+            )
+        {
+            self.end(name.as_ref());
+        }
+    }
+
+    fn end(&mut self, name: &[u8]) {
+        match name {
+            b"p" | b"div" | b"h1" | b"h2" | b"h3" | b"h4" | b"h5" | b"h6" | b"li" => self.block(),
+            b"ul" | b"ol" => {
+                self.lists.pop();
+                self.after_marker = false;
+                self.block();
+            }
+            b"b" | b"strong" => self.push("**"),
+            b"i" | b"em" => self.push("*"),
+            b"s" | b"strike" | b"del" => self.push("~~"),
+            b"code" if !self.pre => self.push("`"),
+            b"pre" => {
+                self.pre = false;
+                self.push("\n```");
+                self.line_has_text = true;
+                self.block();
+            }
+            b"a" => {
+                if let Some(Some(href)) = self.links.pop() {
+                    self.push(&format!("]({})", href));
+                }
+            }
+            b"blockquote" => {
+                self.block();
+                self.quote = self.quote.saturating_sub(1);
+            }
+            b"table" => self.table_end(),
+            _ => {}
+        }
+    }
+
+    fn skip_content(&mut self, empty: bool) {
+        if !empty {
+            self.skip = 1;
+        }
+    }
+
+    /// Starts a new paragraph, or a new line in lists
+    fn block(&mut self) {
+        if self.after_marker {
+            return;
+        }
+        if !self.out.is_empty() {
+            let lines = if self.lists.is_empty() { 2 } else { 1 };
+            self.pending = self.pending.max(lines);
+        }
+        self.line_has_text = false;
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.skip > 0 || text.is_empty() {
+            return;
+        }
+        if self.pre {
+            self.push(text);
+            return;
+        }
+        let words = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let mut collapsed = String::new();
+        if self.line_has_text && text.starts_with(char::is_whitespace) {
+            collapsed.push(' ');
+        }
+        collapsed.push_str(&words);
+        if !words.is_empty() && text.ends_with(char::is_whitespace) {
+            collapsed.push(' ');
+        }
+        if !collapsed.is_empty() {
+            self.inline(&collapsed);
+        }
+    }
+
+    /// Adds text to the current line
+    fn inline(&mut self, text: &str) {
+        self.push(text);
+        self.line_has_text = true;
+        self.after_marker = false;
+    }
+
+    fn push(&mut self, text: &str) {
+        if let Some(rows) = self.table.as_mut() {
+            if let Some(cell) = rows.last_mut().and_then(|row| row.last_mut()) {
+                cell.push_str(&text.replace('\n', " "));
+            }
+            return;
+        }
+        if self.pending > 0 {
+            let trimmed = self.out.trim_end_matches(' ').len();
+            self.out.truncate(trimmed);
+            for line in 0..self.pending {
+                self.out.push('\n');
+                if line + 1 < self.pending {
+                    self.out.push_str(&">".repeat(self.quote));
+                }
+            }
+            self.out.push_str(&"> ".repeat(self.quote));
+            self.pending = 0;
+        } else if self.out.is_empty() {
+            self.out.push_str(&"> ".repeat(self.quote));
+        }
+        self.out.push_str(text);
+    }
+
+    fn media(&mut self, element: &BytesStart) {
+        let hash = attribute(element, "hash").unwrap_or_default();
+        match self.attachments.get(&hash) {
+            Some(attachment) => {
+                let link = markdown_link(attachment.path.get_name(), self.path, &attachment.path);
+                if attachment.is_image {
+                    self.inline(&format!("!{}", link));
+                } else {
+                    self.inline(&link);
+                }
+            }
+            None => self
+                .issues
+                .push(format!("An attachment is missing in the export: {}", hash)),
+        }
+    }
+
+    fn table_end(&mut self) {
+        let rows = self
+            .table
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<Vec<String>>>();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+        if columns == 0 {
+            return;
+        }
+        let mut lines = vec![];
+        for (index, row) in rows.iter().enumerate() {
+            let cells = (0..columns)
+                .map(|column| {
+                    row.get(column)
+                        .map(|cell| cell.trim().replace('|', "\\|"))
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<String>>();
+            lines.push(format!("| {} |", cells.join(" | ")));
+            if index == 0 {
+                lines.push(format!("|{}", " --- |".repeat(columns)));
+            }
+        }
+        self.block();
+        self.inline(&lines.join("\n"));
+        self.block();
+    }
+}
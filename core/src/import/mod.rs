@@ -0,0 +1,433 @@
+mod evernote;
+mod notion;
+mod obsidian;
+
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{FSError, VaultError},
+    nfs::{PathCase, VaultPath},
+    NoteVault,
+};
+
+/// The tools Kimün can import notes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportSource {
+    /// An Obsidian vault directory
+    Obsidian,
+    /// An unzipped Notion export in Markdown & CSV format
+    Notion,
+    /// An Evernote `.enex` export file
+    Evernote,
+}
+
+impl ImportSource {
+    pub fn all() -> [ImportSource; 3] {
+        [Self::Obsidian, Self::Notion, Self::Evernote]
+    }
+
+    /// If the source is a single file instead of a directory
+    pub fn is_file(&self) -> bool {
+        matches!(self, Self::Evernote)
+    }
+}
+
+impl Display for ImportSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportSource::Obsidian => write!(f, "Obsidian"),
+            ImportSource::Notion => write!(f, "Notion"),
+            ImportSource::Evernote => write!(f, "Evernote"),
+        }
+    }
+}
+
+/// What an import wrote into the vault
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub notes: usize,
+    pub attachments: usize,
+    /// Everything that couldn't be converted as it was
+    pub issues: Vec<ImportIssue>,
+}
+
+/// Something in the source that couldn't be imported, or was imported
+/// with changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportIssue {
+    /// The file or note in the source
+    pub source: String,
+    pub reason: String,
+}
+
+impl ImportIssue {
+    fn new<S: AsRef<str>, R: AsRef<str>>(source: S, reason: R) -> Self {
+        Self {
+            source: source.as_ref().to_string(),
+            reason: reason.as_ref().to_string(),
+        }
+    }
+}
+
+impl Display for ImportIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.source, self.reason)
+    }
+}
+
+/// The notes and files to write, with paths relative to the
+/// directory the import goes into
+#[derive(Default)]
+struct ImportPlan {
+    notes: Vec<PlannedNote>,
+    files: Vec<PlannedFile>,
+    issues: Vec<ImportIssue>,
+}
+
+struct PlannedNote {
+    source: String,
+    path: VaultPath,
+    text: String,
+}
+
+struct PlannedFile {
+    source: String,
+    path: VaultPath,
+    data: FileData,
+}
+
+enum FileData {
+    Copy(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+/// Converts the export at `from` and writes it into the `into` directory
+/// of the vault. Nothing already in the vault is overwritten.
+pub(crate) fn import<P: AsRef<Path>>(
+    vault: &NoteVault,
+    source: ImportSource,
+    from: P,
+    into: &VaultPath,
+) -> Result<ImportReport, VaultError> {
+    let from = from.as_ref();
+    info!("Importing {} notes from {}", source, from.to_string_lossy());
+    let plan = match source {
+        ImportSource::Obsidian => obsidian::plan(from)?,
+        ImportSource::Notion => notion::plan(from)?,
+        ImportSource::Evernote => evernote::plan(from)?,
+    };
+
+    let mut report = ImportReport {
+        issues: plan.issues,
+        ..Default::default()
+    };
+    for file in plan.files {
+        let path = into.append(&file.path);
+        if vault.exists(&path).is_some() {
+            report.issues.push(ImportIssue::new(
+                file.source,
+                format!("{} already exists", path),
+            ));
+            continue;
+        }
+        match write_file(&vault.workspace_path, &path, file.data) {
+            Ok(()) => report.attachments += 1,
+            Err(e) => {
+                warn!("Can't import {}: {}", file.source, e);
+                report
+                    .issues
+                    .push(ImportIssue::new(file.source, e.to_string()));
+            }
+        }
+    }
+    for note in plan.notes {
+        let path = into.append(&note.path);
+        match vault.create_note(&path, note.text) {
+            Ok(_) => report.notes += 1,
+            Err(e) => {
+                warn!("Can't import {}: {}", note.source, e);
+                report
+                    .issues
+                    .push(ImportIssue::new(note.source, e.to_string()));
+            }
+        }
+    }
+    info!(
+        "Imported {} notes and {} attachments, {} issues",
+        report.notes,
+        report.attachments,
+        report.issues.len()
+    );
+    Ok(report)
+}
+
+fn write_file(workspace_path: &Path, path: &VaultPath, data: FileData) -> Result<(), FSError> {
+    let os_path = path.to_pathbuf(workspace_path);
+    if let Some(parent) = os_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match data {
+        FileData::Copy(source) => std::fs::copy(source, os_path).map(|_| ()),
+        FileData::Bytes(bytes) => std::fs::write(os_path, bytes),
+    }?;
+    Ok(())
+}
+
+/// Fails if the source is not an existing directory
+fn check_directory(from: &Path) -> Result<(), VaultError> {
+    if from.is_dir() {
+        Ok(())
+    } else {
+        Err(FSError::InvalidPath {
+            path: from.to_string_lossy().to_string(),
+        })?
+    }
+}
+
+/// Reads a text file from the export
+fn read_text(file: &Path, path: &VaultPath) -> Result<String, FSError> {
+    let bytes = std::fs::read(file)?;
+    Ok(crate::nfs::decode_text(bytes, path))
+}
+
+/// Keeps the paths given to the imported files unique, adding a
+/// number to the name when it's taken
+#[derive(Default)]
+struct UsedPaths {
+    keys: HashSet<String>,
+}
+
+impl UsedPaths {
+    fn take(&mut self, path: VaultPath) -> VaultPath {
+        let mut path = path;
+        // Insensitive, so it works in any file system
+        while !self.keys.insert(path.key(PathCase::Insensitive)) {
+            path = path.get_name_on_conflict();
+        }
+        path
+    }
+}
+
+/// A Markdown link from a note to a path, both relative to the same directory
+fn markdown_link<S: AsRef<str>>(label: S, from_note: &VaultPath, to: &VaultPath) -> String {
+    format!("[{}]({})", label.as_ref(), link_destination(from_note, to))
+}
+
+/// The destination of a link from a note, between `<>` when it has
+/// characters that would end it
+fn link_destination(from_note: &VaultPath, to: &VaultPath) -> String {
+    let destination = to.relative_to(&from_note.get_parent_path().0);
+    if destination.contains([' ', '(', ')']) {
+        format!("<{}>", destination)
+    } else {
+        destination
+    }
+}
+
+/// Applies `replace` to each line of a note outside the code blocks
+fn replace_outside_code<F>(text: &str, mut replace: F) -> String
+where
+    F: FnMut(&str) -> String,
+{
+    let mut in_code = false;
+    text.split('\n')
+        .map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code = !in_code;
+            }
+            if in_code {
+                line.to_string()
+            } else {
+                replace(line)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Makes a text valid as a single file name
+fn file_name<S: AsRef<str>>(name: S) -> String {
+    let name = name.as_ref().trim().replace('/', "-");
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::{
+        nfs::VaultPath,
+        test_utils::{indexed_vault, TestDir},
+        NoteVault,
+    };
+
+    use super::ImportSource;
+
+    /// Creates an empty vault and a source directory in a new test directory
+    fn create_vault() -> (TestDir, PathBuf, NoteVault) {
+        let (dir, vault) = indexed_vault(&[]);
+        let source = dir.join("source");
+        std::fs::create_dir_all(&source).unwrap();
+        (dir, source, vault)
+    }
+
+    fn write(base: &Path, path: &str, content: &[u8]) {
+        let file = base.join(path);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(file, content).unwrap();
+    }
+
+    fn read(vault: &NoteVault, path: &str) -> String {
+        std::fs::read_to_string(vault.workspace_path.join(path)).unwrap()
+    }
+
+    #[test]
+    fn import_obsidian_vault() {
+        let (_dir, source, vault) = create_vault();
+        write(&source, ".obsidian/app.json", b"{}");
+        write(
+            &source,
+            "Projects/Plan.md",
+            b"---\ntags: [work]\n---\nSee [[Ideas]], [[Ideas#Next|what's next]] and [[Missing]]\n![[diagram.png]]\n```\n[[Ideas]]\n```\n",
+        );
+        write(&source, "Ideas.md", b"Back to [[Projects/Plan]]");
+        write(&source, "assets/diagram.png", b"png");
+
+        let report = vault
+            .import_notes(
+                ImportSource::Obsidian,
+                &source,
+                &VaultPath::from("imported"),
+            )
+            .unwrap();
+
+        assert_eq!(2, report.notes);
+        assert_eq!(1, report.attachments);
+        assert_eq!(1, report.issues.len());
+        assert!(report.issues[0].reason.contains("[[Missing]]"));
+        assert_eq!(
+            "---\ntags: [work]\n---\nSee [Ideas](../Ideas.md), [what's next](../Ideas.md) and [[Missing]]\n![diagram.png](../assets/diagram.png)\n```\n[[Ideas]]\n```\n",
+            read(&vault, "imported/Projects/Plan.md")
+        );
+        assert_eq!(
+            "Back to [Projects/Plan](Projects/Plan.md)",
+            read(&vault, "imported/Ideas.md")
+        );
+        assert!(!vault.workspace_path.join("imported/.obsidian").exists());
+        assert!(vault
+            .exists(&VaultPath::from("imported/assets/diagram.png"))
+            .is_some());
+
+        // Nothing is overwritten
+        let report = vault
+            .import_notes(
+                ImportSource::Obsidian,
+                &source,
+                &VaultPath::from("imported"),
+            )
+            .unwrap();
+        assert_eq!(0, report.notes);
+        // The attachment and the two notes, plus the missing link
+        assert_eq!(4, report.issues.len());
+    }
+
+    #[test]
+    fn import_notion_export() {
+        let (_dir, source, vault) = create_vault();
+        let id = "0123456789abcdef0123456789abcdef";
+        write(
+            &source,
+            &format!("Home {}.md", id),
+            format!(
+                "# Home\n\n[Tasks](Tasks%20{id}.csv) and [Page](Home%20{id}/Sub%20Page%20{id}.md)\n"
+            )
+            .as_bytes(),
+        );
+        write(
+            &source,
+            &format!("Home {}/Sub Page {}.md", id, id),
+            b"# Sub Page\n\n![photo](photo.jpg)\n",
+        );
+        write(&source, &format!("Home {}/photo.jpg", id), b"jpg");
+        write(
+            &source,
+            &format!("Tasks {}.csv", id),
+            b"\xEF\xBB\xBFName,Done\nWrite | docs,Yes\n",
+        );
+        write(
+            &source,
+            &format!("Tasks {}_all.csv", id),
+            b"Name,Done\nWrite | docs,Yes\nShip,No\n",
+        );
+
+        let report = vault
+            .import_notes(ImportSource::Notion, &source, &VaultPath::root())
+            .unwrap();
+
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+        assert_eq!(3, report.notes);
+        assert_eq!(1, report.attachments);
+        assert_eq!(
+            "# Home\n\n[Tasks](Tasks.md) and [Page](<Home/Sub Page.md>)\n",
+            read(&vault, "Home.md")
+        );
+        assert_eq!(
+            "# Sub Page\n\n![photo](photo.jpg)\n",
+            read(&vault, "Home/Sub Page.md")
+        );
+        assert_eq!(
+            "# Tasks\n\n| Name | Done |\n| --- | --- |\n| Write \\| docs | Yes |\n| Ship | No |\n",
+            read(&vault, "Tasks.md")
+        );
+    }
+
+    #[test]
+    fn import_evernote_file() {
+        let (_dir, source, vault) = create_vault();
+        let enex = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-export SYSTEM "http://xml.evernote.com/pub/evernote-export3.dtd">
+<en-export>
+  <note>
+    <title>Trip / Plans</title>
+    <content><![CDATA[<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-note SYSTEM "http://xml.evernote.com/pub/enml2.dtd">
+<en-note><div>Visit <b>Lisbon</b> &amp; <a href="https://example.com">Porto</a></div><ul><li><div><en-todo checked="true"/>Book hotel</div></li><li><div><en-todo/>Pack</div></li></ul><en-media type="image/png" hash="5f4dcc3b5aa765d61d8327deb882cf99"/><en-crypt>secret</en-crypt></en-note>]]></content>
+    <created>20240102T030405Z</created>
+    <tag>travel</tag>
+    <tag>summer plans</tag>
+    <resource>
+      <data encoding="base64">cGFzc3dvcmQ=</data>
+      <mime>image/png</mime>
+      <resource-attributes><file-name>map.png</file-name></resource-attributes>
+    </resource>
+  </note>
+</en-export>"#;
+        let file = source.join("export.enex");
+        std::fs::write(&file, enex).unwrap();
+
+        let report = vault
+            .import_notes(ImportSource::Evernote, &file, &VaultPath::from("evernote"))
+            .unwrap();
+
+        assert_eq!(1, report.notes);
+        assert_eq!(1, report.attachments);
+        assert_eq!(1, report.issues.len());
+        assert_eq!(
+            "---\ntags: [travel, summer-plans]\ncreated: 2024-01-02T03:04:05Z\n---\n# Trip / Plans\n\nVisit **Lisbon** & [Porto](https://example.com)\n\n- [x] Book hotel\n- [ ] Pack\n\n![map.png](attachments/map.png)\n",
+            read(&vault, "evernote/Trip - Plans.md")
+        );
+        assert_eq!("password", read(&vault, "evernote/attachments/map.png"));
+    }
+}
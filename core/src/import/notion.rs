@@ -0,0 +1,274 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use ignore::WalkBuilder;
+use log::debug;
+use regex::{Captures, Regex};
+
+use crate::{
+    error::VaultError,
+    export::{is_external, join_link, percent_decode},
+    nfs::{PathCase, VaultPath},
+};
+
+use super::{
+    check_directory, link_destination, read_text, replace_outside_code, FileData, ImportIssue,
+    ImportPlan, PlannedFile, PlannedNote, UsedPaths,
+};
+
+/// The id Notion adds at the end of every page and database name
+const ID_SUFFIX_REGEX: &str = r"\s+[0-9a-f]{32}$";
+/// The destination of a Markdown link or image, Notion encodes the spaces
+const LINK_REGEX: &str = r"\]\(([^)\s]+)\)";
+/// Notion exports the databases twice, the one with this suffix has all the rows
+const FULL_DATABASE_SUFFIX: &str = "_all";
+
+enum NotionFile {
+    Page,
+    Database,
+    Attachment,
+}
+
+struct ExportedFile {
+    file: PathBuf,
+    source: VaultPath,
+    path: VaultPath,
+    kind: NotionFile,
+}
+
+/// Imports an unzipped Notion export in Markdown & CSV format. The ids are
+/// removed from the names, the links fixed to the new names and the
+/// databases turned into notes with a table.
+pub(super) fn plan(from: &Path) -> Result<ImportPlan, VaultError> {
+    check_directory(from)?;
+    let walker = WalkBuilder::new(from)
+        .standard_filters(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+    let mut found = vec![];
+    for entry in walker.flatten() {
+        if entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            let source = VaultPath::from_path(from, entry.path())?;
+            found.push((source, entry.into_path()));
+        }
+    }
+
+    // Only the full export of each database is imported
+    let full_databases = found
+        .iter()
+        .filter_map(|(source, _file)| {
+            let name = source.get_name();
+            let stem = name.strip_suffix(".csv")?;
+            stem.strip_suffix(FULL_DATABASE_SUFFIX)
+                .map(|stem| source.get_parent_path().0.append(&VaultPath::from(stem)))
+        })
+        .map(|database| database.key(PathCase::Insensitive))
+        .collect::<HashSet<String>>();
+
+    let mut used_paths = UsedPaths::default();
+    let mut files = vec![];
+    // The partial databases, with the full one they link to
+    let mut partial_databases = vec![];
+    for (source, file) in found {
+        let name = source.get_name();
+        let (stem, extension) = match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => (stem, extension.to_lowercase()),
+            _ => (name.as_str(), String::new()),
+        };
+        let (kind, stem) = match extension.as_str() {
+            "md" => (NotionFile::Page, stem),
+            "csv" => {
+                let full = stem.strip_suffix(FULL_DATABASE_SUFFIX);
+                let database = source
+                    .get_parent_path()
+                    .0
+                    .append(&VaultPath::from(full.unwrap_or(stem)));
+                if full.is_none() && full_databases.contains(&database.key(PathCase::Insensitive)) {
+                    debug!("Skipping {}, there's a full export", source);
+                    let full = format!("{}{}.csv", stem, FULL_DATABASE_SUFFIX);
+                    let full = source.get_parent_path().0.append(&VaultPath::from(full));
+                    partial_databases.push((source, full));
+                    continue;
+                }
+                (NotionFile::Database, full.unwrap_or(stem))
+            }
+            _ => (NotionFile::Attachment, stem),
+        };
+        let directory = source
+            .get_parent_path()
+            .0
+            .get_slices()
+            .iter()
+            .map(|slice| strip_id(slice))
+            .collect::<Vec<String>>()
+            .join("/");
+        let name = match kind {
+            NotionFile::Page | NotionFile::Database => format!("{}.md", strip_id(stem)),
+            NotionFile::Attachment if extension.is_empty() => strip_id(stem),
+            NotionFile::Attachment => format!("{}.{}", strip_id(stem), extension),
+        };
+        let path = used_paths.take(VaultPath::from(directory).append(&VaultPath::from(name)));
+        files.push(ExportedFile {
+            file,
+            source,
+            path,
+            kind,
+        });
+    }
+
+    let mut new_paths = files
+        .iter()
+        .map(|file| (file.source.key(PathCase::Insensitive), file.path.clone()))
+        .collect::<HashMap<String, VaultPath>>();
+    for (source, full) in partial_databases {
+        if let Some(path) = new_paths.get(&full.key(PathCase::Insensitive)).cloned() {
+            new_paths.insert(source.key(PathCase::Insensitive), path);
+        }
+    }
+    let pages = files
+        .iter()
+        .filter(|file| matches!(file.kind, NotionFile::Page))
+        .map(|file| file.path.key(PathCase::Insensitive))
+        .collect::<HashSet<String>>();
+
+    let mut plan = ImportPlan::default();
+    for exported in files {
+        let source = exported.source.to_string();
+        match exported.kind {
+            NotionFile::Attachment => plan.files.push(PlannedFile {
+                source,
+                path: exported.path,
+                data: FileData::Copy(exported.file),
+            }),
+            NotionFile::Page => match read_text(&exported.file, &exported.source) {
+                Ok(text) => {
+                    let text = convert_links(&text, &exported, &new_paths, &mut plan.issues);
+                    plan.notes.push(PlannedNote {
+                        source,
+                        path: exported.path,
+                        text,
+                    });
+                }
+                Err(e) => plan.issues.push(ImportIssue::new(source, e.to_string())),
+            },
+            NotionFile::Database => match database_note(&exported, &pages) {
+                Ok(text) => plan.notes.push(PlannedNote {
+                    source,
+                    path: exported.path,
+                    text,
+                }),
+                Err(e) => plan.issues.push(ImportIssue::new(source, e)),
+            },
+        }
+    }
+    Ok(plan)
+}
+
+fn id_suffix_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(ID_SUFFIX_REGEX).unwrap())
+}
+
+fn link_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(LINK_REGEX).unwrap())
+}
+
+fn strip_id(name: &str) -> String {
+    id_suffix_regex().replace(name, "").to_string()
+}
+
+/// Points the links to other files in the export to their new names
+fn convert_links(
+    text: &str,
+    page: &ExportedFile,
+    new_paths: &HashMap<String, VaultPath>,
+    issues: &mut Vec<ImportIssue>,
+) -> String {
+    let directory = page.source.get_parent_path().0;
+    replace_outside_code(text, |line| {
+        let converted = link_regex().replace_all(line, |captures: &Captures| {
+            let destination = &captures[1];
+            if is_external(destination) {
+                return captures[0].to_string();
+            }
+            let linked = join_link(&directory, &percent_decode(destination))
+                .and_then(|linked| new_paths.get(&linked.key(PathCase::Insensitive)));
+            match linked {
+                Some(linked) => format!("]({})", link_destination(&page.path, linked)),
+                None => {
+                    issues.push(ImportIssue::new(
+                        page.source.to_string(),
+                        format!("Link to a file that is not in the export: {}", destination),
+                    ));
+                    captures[0].to_string()
+                }
+            }
+        });
+        converted.to_string()
+    })
+}
+
+/// A note with the database as a table, the rows with a page link to it
+fn database_note(database: &ExportedFile, pages: &HashSet<String>) -> Result<String, String> {
+    let text = read_text(&database.file, &database.source).map_err(|e| e.to_string())?;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        rows.push(record.iter().map(table_cell).collect::<Vec<String>>());
+    }
+    let title = database
+        .path
+        .get_name()
+        .strip_suffix(".md")
+        .map_or_else(|| database.path.get_name(), |title| title.to_string());
+    let mut note = format!("# {}\n", title);
+    let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+    if columns == 0 {
+        return Ok(note);
+    }
+    // The pages of the rows are in a directory named as the database
+    let pages_directory = database
+        .path
+        .get_parent_path()
+        .0
+        .append(&VaultPath::from(title));
+    note.push('\n');
+    for (index, row) in rows.iter().enumerate() {
+        let cells = (0..columns).map(|column| {
+            let cell = row.get(column).cloned().unwrap_or_default();
+            let page = pages_directory.append(&VaultPath::from(format!("{}.md", cell)));
+            if index > 0 && column == 0 && pages.contains(&page.key(PathCase::Insensitive)) {
+                format!("[{}]({})", cell, link_destination(&database.path, &page))
+            } else {
+                cell
+            }
+        });
+        note.push_str(&format!(
+            "| {} |\n",
+            cells.collect::<Vec<String>>().join(" | ")
+        ));
+        if index == 0 {
+            note.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+        }
+    }
+    Ok(note)
+}
+
+fn table_cell(value: &str) -> String {
+    value
+        .replace('|', "\\|")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
@@ -0,0 +1,187 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use ignore::WalkBuilder;
+use log::debug;
+use regex::{Captures, Regex};
+
+use crate::{error::VaultError, nfs::VaultPath};
+
+use super::{
+    check_directory, markdown_link, read_text, replace_outside_code, FileData, ImportIssue,
+    ImportPlan, PlannedFile, PlannedNote,
+};
+
+const NOTE_EXTENSION: &str = "md";
+/// `[[target#heading|label]]`, with an optional `!` for embeds
+const WIKI_LINK_REGEX: &str = r"(!?)\[\[([^\[\]|#]*)(?:#([^\[\]|]*))?(?:\|([^\[\]]*))?\]\]";
+/// The label Obsidian uses to size embedded images, like `300` or `300x200`
+const SIZE_REGEX: &str = r"^\d+(x\d+)?$";
+
+/// The files in the Obsidian vault, to resolve the links by name
+struct VaultFiles {
+    paths: Vec<VaultPath>,
+    /// The lowercase names of the files, and of the notes without the
+    /// extension, with the files that have them
+    names: HashMap<String, Vec<usize>>,
+}
+
+impl VaultFiles {
+    fn new(paths: Vec<VaultPath>) -> Self {
+        let mut names: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, path) in paths.iter().enumerate() {
+            let name = path.get_name().to_lowercase();
+            if let Some(stem) = name.strip_suffix(&format!(".{}", NOTE_EXTENSION)) {
+                names.entry(stem.to_string()).or_default().push(index);
+            }
+            names.entry(name).or_default().push(index);
+        }
+        Self { paths, names }
+    }
+
+    /// Finds the file a link goes to like Obsidian does: by name, or by
+    /// the end of its path, preferring the one closest to the note
+    fn resolve(&self, target: &str, from: &VaultPath) -> Option<&VaultPath> {
+        let target = target.trim().trim_start_matches('/').to_lowercase();
+        let candidates = if target.contains('/') {
+            let with_extension = format!("{}.{}", target, NOTE_EXTENSION);
+            self.paths
+                .iter()
+                .enumerate()
+                .filter(|(_index, path)| {
+                    let key = path.get_slices().join("/").to_lowercase();
+                    [&target, &with_extension]
+                        .iter()
+                        .any(|target| key == **target || key.ends_with(&format!("/{}", target)))
+                })
+                .map(|(index, _path)| index)
+                .collect()
+        } else {
+            self.names.get(&target).cloned().unwrap_or_default()
+        };
+        let directory = from.get_parent_path().0;
+        candidates
+            .into_iter()
+            .map(|index| &self.paths[index])
+            .min_by_key(|path| {
+                let same_directory = path.get_parent_path().0 == directory;
+                (!same_directory, path.get_slices().len())
+            })
+    }
+}
+
+/// Imports an Obsidian vault: notes are copied with their wiki links
+/// turned into Markdown links, the rest of the files as attachments.
+/// The hidden directories, like `.obsidian`, are skipped.
+pub(super) fn plan(from: &Path) -> Result<ImportPlan, VaultError> {
+    check_directory(from)?;
+    let mut sources: Vec<(VaultPath, PathBuf)> = vec![];
+    let walker = WalkBuilder::new(from)
+        .standard_filters(false)
+        .hidden(true)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+    for entry in walker.flatten() {
+        if entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            let path = VaultPath::from_path(from, entry.path())?;
+            sources.push((path, entry.into_path()));
+        }
+    }
+    let files = VaultFiles::new(sources.iter().map(|(path, _)| path.to_owned()).collect());
+
+    let mut plan = ImportPlan::default();
+    for (path, file) in sources {
+        let source = path.to_string();
+        let is_note = path
+            .get_extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(NOTE_EXTENSION));
+        if !is_note {
+            plan.files.push(PlannedFile {
+                source,
+                path,
+                data: FileData::Copy(file),
+            });
+            continue;
+        }
+        match read_text(&file, &path) {
+            Ok(text) => {
+                debug!("Converting {}", path);
+                let text = convert_links(&text, &path, &files, &mut plan.issues);
+                plan.notes.push(PlannedNote { source, path, text });
+            }
+            Err(e) => plan.issues.push(ImportIssue::new(source, e.to_string())),
+        }
+    }
+    Ok(plan)
+}
+
+fn wiki_link_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(WIKI_LINK_REGEX).unwrap())
+}
+
+fn size_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(SIZE_REGEX).unwrap())
+}
+
+/// Replaces the wiki links and embeds outside code blocks with Markdown
+/// links. The ones that can't be resolved are left as they are.
+fn convert_links(
+    text: &str,
+    path: &VaultPath,
+    files: &VaultFiles,
+    issues: &mut Vec<ImportIssue>,
+) -> String {
+    replace_outside_code(text, |line| {
+        if !line.contains("[[") {
+            return line.to_string();
+        }
+        let converted = wiki_link_regex().replace_all(line, |captures: &Captures| {
+            let original = captures[0].to_string();
+            let embed = !captures[1].is_empty();
+            let target = captures[2].trim();
+            let heading = captures.get(3).map(|heading| heading.as_str().trim());
+            let label = captures.get(4).map(|label| label.as_str().trim());
+            if target.is_empty() {
+                // A heading in the same note, Kimün doesn't link to headings
+                return label.or(heading).unwrap_or_default().to_string();
+            }
+            let Some(linked) = files.resolve(target, path) else {
+                issues.push(ImportIssue::new(
+                    path.to_string(),
+                    format!("Link to a missing note: {}", original),
+                ));
+                return original;
+            };
+            let is_note = linked
+                .get_extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case(NOTE_EXTENSION));
+            if embed && !is_note {
+                let label = label
+                    .filter(|label| !size_regex().is_match(label))
+                    .map_or_else(|| linked.get_name(), |label| label.to_string());
+                return format!("!{}", markdown_link(label, path, linked));
+            }
+            if embed {
+                issues.push(ImportIssue::new(
+                    path.to_string(),
+                    format!("Embedded note is now a link: {}", original),
+                ));
+            }
+            let label = match (label, heading) {
+                (Some(label), _) => label.to_string(),
+                (None, Some(heading)) => format!("{} > {}", target, heading),
+                (None, None) => target.to_string(),
+            };
+            markdown_link(label, path, linked)
+        });
+        converted.to_string()
+    })
+}
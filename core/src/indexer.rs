@@ -0,0 +1,791 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt::Display,
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+use ignore::{ParallelVisitor, ParallelVisitorBuilder, WalkState};
+use log::{debug, error, info, warn};
+use rayon::prelude::*;
+use rusqlite::Connection;
+
+use crate::{
+    config::VaultConfig,
+    db,
+    error::{DBError, VaultError},
+    nfs::{self, note_types::NoteTypes, EntryData, NoteEntryData, PathCase, VaultEntry, VaultPath},
+    NoteDetails, NotesValidation,
+};
+
+/// Notes written to the DB between progress reports and cancellation checks
+const WRITE_BATCH_SIZE: usize = 200;
+/// Notes seen or parsed between progress reports
+const REPORT_EVERY: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexStage {
+    /// Walking the vault looking for notes
+    Scanning,
+    /// Reading and parsing the new and changed notes
+    Parsing,
+    /// Writing the changes to the DB
+    Writing,
+    Done,
+}
+
+/// How far the indexing has gone
+#[derive(Debug, Clone)]
+pub struct IndexProgress {
+    pub stage: IndexStage,
+    /// Notes found in the vault
+    pub seen: usize,
+    /// Notes read and parsed, out of `to_parse`
+    pub parsed: usize,
+    pub to_parse: usize,
+    /// Notes added, updated or removed in the DB, out of `to_write`
+    pub written: usize,
+    pub to_write: usize,
+    pub elapsed: Duration,
+    stage_elapsed: Duration,
+}
+
+impl IndexProgress {
+    /// Estimated time left for the current stage, based on how fast it's
+    /// going. There's no estimate while scanning, as the number of notes
+    /// is not known yet.
+    pub fn eta(&self) -> Option<Duration> {
+        let (done, total) = match self.stage {
+            IndexStage::Scanning => return None,
+            IndexStage::Parsing => (self.parsed, self.to_parse),
+            IndexStage::Writing => (self.written, self.to_write),
+            IndexStage::Done => return Some(Duration::ZERO),
+        };
+        if done == 0 {
+            return None;
+        }
+        let per_note = self.stage_elapsed.div_f64(done as f64);
+        Some(per_note.mul_f64(total.saturating_sub(done) as f64))
+    }
+}
+
+/// A file that couldn't be indexed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedFile {
+    pub path: VaultPath,
+    pub reason: String,
+}
+
+impl Display for SkippedFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.reason)
+    }
+}
+
+/// What changed in the index, and the files that were skipped. A skipped
+/// note that was already indexed keeps its previous data.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexReport {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// Sorted by path
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// Keeps the counters shared by the indexing threads and reports
+/// them, the callback returns false to cancel the indexing
+struct Tracker<'a, F> {
+    on_progress: &'a F,
+    start: Instant,
+    stage: Mutex<(IndexStage, Instant)>,
+    seen: AtomicUsize,
+    parsed: AtomicUsize,
+    to_parse: AtomicUsize,
+    written: AtomicUsize,
+    to_write: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl<'a, F> Tracker<'a, F>
+where
+    F: Fn(&IndexProgress) -> bool + Sync,
+{
+    fn new(on_progress: &'a F) -> Self {
+        let now = Instant::now();
+        Self {
+            on_progress,
+            start: now,
+            stage: Mutex::new((IndexStage::Scanning, now)),
+            seen: AtomicUsize::new(0),
+            parsed: AtomicUsize::new(0),
+            to_parse: AtomicUsize::new(0),
+            written: AtomicUsize::new(0),
+            to_write: AtomicUsize::new(0),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    fn set_stage(&self, stage: IndexStage) {
+        *self.stage.lock().unwrap_or_else(PoisonError::into_inner) = (stage, Instant::now());
+        self.report();
+    }
+
+    fn report(&self) {
+        let (stage, stage_start) = *self.stage.lock().unwrap_or_else(PoisonError::into_inner);
+        let progress = IndexProgress {
+            stage,
+            seen: self.seen.load(Ordering::Relaxed),
+            parsed: self.parsed.load(Ordering::Relaxed),
+            to_parse: self.to_parse.load(Ordering::Relaxed),
+            written: self.written.load(Ordering::Relaxed),
+            to_write: self.to_write.load(Ordering::Relaxed),
+            elapsed: self.start.elapsed(),
+            stage_elapsed: stage_start.elapsed(),
+        };
+        if !(self.on_progress)(&progress) {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Adds to a counter, reporting every few notes
+    fn add(&self, counter: &AtomicUsize, count: usize) {
+        let previous = counter.fetch_add(count, Ordering::Relaxed);
+        if (previous + count) / REPORT_EVERY != previous / REPORT_EVERY {
+            self.report();
+        }
+    }
+
+    fn check_cancelled(&self) -> Result<(), VaultError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            info!("Indexing cancelled");
+            Err(VaultError::IndexCancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Why a note has to be parsed
+enum Change {
+    New,
+    /// Changed since it was cached, when the hash of the cached note is
+    /// set the note is only updated if the content changed
+    Modified {
+        cached_hash: Option<u64>,
+    },
+}
+
+/// Indexes the whole vault in a single pass: walks the vault in parallel,
+/// parses the new and changed notes in the rayon pool, and writes all the
+/// changes in a single transaction, so a cancelled or failed indexing
+/// doesn't leave the DB half updated.
+pub fn index_vault<P, F>(
+    workspace_path: P,
+    config: &VaultConfig,
+    connection: &mut Connection,
+    validation_mode: NotesValidation,
+    on_progress: &F,
+) -> Result<IndexReport, VaultError>
+where
+    P: AsRef<Path>,
+    F: Fn(&IndexProgress) -> bool + Sync,
+{
+    let workspace_path = workspace_path.as_ref();
+    let tracker = Tracker::new(on_progress);
+    tracker.report();
+
+    let (found, mut skipped) = scan_notes(workspace_path, config, &tracker);
+    tracker.check_cancelled()?;
+    debug!("Found {} notes", found.len());
+    let (found, collisions) = split_collisions(found, config.path_case);
+
+    // Keyed by the name as stored, if a note's name is now written
+    // differently on disk it's replaced
+    let mut cached = db::get_notes(connection, &VaultPath::root(), true, config.path_case)?
+        .into_iter()
+        .map(|note| (note.0.path.to_string(), note))
+        .collect::<HashMap<String, (NoteEntryData, NoteDetails)>>();
+    let mut to_parse = vec![];
+    for data in found {
+        match cached.remove(&data.path.to_string()) {
+            None => to_parse.push((data, Change::New)),
+            Some((cached_data, cached_details)) => match validation_mode {
+                NotesValidation::Full => to_parse.push((
+                    data,
+                    Change::Modified {
+                        cached_hash: Some(cached_details.data.hash),
+                    },
+                )),
+                NotesValidation::Fast => {
+                    if cached_data.size != data.size
+                        || cached_data.modified_secs != data.modified_secs
+                    {
+                        to_parse.push((data, Change::Modified { cached_hash: None }));
+                    }
+                }
+                NotesValidation::None => {}
+            },
+        }
+    }
+    // Notes that can't be read now keep their cached data
+    for file in &skipped {
+        cached.remove(&file.path.to_string());
+    }
+    skipped.extend(collisions);
+    let to_delete = cached
+        .into_values()
+        .map(|(data, _details)| data.path)
+        .collect::<Vec<VaultPath>>();
+
+    tracker.to_parse.store(to_parse.len(), Ordering::Relaxed);
+    tracker.set_stage(IndexStage::Parsing);
+    let parse_skipped = Mutex::new(vec![]);
+    let parsed = to_parse
+        .into_par_iter()
+        .filter_map(|(data, change)| {
+            if tracker.cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+            let result = load_details(workspace_path, &config.notes, &data);
+            tracker.add(&tracker.parsed, 1);
+            match result {
+                Ok(details) => match change {
+                    Change::New => Some((true, data, details)),
+                    Change::Modified {
+                        cached_hash: Some(hash),
+                    } if hash == details.data.hash => None,
+                    Change::Modified { .. } => Some((false, data, details)),
+                },
+                Err(reason) => {
+                    warn!("Can't index the note {}: {}", data.path, reason);
+                    parse_skipped
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push(SkippedFile {
+                            path: data.path,
+                            reason,
+                        });
+                    None
+                }
+            }
+        })
+        .collect::<Vec<(bool, NoteEntryData, NoteDetails)>>();
+    tracker.check_cancelled()?;
+    skipped.extend(
+        parse_skipped
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner),
+    );
+    skipped.sort_by_cached_key(|file| file.path.to_string());
+    let (to_add, to_modify): (Vec<_>, Vec<_>) = parsed.into_iter().partition(|note| note.0);
+    let to_add = to_add
+        .into_iter()
+        .map(|(_, data, details)| (data, details))
+        .collect::<Vec<_>>();
+    let to_modify = to_modify
+        .into_iter()
+        .map(|(_, data, details)| (data, details))
+        .collect::<Vec<_>>();
+
+    tracker.to_write.store(
+        to_add.len() + to_modify.len() + to_delete.len(),
+        Ordering::Relaxed,
+    );
+    tracker.set_stage(IndexStage::Writing);
+    debug!(
+        "Adding {} notes, updating {} and removing {}",
+        to_add.len(),
+        to_modify.len(),
+        to_delete.len()
+    );
+    // Dropping the transaction on cancellation rolls back the changes
+    let tx = connection.transaction().map_err(DBError::from)?;
+    for chunk in to_delete.chunks(WRITE_BATCH_SIZE) {
+        db::delete_notes(&tx, chunk)?;
+        tracker.add(&tracker.written, chunk.len());
+        tracker.check_cancelled()?;
+    }
+    for chunk in to_add.chunks(WRITE_BATCH_SIZE) {
+        db::insert_notes(&tx, chunk)?;
+        tracker.add(&tracker.written, chunk.len());
+        tracker.check_cancelled()?;
+    }
+    for chunk in to_modify.chunks(WRITE_BATCH_SIZE) {
+        db::update_notes(&tx, chunk)?;
+        tracker.add(&tracker.written, chunk.len());
+        tracker.check_cancelled()?;
+    }
+    tx.commit().map_err(DBError::from)?;
+    tracker.set_stage(IndexStage::Done);
+
+    if !skipped.is_empty() {
+        warn!("{} files skipped while indexing", skipped.len());
+    }
+    Ok(IndexReport {
+        added: to_add.len(),
+        updated: to_modify.len(),
+        removed: to_delete.len(),
+        skipped,
+    })
+}
+
+/// Splits the notes that have the same name as another one, when
+/// matching with `case`, from the ones to index. The first one by path
+/// is indexed, so it's the same one on every indexing.
+fn split_collisions(
+    mut found: Vec<NoteEntryData>,
+    case: PathCase,
+) -> (Vec<NoteEntryData>, Vec<SkippedFile>) {
+    found.sort_by_cached_key(|data| data.path.to_string());
+    let mut indexed: HashMap<String, VaultPath> = HashMap::new();
+    let mut collisions = vec![];
+    let found = found
+        .into_iter()
+        .filter(|data| match indexed.entry(data.path.key(case)) {
+            Entry::Occupied(first) => {
+                warn!("{} has the same name as {}", data.path, first.get());
+                collisions.push(SkippedFile {
+                    path: data.path.clone(),
+                    reason: format!("Same name as {}", first.get()),
+                });
+                false
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(data.path.clone());
+                true
+            }
+        })
+        .collect();
+    (found, collisions)
+}
+
+/// Reads and parses a note, a note that can't be read or makes the
+/// parser panic is skipped instead of failing the whole indexing
+fn load_details(
+    workspace_path: &Path,
+    types: &NoteTypes,
+    data: &NoteEntryData,
+) -> Result<NoteDetails, String> {
+    std::panic::catch_unwind(AssertUnwindSafe(|| {
+        data.load_details(workspace_path, &data.path, types)
+    }))
+    .map_err(|_| "Can't parse the note".to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Walks the whole vault in parallel returning the notes found and
+/// the entries that couldn't be read
+fn scan_notes<F>(
+    workspace_path: &Path,
+    config: &VaultConfig,
+    tracker: &Tracker<F>,
+) -> (Vec<NoteEntryData>, Vec<SkippedFile>)
+where
+    F: Fn(&IndexProgress) -> bool + Sync,
+{
+    let notes = Mutex::new(vec![]);
+    let skipped = Mutex::new(vec![]);
+    let mut builder = ScanVisitorBuilder {
+        workspace_path: workspace_path.to_path_buf(),
+        types: &config.notes,
+        tracker,
+        notes: &notes,
+        skipped: &skipped,
+    };
+    nfs::get_file_walker(workspace_path, &VaultPath::root(), true, &config.ignore)
+        .visit(&mut builder);
+    (
+        notes.into_inner().unwrap_or_else(PoisonError::into_inner),
+        skipped.into_inner().unwrap_or_else(PoisonError::into_inner),
+    )
+}
+
+/// The path of the entry that failed, if the error has one
+fn error_path(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            error_path(err)
+        }
+        _ => None,
+    }
+}
+
+struct ScanVisitorBuilder<'a, F> {
+    workspace_path: PathBuf,
+    types: &'a NoteTypes,
+    tracker: &'a Tracker<'a, F>,
+    notes: &'a Mutex<Vec<NoteEntryData>>,
+    skipped: &'a Mutex<Vec<SkippedFile>>,
+}
+
+impl<'s, 'a: 's, F> ParallelVisitorBuilder<'s> for ScanVisitorBuilder<'a, F>
+where
+    F: Fn(&IndexProgress) -> bool + Sync,
+{
+    fn build(&mut self) -> Box<dyn ParallelVisitor + 's> {
+        Box::new(ScanVisitor {
+            workspace_path: self.workspace_path.clone(),
+            types: self.types,
+            tracker: self.tracker,
+            notes: self.notes,
+            skipped: self.skipped,
+        })
+    }
+}
+
+struct ScanVisitor<'a, F> {
+    workspace_path: PathBuf,
+    types: &'a NoteTypes,
+    tracker: &'a Tracker<'a, F>,
+    notes: &'a Mutex<Vec<NoteEntryData>>,
+    skipped: &'a Mutex<Vec<SkippedFile>>,
+}
+
+impl<F> ScanVisitor<'_, F> {
+    fn skip(&self, path: Option<&Path>, reason: String) {
+        let path = path.and_then(|path| VaultPath::from_path(&self.workspace_path, path).ok());
+        match path {
+            Some(path) => {
+                warn!("Skipping {}: {}", path, reason);
+                self.skipped
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(SkippedFile { path, reason });
+            }
+            None => error!("{}", reason),
+        }
+    }
+}
+
+impl<F> ParallelVisitor for ScanVisitor<'_, F>
+where
+    F: Fn(&IndexProgress) -> bool + Sync,
+{
+    fn visit(&mut self, entry: Result<ignore::DirEntry, ignore::Error>) -> WalkState {
+        if self.tracker.cancelled.load(Ordering::Relaxed) {
+            return WalkState::Quit;
+        }
+        let dir = match entry {
+            Ok(dir) => dir,
+            Err(e) => {
+                self.skip(error_path(&e), e.to_string());
+                return WalkState::Continue;
+            }
+        };
+        match VaultEntry::from_path(&self.workspace_path, dir.path(), self.types) {
+            Ok(VaultEntry {
+                data: EntryData::Note(data),
+                ..
+            }) => {
+                self.notes
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(data);
+                self.tracker.add(&self.tracker.seen, 1);
+            }
+            Ok(_) => {}
+            Err(e) => self.skip(Some(dir.path()), e.to_string()),
+        }
+        WalkState::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use rusqlite::Connection;
+
+    use crate::{
+        config::VaultConfig,
+        db,
+        nfs::{self, PathCase, VaultPath},
+        tasks::TaskFilter,
+        test_utils::{db_path, indexed_vault, vault_files, workspace},
+        NoteVault, NotesValidation,
+    };
+
+    use super::{index_vault, IndexProgress, IndexStage};
+
+    fn progress(stage: IndexStage, parsed: usize, to_parse: usize) -> IndexProgress {
+        IndexProgress {
+            stage,
+            seen: to_parse,
+            parsed,
+            to_parse,
+            written: 0,
+            to_write: 0,
+            elapsed: Duration::from_secs(2),
+            stage_elapsed: Duration::from_secs(2),
+        }
+    }
+
+    #[test]
+    fn eta_from_stage_rate() {
+        assert_eq!(None, progress(IndexStage::Scanning, 0, 0).eta());
+        assert_eq!(None, progress(IndexStage::Parsing, 0, 10).eta());
+        assert_eq!(
+            Some(Duration::from_secs(6)),
+            progress(IndexStage::Parsing, 10, 40).eta()
+        );
+        assert_eq!(
+            Some(Duration::ZERO),
+            progress(IndexStage::Done, 40, 40).eta()
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn skip_unreadable_notes() {
+        let dir = vault_files(&[("note.md", "# Note")]);
+        let workspace = workspace(&dir);
+        // Windows-1252 text
+        std::fs::write(workspace.join("legacy.md"), b"# Caf\xE9").unwrap();
+        std::fs::write(workspace.join("todo.txt"), "Todo\nbuy milk").unwrap();
+        std::fs::write(workspace.join("image.png"), "not a note").unwrap();
+        std::os::unix::fs::symlink(workspace.join("missing"), workspace.join("broken.md")).unwrap();
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        db::migrate(&mut conn).unwrap();
+        let report = index_vault(
+            &workspace,
+            &VaultConfig::default(),
+            &mut conn,
+            NotesValidation::Full,
+            &|_| true,
+        )
+        .unwrap();
+
+        assert_eq!(3, report.added);
+        assert_eq!(1, report.skipped.len());
+        assert_eq!(VaultPath::from("broken.md"), report.skipped[0].path);
+        let notes = db::search_terms(&mut conn, "Café").unwrap();
+        assert_eq!(1, notes.len());
+        let notes = db::search_terms(&mut conn, "milk").unwrap();
+        assert_eq!(VaultPath::from("todo.txt"), notes[0].0.path);
+    }
+
+    #[test]
+    fn skip_name_collisions() {
+        let dir = vault_files(&[("Note.md", "# Upper"), ("note.md", "# Lower")]);
+        let workspace = workspace(&dir);
+
+        let index = |config: &VaultConfig, conn: &mut Connection| {
+            index_vault(&workspace, config, conn, NotesValidation::Full, &|_| true).unwrap()
+        };
+        let mut conn = Connection::open_in_memory().unwrap();
+        db::migrate(&mut conn).unwrap();
+        let mut config = VaultConfig::default();
+        let report = index(&config, &mut conn);
+        // Two files on case sensitive file systems
+        let case_sensitive_fs = report.added == 2;
+        if case_sensitive_fs {
+            assert!(report.skipped.is_empty());
+
+            config.path_case = PathCase::Insensitive;
+            let report = index(&config, &mut conn);
+            assert_eq!(1, report.removed);
+            assert_eq!(
+                vec![VaultPath::from("note.md")],
+                report
+                    .skipped
+                    .iter()
+                    .map(|file| file.path.clone())
+                    .collect::<Vec<VaultPath>>()
+            );
+        }
+        let notes = db::get_notes(&mut conn, &VaultPath::root(), true, config.path_case).unwrap();
+        assert_eq!(1, notes.len());
+        assert_eq!(
+            Some(VaultPath::from("Note.md")),
+            db::find_note_path(
+                &mut conn,
+                &VaultPath::from("NOTE.md"),
+                PathCase::Insensitive
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            None,
+            db::find_note_path(&mut conn, &VaultPath::from("NOTE.md"), PathCase::Sensitive)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn index_note_aliases() {
+        let dir = vault_files(&[("note.md", "---\naliases: [First]\n---\n# Title")]);
+        let workspace = workspace(&dir);
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        db::migrate(&mut conn).unwrap();
+        let config = VaultConfig::default();
+        index_vault(
+            &workspace,
+            &config,
+            &mut conn,
+            NotesValidation::Full,
+            &|_| true,
+        )
+        .unwrap();
+        let notes = db::get_note_names(&mut conn).unwrap();
+        assert_eq!("Title", notes[0].get_title());
+        assert_eq!(vec!["First"], notes[0].data.aliases);
+
+        std::fs::write(
+            workspace.join("note.md"),
+            "---\naliases: [Second, Third]\n---\n# Title",
+        )
+        .unwrap();
+        index_vault(
+            &workspace,
+            &config,
+            &mut conn,
+            NotesValidation::Full,
+            &|_| true,
+        )
+        .unwrap();
+        let notes = db::get_note_names(&mut conn).unwrap();
+        assert_eq!(vec!["Second", "Third"], notes[0].data.aliases);
+    }
+
+    /// Leaves the index as an older version would, with the notes
+    /// indexed at the schema version
+    fn index_at_version(workspace: &Path, db_path: &Path, version: u32) {
+        std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+        let mut conn = Connection::open(db_path).unwrap();
+        db::migrate_to(&mut conn, version).unwrap();
+        for entry in std::fs::read_dir(workspace).unwrap() {
+            let file = entry.unwrap();
+            let text = std::fs::read_to_string(file.path()).unwrap();
+            let metadata = file.metadata().unwrap();
+            let modified = metadata
+                .modified()
+                .unwrap()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let path = VaultPath::from(file.file_name().to_string_lossy().to_string());
+            let (base_path, name) = path.get_parent_path();
+            conn.execute(
+                "INSERT INTO notes (path, title, size, modified, hash, basePath, noteName, pathKey, basePathKey) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                rusqlite::params![
+                    path.to_string(),
+                    NoteVault::get_title(&text),
+                    metadata.len(),
+                    modified,
+                    nfs::hash_text(&text).to_string(),
+                    base_path.to_string(),
+                    name,
+                    path.key(PathCase::Insensitive),
+                    base_path.key(PathCase::Insensitive),
+                ],
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn upgraded_index_is_filled() {
+        let dir = vault_files(&[
+            (
+                "note.md",
+                "---\naliases: [First]\n---\n# Title\n\n- [ ] Call Bill\n\nSee [[other]]",
+            ),
+            ("other.md", "# Other"),
+        ]);
+        let workspace = workspace(&dir);
+        let db_path = db_path(&dir);
+        // Before the aliases
+        index_at_version(&workspace, &db_path, 2);
+
+        let vault = NoteVault::with_db_path(&workspace, &db_path).unwrap();
+        vault.init_and_validate().unwrap();
+
+        let mut conn = Connection::open(&db_path).unwrap();
+        let notes = db::get_note_names(&mut conn).unwrap();
+        let note = notes
+            .iter()
+            .find(|note| note.path == VaultPath::from("note.md"))
+            .unwrap();
+        assert_eq!(vec!["First"], note.data.aliases);
+
+        let tasks = vault.query_tasks(&TaskFilter::default()).unwrap();
+        assert_eq!(1, tasks.len());
+        assert_eq!("Call Bill", tasks[0].1.text);
+        assert_eq!(vec!["Title"], tasks[0].1.breadcrumb);
+
+        // The links table is what renaming uses to update the links
+        let backlinks = vault.get_backlinks(&VaultPath::from("other.md")).unwrap();
+        assert_eq!(VaultPath::from("note.md"), backlinks[0].0);
+        let changed = vault
+            .rename_note(&VaultPath::from("other.md"), &VaultPath::from("renamed.md"))
+            .unwrap();
+        assert_eq!(vec![VaultPath::from("note.md")], changed);
+
+        let properties = vault
+            .get_note_properties(&VaultPath::from("note.md"))
+            .unwrap();
+        assert_eq!(5, properties.stats.words);
+        assert!(properties.created_secs > 0);
+        assert_eq!(
+            vec!["Title"],
+            vault.get_headings(&VaultPath::from("note.md")).unwrap()
+        );
+        assert_eq!(
+            vec![VaultPath::from("note.md")],
+            vault
+                .search_notes("words:>3")
+                .unwrap()
+                .into_iter()
+                .map(|note| note.path)
+                .collect::<Vec<VaultPath>>()
+        );
+    }
+
+    #[test]
+    fn directories_from_the_index() {
+        let (_dir, vault) = indexed_vault(&[
+            ("home.md", "# Home"),
+            ("projects/kimun/docs/api.md", "# API"),
+            ("Journal/today.md", "# Today"),
+            ("images/photo.png", "not a note"),
+        ]);
+
+        // Only the directories with notes, even if they are deeper
+        assert_eq!(
+            vec![VaultPath::from("Journal"), VaultPath::from("projects")],
+            vault.get_subdirectories(&VaultPath::root()).unwrap()
+        );
+        assert_eq!(
+            vec![VaultPath::from("projects/kimun")],
+            vault
+                .get_subdirectories(&VaultPath::from("projects"))
+                .unwrap()
+        );
+        assert!(vault
+            .get_subdirectories(&VaultPath::from("projects/kimun/docs"))
+            .unwrap()
+            .is_empty());
+
+        vault
+            .delete_note(&VaultPath::from("projects/kimun/docs/api.md"))
+            .unwrap();
+        assert!(!vault
+            .workspace_path
+            .join("projects/kimun/docs/api.md")
+            .exists());
+        assert_eq!(
+            vec![VaultPath::from("Journal")],
+            vault.get_subdirectories(&VaultPath::root()).unwrap()
+        );
+        assert!(vault.delete_note(&VaultPath::from("missing.md")).is_err());
+    }
+}
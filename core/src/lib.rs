@@ -1,39 +1,81 @@
+#[cfg(feature = "async")]
+pub mod async_vault;
+pub mod config;
 mod content_data;
 mod db;
 pub mod error;
+pub mod export;
+pub mod find_replace;
+pub mod import;
+pub mod indexer;
+pub mod links;
 pub mod nfs;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod tasks;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 pub mod utilities;
 
 use std::{
-    collections::HashSet,
     fmt::Display,
     path::{Path, PathBuf},
     sync::mpsc::{Receiver, Sender},
 };
 
+#[cfg(feature = "async")]
+pub use async_vault::AsyncNoteVault;
 use chrono::Utc;
+use config::{Pin, SavedSearch, VaultConfig};
 use content_data::{extract_data, NoteContentData};
+pub use content_data::{NoteStats, OutlineHeading};
 use db::VaultDB;
 use error::{DBError, FSError, VaultError};
-use log::{debug, info};
+use export::{join_link, percent_decode};
+use export::{ExportReport, ExportTarget};
+use find_replace::{NoteReplacement, ReplacePreview, TextFinder};
+use import::{ImportReport, ImportSource};
+use indexer::{IndexProgress, IndexReport, SkippedFile};
+use links::{LinkEdit, LinkKind, NoteLink};
+use log::{debug, error, info, warn};
 use nfs::{
-    load_note, save_note, visitor::NoteListVisitorBuilder, NoteEntryData, VaultEntry, VaultPath,
+    load_note,
+    note_types::{NoteFormat, NoteTypes},
+    save_note,
+    visitor::NoteListVisitorBuilder,
+    NoteEntryData, VaultEntry, VaultPath,
 };
+use tasks::{NoteTask, TaskFilter};
 use utilities::path_to_string;
 
 const JOURNAL_PATH: &str = "journal";
+const VAULT_CONFIG_DIR: &str = ".kimun";
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct NoteVault {
     pub workspace_path: PathBuf,
     vault_db: VaultDB,
+    config: VaultConfig,
 }
 
 impl NoteVault {
     /// Creates a new instance of the Note Vault.
     /// Make sure you call `NoteVault::init_and_validate(&self)` to initialize the DB index if
     /// needed
+    /// The index DB is stored in the user's cache directory, use
+    /// `NoteVault::with_db_path` to store it somewhere else.
     pub fn new<P: AsRef<Path>>(workspace_path: P) -> Result<Self, VaultError> {
+        let db_path = db::default_db_path(&workspace_path);
+        Self::with_db_path(workspace_path, db_path)
+    }
+
+    /// Creates a new instance of the Note Vault keeping the index DB
+    /// at the specified file path. Fails if the vault config can't be
+    /// read or parsed.
+    pub fn with_db_path<P: AsRef<Path>, Q: AsRef<Path>>(
+        workspace_path: P,
+        db_path: Q,
+    ) -> Result<Self, VaultError> {
         debug!("Creating new vault Instance");
         let workspace_path = workspace_path.as_ref().to_path_buf();
         if !workspace_path.exists() {
@@ -47,10 +89,14 @@ impl NoteVault {
             }))?;
         };
 
-        let vault_db = VaultDB::new(&workspace_path);
+        let vault_db = VaultDB::new(db_path);
+        // A config that can't be loaded is an error, using the default
+        // one would overwrite the user's file on the next change
+        let config = VaultConfig::load(workspace_path.join(VAULT_CONFIG_DIR))?;
         let note_vault = Self {
             workspace_path,
             vault_db,
+            config,
         };
         Ok(note_vault)
     }
@@ -59,49 +105,91 @@ impl NoteVault {
     ///
     /// 1. It exists
     /// 2. It is valid.
-    /// 3. Its schema is updated
+    /// 3. Its schema is updated, applying the pending migrations
     ///
     /// Then does a quick scan of the workspace directory to update the index if there are new or
     /// missing notes.
     /// This can be slow on large vaults.
-    pub fn init_and_validate(&self) -> Result<(), VaultError> {
+    pub fn init_and_validate(&self) -> Result<IndexReport, VaultError> {
         debug!("Initializing DB and validating it");
         let db_path = self.vault_db.get_db_path();
+        db::move_legacy_db(&self.workspace_path, &db_path)?;
         let db_result = self.vault_db.check_db()?;
         match db_result {
             db::DBStatus::Ready => {
                 // We only check if there are new notes
-                self.index_notes(NotesValidation::None)?;
+                self.index_notes(NotesValidation::None)
             }
             db::DBStatus::Outdated => {
-                self.recreate_index()?;
+                // Upgrade in place, the migrations that need the notes
+                // parsed again mark them as modified, so they are found
+                // by a fast validation
+                self.vault_db.call(db::migrate)?;
+                self.index_notes(NotesValidation::Fast)
             }
+            db::DBStatus::Incompatible => self.recreate_index(),
             db::DBStatus::NotValid => {
+                self.vault_db.close_connections();
                 let md = std::fs::metadata(&db_path).map_err(FSError::ReadFileError)?;
                 if md.is_dir() {
                     std::fs::remove_dir_all(db_path).map_err(FSError::ReadFileError)?;
                 } else {
-                    std::fs::remove_file(db_path).map_err(FSError::ReadFileError)?;
+                    db::remove_db_files(db_path).map_err(FSError::ReadFileError)?;
                 }
-                self.recreate_index()?;
+                self.recreate_index()
             }
             db::DBStatus::FileNotFound => {
                 // No need to validate, no data there
                 self.create_tables()?;
-                self.index_notes(NotesValidation::None)?;
+                self.index_notes(NotesValidation::None)
             }
         }
+    }
+
+    /// Where the index DB is stored
+    pub fn get_db_path(&self) -> PathBuf {
+        self.vault_db.get_db_path()
+    }
+
+    /// The vault's own configuration directory, for anything that must
+    /// travel with the notes. It's created if it doesn't exist.
+    pub fn get_config_dir(&self) -> Result<PathBuf, VaultError> {
+        let config_dir = self.workspace_path.join(VAULT_CONFIG_DIR);
+        if !config_dir.exists() {
+            std::fs::create_dir_all(&config_dir).map_err(FSError::ReadFileError)?;
+        }
+        Ok(config_dir)
+    }
+
+    pub fn get_config(&self) -> &VaultConfig {
+        &self.config
+    }
+
+    /// Changes the vault config and saves it in the config directory.
+    /// Changes to the ignore rules and note types apply to the index on
+    /// the next indexing.
+    pub fn set_config(&mut self, config: VaultConfig) -> Result<(), VaultError> {
+        config.save(self.get_config_dir()?)?;
+        self.config = config;
         Ok(())
     }
 
     /// Deletes all the cached data from the DB
     /// and recreates the index
-    pub fn recreate_index(&self) -> Result<(), VaultError> {
+    pub fn recreate_index(&self) -> Result<IndexReport, VaultError> {
+        self.recreate_index_with_progress(|_| true)
+    }
+
+    /// Same as `recreate_index`, reporting the progress of the indexing.
+    /// If cancelled the index is left empty, the next index fills it again.
+    pub fn recreate_index_with_progress<F>(&self, on_progress: F) -> Result<IndexReport, VaultError>
+    where
+        F: Fn(&IndexProgress) -> bool + Sync,
+    {
         debug!("Initializing DB from Vault request");
         self.create_tables()?;
         debug!("Tables created, creating index");
-        self.index_notes(NotesValidation::Full)?;
-        Ok(())
+        self.index_notes_with_progress(NotesValidation::Full, on_progress)
     }
 
     fn create_tables(&self) -> Result<(), VaultError> {
@@ -118,12 +206,33 @@ impl NoteVault {
     /// NotesValidation::Fast Checks the size of the file to identify if the note has changed and
     /// then update the DB entry.
     /// NotesValidation::None Checks if the note exists or not.
-    pub fn index_notes(&self, validation_mode: NotesValidation) -> Result<(), VaultError> {
+    ///
+    /// Files that can't be read are skipped and listed in the returned report.
+    pub fn index_notes(&self, validation_mode: NotesValidation) -> Result<IndexReport, VaultError> {
+        self.index_notes_with_progress(validation_mode, |_| true)
+    }
+
+    /// Same as `index_notes`, calling `on_progress` as the indexing goes.
+    /// The callback is called from different threads, returning false
+    /// cancels the indexing without changing the DB.
+    pub fn index_notes_with_progress<F>(
+        &self,
+        validation_mode: NotesValidation,
+        on_progress: F,
+    ) -> Result<IndexReport, VaultError>
+    where
+        F: Fn(&IndexProgress) -> bool + Sync,
+    {
         info!("Start indexing files");
         let start = std::time::SystemTime::now();
-        let workspace_path = self.workspace_path.clone();
-        self.vault_db.call(move |conn| {
-            create_index_for(&workspace_path, conn, &VaultPath::root(), validation_mode)
+        let report = self.vault_db.call(|conn| {
+            indexer::index_vault(
+                &self.workspace_path,
+                &self.config,
+                conn,
+                validation_mode,
+                &on_progress,
+            )
         })?;
 
         let time = std::time::SystemTime::now()
@@ -133,20 +242,68 @@ impl NoteVault {
             "Files indexed in the DB in {} milliseconds",
             time.as_millis()
         );
-        Ok(())
+        Ok(report)
     }
 
+    /// Returns the entry at the path, the path of the entry is the one on
+    /// disk, that may be written differently, see `PathCase`
     pub fn exists(&self, path: &VaultPath) -> Option<VaultEntry> {
-        match VaultEntry::new(&self.workspace_path, path.to_owned()) {
-            Ok(entry) => Some(entry),
-            Err(_e) => None,
+        VaultEntry::new(
+            &self.workspace_path,
+            self.resolve_path(path),
+            &self.config.notes,
+        )
+        .ok()
+    }
+
+    /// If there's nothing at the path, looks for an indexed note with
+    /// the same name written in another Unicode form, or with another
+    /// case if the vault ignores it
+    fn resolve_path(&self, path: &VaultPath) -> VaultPath {
+        if path.to_pathbuf(&self.workspace_path).exists() {
+            return path.to_owned();
+        }
+        let query = path.to_owned();
+        let case = self.config.path_case;
+        match self
+            .vault_db
+            .call_read(move |conn| db::find_note_path(conn, &query, case))
+        {
+            Ok(found) => found.unwrap_or_else(|| path.to_owned()),
+            Err(e) => {
+                warn!("Can't look for the note {} in the index: {}", path, e);
+                path.to_owned()
+            }
         }
     }
 
+    /// The directory as it's written in the index, so the paths of its
+    /// notes can be changed. Directories without notes are kept as they are.
+    fn resolve_directory(&self, path: &VaultPath) -> VaultPath {
+        let query = path.to_owned();
+        let case = self.config.path_case;
+        match self
+            .vault_db
+            .call_read(move |conn| db::find_directory_path(conn, &query, case))
+        {
+            Ok(found) => found.unwrap_or_else(|| path.to_owned()),
+            Err(e) => {
+                warn!("Can't look for the directory {} in the index: {}", path, e);
+                path.to_owned()
+            }
+        }
+    }
+
+    /// If the path has one of the note extensions of the vault
+    pub fn is_note(&self, path: &VaultPath) -> bool {
+        self.config.notes.is_note(path)
+    }
+
     pub fn journal_entry(&self) -> Result<(NoteDetails, String), VaultError> {
         let (title, note_path) = self.get_todays_journal();
         let content = self.load_or_create_note(&note_path, Some(format!("# {}\n\n", title)))?;
-        let details = NoteDetails::from_content(&content, &note_path);
+        let format = self.config.notes.format(&note_path).unwrap_or_default();
+        let details = NoteDetails::from_content(&content, &note_path, format);
         Ok((details, content))
     }
 
@@ -168,7 +325,7 @@ impl NoteVault {
         path: &VaultPath,
         default_text: Option<String>,
     ) -> Result<String, VaultError> {
-        match load_note(&self.workspace_path, path) {
+        match load_note(&self.workspace_path, &self.resolve_path(path)) {
             Ok(text) => Ok(text),
             Err(e) => {
                 if let FSError::VaultPathNotFound { path: _ } = e {
@@ -187,7 +344,7 @@ impl NoteVault {
     // FSError::NotePathNotFound as the source, you can use that to
     // lazy create a note, or use the load_or_create_note function instead
     pub fn get_note_text(&self, path: &VaultPath) -> Result<String, VaultError> {
-        let text = load_note(&self.workspace_path, path)?;
+        let text = load_note(&self.workspace_path, &self.resolve_path(path))?;
         Ok(text)
     }
 
@@ -196,6 +353,12 @@ impl NoteVault {
         data.title
     }
 
+    /// Returns the headings of the note's text, with their level and
+    /// where they are in the text
+    pub fn get_outline<S: AsRef<str>>(text: S) -> Vec<OutlineHeading> {
+        content_data::extract_outline(text)
+    }
+
     // Search notes using terms
     pub fn search_notes<S: AsRef<str>>(&self, terms: S) -> Result<Vec<NoteDetails>, VaultError> {
         // let mut connection = ConnectionBuilder::new(&self.workspace_path)
@@ -203,7 +366,7 @@ impl NoteVault {
         //     .unwrap();
         let terms = terms.as_ref().to_owned();
 
-        let a = self.vault_db.call(move |conn| {
+        let a = self.vault_db.call_read(move |conn| {
             db::search_terms(conn, terms).map(|vec| {
                 vec.into_iter()
                     .map(|(_data, details)| details)
@@ -214,20 +377,668 @@ impl NoteVault {
         Ok(a)
     }
 
+    pub fn get_saved_searches(&self) -> &[SavedSearch] {
+        &self.config.saved_searches
+    }
+
+    /// Saves the query under the name, replacing the saved search with
+    /// the same name if there is one. It's saved in the vault config.
+    pub fn save_search<S: AsRef<str>, Q: AsRef<str>>(
+        &mut self,
+        name: S,
+        query: Q,
+    ) -> Result<(), VaultError> {
+        let name = name.as_ref().trim();
+        if name.is_empty() {
+            return Err(VaultError::InvalidConfig(
+                "A saved search needs a name".to_string(),
+            ));
+        }
+        let search = SavedSearch {
+            name: name.to_string(),
+            query: query.as_ref().trim().to_string(),
+        };
+        let mut config = self.config.clone();
+        match config
+            .saved_searches
+            .iter_mut()
+            .find(|saved| saved.name.eq_ignore_ascii_case(name))
+        {
+            Some(saved) => *saved = search,
+            None => config.saved_searches.push(search),
+        }
+        self.set_config(config)
+    }
+
+    pub fn delete_saved_search<S: AsRef<str>>(&mut self, name: S) -> Result<(), VaultError> {
+        let name = name.as_ref();
+        let mut config = self.config.clone();
+        let count = config.saved_searches.len();
+        config
+            .saved_searches
+            .retain(|saved| !saved.name.eq_ignore_ascii_case(name));
+        if config.saved_searches.len() == count {
+            return Err(VaultError::SavedSearchNotFound {
+                name: name.to_string(),
+            });
+        }
+        config.pins.retain(|pin| match pin {
+            Pin::Search { name: pinned } => !pinned.eq_ignore_ascii_case(name),
+            Pin::Note { .. } => true,
+        });
+        self.set_config(config)
+    }
+
+    pub fn get_pins(&self) -> &[Pin] {
+        &self.config.pins
+    }
+
+    pub fn is_pinned(&self, pin: &Pin) -> bool {
+        self.pin_position(pin).is_some()
+    }
+
+    fn pin_position(&self, pin: &Pin) -> Option<usize> {
+        let case = self.config.path_case;
+        self.config
+            .pins
+            .iter()
+            .position(|pinned| match (pinned, pin) {
+                (Pin::Note { path: pinned }, Pin::Note { path }) => pinned.matches(path, case),
+                (Pin::Search { name: pinned }, Pin::Search { name }) => {
+                    pinned.eq_ignore_ascii_case(name)
+                }
+                _ => false,
+            })
+    }
+
+    /// Adds the pin at the end, the pins are saved in the vault config.
+    /// A search must be saved before pinning it.
+    pub fn pin(&mut self, pin: Pin) -> Result<(), VaultError> {
+        if self.is_pinned(&pin) {
+            return Ok(());
+        }
+        if let Pin::Search { name } = &pin {
+            if !self
+                .config
+                .saved_searches
+                .iter()
+                .any(|saved| saved.name.eq_ignore_ascii_case(name))
+            {
+                return Err(VaultError::SavedSearchNotFound { name: name.clone() });
+            }
+        }
+        let mut config = self.config.clone();
+        config.pins.push(pin);
+        self.set_config(config)
+    }
+
+    pub fn unpin(&mut self, pin: &Pin) -> Result<(), VaultError> {
+        if let Some(position) = self.pin_position(pin) {
+            let mut config = self.config.clone();
+            config.pins.remove(position);
+            self.set_config(config)?;
+        }
+        Ok(())
+    }
+
+    /// Moves the pin to the position, or to the end if it's past it
+    pub fn move_pin(&mut self, pin: &Pin, position: usize) -> Result<(), VaultError> {
+        if let Some(current) = self.pin_position(pin) {
+            let mut config = self.config.clone();
+            let pin = config.pins.remove(current);
+            let position = position.min(config.pins.len());
+            config.pins.insert(position, pin);
+            self.set_config(config)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the saved search, the results are the notes matching its
+    /// query now
+    pub fn run_saved_search<S: AsRef<str>>(&self, name: S) -> Result<Vec<NoteDetails>, VaultError> {
+        let name = name.as_ref();
+        let search = self
+            .config
+            .saved_searches
+            .iter()
+            .find(|saved| saved.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| VaultError::SavedSearchNotFound {
+                name: name.to_string(),
+            })?;
+        if search.query.is_empty() {
+            return Ok(vec![]);
+        }
+        self.search_notes(&search.query)
+    }
+
+    /// Returns all the tags used in the vault with the number of notes
+    /// using each one, the most used first
+    pub fn get_tags(&self) -> Result<Vec<(String, usize)>, VaultError> {
+        let tags = self.vault_db.call_read(db::get_tags)?;
+        Ok(tags)
+    }
+
+    /// Renders the note, or all the notes in the directory, to HTML files
+    /// in the destination. Directories also get an index page and a page
+    /// for each tag.
+    pub fn export_html<P: AsRef<Path>>(
+        &self,
+        path: &VaultPath,
+        destination: P,
+    ) -> Result<ExportReport, VaultError> {
+        let path = self.resolve_path(path);
+        let os_path = path.to_pathbuf(&self.workspace_path);
+        let target = if self.is_note(&path) {
+            if !os_path.is_file() {
+                return Err(FSError::VaultPathNotFound { path })?;
+            }
+            ExportTarget::Note(path)
+        } else {
+            if !os_path.is_dir() {
+                return Err(VaultError::PathIsNotDirectory { path });
+            }
+            let notes = self
+                .get_notes(&path, true)?
+                .into_iter()
+                .map(|note| note.path)
+                .collect();
+            ExportTarget::Site {
+                directory: path,
+                notes,
+            }
+        };
+        export::export_html(
+            &self.workspace_path,
+            &self.config.notes,
+            self.config.path_case,
+            target,
+            destination,
+        )
+    }
+
+    /// Returns the tasks of the indexed notes that match the filter, the
+    /// ones with a due date first
+    pub fn query_tasks(
+        &self,
+        filter: &TaskFilter,
+    ) -> Result<Vec<(VaultPath, NoteTask)>, VaultError> {
+        let filter = filter.to_owned();
+        let case = self.config.path_case;
+        let tasks = self
+            .vault_db
+            .call_read(move |conn| db::query_tasks(conn, &filter, case))?;
+        Ok(tasks)
+    }
+
+    /// Checks or unchecks a task, as returned by `query_tasks`, rewriting
+    /// its checkbox in the note. Fails if the note changed and the task is
+    /// not at the same line anymore.
+    pub fn set_task_done(
+        &self,
+        path: &VaultPath,
+        task: &NoteTask,
+        done: bool,
+    ) -> Result<(NoteEntryData, NoteDetails), VaultError> {
+        let path = self.resolve_path(path);
+        let text = load_note(&self.workspace_path, &path)?;
+        let text = tasks::set_task_done(&text, task, done)
+            .ok_or_else(|| VaultError::TaskNotFound { path: path.clone() })?;
+        self.save_note(&path, text)
+    }
+
+    /// Imports the notes exported from another tool into the directory,
+    /// see `ImportSource`. Nothing in the vault is overwritten, the
+    /// report lists what couldn't be imported or was imported with changes.
+    pub fn import_notes<P: AsRef<Path>>(
+        &self,
+        source: ImportSource,
+        from: P,
+        into: &VaultPath,
+    ) -> Result<ImportReport, VaultError> {
+        let into = self.resolve_path(into);
+        import::import(self, source, from, &into)
+    }
+
+    /// Returns the headings of an indexed note, in the order they appear
+    pub fn get_headings(&self, path: &VaultPath) -> Result<Vec<String>, VaultError> {
+        let path = path.to_owned();
+        let case = self.config.path_case;
+        let headings = self
+            .vault_db
+            .call_read(move |conn| db::get_headings(conn, &path, case))?;
+        Ok(headings)
+    }
+
+    /// The headings of the indexed notes with the text, with the note
+    /// they are in and their breadcrumb, the heading being the last one
+    pub fn search_headings<S: AsRef<str>>(
+        &self,
+        text: S,
+        limit: usize,
+    ) -> Result<Vec<(VaultPath, Vec<String>)>, VaultError> {
+        let text = text.as_ref().to_owned();
+        let headings = self
+            .vault_db
+            .call_read(move |conn| db::search_headings(conn, &text, limit))?;
+        Ok(headings)
+    }
+
+    /// The indexed note a link in the note `from` goes to, if any
+    pub fn resolve_link(
+        &self,
+        from: &VaultPath,
+        link: &NoteLink,
+    ) -> Result<Option<VaultPath>, VaultError> {
+        if link.target.is_empty() {
+            return Ok(Some(from.to_owned()));
+        }
+        let case = self.config.path_case;
+        let path = match link.kind {
+            LinkKind::Wiki if !link.target.contains('/') => {
+                let name = link.target.clone();
+                let notes = self
+                    .vault_db
+                    .call_read(move |conn| db::find_notes_by_name(conn, &name))?;
+                // The one in the same directory first
+                let directory = from.get_parent_path().0;
+                let found = notes
+                    .iter()
+                    .find(|note| note.get_parent_path().0.matches(&directory, case))
+                    .or(notes.first())
+                    .cloned();
+                return Ok(found);
+            }
+            LinkKind::Wiki => join_link(&VaultPath::root(), &link.target),
+            LinkKind::Markdown => {
+                join_link(&from.get_parent_path().0, &percent_decode(&link.target))
+            }
+        };
+        let Some(path) = path else {
+            return Ok(None);
+        };
+        // Links can leave out the extension
+        let paths = [path.clone(), VaultPath::file_from(path.to_string())];
+        let found = self.vault_db.call_read(move |conn| {
+            for path in &paths {
+                if let Some(found) = db::find_note_path(conn, path, case)? {
+                    return Ok(Some(found));
+                }
+            }
+            Ok(None)
+        })?;
+        Ok(found)
+    }
+
+    /// The links in other notes to the note, with the note they are
+    /// in, sorted by note. The links to the note from itself are
+    /// included if they are not just to a heading.
+    pub fn get_backlinks(
+        &self,
+        path: &VaultPath,
+    ) -> Result<Vec<(VaultPath, NoteLink)>, VaultError> {
+        let query = path.to_owned();
+        let case = self.config.path_case;
+        let Some(path) = self
+            .vault_db
+            .call_read(move |conn| db::find_note_path(conn, &query, case))?
+        else {
+            return Ok(vec![]);
+        };
+        let name = path.get_name().to_lowercase();
+        let mut names = vec![name.clone()];
+        if let Some((stem, _extension)) = name.rsplit_once('.') {
+            names.push(stem.to_string());
+        }
+        let query = names.clone();
+        let sources = self
+            .vault_db
+            .call_read(move |conn| db::get_linking_notes(conn, &query))?;
+        let mut backlinks = vec![];
+        for source in sources {
+            let text = match self.get_note_text(&source) {
+                Ok(text) => text,
+                Err(e) => {
+                    warn!("Can't read {} to look for links: {}", source, e);
+                    continue;
+                }
+            };
+            for link in links::extract_links(&text) {
+                if link.target.is_empty() || !names.contains(&link.name()) {
+                    continue;
+                }
+                if self
+                    .resolve_link(&source, &link)?
+                    .is_some_and(|linked| linked.matches(&path, case))
+                {
+                    backlinks.push((source.clone(), link));
+                }
+            }
+        }
+        Ok(backlinks)
+    }
+
+    /// The dates and counts of the note as stored in the index, with the
+    /// notes linking to it
+    pub fn get_note_properties(&self, path: &VaultPath) -> Result<NoteProperties, VaultError> {
+        let query = path.to_owned();
+        let case = self.config.path_case;
+        let stored = self.vault_db.call_read(move |conn| {
+            let Some(path) = db::find_note_path(conn, &query, case)? else {
+                return Ok(None);
+            };
+            db::get_note_stats(conn, &path)
+        })?;
+        let Some((data, stats, links)) = stored else {
+            return Err(FSError::VaultPathNotFound {
+                path: path.to_owned(),
+            }
+            .into());
+        };
+        let mut backlinks = vec![];
+        for (source, _link) in self.get_backlinks(&data.path)? {
+            if !backlinks.contains(&source) {
+                backlinks.push(source);
+            }
+        }
+        Ok(NoteProperties {
+            path: data.path,
+            created_secs: data.created_secs,
+            modified_secs: data.modified_secs,
+            size: data.size,
+            stats,
+            links,
+            backlinks,
+        })
+    }
+
+    /// The links in the text of the note that go nowhere in the vault
+    pub fn get_broken_links<S: AsRef<str>>(
+        &self,
+        from: &VaultPath,
+        text: S,
+    ) -> Result<Vec<NoteLink>, VaultError> {
+        let mut broken = vec![];
+        for link in links::extract_links(text.as_ref()) {
+            if self.resolve_link(from, &link)?.is_some() {
+                continue;
+            }
+            // Markdown links can go to attachments
+            let is_file = link.kind == LinkKind::Markdown
+                && join_link(&from.get_parent_path().0, &percent_decode(&link.target))
+                    .is_some_and(|path| self.exists(&path).is_some());
+            if !is_file {
+                broken.push(link);
+            }
+        }
+        Ok(broken)
+    }
+
+    /// The changes to the notes linking to `from` so they link to `to`,
+    /// by note. The edits of `from` itself are for its text once renamed.
+    pub fn get_rename_edits(
+        &self,
+        from: &VaultPath,
+        to: &VaultPath,
+    ) -> Result<Vec<(VaultPath, Vec<LinkEdit>)>, VaultError> {
+        let case = self.config.path_case;
+        let mut edits: Vec<(VaultPath, Vec<LinkEdit>)> = vec![];
+        for (source, link) in self.get_backlinks(from)? {
+            // The relative links in the note itself are from its new place
+            let linking = if source.matches(from, case) {
+                to
+            } else {
+                &source
+            };
+            let edit = LinkEdit {
+                range: link.target_range.clone(),
+                text: link.retarget(linking, to),
+            };
+            match edits.last_mut() {
+                Some((last, note_edits)) if *last == source => note_edits.push(edit),
+                _ => edits.push((source, vec![edit])),
+            }
+        }
+        Ok(edits)
+    }
+
+    /// Moves the note to a new path, updating the links to it in the
+    /// other notes. Returns the notes that were changed.
+    pub fn rename_note(
+        &self,
+        from: &VaultPath,
+        to: &VaultPath,
+    ) -> Result<Vec<VaultPath>, VaultError> {
+        let from = self.resolve_path(from);
+        if self.exists(&from).is_none() {
+            return Err(FSError::VaultPathNotFound { path: from }.into());
+        }
+        if !self.is_note(to) {
+            return Err(FSError::InvalidPath {
+                path: to.to_string(),
+            }
+            .into());
+        }
+        if self.exists(to).is_some() {
+            return Err(VaultError::NoteExists { path: to.clone() });
+        }
+        let edits = self.get_rename_edits(&from, to)?;
+        let to_file = to.to_pathbuf(&self.workspace_path);
+        if let Some(parent) = to_file.parent() {
+            std::fs::create_dir_all(parent).map_err(FSError::ReadFileError)?;
+        }
+        std::fs::rename(from.to_pathbuf(&self.workspace_path), &to_file)
+            .map_err(FSError::ReadFileError)?;
+        let removed = from.clone();
+        self.vault_db.call(move |conn| {
+            let tx = conn.transaction()?;
+            db::delete_notes(&tx, &[removed])?;
+            tx.commit()?;
+            Ok::<(), DBError>(())
+        })?;
+
+        let case = self.config.path_case;
+        let mut renamed_edits = vec![];
+        let mut changed = vec![];
+        for (source, source_edits) in edits {
+            if source.matches(&from, case) {
+                renamed_edits = source_edits;
+                continue;
+            }
+            let text = self.get_note_text(&source)?;
+            self.save_note(&source, links::apply_edits(&text, &source_edits))?;
+            changed.push(source);
+        }
+        let text = self.get_note_text(to)?;
+        self.save_note(to, links::apply_edits(&text, &renamed_edits))?;
+        info!("Renamed {} to {}", from, to);
+        Ok(changed)
+    }
+
+    /// Creates an empty directory, the index only has the directories
+    /// with notes
+    pub fn create_directory(&self, path: &VaultPath) -> Result<(), VaultError> {
+        if self.exists(path).is_some() {
+            return Err(VaultError::DirectoryExists { path: path.clone() });
+        }
+        std::fs::create_dir_all(path.to_pathbuf(&self.workspace_path))
+            .map_err(FSError::ReadFileError)?;
+        info!("Created {}", path);
+        Ok(())
+    }
+
+    /// Moves the directory with all its notes, updating the links to them
+    /// in the notes outside of it. Returns the notes that were changed.
+    pub fn rename_directory(
+        &self,
+        from: &VaultPath,
+        to: &VaultPath,
+    ) -> Result<Vec<VaultPath>, VaultError> {
+        let case = self.config.path_case;
+        let from = &self.resolve_directory(from);
+        let from_dir = from.to_pathbuf(&self.workspace_path);
+        if from == &VaultPath::root() || !from_dir.is_dir() {
+            return Err(VaultError::PathIsNotDirectory { path: from.clone() });
+        }
+        if to.is_in(from, case) {
+            return Err(FSError::InvalidPath {
+                path: to.to_string(),
+            }
+            .into());
+        }
+        if self.exists(to).is_some() {
+            return Err(VaultError::DirectoryExists { path: to.clone() });
+        }
+
+        // The links are looked up before moving, while the index has the
+        // notes in their old place
+        let mut edits: Vec<(VaultPath, Vec<LinkEdit>)> = vec![];
+        for note in self.get_notes(from, true)? {
+            let moved = to.append(&VaultPath::from(note.path.relative_to(from)));
+            for (source, source_edits) in self.get_rename_edits(&note.path, &moved)? {
+                if source.is_in(from, case) {
+                    continue;
+                }
+                match edits.iter_mut().find(|(edited, _edits)| *edited == source) {
+                    Some((_source, note_edits)) => note_edits.extend(source_edits),
+                    None => edits.push((source, source_edits)),
+                }
+            }
+        }
+
+        let to_dir = to.to_pathbuf(&self.workspace_path);
+        if let Some(parent) = to_dir.parent() {
+            std::fs::create_dir_all(parent).map_err(FSError::ReadFileError)?;
+        }
+        std::fs::rename(&from_dir, &to_dir).map_err(FSError::ReadFileError)?;
+        let (renamed_from, renamed_to) = (from.clone(), to.clone());
+        let moved = self.vault_db.call(move |conn| {
+            let tx = conn.transaction()?;
+            db::rename_directory(&tx, &renamed_from, &renamed_to)?;
+            tx.commit()?;
+            Ok::<(), DBError>(())
+        });
+        if let Err(e) = moved {
+            // The index still has the notes in the old place
+            if let Err(rollback) = std::fs::rename(&to_dir, &from_dir) {
+                error!("Can't move {} back to {}: {}", to, from, rollback);
+            }
+            return Err(e.into());
+        }
+
+        // The directory is moved in the index and on disk, a note that
+        // fails here keeps its old links
+        let mut changed = vec![];
+        for (source, source_edits) in edits {
+            let text = self.get_note_text(&source)?;
+            self.save_note(&source, links::apply_edits(&text, &source_edits))?;
+            changed.push(source);
+        }
+        info!("Renamed {} to {}", from, to);
+        Ok(changed)
+    }
+
+    /// Deletes the directory with everything in it and removes its notes
+    /// from the index
+    pub fn delete_directory(&self, path: &VaultPath) -> Result<(), VaultError> {
+        let path = &self.resolve_directory(path);
+        let directory = path.to_pathbuf(&self.workspace_path);
+        if path == &VaultPath::root() || !directory.is_dir() {
+            return Err(VaultError::PathIsNotDirectory { path: path.clone() });
+        }
+        std::fs::remove_dir_all(directory).map_err(FSError::ReadFileError)?;
+        let removed = vec![path.clone()];
+        self.vault_db.call(move |conn| {
+            let tx = conn.transaction()?;
+            db::delete_directories(&tx, &removed)?;
+            tx.commit()?;
+            Ok::<(), DBError>(())
+        })?;
+        info!("Deleted {}", path);
+        Ok(())
+    }
+
+    /// Previews replacing the matches of the finder in all the notes of
+    /// the vault, nothing is changed until `replace_in_notes` is called
+    pub fn preview_replace<S: AsRef<str>>(
+        &self,
+        finder: &TextFinder,
+        replacement: S,
+    ) -> Result<ReplacePreview, VaultError> {
+        let candidates = match finder.index_terms() {
+            Some(terms) => self
+                .vault_db
+                .call_read(move |conn| db::find_text_candidates(conn, &terms))?,
+            None => None,
+        };
+        let mut paths = match candidates {
+            Some(paths) => paths,
+            None => self
+                .get_notes(&VaultPath::root(), true)?
+                .into_iter()
+                .map(|note| note.path)
+                .collect(),
+        };
+        paths.sort_by_key(|path| path.to_string());
+
+        let mut preview = ReplacePreview::default();
+        for path in paths {
+            // The note may have changed since it was indexed
+            let text = match self.get_note_text(&path) {
+                Ok(text) => text,
+                Err(e) => {
+                    warn!("Can't preview the replacement in {}: {}", path, e);
+                    preview.skipped.push(SkippedFile {
+                        path,
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let changes = finder.preview(&text, replacement.as_ref());
+            if !changes.is_empty() {
+                preview.notes.push(NoteReplacement { path, changes });
+            }
+        }
+        Ok(preview)
+    }
+
+    /// Replaces the matches of the finder in the notes, saving and
+    /// reindexing them. Returns the notes that changed.
+    pub fn replace_in_notes<S: AsRef<str>>(
+        &self,
+        finder: &TextFinder,
+        replacement: S,
+        paths: &[VaultPath],
+    ) -> Result<Vec<VaultPath>, VaultError> {
+        let mut changed = vec![];
+        for path in paths {
+            let text = self.get_note_text(path)?;
+            let (new_text, count) = finder.replace_all(&text, replacement.as_ref());
+            if count > 0 {
+                debug!("Replacing {} matches in {}", count, path);
+                self.save_note(path, new_text)?;
+                changed.push(path.to_owned());
+            }
+        }
+        Ok(changed)
+    }
+
     pub fn browse_vault(&self, options: VaultBrowseOptions) -> Result<(), VaultError> {
         let start = std::time::SystemTime::now();
         debug!("> Start fetching files with Options:\n{}", options);
 
         // TODO: See if we can put everything inside the closure
         let query_path = options.path.clone();
-        let cached_notes = self.vault_db.call(move |conn| {
-            let notes = db::get_notes(conn, &query_path, options.recursive)?;
+        let case = self.config.path_case;
+        let cached_notes = self.vault_db.call_read(move |conn| {
+            let notes = db::get_notes(conn, &query_path, options.recursive, case)?;
             Ok(notes)
         })?;
 
         let mut builder = NoteListVisitorBuilder::new(
             &self.workspace_path,
             options.validation,
+            self.config.notes.clone(),
             cached_notes,
             Some(options.sender.clone()),
         );
@@ -236,6 +1047,7 @@ impl NoteVault {
             self.workspace_path.clone(),
             &options.path,
             options.recursive,
+            &self.config.ignore,
         );
         walker.visit(&mut builder);
 
@@ -243,7 +1055,7 @@ impl NoteVault {
         let notes_to_delete = builder.get_notes_to_delete();
         let notes_to_modify = builder.get_notes_to_modify();
 
-        self.vault_db.call(move |conn| {
+        self.vault_db.call(move |conn| -> Result<(), DBError> {
             let tx = conn.transaction()?;
             db::insert_notes(&tx, &notes_to_add)?;
             db::delete_notes(&tx, &notes_to_delete)?;
@@ -268,9 +1080,10 @@ impl NoteVault {
         let start = std::time::SystemTime::now();
         debug!("> Start fetching files from cache");
         let note_path = path.into();
+        let case = self.config.path_case;
 
-        let cached_notes = self.vault_db.call(move |conn| {
-            let notes = db::get_notes(conn, &note_path, recursive)?;
+        let cached_notes = self.vault_db.call_read(move |conn| {
+            let notes = db::get_notes(conn, &note_path, recursive, case)?;
             Ok(notes)
         })?;
 
@@ -285,6 +1098,43 @@ impl NoteVault {
         Ok(result)
     }
 
+    /// The directories inside the directory, from the index, so only
+    /// the ones with notes are listed
+    pub fn get_subdirectories(&self, path: &VaultPath) -> Result<Vec<VaultPath>, VaultError> {
+        let path = path.to_owned();
+        let case = self.config.path_case;
+        let directories = self
+            .vault_db
+            .call_read(move |conn| db::get_directories(conn, &path, case))?;
+        Ok(directories)
+    }
+
+    /// All the notes in the vault with their title and aliases, to look
+    /// them up by name. The rest of the note data is not loaded.
+    pub fn get_note_names(&self) -> Result<Vec<NoteDetails>, VaultError> {
+        let notes = self.vault_db.call_read(db::get_note_names)?;
+        Ok(notes)
+    }
+
+    /// Deletes the note file and removes it from the index
+    pub fn delete_note(&self, path: &VaultPath) -> Result<(), VaultError> {
+        let path = self.resolve_path(path);
+        if !self.is_note(&path) || self.exists(&path).is_none() {
+            return Err(FSError::VaultPathNotFound { path }.into());
+        }
+        std::fs::remove_file(path.to_pathbuf(&self.workspace_path))
+            .map_err(FSError::ReadFileError)?;
+        let removed = path.clone();
+        self.vault_db.call(move |conn| {
+            let tx = conn.transaction()?;
+            db::delete_notes(&tx, &[removed])?;
+            tx.commit()?;
+            Ok::<(), DBError>(())
+        })?;
+        info!("Deleted {}", path);
+        Ok(())
+    }
+
     pub fn create_note<S: AsRef<str>>(
         &self,
         path: &VaultPath,
@@ -302,9 +1152,8 @@ impl NoteVault {
         path: &VaultPath,
         text: S,
     ) -> Result<(NoteEntryData, NoteDetails), VaultError> {
-        // Save to disk
-        let entry_data = save_note(&self.workspace_path, path, &text)?;
-        let details = entry_data.load_details(&self.workspace_path, path)?;
+        let (entry_data, details) =
+            write_note(&self.workspace_path, path, text, &self.config.notes)?;
         let result = (entry_data.clone(), details.clone());
 
         // Save to DB
@@ -344,6 +1193,12 @@ impl NoteDetails {
             hash,
             title: Some(title),
             content_chunks: vec![],
+            tags: vec![],
+            aliases: vec![],
+            tasks: vec![],
+            links: vec![],
+            headings: vec![],
+            stats: NoteStats::default(),
         };
         Self {
             path: note_path,
@@ -352,8 +1207,8 @@ impl NoteDetails {
         }
     }
 
-    fn from_content<S: AsRef<str>>(text: S, note_path: &VaultPath) -> Self {
-        let data = content_data::extract_data(&text);
+    fn from_content<S: AsRef<str>>(text: S, note_path: &VaultPath, format: NoteFormat) -> Self {
+        let data = format.extract_data(&text);
         Self {
             path: note_path.to_owned(),
             data,
@@ -382,11 +1237,28 @@ impl NoteDetails {
     }
 }
 
+/// What is known of a note without reading it, see
+/// [`NoteVault::get_note_properties`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NoteProperties {
+    pub path: VaultPath,
+    pub created_secs: u64,
+    pub modified_secs: u64,
+    pub size: u64,
+    pub stats: NoteStats,
+    /// The links to other notes or files
+    pub links: usize,
+    /// The notes with links to this one
+    pub backlinks: Vec<VaultPath>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct DirectoryDetails {
     pub path: VaultPath,
 }
 
+// Most entries are notes, boxing them wouldn't save memory
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum SearchResult {
     Note(NoteDetails),
@@ -394,28 +1266,6 @@ pub enum SearchResult {
     Attachment(VaultPath),
 }
 
-fn collect_from_cache(
-    cached_notes: &[(nfs::NoteEntryData, NoteDetails)],
-) -> Result<Vec<SearchResult>, VaultError> {
-    let mut directories = HashSet::new();
-    let mut notes = vec![];
-
-    for (_note_data, note_details) in cached_notes {
-        directories.insert(note_details.path.get_parent_path().0);
-        notes.push(SearchResult::Note(note_details.clone()));
-    }
-
-    let result = directories
-        .iter()
-        .map(|directory_path| {
-            SearchResult::Directory(DirectoryDetails {
-                path: directory_path.clone(),
-            })
-        })
-        .chain(notes);
-    Ok(result.collect())
-}
-
 pub struct VaultBrowseOptionsBuilder {
     path: VaultPath,
     validation: NotesValidation,
@@ -522,34 +1372,323 @@ impl Display for NotesValidation {
     }
 }
 
-fn create_index_for<P: AsRef<Path>>(
+/// Saves the note to disk, returning the data to store in the DB
+fn write_note<P: AsRef<Path>, S: AsRef<str>>(
     workspace_path: P,
-    connection: &mut rusqlite::Connection,
     path: &VaultPath,
-    validation_mode: NotesValidation,
-) -> Result<(), DBError> {
-    debug!("Start fetching files at {}", path);
-    let workspace_path = workspace_path.as_ref();
-    let walker = nfs::get_file_walker(workspace_path, path, false);
-
-    let cached_notes = db::get_notes(connection, path, false)?;
-    let mut builder =
-        NoteListVisitorBuilder::new(workspace_path, validation_mode, cached_notes, None);
-    walker.visit(&mut builder);
-    let notes_to_add = builder.get_notes_to_add();
-    let notes_to_delete = builder.get_notes_to_delete();
-    let notes_to_modify = builder.get_notes_to_modify();
-
-    let tx = connection.transaction()?;
-    db::delete_notes(&tx, &notes_to_delete)?;
-    db::insert_notes(&tx, &notes_to_add)?;
-    db::update_notes(&tx, &notes_to_modify)?;
-    tx.commit()?;
-
-    let directories_to_insert = builder.get_directories_found();
-    for directory in directories_to_insert.iter().filter(|p| !p.eq(&path)) {
-        create_index_for(workspace_path, connection, directory, validation_mode)?;
-    }
-
-    Ok(())
+    text: S,
+    types: &NoteTypes,
+) -> Result<(NoteEntryData, NoteDetails), VaultError> {
+    let entry_data = save_note(&workspace_path, path, &text, types)?;
+    let details = entry_data.load_details(&workspace_path, path, types)?;
+    Ok((entry_data, details))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::VaultError,
+        find_replace::{FindOptions, TextFinder},
+        nfs::{PathCase, VaultPath},
+        tasks::TaskFilter,
+        test_utils::{db_path, indexed_vault, vault_files, workspace},
+        NoteVault,
+    };
+
+    fn replacement_paths(vault: &NoteVault, pattern: &str) -> Vec<VaultPath> {
+        let finder = TextFinder::new(pattern, FindOptions::default()).unwrap();
+        vault
+            .preview_replace(&finder, "")
+            .unwrap()
+            .notes
+            .into_iter()
+            .map(|replacement| replacement.path)
+            .collect()
+    }
+
+    #[test]
+    fn preview_replace_in_words() {
+        let (_dir, vault) = indexed_vault(&[
+            ("hello.md", "Hello big world"),
+            ("yellow.md", "# Yellow\n\nA color"),
+            ("other.md", "Nothing here"),
+        ]);
+
+        assert_eq!(
+            vec![VaultPath::from("hello.md"), VaultPath::from("yellow.md")],
+            replacement_paths(&vault, "llo")
+        );
+    }
+
+    #[test]
+    fn preview_replace_skips_missing_notes() {
+        let (_dir, vault) = indexed_vault(&[("a.md", "Hello there"), ("b.md", "Hello again")]);
+        std::fs::remove_file(vault.workspace_path.join("b.md")).unwrap();
+
+        let finder = TextFinder::new("hello", FindOptions::default()).unwrap();
+        let preview = vault.preview_replace(&finder, "Bye").unwrap();
+        assert_eq!(
+            vec![VaultPath::from("a.md")],
+            preview
+                .notes
+                .into_iter()
+                .map(|replacement| replacement.path)
+                .collect::<Vec<VaultPath>>()
+        );
+        assert_eq!(1, preview.skipped.len());
+        assert_eq!(VaultPath::from("b.md"), preview.skipped[0].path);
+    }
+
+    #[test]
+    fn replace_in_link_destinations() {
+        let (_dir, vault) = indexed_vault(&[
+            ("a.md", "See [the plan](projects/roadmap.md)"),
+            ("projects/roadmap.md", "# Roadmap"),
+        ]);
+
+        assert_eq!(
+            vec![VaultPath::from("a.md")],
+            replacement_paths(&vault, "roadmap.md")
+        );
+        let finder = TextFinder::new("roadmap.md", FindOptions::default()).unwrap();
+        let changed = vault
+            .replace_in_notes(&finder, "plan.md", &[VaultPath::from("a.md")])
+            .unwrap();
+        assert_eq!(vec![VaultPath::from("a.md")], changed);
+        assert_eq!(
+            "See [the plan](projects/plan.md)",
+            vault.get_note_text(&VaultPath::from("a.md")).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_config_is_not_overwritten() {
+        let config = "[server\ntoken = \"secret\"";
+        let dir = vault_files(&[(".kimun/config.toml", config), ("note.md", "# Note")]);
+
+        let result = NoteVault::with_db_path(workspace(&dir), db_path(&dir));
+        assert!(matches!(result, Err(VaultError::InvalidConfig(_))));
+        assert_eq!(
+            config,
+            std::fs::read_to_string(workspace(&dir).join(".kimun/config.toml")).unwrap()
+        );
+    }
+
+    #[test]
+    fn directory_operations() {
+        let (_dir, vault) = indexed_vault(&[
+            ("home.md", "See [api](projects/kimun/api.md)"),
+            ("projects/kimun/api.md", "# API #docs"),
+            ("projects-old/old.md", "# Old"),
+        ]);
+        let workspace = vault.workspace_path.clone();
+
+        vault.create_directory(&VaultPath::from("archive")).unwrap();
+        assert!(workspace.join("archive").is_dir());
+        assert!(vault.create_directory(&VaultPath::from("archive")).is_err());
+
+        let changed = vault
+            .rename_directory(
+                &VaultPath::from("projects"),
+                &VaultPath::from("archive/work"),
+            )
+            .unwrap();
+        assert_eq!(vec![VaultPath::from("home.md")], changed);
+        assert!(workspace.join("archive/work/kimun/api.md").exists());
+        assert_eq!(
+            "See [api](archive/work/kimun/api.md)",
+            vault.get_note_text(&VaultPath::from("home.md")).unwrap()
+        );
+        assert_eq!(
+            vec![VaultPath::from("archive/work/kimun")],
+            vault
+                .get_subdirectories(&VaultPath::from("archive/work"))
+                .unwrap()
+        );
+        // The directory with the same prefix is not touched
+        assert_eq!(
+            vec![VaultPath::from("archive"), VaultPath::from("projects-old")],
+            vault.get_subdirectories(&VaultPath::root()).unwrap()
+        );
+        assert!(vault
+            .rename_directory(
+                &VaultPath::from("archive"),
+                &VaultPath::from("archive/inner")
+            )
+            .is_err());
+
+        vault.delete_directory(&VaultPath::from("archive")).unwrap();
+        assert!(!workspace.join("archive").exists());
+        assert_eq!(
+            vec![VaultPath::from("projects-old")],
+            vault.get_subdirectories(&VaultPath::root()).unwrap()
+        );
+        assert!(vault.delete_directory(&VaultPath::root()).is_err());
+    }
+
+    #[test]
+    fn directory_names_are_not_patterns() {
+        let (_dir, vault) = indexed_vault(&[("my_dir/a.md", "# A"), ("my-dir/b.md", "# B")]);
+        let sibling_notes = |vault: &NoteVault| {
+            vault
+                .get_notes(&VaultPath::from("my-dir"), false)
+                .unwrap()
+                .into_iter()
+                .map(|details| details.path)
+                .collect::<Vec<VaultPath>>()
+        };
+
+        // `_` would match the `-` of the sibling as a LIKE pattern
+        vault
+            .rename_directory(&VaultPath::from("my_dir"), &VaultPath::from("renamed"))
+            .unwrap();
+        assert_eq!(vec![VaultPath::from("my-dir/b.md")], sibling_notes(&vault));
+        vault
+            .rename_directory(&VaultPath::from("renamed"), &VaultPath::from("my_dir"))
+            .unwrap();
+
+        vault.delete_directory(&VaultPath::from("my_dir")).unwrap();
+        assert_eq!(vec![VaultPath::from("my-dir/b.md")], sibling_notes(&vault));
+        assert_eq!(
+            vec![VaultPath::from("my-dir")],
+            vault.get_subdirectories(&VaultPath::root()).unwrap()
+        );
+    }
+
+    #[test]
+    fn directory_operations_ignoring_case() {
+        let (_dir, mut vault) = indexed_vault(&[
+            ("home.md", "See [api](Projects/api.md)"),
+            ("Projects/api.md", "# API"),
+            ("Café/menu.md", "# Menu"),
+        ]);
+        let mut config = vault.get_config().clone();
+        config.path_case = PathCase::Insensitive;
+        vault.set_config(config).unwrap();
+        let notes = |vault: &NoteVault, path: &str| {
+            vault
+                .get_notes(&VaultPath::from(path), true)
+                .unwrap()
+                .into_iter()
+                .map(|details| details.path)
+                .collect::<Vec<VaultPath>>()
+        };
+
+        vault
+            .rename_directory(&VaultPath::from("projects"), &VaultPath::from("archive"))
+            .unwrap();
+        assert!(vault.workspace_path.join("archive/api.md").exists());
+        assert_eq!(
+            vec![VaultPath::from("archive/api.md")],
+            notes(&vault, "archive")
+        );
+        assert!(notes(&vault, "Projects").is_empty());
+        assert_eq!(
+            "See [api](archive/api.md)",
+            vault.get_note_text(&VaultPath::from("home.md")).unwrap()
+        );
+
+        // The name written in the decomposed Unicode form
+        vault
+            .delete_directory(&VaultPath::from("CAFE\u{301}"))
+            .unwrap();
+        assert!(!vault.workspace_path.join("Café").exists());
+        assert!(notes(&vault, "Café").is_empty());
+    }
+
+    #[test]
+    fn rename_note_to_a_note_path() {
+        let (_dir, vault) = indexed_vault(&[("note.md", "# Note")]);
+
+        assert!(vault
+            .rename_note(&VaultPath::from("note.md"), &VaultPath::from("archive"))
+            .is_err());
+        assert!(vault.workspace_path.join("note.md").exists());
+        assert!(!vault.workspace_path.join("archive").exists());
+    }
+
+    #[test]
+    fn headings_with_the_separator() {
+        let (_dir, vault) = indexed_vault(&[(
+            "note.md",
+            "# Intro\n\n## Input > Output\n\nText\n\n### Deep\n\n## Other",
+        )]);
+
+        assert_eq!(
+            vec!["Intro", "Input > Output", "Deep", "Other"],
+            vault.get_headings(&VaultPath::from("note.md")).unwrap()
+        );
+        assert_eq!(
+            vec![(
+                VaultPath::from("note.md"),
+                vec!["Intro".to_string(), "Input > Output".to_string()]
+            )],
+            vault.search_headings("output", 10).unwrap()
+        );
+        assert_eq!(
+            vec![(
+                VaultPath::from("note.md"),
+                vec!["Intro".to_string(), "Other".to_string()]
+            )],
+            vault.search_headings("other", 10).unwrap()
+        );
+        assert_eq!(2, vault.search_headings("t", 2).unwrap().len());
+    }
+
+    #[test]
+    fn note_properties_and_stats_search() {
+        let (_dir, vault) = indexed_vault(&[
+            (
+                "long.md",
+                "# Long\n\none two three four five six\n\n## More\n\nSee [short](short.md)",
+            ),
+            ("short.md", "# Short\n\nSee [[long]]"),
+        ]);
+
+        let properties = vault
+            .get_note_properties(&VaultPath::from("long.md"))
+            .unwrap();
+        assert_eq!(10, properties.stats.words);
+        assert_eq!(2, properties.stats.headings);
+        assert_eq!(1, properties.links);
+        assert_eq!(vec![VaultPath::from("short.md")], properties.backlinks);
+        assert!(properties.created_secs > 0);
+        assert!(vault
+            .get_note_properties(&VaultPath::from("missing.md"))
+            .is_err());
+
+        let paths = |query: &str| {
+            vault
+                .search_notes(query)
+                .unwrap()
+                .into_iter()
+                .map(|note| note.path)
+                .collect::<Vec<VaultPath>>()
+        };
+        assert_eq!(vec![VaultPath::from("long.md")], paths("words:>5"));
+        assert_eq!(vec![VaultPath::from("short.md")], paths("words:<=5 see"));
+        assert_eq!(
+            vec![VaultPath::from("long.md"), VaultPath::from("short.md")],
+            paths("links:1 sort:-words")
+        );
+        assert_eq!(
+            vec![VaultPath::from("short.md"), VaultPath::from("long.md")],
+            paths("see sort:words")
+        );
+    }
+
+    #[test]
+    fn task_breadcrumbs_with_the_separator() {
+        let (_dir, vault) = indexed_vault(&[(
+            "note.md",
+            "# Plan\n\n## Input > Output\n\n- [ ] Parse\n\n- [x] Write",
+        )]);
+
+        let tasks = vault.query_tasks(&TaskFilter::open()).unwrap();
+        assert_eq!(1, tasks.len());
+        assert_eq!(
+            vec!["Plan".to_string(), "Input > Output".to_string()],
+            tasks[0].1.breadcrumb
+        );
+    }
 }
@@ -0,0 +1,287 @@
+use std::{ops::Range, sync::OnceLock};
+
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+
+use crate::{
+    export::{is_external, percent_decode},
+    nfs::VaultPath,
+};
+
+/// The target of a wiki link is the first group, the heading the second
+const WIKI_LINK_REGEX: &str = r"\[\[([^\[\]|#\n]*)(?:#([^\[\]|\n]*))?(?:\|[^\[\]\n]*)?\]\]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// `[[Note]]`, the note is found by name, or by its path from the
+    /// root of the vault if it has a `/`
+    Wiki,
+    /// `[label](note.md)`, the path is relative to the note
+    Markdown,
+}
+
+/// A link in a note to another note, or to a heading in the same note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteLink {
+    pub kind: LinkKind,
+    /// The note linked as written, without the heading. Empty when
+    /// linking to a heading in the same note.
+    pub target: String,
+    pub heading: Option<String>,
+    /// Where the whole link is in the note's text, in bytes
+    pub range: Range<usize>,
+    /// Where the target is in the note's text, in bytes
+    pub target_range: Range<usize>,
+}
+
+impl NoteLink {
+    /// The name of the file linked, in lowercase, to look up the notes
+    /// that may link to another one
+    pub(crate) fn name(&self) -> String {
+        let target = match self.kind {
+            LinkKind::Wiki => self.target.clone(),
+            LinkKind::Markdown => percent_decode(&self.target),
+        };
+        target
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+    }
+
+    /// The new text for the target, so the link from the note `from`
+    /// points to `to`, keeping how it was written
+    pub fn retarget(&self, from: &VaultPath, to: &VaultPath) -> String {
+        let has_extension = self
+            .target
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| name.contains('.'));
+        let strip = |link: String| match to.get_extension() {
+            Some(extension) if !has_extension => link
+                .strip_suffix(&format!(".{}", extension))
+                .map(str::to_string)
+                .unwrap_or(link),
+            _ => link,
+        };
+        match self.kind {
+            LinkKind::Wiki if self.target.contains('/') => {
+                strip(to.to_string().trim_start_matches('/').to_string())
+            }
+            LinkKind::Wiki => strip(to.get_name()),
+            LinkKind::Markdown => {
+                let mut destination = strip(to.relative_to(&from.get_parent_path().0));
+                if let Some(heading) = &self.heading {
+                    destination = format!("{}#{}", destination, heading);
+                }
+                if destination.contains([' ', '(', ')']) {
+                    format!("<{}>", destination)
+                } else {
+                    destination
+                }
+            }
+        }
+    }
+}
+
+/// A change to the text of a note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkEdit {
+    /// The bytes to replace
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+fn wiki_link_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(WIKI_LINK_REGEX).unwrap())
+}
+
+/// Gets the links to notes in a Markdown text in the order they appear.
+/// Images, external links and links inside code are left out.
+pub fn extract_links(md_text: &str) -> Vec<NoteLink> {
+    let options = Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+    let mut links = vec![];
+    // Wiki links are not CommonMark, so we look for them in the raw text
+    // skipping code, html and the front matter
+    let mut literal_ranges = vec![];
+    let mut literal_start = None;
+    for (event, range) in Parser::new_ext(md_text, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_))
+            | Event::Start(Tag::HtmlBlock)
+            | Event::Start(Tag::MetadataBlock(_)) => literal_start = Some(range.start),
+            Event::End(TagEnd::CodeBlock)
+            | Event::End(TagEnd::HtmlBlock)
+            | Event::End(TagEnd::MetadataBlock(_)) => {
+                if let Some(start) = literal_start.take() {
+                    literal_ranges.push(start..range.end);
+                }
+            }
+            Event::Code(_) | Event::InlineHtml(_) | Event::Html(_) => literal_ranges.push(range),
+            Event::Start(Tag::Link {
+                link_type: LinkType::Inline,
+                dest_url,
+                ..
+            }) => {
+                if let Some(link) = markdown_link(md_text, range, &dest_url) {
+                    links.push(link);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for captures in wiki_link_regex().captures_iter(md_text) {
+        let whole = captures.get(0).unwrap();
+        let is_literal = literal_ranges
+            .iter()
+            .any(|range| range.start < whole.end() && whole.start() < range.end);
+        if is_literal {
+            continue;
+        }
+        let target = captures.get(1).unwrap();
+        let trimmed = target.as_str().trim();
+        let target_start = target.start() + target.as_str().find(trimmed).unwrap_or_default();
+        links.push(NoteLink {
+            kind: LinkKind::Wiki,
+            target: trimmed.to_string(),
+            heading: captures
+                .get(2)
+                .map(|heading| heading.as_str().trim().to_string()),
+            range: whole.range(),
+            target_range: target_start..target_start + trimmed.len(),
+        });
+    }
+    links.sort_by_key(|link| link.range.start);
+    links
+}
+
+/// The link if it goes to another file in the vault, the destination is
+/// looked up in the raw text as it may be between `<>`
+fn markdown_link(md_text: &str, range: Range<usize>, dest_url: &str) -> Option<NoteLink> {
+    if is_external(dest_url) {
+        return None;
+    }
+    let text = &md_text[range.clone()];
+    let open = text.rfind("](")? + 2;
+    let rest = &text[open..];
+    let skipped = rest.len() - rest.trim_start().len();
+    let rest = rest.trim_start();
+    let length = if rest.starts_with('<') {
+        rest.find('>')? + 1
+    } else {
+        rest.find(|c: char| c.is_whitespace() || c == ')')
+            .unwrap_or(rest.len())
+    };
+    let target_start = range.start + open + skipped;
+    let (target, heading) = match dest_url.split_once('#') {
+        Some((target, heading)) => (target, Some(percent_decode(heading))),
+        None => (dest_url, None),
+    };
+    Some(NoteLink {
+        kind: LinkKind::Markdown,
+        target: target.to_string(),
+        heading,
+        range,
+        target_range: target_start..target_start + length,
+    })
+}
+
+/// Applies the edits to the text, they can't overlap
+pub(crate) fn apply_edits(text: &str, edits: &[LinkEdit]) -> String {
+    let mut edits = edits.iter().collect::<Vec<&LinkEdit>>();
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+    let mut text = text.to_string();
+    for edit in edits {
+        text.replace_range(edit.range.clone(), &edit.text);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{nfs::VaultPath, test_utils::indexed_vault};
+
+    use super::{extract_links, LinkKind};
+
+    #[test]
+    fn extract_and_retarget_links() {
+        let markdown = "---\nsee: \"[[Not a link]]\"\n---\n# Links\n\n[[Plan]] and [[projects/Plan#Goals|the goals]]\n\
+                        [Plan](../projects/Plan.md#goals), [same](#links) and [web](https://example.com)\n\
+                        `[[Code]]` and [spaced](<My Plan.md>) ![image](image.png)\n";
+        let links = extract_links(markdown);
+
+        let targets = links
+            .iter()
+            .map(|link| link.target.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            vec!["Plan", "projects/Plan", "../projects/Plan.md", "My Plan.md"],
+            targets
+        );
+        assert_eq!(LinkKind::Wiki, links[0].kind);
+        assert_eq!("[[Plan]]", &markdown[links[0].range.clone()]);
+        assert_eq!(Some("Goals".to_string()), links[1].heading);
+        assert_eq!(LinkKind::Markdown, links[2].kind);
+        assert_eq!(Some("goals".to_string()), links[2].heading);
+        assert_eq!(
+            "../projects/Plan.md#goals",
+            &markdown[links[2].target_range.clone()]
+        );
+        assert_eq!("<My Plan.md>", &markdown[links[3].target_range.clone()]);
+
+        let from = VaultPath::from("notes/today.md");
+        let to = VaultPath::from("projects/New Plan.md");
+        assert_eq!("New Plan", links[0].retarget(&from, &to));
+        assert_eq!("projects/New Plan", links[1].retarget(&from, &to));
+        assert_eq!(
+            "<../projects/New Plan.md#goals>",
+            links[2].retarget(&from, &to)
+        );
+        assert_eq!("<../projects/New Plan.md>", links[3].retarget(&from, &to));
+    }
+
+    #[test]
+    fn backlinks_and_rename() {
+        let (_dir, vault) = indexed_vault(&[
+            ("projects/plan.md", "# Plan\n\nSee [[#Plan]]"),
+            (
+                "home.md",
+                "# Home\n\n[[plan]], [the plan](projects/plan.md) and [[Missing]]",
+            ),
+            ("projects/other.md", "[[projects/plan|Plan]]"),
+        ]);
+
+        let plan = VaultPath::from("projects/plan.md");
+        let backlinks = vault.get_backlinks(&plan).unwrap();
+        assert_eq!(3, backlinks.len());
+        assert_eq!(VaultPath::from("home.md"), backlinks[0].0);
+        assert_eq!(VaultPath::from("projects/other.md"), backlinks[2].0);
+
+        let home = VaultPath::from("home.md");
+        let text = vault.get_note_text(&home).unwrap();
+        let broken = vault.get_broken_links(&home, &text).unwrap();
+        assert_eq!(1, broken.len());
+        assert_eq!("Missing", broken[0].target);
+
+        let new_plan = VaultPath::from("archive/old plan.md");
+        let changed = vault.rename_note(&plan, &new_plan).unwrap();
+        assert_eq!(2, changed.len());
+        assert!(!vault.workspace_path.join("projects/plan.md").exists());
+        assert_eq!(
+            "# Home\n\n[[old plan]], [the plan](<archive/old plan.md>) and [[Missing]]",
+            vault.get_note_text(&home).unwrap()
+        );
+        assert_eq!(
+            "[[archive/old plan|Plan]]",
+            vault
+                .get_note_text(&VaultPath::from("projects/other.md"))
+                .unwrap()
+        );
+        assert_eq!(3, vault.get_backlinks(&new_plan).unwrap().len());
+        assert!(vault.get_backlinks(&plan).unwrap().is_empty());
+    }
+}
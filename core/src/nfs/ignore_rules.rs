@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+
+use ignore::{DirEntry, WalkBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::VaultPath;
+
+/// File with gitignore style glob patterns of the entries to skip,
+/// it can be in any directory of the vault
+pub const IGNORE_FILE: &str = ".kimunignore";
+/// Directories that are never part of the notes
+const ALWAYS_EXCLUDED: [&str; 1] = [crate::VAULT_CONFIG_DIR];
+
+/// Which files and directories of the vault are skipped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IgnoreRules {
+    /// Skip files and directories starting with a dot
+    pub skip_hidden: bool,
+    /// Skip the entries matching the `.gitignore` files of the vault,
+    /// off by default as notes are often kept in a repository with others
+    pub use_gitignore: bool,
+    /// Directories to skip, with all their content
+    pub excluded_folders: Vec<VaultPath>,
+}
+
+impl Default for IgnoreRules {
+    fn default() -> Self {
+        Self {
+            skip_hidden: true,
+            use_gitignore: false,
+            excluded_folders: vec![],
+        }
+    }
+}
+
+impl IgnoreRules {
+    /// Configures a walker over the vault to skip the ignored entries
+    pub(crate) fn apply<P: AsRef<Path>>(&self, builder: &mut WalkBuilder, workspace_path: P) {
+        let excluded = self.excluded_paths(workspace_path);
+        builder
+            .hidden(self.skip_hidden)
+            .git_ignore(self.use_gitignore)
+            .git_exclude(self.use_gitignore)
+            // Only the rules inside the vault, not the ones of the
+            // directories above it nor the user's global ones
+            .parents(false)
+            .git_global(false)
+            // Vaults are often synced without the git directory
+            .require_git(false)
+            // Only our own ignore file, `.ignore` files are for other tools
+            .ignore(false)
+            .add_custom_ignore_filename(IGNORE_FILE)
+            .filter_entry(move |entry: &DirEntry| {
+                !excluded.iter().any(|path| entry.path().starts_with(path))
+            });
+    }
+
+    fn excluded_paths<P: AsRef<Path>>(&self, workspace_path: P) -> Vec<PathBuf> {
+        let workspace_path = workspace_path.as_ref();
+        self.excluded_folders
+            .iter()
+            .filter(|path| !path.slices.is_empty())
+            .map(|path| path.to_pathbuf(workspace_path))
+            .chain(ALWAYS_EXCLUDED.iter().map(|dir| workspace_path.join(dir)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use ignore::WalkBuilder;
+
+    use crate::{
+        nfs::VaultPath,
+        test_utils::{vault_files, workspace},
+    };
+
+    use super::{IgnoreRules, IGNORE_FILE};
+
+    fn walk(workspace: &Path, rules: &IgnoreRules) -> Vec<String> {
+        let mut builder = WalkBuilder::new(workspace);
+        rules.apply(&mut builder, workspace);
+        let mut paths = builder
+            .build()
+            .flatten()
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| {
+                VaultPath::from_path(workspace, entry.path())
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<String>>();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn skip_ignored_entries() {
+        let dir = vault_files(&[
+            (".git/config.md", "text"),
+            (".obsidian/app.md", "text"),
+            (".kimun/config.md", "text"),
+            ("templates/daily.md", "text"),
+            ("notes/note.md", "text"),
+            ("notes/draft.md", "text"),
+            ("build/out.md", "text"),
+            ("root.md", "text"),
+            (".gitignore", "build/\n"),
+        ]);
+        let workspace = workspace(&dir);
+        std::fs::write(workspace.join("notes").join(IGNORE_FILE), "draft*\n").unwrap();
+        // Outside of the vault, not applied
+        std::fs::write(dir.join(".gitignore"), "root.md\n").unwrap();
+
+        let rules = IgnoreRules {
+            excluded_folders: vec![VaultPath::new("templates")],
+            ..Default::default()
+        };
+        assert_eq!(
+            vec!["/build/out.md", "/notes/note.md", "/root.md"],
+            walk(&workspace, &rules)
+        );
+
+        let rules = IgnoreRules {
+            use_gitignore: true,
+            excluded_folders: vec![VaultPath::new("templates")],
+            ..Default::default()
+        };
+        assert_eq!(vec!["/notes/note.md", "/root.md"], walk(&workspace, &rules));
+
+        let rules = IgnoreRules {
+            skip_hidden: false,
+            use_gitignore: false,
+            excluded_folders: vec![],
+        };
+        assert_eq!(
+            vec![
+                "/.git/config.md",
+                "/.gitignore",
+                "/.obsidian/app.md",
+                "/build/out.md",
+                "/notes/.kimunignore",
+                "/notes/note.md",
+                "/root.md",
+                "/templates/daily.md"
+            ],
+            walk(&workspace, &rules)
+        );
+    }
+}
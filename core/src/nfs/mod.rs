@@ -1,3 +1,5 @@
+pub mod ignore_rules;
+pub mod note_types;
 pub mod visitor;
 // Contains the structs to support the data types
 use std::{
@@ -6,16 +8,20 @@ use std::{
     hash::Hash,
     io::Write,
     path::{Path, PathBuf},
+    sync::OnceLock,
     time::UNIX_EPOCH,
 };
 
 use gxhash::gxhash64;
 use ignore::{WalkBuilder, WalkParallel};
-use log::info;
+use log::{debug, info, warn};
 use regex::Regex;
 use serde::{de::Visitor, Deserialize, Serialize};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 
 use super::{error::FSError, DirectoryDetails, NoteDetails};
+use ignore_rules::IgnoreRules;
+use note_types::NoteTypes;
 
 use super::utilities::path_to_string;
 
@@ -50,16 +56,23 @@ pub struct NoteEntryData {
     // File size, for fast check
     pub size: u64,
     pub modified_secs: u64,
+    /// When the file was created, the modified time if the file system
+    /// doesn't keep it
+    pub created_secs: u64,
 }
 
 impl NoteEntryData {
+    /// Reads the note from disk, extracting its data with the
+    /// format of its extension
     pub fn load_details<P: AsRef<Path>>(
         &self,
         workspace_path: P,
         path: &VaultPath,
+        types: &NoteTypes,
     ) -> Result<NoteDetails, FSError> {
         let content = load_note(workspace_path, path)?;
-        Ok(NoteDetails::from_content(content, path))
+        let format = types.format(path).unwrap_or_default();
+        Ok(NoteDetails::from_content(content, path, format))
     }
 
     fn from_path<P: AsRef<Path>>(
@@ -74,10 +87,15 @@ impl NoteEntryData {
             .modified()
             .map(|t| t.duration_since(UNIX_EPOCH).unwrap().as_secs())
             .unwrap_or_else(|_e| 0);
+        let created_secs = metadata
+            .created()
+            .map(|t| t.duration_since(UNIX_EPOCH).unwrap().as_secs())
+            .unwrap_or(modified_secs);
         Ok(NoteEntryData {
             path: path.clone(),
             size,
             modified_secs,
+            created_secs,
         })
     }
 }
@@ -99,10 +117,10 @@ fn _get_dir_content_size<P: AsRef<Path>>(
     path: &VaultPath,
 ) -> Result<u64, FSError> {
     let os_path = path.to_pathbuf(&workspace_path);
-    let walker = ignore::WalkBuilder::new(&os_path)
-        .max_depth(Some(1))
-        .filter_entry(filter_files)
-        .build();
+    let mut builder = ignore::WalkBuilder::new(&os_path);
+    builder.max_depth(Some(1));
+    IgnoreRules::default().apply(&mut builder, &workspace_path);
+    let walker = builder.build();
     let mut content_size = 0;
     for entry in walker.flatten() {
         let entry_path = entry.path();
@@ -116,7 +134,11 @@ fn _get_dir_content_size<P: AsRef<Path>>(
 }
 
 impl VaultEntry {
-    pub fn new<P: AsRef<Path>>(workspace_path: P, path: VaultPath) -> Result<Self, FSError> {
+    pub fn new<P: AsRef<Path>>(
+        workspace_path: P,
+        path: VaultPath,
+        types: &NoteTypes,
+    ) -> Result<Self, FSError> {
         let os_path = path.to_pathbuf(&workspace_path);
         if !os_path.exists() {
             return Err(FSError::NoFileOrDirectoryFound {
@@ -126,7 +148,7 @@ impl VaultEntry {
 
         let kind = if os_path.is_dir() {
             EntryData::Directory(DirectoryEntryData { path: path.clone() })
-        } else if path.is_note() {
+        } else if types.is_note(&path) {
             let note_entry_data = NoteEntryData::from_path(workspace_path, &path)?;
             EntryData::Note(note_entry_data)
         } else {
@@ -144,9 +166,10 @@ impl VaultEntry {
     pub fn from_path<P: AsRef<Path>, F: AsRef<Path>>(
         workspace_path: P,
         full_path: F,
+        types: &NoteTypes,
     ) -> Result<Self, FSError> {
         let note_path = VaultPath::from_path(&workspace_path, &full_path)?;
-        Self::new(&workspace_path, note_path)
+        Self::new(&workspace_path, note_path, types)
     }
 }
 
@@ -160,6 +183,8 @@ impl Display for VaultEntry {
     }
 }
 
+// Most entries are notes, boxing them wouldn't save memory
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum VaultEntryDetails {
     // Hash
@@ -183,17 +208,15 @@ pub(crate) fn hash_text<S: AsRef<str>>(text: S) -> u64 {
 }
 
 /// Loads a note from disk, if the file doesn't exist, returns a FSError::NotePathNotFound
-/// Returns the note's text. If you want the details, use NoteDetails::from_content
+/// Returns the note's text, see `decode_text` for the supported encodings.
+/// If you want the details, use NoteDetails::from_content
 pub(crate) fn load_note<P: AsRef<Path>>(
     workspace_path: P,
     path: &VaultPath,
 ) -> Result<String, FSError> {
     let os_path = path.to_pathbuf(&workspace_path);
     match std::fs::read(&os_path) {
-        Ok(file) => {
-            let text = String::from_utf8(file)?;
-            Ok(text)
-        }
+        Ok(file) => Ok(decode_text(file, path)),
         Err(e) => match e.kind() {
             std::io::ErrorKind::NotFound => Err(FSError::VaultPathNotFound {
                 path: path.to_owned(),
@@ -203,12 +226,57 @@ pub(crate) fn load_note<P: AsRef<Path>>(
     }
 }
 
+/// Windows-1252 characters for the bytes 0x80 to 0x9F, the undefined
+/// ones are mapped to the replacement character
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{FFFD}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{FFFD}', 'Ž',
+    '\u{FFFD}', '\u{FFFD}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{FFFD}',
+    'ž', 'Ÿ',
+];
+
+/// Decodes the content of a note. Notes are expected in UTF-8, but older
+/// ones may be in UTF-16 (detected by its BOM) or in a legacy single byte
+/// encoding, that is read as Windows-1252. The note is saved back as UTF-8.
+pub(crate) fn decode_text(bytes: Vec<u8>, path: &VaultPath) -> String {
+    let bytes = match String::from_utf8(bytes) {
+        Ok(text) => {
+            return match text.strip_prefix('\u{FEFF}') {
+                Some(stripped) => stripped.to_string(),
+                None => text,
+            }
+        }
+        Err(e) => e.into_bytes(),
+    };
+    let utf16 = match bytes.as_slice() {
+        [0xFF, 0xFE, rest @ ..] => Some((rest, u16::from_le_bytes as fn([u8; 2]) -> u16)),
+        [0xFE, 0xFF, rest @ ..] => Some((rest, u16::from_be_bytes as fn([u8; 2]) -> u16)),
+        _ => None,
+    };
+    if let Some((rest, from_bytes)) = utf16 {
+        debug!("Reading {} as UTF-16", path);
+        let units = rest
+            .chunks_exact(2)
+            .map(|pair| from_bytes([pair[0], pair[1]]))
+            .collect::<Vec<u16>>();
+        return String::from_utf16_lossy(&units);
+    }
+    warn!("{} is not valid UTF-8, reading it as Windows-1252", path);
+    bytes
+        .into_iter()
+        .map(|byte| match byte {
+            0x80..=0x9F => WINDOWS_1252_HIGH[(byte - 0x80) as usize],
+            _ => byte as char,
+        })
+        .collect()
+}
+
 pub fn save_note<P: AsRef<Path>, S: AsRef<str>>(
     workspace_path: P,
     path: &VaultPath,
     text: S,
+    types: &NoteTypes,
 ) -> Result<NoteEntryData, FSError> {
-    if !path.is_note() {
+    if !types.is_note(path) {
         return Err(FSError::InvalidPath {
             path: path.to_string(),
         });
@@ -229,6 +297,21 @@ pub fn save_note<P: AsRef<Path>, S: AsRef<str>>(
     Ok(entry)
 }
 
+/// How paths are matched. Names are always compared in Unicode NFC form
+/// and without trailing spaces, so the same name written by different
+/// systems is the same path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathCase {
+    #[default]
+    Sensitive,
+    /// `Note.md` and `note.md` are the same note, like in the default
+    /// macOS and Windows file systems
+    Insensitive,
+}
+
+/// A path in the vault, keeping the names as they are on disk. Two paths
+/// are equal if their names are, after normalizing them, see [`PathCase`].
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct VaultPath {
     slices: Vec<VaultPathSlice>,
@@ -334,10 +417,12 @@ impl VaultPath {
         match slices.pop() {
             Some(slice) => {
                 let name = &slice.name;
-                let new_name = if let Some(name) = name.strip_suffix(NOTE_EXTENSION) {
-                    format!("{}{}", Self::increment(name), NOTE_EXTENSION)
-                } else {
-                    Self::increment(name)
+                let stem = self
+                    .get_extension()
+                    .and_then(|extension| name.strip_suffix(&format!(".{}", extension)));
+                let new_name = match stem {
+                    Some(stem) => format!("{}{}", Self::increment(stem), &name[stem.len()..]),
+                    None => Self::increment(name),
                 };
                 slices.push(VaultPathSlice::new(new_name));
                 VaultPath { slices }
@@ -363,6 +448,39 @@ impl VaultPath {
         format!("{}_{}", n, suffix_num)
     }
 
+    /// The normalized path, two paths with the same key are the same
+    /// entry when matching with `case`
+    pub fn key(&self, case: PathCase) -> String {
+        let key = self
+            .slices
+            .iter()
+            .map(|slice| slice.key.as_str())
+            .collect::<Vec<&str>>()
+            .join(&PATH_SEPARATOR.to_string());
+        let key = format!("{}{}", PATH_SEPARATOR, key);
+        match case {
+            PathCase::Sensitive => key,
+            PathCase::Insensitive => key.to_lowercase(),
+        }
+    }
+
+    /// If both paths are the same entry when matching with `case`
+    pub fn matches(&self, other: &VaultPath, case: PathCase) -> bool {
+        match case {
+            PathCase::Sensitive => self == other,
+            PathCase::Insensitive => self.key(case) == other.key(case),
+        }
+    }
+
+    /// If the path is inside the directory, or is the directory itself
+    pub fn is_in(&self, directory: &VaultPath, case: PathCase) -> bool {
+        self.slices.len() >= directory.slices.len()
+            && VaultPath {
+                slices: self.slices[..directory.slices.len()].to_vec(),
+            }
+            .matches(directory, case)
+    }
+
     pub fn get_slices(&self) -> Vec<String> {
         self.slices
             .iter()
@@ -370,7 +488,7 @@ impl VaultPath {
             .collect()
     }
 
-    pub(super) fn to_pathbuf<P: AsRef<Path>>(&self, workspace_path: P) -> PathBuf {
+    pub fn to_pathbuf<P: AsRef<Path>>(&self, workspace_path: P) -> PathBuf {
         let mut path = workspace_path.as_ref().to_path_buf();
         for p in &self.slices {
             let slice = p.name.clone();
@@ -410,17 +528,14 @@ impl VaultPath {
         Ok(Self { slices: path_list })
     }
 
-    pub fn is_note(&self) -> bool {
-        match self.slices.last() {
-            Some(path_slice) => {
-                let last_slice: &Path = Path::new(&path_slice.name);
-                last_slice
-                    .extension()
-                    .and_then(OsStr::to_str)
-                    .map_or_else(|| false, |s| s == "md")
-            }
-            None => false,
-        }
+    /// The extension of the last slice, if it has one
+    pub fn get_extension(&self) -> Option<String> {
+        self.slices.last().and_then(|path_slice| {
+            Path::new(&path_slice.name)
+                .extension()
+                .and_then(OsStr::to_str)
+                .map(|extension| extension.to_string())
+        })
     }
 
     pub fn get_parent_path(&self) -> (VaultPath, String) {
@@ -436,6 +551,22 @@ impl VaultPath {
         slices.append(&mut other_slices);
         VaultPath { slices }
     }
+
+    /// Returns this path relative to the `base` directory, using `..` to go
+    /// up when needed, so it can be used as a link from a note in `base`
+    pub fn relative_to(&self, base: &VaultPath) -> String {
+        let common = self
+            .slices
+            .iter()
+            .zip(base.slices.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let ups = std::iter::repeat_n("..".to_string(), base.slices.len() - common);
+        let downs = self.slices[common..].iter().map(|s| s.name.clone());
+        ups.chain(downs)
+            .collect::<Vec<String>>()
+            .join(&PATH_SEPARATOR.to_string())
+    }
 }
 
 impl Display for VaultPath {
@@ -453,21 +584,42 @@ impl Display for VaultPath {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A name in the path, the `key` is the name used to compare it
+#[derive(Debug, Clone)]
 struct VaultPathSlice {
     name: String,
+    key: String,
+}
+
+impl PartialEq for VaultPathSlice {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for VaultPathSlice {}
+
+impl Hash for VaultPathSlice {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
 }
 
 impl VaultPathSlice {
     fn new<S: Into<String>>(slice: S) -> Self {
-        let re = regex::Regex::new(NON_VALID_PATH_CHARS_REGEX).unwrap();
+        static RE: OnceLock<Regex> = OnceLock::new();
+        let re = RE.get_or_init(|| Regex::new(NON_VALID_PATH_CHARS_REGEX).unwrap());
 
         let into = slice.into();
-        let final_slice = re.replace_all(&into, "_");
+        let name = re.replace_all(&into, "_").to_string();
+        let trimmed = name.trim_end();
+        let key = if is_nfc_quick(trimmed.chars()) == IsNormalized::Yes {
+            trimmed.to_string()
+        } else {
+            trimmed.nfc().collect()
+        };
 
-        Self {
-            name: final_slice.to_string(),
-        }
+        Self { name, key }
     }
 }
 
@@ -477,22 +629,18 @@ impl Display for VaultPathSlice {
     }
 }
 
-fn filter_files(dir: &ignore::DirEntry) -> bool {
-    !dir.path().starts_with(".")
-}
-
+/// Walks the vault from the path skipping the entries the rules ignore
 pub fn get_file_walker<P: AsRef<Path>>(
     base_path: P,
     path: &VaultPath,
     recurse: bool,
+    rules: &IgnoreRules,
 ) -> WalkParallel {
-    let w = WalkBuilder::new(path.to_pathbuf(base_path))
-        .max_depth(if recurse { None } else { Some(1) })
-        .filter_entry(filter_files)
-        // .threads(0)
-        .build_parallel();
-
-    w
+    let mut builder = WalkBuilder::new(path.to_pathbuf(&base_path));
+    builder.max_depth(if recurse { None } else { Some(1) });
+    rules.apply(&mut builder, &base_path);
+    // builder.threads(0);
+    builder.build_parallel()
 }
 
 #[cfg(test)]
@@ -501,7 +649,28 @@ mod tests {
 
     use crate::{error::FSError, utilities::path_to_string};
 
-    use super::{load_note, VaultPath, VaultPathSlice};
+    use super::{decode_text, load_note, PathCase, VaultPath, VaultPathSlice};
+
+    #[test]
+    fn decode_legacy_encodings() {
+        let path = VaultPath::new("note.md");
+        assert_eq!(
+            "Café",
+            decode_text("\u{FEFF}Café".as_bytes().to_vec(), &path)
+        );
+        // Windows-1252
+        assert_eq!(
+            "“Café”",
+            decode_text(vec![0x93, b'C', b'a', b'f', 0xE9, 0x94], &path)
+        );
+        // UTF-16 with BOM
+        let mut utf16_le = vec![0xFF, 0xFE];
+        utf16_le.extend("Café".encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+        assert_eq!("Café", decode_text(utf16_le, &path));
+        let mut utf16_be = vec![0xFE, 0xFF];
+        utf16_be.extend("Café".encode_utf16().flat_map(|unit| unit.to_be_bytes()));
+        assert_eq!("Café", decode_text(utf16_be, &path));
+    }
 
     #[test]
     fn test_file_not_exists() {
@@ -560,6 +729,52 @@ mod tests {
         assert_eq!("/usr/john/notes/some/subpath", path_string);
     }
 
+    #[test]
+    fn test_relative_path() {
+        let note = VaultPath::from("projects/kimun/features.md");
+
+        assert_eq!(
+            "projects/kimun/features.md",
+            note.relative_to(&VaultPath::root())
+        );
+        assert_eq!(
+            "features.md",
+            note.relative_to(&VaultPath::from("projects/kimun"))
+        );
+        assert_eq!(
+            "../kimun/features.md",
+            note.relative_to(&VaultPath::from("projects/semtag"))
+        );
+        assert_eq!(
+            "../../projects/kimun/features.md",
+            note.relative_to(&VaultPath::from("journal/2025"))
+        );
+    }
+
+    #[test]
+    fn normalized_path_equality() {
+        // NFC and NFD forms of the same name, and a trailing space
+        let nfc = VaultPath::from("Notes/Caf\u{e9}.md");
+        let nfd = VaultPath::from("Notes /Cafe\u{301}.md");
+        assert_eq!(nfc, nfd);
+        // The names on disk are kept
+        assert_eq!("/Notes /Cafe\u{301}.md", nfd.to_string());
+
+        let lower = VaultPath::from("notes/café.md");
+        assert_ne!(nfc, lower);
+        assert!(!nfc.matches(&lower, PathCase::Sensitive));
+        assert!(nfc.matches(&lower, PathCase::Insensitive));
+        assert_eq!(
+            nfd.key(PathCase::Insensitive),
+            lower.key(PathCase::Insensitive)
+        );
+
+        assert!(nfd.is_in(&VaultPath::from("notes"), PathCase::Insensitive));
+        assert!(!nfd.is_in(&VaultPath::from("notes"), PathCase::Sensitive));
+        assert!(!nfd.is_in(&VaultPath::from("Notes/Other"), PathCase::Sensitive));
+        assert!(nfd.is_in(&VaultPath::root(), PathCase::Sensitive));
+    }
+
     #[test]
     fn test_path_check_valid() {
         let path = PathBuf::from("/some/valid/path/workspace/note.md");
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::content_data::{self, NoteContentData};
+
+use super::VaultPath;
+
+/// How the content of a note is read for the index
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteFormat {
+    /// Split by headers, with tags, front matter and links
+    #[default]
+    Markdown,
+    /// The whole text is a single chunk, the first line is the title
+    Text,
+}
+
+impl NoteFormat {
+    pub(crate) fn extract_data<S: AsRef<str>>(&self, text: S) -> NoteContentData {
+        match self {
+            NoteFormat::Markdown => content_data::extract_data(text),
+            NoteFormat::Text => content_data::extract_text_data(text),
+        }
+    }
+}
+
+/// The file extensions that are notes, and the format of each one.
+/// Any other file in the vault is an attachment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    from = "BTreeMap<String, NoteFormat>",
+    into = "BTreeMap<String, NoteFormat>"
+)]
+pub struct NoteTypes {
+    extensions: BTreeMap<String, NoteFormat>,
+}
+
+impl From<BTreeMap<String, NoteFormat>> for NoteTypes {
+    fn from(extensions: BTreeMap<String, NoteFormat>) -> Self {
+        Self::new(extensions)
+    }
+}
+
+impl From<NoteTypes> for BTreeMap<String, NoteFormat> {
+    fn from(types: NoteTypes) -> Self {
+        types.extensions
+    }
+}
+
+impl Default for NoteTypes {
+    fn default() -> Self {
+        Self::new([
+            ("md", NoteFormat::Markdown),
+            ("markdown", NoteFormat::Markdown),
+            ("mdx", NoteFormat::Markdown),
+            ("txt", NoteFormat::Text),
+            ("org", NoteFormat::Text),
+        ])
+    }
+}
+
+impl NoteTypes {
+    /// Extensions are case insensitive, with or without the leading dot
+    pub fn new<I, S>(extensions: I) -> Self
+    where
+        I: IntoIterator<Item = (S, NoteFormat)>,
+        S: AsRef<str>,
+    {
+        Self {
+            extensions: extensions
+                .into_iter()
+                .map(|(extension, format)| (normalize(extension.as_ref()), format))
+                .collect(),
+        }
+    }
+
+    /// The format of the note at the path, none if it's not a note
+    pub fn format(&self, path: &VaultPath) -> Option<NoteFormat> {
+        path.get_extension()
+            .and_then(|extension| self.extensions.get(&normalize(&extension)))
+            .copied()
+    }
+
+    pub fn is_note(&self, path: &VaultPath) -> bool {
+        self.format(path).is_some()
+    }
+
+    /// The extensions, sorted, without the leading dot
+    pub fn extensions(&self) -> impl Iterator<Item = (&str, NoteFormat)> {
+        self.extensions
+            .iter()
+            .map(|(extension, format)| (extension.as_str(), *format))
+    }
+}
+
+fn normalize(extension: &str) -> String {
+    extension.trim_start_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nfs::VaultPath;
+
+    use super::{NoteFormat, NoteTypes};
+
+    #[test]
+    fn format_from_extension() {
+        let types = NoteTypes::default();
+        assert_eq!(
+            Some(NoteFormat::Markdown),
+            types.format(&VaultPath::from("notes/note.md"))
+        );
+        assert_eq!(
+            Some(NoteFormat::Markdown),
+            types.format(&VaultPath::from("README.Markdown"))
+        );
+        assert_eq!(
+            Some(NoteFormat::Text),
+            types.format(&VaultPath::from("todo.txt"))
+        );
+        assert!(!types.is_note(&VaultPath::from("image.png")));
+        assert!(!types.is_note(&VaultPath::from("notes")));
+        assert!(!types.is_note(&VaultPath::root()));
+
+        let types = NoteTypes::new([(".TXT", NoteFormat::Text)]);
+        assert!(types.is_note(&VaultPath::from("todo.txt")));
+        assert!(!types.is_note(&VaultPath::from("note.md")));
+    }
+
+    #[test]
+    fn parse_from_config() {
+        let types: NoteTypes = toml::from_str("MD = \"markdown\"\n\".org\" = \"text\"").unwrap();
+        assert_eq!(
+            vec![("md", NoteFormat::Markdown), ("org", NoteFormat::Text)],
+            types.extensions().collect::<Vec<_>>()
+        );
+    }
+}
@@ -8,13 +8,17 @@ use ignore::{ParallelVisitor, ParallelVisitorBuilder};
 use log::error;
 
 use crate::{
-    nfs::{DirectoryDetails, EntryData, NoteDetails, NoteEntryData, VaultEntry, VaultPath},
+    nfs::{
+        note_types::NoteTypes, DirectoryDetails, EntryData, NoteDetails, NoteEntryData, VaultEntry,
+        VaultPath,
+    },
     NotesValidation, SearchResult,
 };
 
 struct NoteListVisitor {
     workspace_path: PathBuf,
     validation: NotesValidation,
+    types: Arc<NoteTypes>,
     notes_to_delete: Arc<Mutex<HashMap<VaultPath, (NoteEntryData, NoteDetails)>>>,
     notes_to_modify: Arc<Mutex<Vec<(NoteEntryData, NoteDetails)>>>,
     notes_to_add: Arc<Mutex<Vec<(NoteEntryData, NoteDetails)>>>,
@@ -25,7 +29,10 @@ struct NoteListVisitor {
 impl NoteListVisitor {
     fn verify_cache(&self, entry: &VaultEntry) {
         let result = match &entry.data {
-            EntryData::Note(note_data) => SearchResult::Note(self.verify_cached_note(note_data)),
+            EntryData::Note(note_data) => match self.verify_cached_note(note_data) {
+                Some(details) => SearchResult::Note(details),
+                None => return,
+            },
             EntryData::Directory(directory_data) => {
                 let details = DirectoryDetails {
                     path: directory_data.path.clone(),
@@ -54,17 +61,25 @@ impl NoteListVisitor {
     }
 
     fn has_changed_deep_check(&self, cached: &mut NoteDetails, disk: &NoteEntryData) -> bool {
-        let details = disk.load_details(&self.workspace_path, &disk.path).unwrap();
-        let details_hash = details.data.hash;
-        let cached_hash = cached.data.hash;
-        !details_hash.eq(&cached_hash)
+        match self.load_details(disk) {
+            Some(details) => !details.data.hash.eq(&cached.data.hash),
+            // Keep the cached note, it is still there
+            None => false,
+        }
+    }
+
+    fn load_details(&self, data: &NoteEntryData) -> Option<NoteDetails> {
+        data.load_details(&self.workspace_path, &data.path, &self.types)
+            .inspect_err(|e| error!("Can't get details for note {}: {}", data.path, e))
+            .ok()
     }
 
-    fn verify_cached_note(&self, data: &NoteEntryData) -> NoteDetails {
+    /// Returns the details of the note, or none if the note can't be read
+    fn verify_cached_note(&self, data: &NoteEntryData) -> Option<NoteDetails> {
         let mut ntd = self.notes_to_delete.lock().unwrap();
         let cached_option = ntd.remove(&data.path);
 
-        let details = if let Some((cached_data, mut cached_details)) = cached_option {
+        if let Some((cached_data, mut cached_details)) = cached_option {
             // entry exists
             let changed = match self.validation {
                 NotesValidation::Full => self.has_changed_deep_check(&mut cached_details, data),
@@ -72,28 +87,24 @@ impl NoteListVisitor {
                 NotesValidation::None => false,
             };
             if changed {
-                let details = data
-                    .load_details(&self.workspace_path, &data.path)
-                    .expect("Can't get details for note");
-                self.notes_to_modify
-                    .lock()
-                    .unwrap()
-                    .push((data.to_owned(), details.to_owned()));
-                details
-            } else {
-                cached_details
+                if let Some(details) = self.load_details(data) {
+                    self.notes_to_modify
+                        .lock()
+                        .unwrap()
+                        .push((data.to_owned(), details.to_owned()));
+                    return Some(details);
+                }
             }
+            // Not changed or can't be read, the cached one is still valid
+            Some(cached_details)
         } else {
-            let details = data
-                .load_details(&self.workspace_path, &data.path)
-                .expect("Can't get Details for note");
+            let details = self.load_details(data)?;
             self.notes_to_add
                 .lock()
                 .unwrap()
                 .push((data.to_owned(), details.to_owned()));
-            details
-        };
-        details
+            Some(details)
+        }
     }
 }
 
@@ -102,7 +113,7 @@ impl ParallelVisitor for NoteListVisitor {
         match entry {
             Ok(dir) => {
                 // debug!("Scanning: {}", dir.path().as_os_str().to_string_lossy());
-                let npe = VaultEntry::from_path(&self.workspace_path, dir.path());
+                let npe = VaultEntry::from_path(&self.workspace_path, dir.path(), &self.types);
                 match npe {
                     Ok(entry) => {
                         self.verify_cache(&entry);
@@ -124,6 +135,7 @@ impl ParallelVisitor for NoteListVisitor {
 pub struct NoteListVisitorBuilder {
     workspace_path: PathBuf,
     validation: NotesValidation,
+    types: Arc<NoteTypes>,
     notes_to_delete: Arc<Mutex<HashMap<VaultPath, (NoteEntryData, NoteDetails)>>>,
     notes_to_modify: Arc<Mutex<Vec<(NoteEntryData, NoteDetails)>>>,
     notes_to_add: Arc<Mutex<Vec<(NoteEntryData, NoteDetails)>>>,
@@ -135,6 +147,7 @@ impl NoteListVisitorBuilder {
     pub fn new<P: AsRef<Path>>(
         workspace_path: P,
         validation: NotesValidation,
+        types: NoteTypes,
         cached_notes: Vec<(NoteEntryData, NoteDetails)>,
         sender: Option<Sender<SearchResult>>,
     ) -> Self {
@@ -146,6 +159,7 @@ impl NoteListVisitorBuilder {
         Self {
             workspace_path: workspace_path.as_ref().to_path_buf(),
             validation,
+            types: Arc::new(types),
             notes_to_delete: Arc::new(Mutex::new(notes_to_delete)),
             notes_to_modify: Arc::new(Mutex::new(Vec::new())),
             notes_to_add: Arc::new(Mutex::new(Vec::new())),
@@ -195,7 +209,8 @@ impl<'s> ParallelVisitorBuilder<'s> for NoteListVisitorBuilder {
     fn build(&mut self) -> Box<dyn ParallelVisitor + 's> {
         let dbv = NoteListVisitor {
             workspace_path: self.workspace_path.clone(),
-            validation: self.validation.clone(),
+            validation: self.validation,
+            types: self.types.clone(),
             notes_to_delete: self.notes_to_delete.clone(),
             notes_to_modify: self.notes_to_modify.clone(),
             notes_to_add: self.notes_to_add.clone(),
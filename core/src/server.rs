@@ -0,0 +1,401 @@
+//! Serves the vault as a JSON API over HTTP on localhost, so other tools
+//! can use the notes without linking Rust. Every request needs the token
+//! from the vault config as `Authorization: Bearer <token>`.
+//!
+//! - `GET /notes?path=<dir>&recursive=true` lists the notes
+//! - `GET /notes/<path>` reads a note
+//! - `POST /notes/<path>` creates a note, fails if it exists
+//! - `PUT /notes/<path>` creates or updates a note
+//! - `GET /search?q=<terms>` searches with the same syntax as the app
+//! - `GET /journal` gets today's journal entry, creating it if needed
+//! - `GET /index` gives the number of notes in the index
+//! - `POST /index` indexes the vault again
+//!
+//! Notes are written with a JSON body like `{"text": "# My note"}`.
+
+use std::net::{Ipv4Addr, SocketAddr};
+
+use log::{debug, error, info};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    error::{FSError, VaultError},
+    export::percent_decode,
+    nfs::VaultPath,
+    NoteDetails, NoteVault, NotesValidation,
+};
+
+const NOTES_ROUTE: &str = "/notes";
+const SEARCH_ROUTE: &str = "/search";
+const JOURNAL_ROUTE: &str = "/journal";
+const INDEX_ROUTE: &str = "/index";
+
+/// An error answered to the client, with its HTTP status
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new<S: Into<String>>(status: u16, message: S) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<VaultError> for ApiError {
+    fn from(value: VaultError) -> Self {
+        let status = match &value {
+            VaultError::NoteExists { .. } => 409,
+            VaultError::FSError(FSError::VaultPathNotFound { .. })
+            | VaultError::VaultPathNotFound { .. } => 404,
+            _ => 500,
+        };
+        Self::new(status, value.to_string())
+    }
+}
+
+/// The local HTTP server of a vault, it only listens on localhost
+pub struct VaultServer {
+    vault: NoteVault,
+    token: String,
+    server: Server,
+}
+
+impl VaultServer {
+    /// Starts listening on the port of the vault config, the index must
+    /// be initialized with `NoteVault::init_and_validate` before
+    pub fn new(vault: NoteVault) -> Result<Self, VaultError> {
+        let port = vault.get_config().server.port;
+        Self::with_port(vault, port)
+    }
+
+    /// Starts listening on a specific port, 0 picks a free one
+    pub fn with_port(vault: NoteVault, port: u16) -> Result<Self, VaultError> {
+        let token = vault
+            .get_config()
+            .server
+            .token
+            .clone()
+            .filter(|token| !token.trim().is_empty())
+            .ok_or_else(|| {
+                VaultError::InvalidConfig(
+                    "Set a token in the [server] section to start the server".to_string(),
+                )
+            })?;
+        let server = Server::http((Ipv4Addr::LOCALHOST, port))
+            .map_err(|e| VaultError::ServerError(e.to_string()))?;
+        Ok(Self {
+            vault,
+            token,
+            server,
+        })
+    }
+
+    /// The address the server is listening on
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Answers the requests until `stop` is called
+    pub fn run(&self) {
+        info!("Serving the vault at {:?}", self.address());
+        for request in self.server.incoming_requests() {
+            self.answer(request);
+        }
+        info!("Server stopped");
+    }
+
+    /// Makes `run` return
+    pub fn stop(&self) {
+        self.server.unblock();
+    }
+
+    fn answer(&self, mut request: Request) {
+        debug!("{} {}", request.method(), request.url());
+        let (status, body) = match self.route(&mut request) {
+            Ok(body) => (200, body),
+            Err(e) => (e.status, json!({ "error": e.message })),
+        };
+        let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(e) = request.respond(response) {
+            error!("Error answering the request: {}", e);
+        }
+    }
+
+    fn is_authorized(&self, request: &Request) -> bool {
+        request.headers().iter().any(|header| {
+            header.field.equiv("Authorization")
+                && header
+                    .value
+                    .as_str()
+                    .strip_prefix("Bearer ")
+                    .is_some_and(|token| token.trim() == self.token)
+        })
+    }
+
+    fn route(&self, request: &mut Request) -> Result<Value, ApiError> {
+        if !self.is_authorized(request) {
+            return Err(ApiError::new(401, "Missing or wrong token"));
+        }
+        let url = request.url().to_string();
+        let (route, query) = url.split_once('?').unwrap_or((&url, ""));
+        let query = parse_query(query);
+        let param = |name: &str| {
+            query
+                .iter()
+                .find(|(key, _value)| key == name)
+                .map(|(_key, value)| value.as_str())
+        };
+
+        let method = request.method().clone();
+        match (&method, route) {
+            (Method::Get, NOTES_ROUTE) => {
+                let path = VaultPath::from(param("path").unwrap_or_default());
+                let recursive = param("recursive").is_some_and(|value| value == "true");
+                let notes = self.vault.get_notes(&path, recursive)?;
+                Ok(notes_list(&notes))
+            }
+            (Method::Get, SEARCH_ROUTE) => {
+                let terms = param("q").unwrap_or_default();
+                let notes = self.vault.search_notes(terms)?;
+                Ok(notes_list(&notes))
+            }
+            (Method::Get, JOURNAL_ROUTE) => {
+                let (details, text) = self.vault.journal_entry()?;
+                Ok(note_json(&details.path, &details.get_title(), &text))
+            }
+            (Method::Get, INDEX_ROUTE) => {
+                let notes = self.vault.get_note_names()?.len();
+                Ok(json!({ "notes": notes }))
+            }
+            (Method::Post, INDEX_ROUTE) => {
+                let report = self.vault.index_notes(NotesValidation::Fast)?;
+                Ok(json!({
+                    "added": report.added,
+                    "updated": report.updated,
+                    "removed": report.removed,
+                    "skipped": report.skipped.len(),
+                }))
+            }
+            (method, route) => {
+                let Some(path) = route
+                    .strip_prefix(NOTES_ROUTE)
+                    .and_then(|path| path.strip_prefix('/'))
+                else {
+                    return Err(ApiError::new(404, format!("Unknown route {}", route)));
+                };
+                let path = note_path(&self.vault, path)?;
+                match method {
+                    Method::Get => {
+                        let text = self.vault.get_note_text(&path)?;
+                        let title =
+                            NoteVault::get_title(&text).unwrap_or_else(|| path.get_parent_path().1);
+                        Ok(note_json(&path, &title, &text))
+                    }
+                    Method::Post | Method::Put => {
+                        let text = read_note_text(request)?;
+                        let (_data, details) = if *method == Method::Post {
+                            self.vault.create_note(&path, &text)?
+                        } else {
+                            self.vault.save_note(&path, &text)?
+                        };
+                        Ok(note_json(&details.path, &details.get_title(), &text))
+                    }
+                    _ => Err(ApiError::new(405, format!("Can't {} a note", method))),
+                }
+            }
+        }
+    }
+}
+
+/// The path to a note from the url, it can't go out of the vault
+fn note_path(vault: &NoteVault, path: &str) -> Result<VaultPath, ApiError> {
+    let path = VaultPath::from(percent_decode(path));
+    if path
+        .get_slices()
+        .iter()
+        .any(|slice| slice == "." || slice == "..")
+    {
+        return Err(ApiError::new(400, "The path can't be relative"));
+    }
+    if !vault.is_note(&path) {
+        return Err(ApiError::new(400, format!("{} is not a note", path)));
+    }
+    Ok(path)
+}
+
+fn read_note_text(request: &mut Request) -> Result<String, ApiError> {
+    let mut body = String::new();
+    std::io::Read::read_to_string(request.as_reader(), &mut body)
+        .map_err(|e| ApiError::new(400, e.to_string()))?;
+    let body: Value = serde_json::from_str(&body)
+        .map_err(|e| ApiError::new(400, format!("The body is not valid JSON: {}", e)))?;
+    body.get("text")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| ApiError::new(400, "The body needs a text"))
+}
+
+/// The key and value pairs of a query string, decoded
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let decode = |text: &str| percent_decode(&text.replace('+', " "));
+            (decode(key), decode(value))
+        })
+        .collect()
+}
+
+fn notes_list(notes: &[NoteDetails]) -> Value {
+    let notes = notes
+        .iter()
+        .map(|note| {
+            json!({
+                "path": note.path,
+                "title": note.get_title(),
+            })
+        })
+        .collect::<Vec<Value>>();
+    Value::Array(notes)
+}
+
+fn note_json(path: &VaultPath, title: &str, text: &str) -> Value {
+    json!({
+        "path": path,
+        "title": title,
+        "text": text,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{SocketAddr, TcpStream},
+        sync::Arc,
+    };
+
+    use serde_json::{json, Value};
+
+    use crate::{config::VaultConfig, test_utils::indexed_vault};
+
+    use super::VaultServer;
+
+    const TOKEN: &str = "test-token";
+
+    /// Sends a request and returns the status and the JSON body
+    fn request(
+        address: SocketAddr,
+        method: &str,
+        url: &str,
+        token: &str,
+        body: Option<Value>,
+    ) -> (u16, Value) {
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            url,
+            token,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn serve_the_vault() {
+        let (_dir, mut vault) = indexed_vault(&[
+            ("projects/plan.md", "# The Plan\n\nSome #work to do"),
+            ("home.md", "# Home"),
+        ]);
+        let workspace = vault.workspace_path.clone();
+        // No token, no server
+        assert!(VaultServer::with_port(vault.clone(), 0).is_err());
+        let mut config = VaultConfig::default();
+        config.server.token = Some(TOKEN.to_string());
+        vault.set_config(config).unwrap();
+
+        let server = Arc::new(VaultServer::with_port(vault, 0).unwrap());
+        let address = server.address().unwrap();
+        let running = Arc::clone(&server);
+        let handle = std::thread::spawn(move || running.run());
+
+        let (status, _body) = request(address, "GET", "/index", "wrong", None);
+        assert_eq!(401, status);
+        let (status, body) = request(address, "GET", "/index", TOKEN, None);
+        assert_eq!(200, status);
+        assert_eq!(json!({ "notes": 2 }), body);
+
+        let (_status, body) = request(address, "GET", "/notes?recursive=true", TOKEN, None);
+        assert_eq!(2, body.as_array().unwrap().len());
+        let (_status, body) = request(address, "GET", "/search?q=%23work", TOKEN, None);
+        assert_eq!(
+            json!([{ "path": "/projects/plan.md", "title": "The Plan" }]),
+            body
+        );
+        let (status, body) = request(address, "GET", "/notes/projects/plan.md", TOKEN, None);
+        assert_eq!(200, status);
+        assert_eq!("# The Plan\n\nSome #work to do", body["text"]);
+        let (status, _body) = request(address, "GET", "/notes/missing.md", TOKEN, None);
+        assert_eq!(404, status);
+        let (status, _body) = request(address, "GET", "/notes/../secret.md", TOKEN, None);
+        assert_eq!(400, status);
+
+        let note = json!({ "text": "# New Note\n\nWith #ideas" });
+        let (status, body) = request(
+            address,
+            "POST",
+            "/notes/ideas/New%20Note.md",
+            TOKEN,
+            Some(note.clone()),
+        );
+        assert_eq!(200, status);
+        assert_eq!("New Note", body["title"]);
+        let (status, _body) = request(
+            address,
+            "POST",
+            "/notes/ideas/New%20Note.md",
+            TOKEN,
+            Some(note),
+        );
+        assert_eq!(409, status);
+        let changed = json!({ "text": "# Changed" });
+        let (status, _body) = request(address, "PUT", "/notes/home.md", TOKEN, Some(changed));
+        assert_eq!(200, status);
+        assert_eq!(
+            "# Changed",
+            std::fs::read_to_string(workspace.join("home.md")).unwrap()
+        );
+        let (_status, body) = request(address, "GET", "/search?q=%23ideas", TOKEN, None);
+        assert_eq!("/ideas/New Note.md", body[0]["path"]);
+
+        let (status, body) = request(address, "GET", "/journal", TOKEN, None);
+        assert_eq!(200, status);
+        assert!(body["path"].as_str().unwrap().starts_with("/journal/"));
+        std::fs::write(workspace.join("outside.md"), "# Outside").unwrap();
+        let (_status, body) = request(address, "POST", "/index", TOKEN, None);
+        assert_eq!(1, body["added"]);
+
+        server.stop();
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use std::sync::OnceLock;
+
+use chrono::NaiveDate;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+
+use crate::{content_data::heading_level, nfs::VaultPath};
+
+/// A due date written as `📅 2026-10-20` or `due:2026-10-20`
+const DUE_DATE_REGEX: &str = r"(?:📅|due:)\s*(\d{4}-\d{2}-\d{2})";
+/// A task list item, the checkbox is the second group and the text the fourth
+const TASK_LINE_REGEX: &str = r"^(\s*(?:>\s*)*(?:[-*+]|\d+[.)])\s+\[)([ xX])(\]\s*)(.*?)\s*$";
+const DUE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// A task list item in a note, like `- [ ] Do something`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteTask {
+    /// The text after the checkbox, as written in the note
+    pub text: String,
+    pub done: bool,
+    /// The headings the task is under
+    pub breadcrumb: Vec<String>,
+    pub due: Option<NaiveDate>,
+    /// The line of the task in the note, from 0 and counting the front matter
+    pub line: usize,
+}
+
+/// Which tasks `NoteVault::query_tasks` returns, by default all of them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskFilter {
+    /// Only the open tasks with `Some(false)`, or the done ones with `Some(true)`
+    pub done: Option<bool>,
+    /// Only the tasks due on this date or before
+    pub due_before: Option<NaiveDate>,
+    /// Only the tasks in notes inside this directory
+    pub directory: Option<VaultPath>,
+}
+
+impl TaskFilter {
+    pub fn open() -> Self {
+        Self {
+            done: Some(false),
+            ..Default::default()
+        }
+    }
+}
+
+fn due_date_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(DUE_DATE_REGEX).unwrap())
+}
+
+fn task_line_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(TASK_LINE_REGEX).unwrap())
+}
+
+pub(crate) fn parse_due_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, DUE_DATE_FORMAT).ok()
+}
+
+pub(crate) fn format_due_date(date: &NaiveDate) -> String {
+    date.format(DUE_DATE_FORMAT).to_string()
+}
+
+/// Gets the tasks of a Markdown note in the order they appear,
+/// the ones in code blocks are not tasks
+pub(crate) fn extract_tasks(md_text: &str) -> Vec<NoteTask> {
+    let options = Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+    let mut tasks = vec![];
+    let mut breadcrumb: Vec<(u8, String)> = vec![];
+    let mut heading: Option<(u8, String)> = None;
+    for (event, range) in Parser::new_ext(md_text, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                heading = Some((heading_level(level), String::new()));
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((level, text)) = heading.take() {
+                    breadcrumb.retain(|(parent_level, _)| *parent_level < level);
+                    breadcrumb.push((level, text.trim().to_string()));
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, heading)) = heading.as_mut() {
+                    heading.push_str(&text);
+                }
+            }
+            Event::TaskListMarker(done) => {
+                let line = md_text[..range.start].matches('\n').count();
+                let line_end = md_text[range.end..]
+                    .find('\n')
+                    .map_or(md_text.len(), |end| range.end + end);
+                let text = md_text[range.end..line_end].trim().to_string();
+                let due = due_date_regex()
+                    .captures(&text)
+                    .and_then(|captures| parse_due_date(&captures[1]));
+                tasks.push(NoteTask {
+                    text,
+                    done,
+                    breadcrumb: breadcrumb.iter().map(|(_, text)| text.clone()).collect(),
+                    due,
+                    line,
+                });
+            }
+            _ => {}
+        }
+    }
+    tasks
+}
+
+/// Checks or unchecks the task in the note's text, none if the task
+/// is not at its line anymore
+pub(crate) fn set_task_done(md_text: &str, task: &NoteTask, done: bool) -> Option<String> {
+    let line_start = if task.line == 0 {
+        0
+    } else {
+        md_text.match_indices('\n').nth(task.line - 1)?.0 + 1
+    };
+    let line_end = md_text[line_start..]
+        .find('\n')
+        .map_or(md_text.len(), |end| line_start + end);
+    let captures = task_line_regex().captures(&md_text[line_start..line_end])?;
+    if captures[4] != task.text {
+        return None;
+    }
+    let checkbox = line_start + captures.get(2)?.start();
+    let mark = if done { "x" } else { " " };
+    Some([&md_text[..checkbox], mark, &md_text[checkbox + 1..]].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{nfs::VaultPath, test_utils::indexed_vault};
+
+    use super::{extract_tasks, set_task_done, TaskFilter};
+
+    #[test]
+    fn extract_and_toggle_tasks() {
+        let markdown = "---\ntitle: Tasks\n---\n# Project\n\n- [ ] Write docs 📅 2026-10-20\n\
+                        - [x] Ship it\n\n## Later\n\n1. [ ] Plan due:2026-11-01\n\n```\n- [ ] Not a task\n```\n";
+        let tasks = extract_tasks(markdown);
+
+        assert_eq!(3, tasks.len());
+        assert_eq!("Write docs 📅 2026-10-20", tasks[0].text);
+        assert!(!tasks[0].done);
+        assert_eq!(vec!["Project"], tasks[0].breadcrumb);
+        assert_eq!(NaiveDate::from_ymd_opt(2026, 10, 20), tasks[0].due);
+        assert_eq!(5, tasks[0].line);
+        assert!(tasks[1].done);
+        assert_eq!(None, tasks[1].due);
+        assert_eq!(vec!["Project", "Later"], tasks[2].breadcrumb);
+        assert_eq!(NaiveDate::from_ymd_opt(2026, 11, 1), tasks[2].due);
+
+        let done = set_task_done(markdown, &tasks[0], true).unwrap();
+        assert!(done.contains("- [x] Write docs"));
+        assert_eq!(markdown.len(), done.len());
+        let open = set_task_done(&done, &tasks[1], false).unwrap();
+        assert!(open.contains("- [ ] Ship it"));
+        assert!(set_task_done(&open, &tasks[2], true)
+            .unwrap()
+            .contains("1. [x] Plan"));
+
+        // The task changed since it was extracted
+        let mut moved = tasks[0].clone();
+        moved.line = 6;
+        assert_eq!(None, set_task_done(markdown, &moved, true));
+    }
+
+    #[test]
+    fn query_and_toggle_vault_tasks() {
+        let (_dir, vault) = indexed_vault(&[
+            (
+                "work/plan.md",
+                "# Plan\n\n- [ ] Later\n- [ ] Soon 📅 2026-01-10\n- [x] Done",
+            ),
+            ("home.md", "- [ ] Groceries due:2026-01-05"),
+        ]);
+
+        let open = vault.query_tasks(&TaskFilter::open()).unwrap();
+        let texts = open
+            .iter()
+            .map(|(_path, task)| task.text.as_str())
+            .collect::<Vec<&str>>();
+        // The ones with a due date first
+        assert_eq!(
+            vec!["Groceries due:2026-01-05", "Soon 📅 2026-01-10", "Later"],
+            texts
+        );
+        let filter = TaskFilter {
+            due_before: NaiveDate::from_ymd_opt(2026, 1, 8),
+            ..Default::default()
+        };
+        assert_eq!(1, vault.query_tasks(&filter).unwrap().len());
+        let filter = TaskFilter {
+            directory: Some(VaultPath::from("work")),
+            ..Default::default()
+        };
+        assert_eq!(3, vault.query_tasks(&filter).unwrap().len());
+
+        let (path, task) = &open[1];
+        vault.set_task_done(path, task, true).unwrap();
+        assert_eq!(
+            "# Plan\n\n- [ ] Later\n- [x] Soon 📅 2026-01-10\n- [x] Done",
+            vault.get_note_text(path).unwrap()
+        );
+        assert_eq!(2, vault.query_tasks(&TaskFilter::open()).unwrap().len());
+        let found = vault.search_notes("task:done").unwrap();
+        assert_eq!(1, found.len());
+        assert_eq!(VaultPath::from("work/plan.md"), found[0].path);
+        assert_eq!(2, vault.search_notes("task:open").unwrap().len());
+        // The task is not in the note anymore
+        let mut changed = task.clone();
+        changed.text = "Soon".to_string();
+        assert!(vault.set_task_done(path, &changed, false).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::NoteVault;
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A new directory for a test, unique so tests can run in parallel.
+/// It's removed with everything in it when dropped, also when the
+/// test fails.
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "kimun_{}_{}_{}",
+            name,
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// The workspace of the vault in a test directory
+pub fn workspace(dir: &Path) -> PathBuf {
+    dir.join("vault")
+}
+
+/// A new test directory with the files written in its workspace,
+/// creating their directories
+pub fn vault_files(files: &[(&str, &str)]) -> TestDir {
+    let dir = TestDir::new("vault");
+    let workspace = workspace(&dir);
+    std::fs::create_dir_all(&workspace).unwrap();
+    for (path, text) in files {
+        let file = workspace.join(path);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(file, text).unwrap();
+    }
+    dir
+}
+
+/// Where the vault of a test directory keeps its DB
+pub fn db_path(dir: &Path) -> PathBuf {
+    dir.join("db").join("notes.sqlite")
+}
+
+/// An indexed vault with the files, in a new test directory that also
+/// holds its DB
+pub fn indexed_vault(files: &[(&str, &str)]) -> (TestDir, NoteVault) {
+    let dir = vault_files(files);
+    let vault = NoteVault::with_db_path(workspace(&dir), db_path(&dir)).unwrap();
+    vault.recreate_index().unwrap();
+    (dir, vault)
+}
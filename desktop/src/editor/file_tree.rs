@@ -0,0 +1,618 @@
+use std::collections::{HashMap, HashSet};
+
+use eframe::egui;
+use kimun_core::{
+    nfs::{PathCase, VaultPath},
+    NoteVault,
+};
+use log::error;
+
+use crate::helpers;
+
+const INDENT_PER_LEVEL: f32 = 12.0;
+const ID_TREE_EDIT: &str = "File Tree Edit";
+
+/// What was done in the tree
+pub enum TreeAction {
+    Open(VaultPath),
+    NewNote(VaultPath),
+    /// Renames or moves the note
+    Rename {
+        from: VaultPath,
+        to: VaultPath,
+    },
+    Delete(VaultPath),
+    NewFolder(VaultPath),
+    /// Renames or moves the folder with its notes
+    RenameFolder {
+        from: VaultPath,
+        to: VaultPath,
+    },
+    /// Deletes the folder and everything in it
+    DeleteFolder(VaultPath),
+    /// Gives the keyboard back to the note
+    Leave,
+}
+
+#[derive(Clone, PartialEq)]
+enum TreeEntry {
+    Directory(VaultPath),
+    Note { path: VaultPath, title: String },
+}
+
+impl TreeEntry {
+    fn path(&self) -> &VaultPath {
+        match self {
+            TreeEntry::Directory(path) => path,
+            TreeEntry::Note { path, title: _ } => path,
+        }
+    }
+}
+
+/// A name being written in the tree
+enum TreeEdit {
+    NewNote { directory: VaultPath },
+    NewFolder { directory: VaultPath },
+    Rename { path: VaultPath },
+    RenameFolder { path: VaultPath },
+}
+
+impl TreeEdit {
+    /// The directory where the text box shows, after its entries
+    fn directory(&self) -> Option<&VaultPath> {
+        match self {
+            TreeEdit::NewNote { directory } | TreeEdit::NewFolder { directory } => Some(directory),
+            TreeEdit::Rename { .. } | TreeEdit::RenameFolder { .. } => None,
+        }
+    }
+}
+
+/// Side panel with the folders and notes of the vault. The folders are
+/// loaded from the index when expanded.
+pub struct FileTreePanel {
+    visible: bool,
+    /// The arrows move in the tree instead of the note
+    focused: bool,
+    /// Takes the keyboard from the note on the next frame
+    take_keyboard: bool,
+    expanded: HashSet<VaultPath>,
+    children: HashMap<VaultPath, Vec<TreeEntry>>,
+    /// The index only knows the folders with notes, the empty ones
+    /// created here are listed until they have one
+    new_folders: Vec<VaultPath>,
+    selected: Option<VaultPath>,
+    scroll_to_selected: bool,
+    edit: Option<(TreeEdit, String)>,
+    edit_focus: bool,
+    delete: Option<TreeEntry>,
+}
+
+impl FileTreePanel {
+    pub fn new(visible: bool) -> Self {
+        Self {
+            visible,
+            focused: false,
+            take_keyboard: false,
+            expanded: HashSet::new(),
+            children: HashMap::new(),
+            new_folders: vec![],
+            selected: None,
+            scroll_to_selected: false,
+            edit: None,
+            edit_focus: false,
+            delete: None,
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Shows the tree with the keyboard on it, or hides it if it had it
+    pub fn toggle(&mut self) {
+        if self.visible && self.focused {
+            self.visible = false;
+            self.focused = false;
+        } else {
+            self.visible = true;
+            self.focused = true;
+            self.take_keyboard = true;
+            self.scroll_to_selected = true;
+        }
+    }
+
+    /// Marks the folders to be loaded again from the index
+    pub fn vault_changed(&mut self) {
+        self.children.clear();
+    }
+
+    /// Expands the folders down to the note and selects it
+    pub fn note_opened(&mut self, path: &VaultPath) {
+        let mut directory = path.get_parent_path().0;
+        // The note may be new in its folder
+        self.children.remove(&directory);
+        while directory != VaultPath::root() {
+            let parent = directory.get_parent_path().0;
+            self.expanded.insert(directory);
+            directory = parent;
+        }
+        self.selected = Some(path.to_owned());
+        self.scroll_to_selected = true;
+    }
+
+    fn load(&mut self, vault: &NoteVault, directory: &VaultPath) -> Vec<TreeEntry> {
+        if let Some(entries) = self.children.get(directory) {
+            return entries.to_owned();
+        }
+        let mut directories = vault.get_subdirectories(directory).unwrap_or_else(|e| {
+            error!("Error loading the folders of {}: {}", directory, e);
+            vec![]
+        });
+        self.new_folders
+            .retain(|folder| !directories.contains(folder) && vault.exists(folder).is_some());
+        for folder in &self.new_folders {
+            if &folder.get_parent_path().0 == directory {
+                directories.push(folder.to_owned());
+            }
+        }
+        directories.sort_by_key(|path| path.get_name().to_lowercase());
+        let mut notes = vault.get_notes(directory, false).unwrap_or_else(|e| {
+            error!("Error loading the notes of {}: {}", directory, e);
+            vec![]
+        });
+        notes.sort_by_key(|note| note.path.get_name().to_lowercase());
+
+        let entries = directories
+            .into_iter()
+            .map(TreeEntry::Directory)
+            .chain(notes.into_iter().map(|note| TreeEntry::Note {
+                title: note.get_title(),
+                path: note.path,
+            }))
+            .collect::<Vec<TreeEntry>>();
+        self.children
+            .insert(directory.to_owned(), entries.to_owned());
+        entries
+    }
+
+    /// The entries shown, with their depth, in order
+    fn rows(&mut self, vault: &NoteVault) -> Vec<(usize, TreeEntry)> {
+        let mut rows = vec![];
+        let mut pending = self
+            .load(vault, &VaultPath::root())
+            .into_iter()
+            .rev()
+            .map(|entry| (0, entry))
+            .collect::<Vec<(usize, TreeEntry)>>();
+        while let Some((depth, entry)) = pending.pop() {
+            if let TreeEntry::Directory(path) = &entry {
+                if self.expanded.contains(path) {
+                    let children = self.load(vault, path);
+                    pending.extend(children.into_iter().rev().map(|child| (depth + 1, child)));
+                }
+            }
+            rows.push((depth, entry));
+        }
+        rows
+    }
+
+    fn toggle_directory(&mut self, directory: &VaultPath) {
+        if !self.expanded.remove(directory) {
+            self.expanded.insert(directory.to_owned());
+        }
+    }
+
+    fn start_edit(&mut self, edit: TreeEdit) {
+        let text = match &edit {
+            TreeEdit::Rename { path } | TreeEdit::RenameFolder { path } => path.get_name(),
+            TreeEdit::NewNote { directory } | TreeEdit::NewFolder { directory } => {
+                self.expanded.insert(directory.to_owned());
+                String::new()
+            }
+        };
+        self.edit = Some((edit, text));
+        self.edit_focus = true;
+    }
+
+    /// What to do with the name written, if anything
+    fn finish_edit(&mut self, vault: &NoteVault) -> Option<TreeAction> {
+        let (edit, text) = self.edit.take()?;
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        match edit {
+            TreeEdit::NewNote { directory } => Some(TreeAction::NewNote(
+                directory.append(&VaultPath::file_from(text)),
+            )),
+            TreeEdit::NewFolder { directory } => {
+                let folder = directory.append(&VaultPath::from(text));
+                self.new_folders.push(folder.to_owned());
+                self.selected = Some(folder.to_owned());
+                Some(TreeAction::NewFolder(folder))
+            }
+            TreeEdit::RenameFolder { path } => {
+                let to = path.get_parent_path().0.append(&VaultPath::from(text));
+                (to != path).then(|| self.folder_moved(path, to))
+            }
+            TreeEdit::Rename { path } => {
+                let mut to = path.get_parent_path().0.append(&VaultPath::from(text));
+                if !vault.is_note(&to) {
+                    if let Some(extension) = path.get_extension() {
+                        to = to
+                            .get_parent_path()
+                            .0
+                            .append(&VaultPath::from(format!("{}.{}", text, extension)));
+                    }
+                }
+                (to != path).then_some(TreeAction::Rename { from: path, to })
+            }
+        }
+    }
+
+    /// Keeps listing the folder in its new place if it has no notes
+    fn folder_moved(&mut self, from: VaultPath, to: VaultPath) -> TreeAction {
+        self.new_folders.push(to.to_owned());
+        if self.expanded.remove(&from) {
+            self.expanded.insert(to.to_owned());
+        }
+        TreeAction::RenameFolder { from, to }
+    }
+
+    /// The row after which the box for a new entry goes, after the
+    /// entries of its directory, and its depth. No row for the root, it
+    /// goes at the top.
+    fn edit_position(&self, rows: &[(usize, TreeEntry)]) -> Option<(Option<usize>, usize)> {
+        let (edit, _text) = self.edit.as_ref()?;
+        let directory = edit.directory()?;
+        let Some(index) = rows
+            .iter()
+            .position(|(_depth, entry)| entry.path() == directory)
+        else {
+            return Some((None, 0));
+        };
+        let depth = rows[index].0;
+        let last = rows[index + 1..]
+            .iter()
+            .take_while(|(child_depth, _entry)| *child_depth > depth)
+            .count();
+        Some((Some(index + last), depth + 1))
+    }
+
+    fn edit_row(
+        &mut self,
+        ui: &mut egui::Ui,
+        depth: usize,
+        vault: &NoteVault,
+    ) -> Option<TreeAction> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.add_space(depth as f32 * INDENT_PER_LEVEL);
+            let Some((edit, text)) = self.edit.as_mut() else {
+                return;
+            };
+            let hint = match edit {
+                TreeEdit::NewNote { .. } => "Note name",
+                TreeEdit::NewFolder { .. } => "Folder name",
+                TreeEdit::Rename { .. } | TreeEdit::RenameFolder { .. } => "New name",
+            };
+            let response = ui.add(
+                egui::TextEdit::singleline(text)
+                    .hint_text(hint)
+                    .desired_width(f32::INFINITY)
+                    .id(ID_TREE_EDIT.into()),
+            );
+            if self.edit_focus {
+                response.request_focus();
+                self.edit_focus = false;
+            }
+            if response.lost_focus() {
+                if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    action = self.finish_edit(vault);
+                } else {
+                    self.edit = None;
+                }
+            }
+        });
+        action
+    }
+
+    fn delete_confirmation(&mut self, ui: &mut egui::Ui) -> Option<TreeAction> {
+        let entry = self.delete.clone()?;
+        let mut action = None;
+        let modal = egui::Modal::new(egui::Id::new("delete_note")).show(ui.ctx(), |ui| {
+            match &entry {
+                TreeEntry::Directory(path) => {
+                    ui.label(format!("Delete {} and everything in it?", path))
+                }
+                TreeEntry::Note { path, title: _ } => ui.label(format!("Delete {}?", path)),
+            };
+            ui.horizontal(|ui| {
+                if ui.button("Delete").clicked() {
+                    action = Some(match &entry {
+                        TreeEntry::Directory(path) => TreeAction::DeleteFolder(path.to_owned()),
+                        TreeEntry::Note { path, title: _ } => TreeAction::Delete(path.to_owned()),
+                    });
+                    self.delete = None;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.delete = None;
+                }
+            });
+        });
+        if modal.should_close() {
+            self.delete = None;
+        }
+        action
+    }
+
+    fn directory_menu(&mut self, response: &egui::Response, directory: &VaultPath) {
+        response.context_menu(|ui| {
+            if ui.button("New note").clicked() {
+                self.start_edit(TreeEdit::NewNote {
+                    directory: directory.to_owned(),
+                });
+                ui.close_menu();
+            }
+            if ui.button("New folder").clicked() {
+                self.start_edit(TreeEdit::NewFolder {
+                    directory: directory.to_owned(),
+                });
+                ui.close_menu();
+            }
+            if directory == &VaultPath::root() {
+                return;
+            }
+            ui.separator();
+            if ui.button("Rename").clicked() {
+                self.start_edit(TreeEdit::RenameFolder {
+                    path: directory.to_owned(),
+                });
+                ui.close_menu();
+            }
+            if ui.button("Delete").clicked() {
+                self.delete = Some(TreeEntry::Directory(directory.to_owned()));
+                ui.close_menu();
+            }
+        });
+    }
+
+    fn note_menu(&mut self, response: &egui::Response, entry: &TreeEntry) -> Option<TreeAction> {
+        let path = entry.path();
+        let mut action = None;
+        response.context_menu(|ui| {
+            if ui.button("Open").clicked() {
+                action = Some(TreeAction::Open(path.to_owned()));
+                ui.close_menu();
+            }
+            if ui.button("Rename").clicked() {
+                self.start_edit(TreeEdit::Rename {
+                    path: path.to_owned(),
+                });
+                ui.close_menu();
+            }
+            if ui.button("Delete").clicked() {
+                self.delete = Some(entry.to_owned());
+                ui.close_menu();
+            }
+        });
+        action
+    }
+
+    /// A note or folder dropped in the directory is moved there, a folder
+    /// can't go inside itself, matching the paths with `case`
+    fn drop_in(
+        &mut self,
+        response: &egui::Response,
+        directory: &VaultPath,
+        case: PathCase,
+    ) -> Option<TreeAction> {
+        let entry = response.dnd_release_payload::<TreeEntry>()?;
+        let from = entry.path().to_owned();
+        let to = directory.append(&VaultPath::from(from.get_name()));
+        if to == from {
+            return None;
+        }
+        match entry.as_ref() {
+            TreeEntry::Directory(_) => {
+                (!directory.is_in(&from, case)).then(|| self.folder_moved(from, to))
+            }
+            TreeEntry::Note { .. } => Some(TreeAction::Rename { from, to }),
+        }
+    }
+
+    fn keys(&mut self, ui: &mut egui::Ui, rows: &[(usize, TreeEntry)]) -> Option<TreeAction> {
+        let key = |key| ui.input_mut(|input| input.consume_key(egui::Modifiers::NONE, key));
+        let current = rows
+            .iter()
+            .position(|(_depth, entry)| Some(entry.path()) == self.selected.as_ref());
+        let entry = current.map(|index| rows[index].1.to_owned());
+        let mut select = None;
+        let mut action = None;
+        if key(egui::Key::ArrowDown) {
+            select = Some(current.map_or(0, |index| (index + 1).min(rows.len() - 1)));
+        } else if key(egui::Key::ArrowUp) {
+            select = Some(current.map_or(0, |index| index.saturating_sub(1)));
+        } else if key(egui::Key::ArrowRight) {
+            if let Some(TreeEntry::Directory(path)) = &entry {
+                if !self.expanded.insert(path.to_owned()) {
+                    select = current.map(|index| (index + 1).min(rows.len() - 1));
+                }
+            }
+        } else if key(egui::Key::ArrowLeft) {
+            match &entry {
+                Some(TreeEntry::Directory(path)) if self.expanded.contains(path) => {
+                    self.expanded.remove(path);
+                }
+                Some(entry) => {
+                    let parent = entry.path().get_parent_path().0;
+                    if parent != VaultPath::root() {
+                        self.selected = Some(parent);
+                        self.scroll_to_selected = true;
+                    }
+                }
+                None => {}
+            }
+        } else if key(egui::Key::Enter) {
+            match entry {
+                Some(TreeEntry::Directory(path)) => self.toggle_directory(&path),
+                Some(TreeEntry::Note { path, title: _ }) => {
+                    // Opening the note moves the keyboard to it
+                    self.focused = false;
+                    action = Some(TreeAction::Open(path));
+                }
+                None => {}
+            }
+        } else if key(egui::Key::F2) {
+            match entry {
+                Some(TreeEntry::Directory(path)) => {
+                    self.start_edit(TreeEdit::RenameFolder { path })
+                }
+                Some(TreeEntry::Note { path, title: _ }) => {
+                    self.start_edit(TreeEdit::Rename { path })
+                }
+                None => {}
+            }
+        } else if key(egui::Key::Delete) {
+            self.delete = entry;
+        } else if key(egui::Key::Escape) {
+            self.focused = false;
+            action = Some(TreeAction::Leave);
+        }
+        if let Some(index) = select.filter(|_| !rows.is_empty()) {
+            self.selected = Some(rows[index].1.path().to_owned());
+            self.scroll_to_selected = true;
+        }
+        action
+    }
+
+    /// Shows the panel if visible, returns what to do after the
+    /// user's action, if anything
+    pub fn view(
+        &mut self,
+        ui: &mut egui::Ui,
+        vault: &NoteVault,
+        current_note: Option<VaultPath>,
+    ) -> Option<TreeAction> {
+        let mut action = None;
+        if !self.visible {
+            return action;
+        }
+        let rows = self.rows(vault);
+        let case = vault.get_config().path_case;
+        if self.take_keyboard {
+            ui.memory_mut(|memory| memory.stop_text_input());
+            self.take_keyboard = false;
+        }
+        // The keys go to the tree when nothing else has the keyboard
+        if self.focused && self.edit.is_none() && self.delete.is_none() {
+            if ui.memory(|memory| memory.focused().is_some()) {
+                self.focused = false;
+            } else {
+                action = self.keys(ui, &rows);
+            }
+        }
+        action = action.or(self.delete_confirmation(ui));
+
+        egui::SidePanel::left("file_tree")
+            .resizable(true)
+            .default_width(220.0)
+            .show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let heading = ui.heading("Notes");
+                    self.directory_menu(&heading, &VaultPath::root());
+                    action = action.take().or(self.drop_in(&heading, &VaultPath::root(), case));
+                    if ui.small_button("Reload").clicked() {
+                        self.vault_changed();
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let edit_position = self.edit_position(&rows);
+                    if let Some((None, depth)) = edit_position {
+                        action = action.take().or(self.edit_row(ui, depth, vault));
+                    }
+                    for (index, (depth, entry)) in rows.iter().enumerate() {
+                        let path = entry.path();
+                        let renaming = matches!(
+                            &self.edit,
+                            Some((TreeEdit::Rename { path: renamed } | TreeEdit::RenameFolder { path: renamed }, _))
+                                if renamed == path
+                        );
+                        if renaming {
+                            action = action.take().or(self.edit_row(ui, *depth, vault));
+                            continue;
+                        }
+                        let selected = self.selected.as_ref() == Some(path);
+                        let row = ui.horizontal(|ui| {
+                            ui.add_space(*depth as f32 * INDENT_PER_LEVEL);
+                            match entry {
+                                TreeEntry::Directory(directory) => {
+                                    let arrow = if self.expanded.contains(directory) {
+                                        "⏷"
+                                    } else {
+                                        "⏵"
+                                    };
+                                    let label = format!("{} {}", arrow, directory.get_name());
+                                    let id = egui::Id::new("file_tree_folder")
+                                        .with(directory.to_string());
+                                    let mut response = ui
+                                        .dnd_drag_source(id, entry.to_owned(), |ui| {
+                                            ui.selectable_label(selected, label)
+                                        })
+                                        .inner;
+                                    // Where the dragged entry would go
+                                    if response.dnd_hover_payload::<TreeEntry>().is_some() {
+                                        response = response.highlight();
+                                    }
+                                    if response.clicked() {
+                                        self.toggle_directory(directory);
+                                        self.selected = Some(directory.to_owned());
+                                    }
+                                    self.directory_menu(&response, directory);
+                                    action = action.take().or(self.drop_in(&response, directory, case));
+                                    response
+                                }
+                                TreeEntry::Note { path, title } => {
+                                    let is_open = current_note.as_ref() == Some(path);
+                                    let mut text = egui::RichText::new(helpers::note_name(path));
+                                    if is_open {
+                                        text = text.strong();
+                                    }
+                                    let id = egui::Id::new("file_tree_note").with(path.to_string());
+                                    let response = ui
+                                        .dnd_drag_source(id, entry.to_owned(), |ui| {
+                                            ui.selectable_label(selected || is_open, text)
+                                                .on_hover_text(title)
+                                        })
+                                        .inner;
+                                    if response.clicked() {
+                                        self.selected = Some(path.to_owned());
+                                        self.focused = false;
+                                        action = Some(TreeAction::Open(path.to_owned()));
+                                    }
+                                    action = action.take().or(self.note_menu(&response, entry));
+                                    response
+                                }
+                            }
+                        });
+                        let is_directory = matches!(entry, TreeEntry::Directory(_));
+                        if (is_directory && row.inner.clicked()) || row.inner.secondary_clicked() {
+                            self.focused = true;
+                            self.take_keyboard = true;
+                        }
+                        if selected && self.scroll_to_selected {
+                            row.response.scroll_to_me(None);
+                            self.scroll_to_selected = false;
+                        }
+                        if let Some((Some(after), depth)) = edit_position {
+                            if after == index {
+                                action = action.take().or(self.edit_row(ui, depth, vault));
+                            }
+                        }
+                    }
+                });
+            });
+        action
+    }
+}
@@ -1,15 +1,25 @@
+mod file_tree;
 mod modals;
+mod outline;
+mod pins;
+mod properties;
 mod save_manager;
+mod tasks;
 mod viewers;
 
 use anyhow::bail;
 use crossbeam_channel::{Receiver, Sender};
 use eframe::egui;
-use kimun_core::{nfs::VaultPath, NoteVault};
-use log::{debug, error};
+use file_tree::{FileTreePanel, TreeAction};
+use kimun_core::{config::Pin, find_replace::TextFinder, nfs::VaultPath, NoteVault};
+use log::{debug, error, warn};
 use modals::{ModalManager, Modals};
+use outline::OutlinePanel;
+use pins::{PinAction, PinsPanel};
+use properties::{PropertiesAction, PropertiesPanel};
 use save_manager::SaveManager;
-use viewers::{NoView, NoteViewer, ViewerType};
+use tasks::{TaskAction, TasksPanel};
+use viewers::{edit_commands::EditCommand, NoView, NoteViewer, ViewerType};
 
 use crate::{settings::Settings, WindowSwitch};
 
@@ -21,6 +31,11 @@ pub struct Editor {
     text: String,
     save_manager: SaveManager,
     modal_manager: ModalManager,
+    outline: OutlinePanel,
+    tasks: TasksPanel,
+    pins: PinsPanel,
+    properties: PropertiesPanel,
+    file_tree: FileTreePanel,
     vault: NoteVault,
     message_sender: Sender<EditorMessage>,
     message_receiver: Receiver<EditorMessage>,
@@ -32,13 +47,16 @@ impl Editor {
     pub fn new(settings: &Settings, recreate_index: bool) -> anyhow::Result<Self> {
         if let Some(workspace_dir) = &settings.workspace_dir {
             let (sender, receiver) = crossbeam_channel::unbounded();
-            let vault = NoteVault::new(workspace_dir)?;
+            let vault = settings.open_vault(workspace_dir)?;
             if recreate_index {
-                vault.init_and_validate()?;
+                let report = vault.init_and_validate()?;
+                for file in &report.skipped {
+                    warn!("Couldn't index {}", file);
+                }
             }
 
             let note_path = settings.last_paths.last().and_then(|path| {
-                if !path.is_note() {
+                if !vault.is_note(path) {
                     None
                 } else {
                     Some(path.to_owned())
@@ -51,6 +69,11 @@ impl Editor {
                 viewer: Box::new(NoView::new()),
                 text: String::new(),
                 modal_manager,
+                outline: OutlinePanel::new(),
+                tasks: TasksPanel::new(),
+                pins: PinsPanel::new(),
+                properties: PropertiesPanel::new(),
+                file_tree: FileTreePanel::new(!settings.hide_file_tree),
                 save_manager,
                 vault,
                 message_sender: sender,
@@ -73,8 +96,11 @@ impl Editor {
     /// if the path is a note, then we load the note in the current view
     fn load_note_path(&mut self, note_path: &Option<VaultPath>) -> anyhow::Result<()> {
         if let Some(path) = &note_path {
-            if path.is_note() && self.vault.exists(path).is_some() {
-                let text = self.vault.load_note(path)?;
+            // The note may be written differently on disk
+            let entry = self.vault.exists(path);
+            if let Some(entry) = entry.filter(|entry| self.vault.is_note(&entry.path)) {
+                let path = &entry.path;
+                let text = self.vault.get_note_text(path)?;
                 self.settings.add_path_history(path);
                 self.settings.save_to_disk()?;
                 self.load_content(path, text);
@@ -89,15 +115,34 @@ impl Editor {
         Ok(())
     }
 
+    /// Opens an empty note at the path, or next to it if there is
+    /// already one there
+    fn new_note(&mut self, note_path: &VaultPath) -> VaultPath {
+        let mut np = note_path.clone();
+        loop {
+            if self.vault.exists(&np).is_none() {
+                break;
+            } else {
+                np = np.get_name_on_conflict();
+            }
+        }
+        debug!("New note at: {}", np);
+        self.load_content(&np, String::new());
+        np
+    }
+
     pub fn load_content(&mut self, path: &VaultPath, text: String) {
         self.text = text.clone();
         self.save_manager.load(&text, path);
+        self.file_tree.note_opened(path);
 
-        self.viewer = self.viewer.view_change_on_content(path);
+        self.viewer = self.viewer.view_change_on_content(path, &self.vault);
         self.viewer.init(text);
+        self.outline.content_changed();
+        self.properties.note_changed();
     }
     pub fn set_view(&mut self, vtype: ViewerType) {
-        self.viewer = vtype.get_view();
+        self.viewer = vtype.get_view(&self.vault);
         self.viewer.init(self.text.clone());
     }
 
@@ -106,12 +151,110 @@ impl Editor {
     }
 
     fn manage_keys(&mut self, ctx: &egui::Context) {
+        // Checked before the shortcuts without shift, as they also match with it
+        if ctx.input_mut(|input| {
+            input.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::O,
+            )
+        }) {
+            self.outline.toggle();
+        }
+        if ctx.input_mut(|input| {
+            input.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::T,
+            )
+        }) {
+            // The tasks are read from the index, updated when saving
+            if let Err(e) = self.save_note() {
+                error!("Error saving the note before listing the tasks: {}", e);
+            }
+            self.tasks.toggle();
+        }
+        if ctx.input_mut(|input| {
+            input.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::F,
+            )
+        }) {
+            self.modal_manager.set_modal(Modals::VaultReplace);
+        }
+        if ctx.input_mut(|input| {
+            input.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::E,
+            )
+        }) {
+            // The export reads the note from disk
+            if let Err(e) = self.save_note() {
+                error!("Error saving the note before exporting: {}", e);
+            }
+            self.modal_manager
+                .set_modal(Modals::VaultExport(self.save_manager.get_path()));
+        }
+        if ctx.input_mut(|input| {
+            input.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::I,
+            )
+        }) {
+            self.modal_manager.set_modal(Modals::VaultImport);
+        }
+        if ctx.input_mut(|input| {
+            input.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::B,
+            )
+        }) {
+            self.pins.toggle();
+        }
+        if ctx.input_mut(|input| {
+            input.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::P,
+            )
+        }) {
+            // The counts are read from the index, updated when saving
+            if let Err(e) = self.save_note() {
+                error!("Error saving the note before showing its properties: {}", e);
+            }
+            self.properties.toggle();
+        }
+        if ctx.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::Backslash))
+        {
+            self.file_tree.toggle();
+            self.settings.hide_file_tree = !self.file_tree.is_visible();
+            if let Err(e) = self.settings.save_to_disk() {
+                error!("Error saving the settings: {}", e);
+            }
+        }
+        let pin_keys = [
+            egui::Key::Num1,
+            egui::Key::Num2,
+            egui::Key::Num3,
+            egui::Key::Num4,
+            egui::Key::Num5,
+            egui::Key::Num6,
+            egui::Key::Num7,
+            egui::Key::Num8,
+            egui::Key::Num9,
+        ];
+        for (position, key) in pin_keys.into_iter().enumerate() {
+            if ctx.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, key)) {
+                if let Some(pin) = self.vault.get_pins().get(position).cloned() {
+                    if let Err(e) = self.pin_action(PinAction::Open(pin)) {
+                        error!("Error opening the pin: {}", e);
+                    }
+                }
+            }
+        }
         if ctx.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::O)) {
             let browse_path = self
                 .save_manager
                 .get_path()
                 .map(|path| {
-                    if path.is_note() {
+                    if self.vault.is_note(&path) {
                         path.get_parent_path().0
                     } else {
                         path
@@ -121,6 +264,10 @@ impl Editor {
             self.modal_manager
                 .set_modal(Modals::VaultBrowse(browse_path));
         }
+        if ctx.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::P)) {
+            self.modal_manager
+                .set_modal(Modals::QuickSwitcher(self.settings.last_paths.clone()));
+        }
         if ctx.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::S)) {
             self.modal_manager.set_modal(Modals::VaultSearch);
         }
@@ -156,33 +303,283 @@ impl Editor {
                     }
                 }
                 EditorMessage::NewNote(note_path) => {
-                    let mut np = note_path.clone();
-                    loop {
-                        if self.vault.exists(&np).is_none() {
-                            break;
-                        } else {
-                            np = np.get_name_on_conflict();
-                        }
-                    }
-                    debug!("New note at: {}", np);
-                    self.load_content(&np, String::new());
+                    self.new_note(&note_path);
                     self.modal_manager.close_modal();
                     self.request_focus = true;
                 }
-                EditorMessage::Save => {
-                    self.save_note()?;
-                }
                 EditorMessage::SwitchNoteViewer(viewer_type) => {
                     self.change_viewer(viewer_type)?;
                 }
                 EditorMessage::OpenSettings => {
                     self.request_windows_switch = Some(WindowSwitch::Settings)
                 }
+                EditorMessage::PickLink => {
+                    self.modal_manager.set_modal(Modals::LinkPicker);
+                }
+                EditorMessage::InsertLink { path, title } => {
+                    let base_path = self
+                        .save_manager
+                        .get_path()
+                        .map(|note_path| note_path.get_parent_path().0)
+                        .unwrap_or_default();
+                    self.viewer.queue_command(EditCommand::InsertLink {
+                        title,
+                        destination: path.relative_to(&base_path),
+                    });
+                    self.modal_manager.close_modal();
+                    self.request_focus = true;
+                }
+                EditorMessage::ReplaceInVault {
+                    finder,
+                    replacement,
+                    paths,
+                } => {
+                    // The open note is saved first, so the replacement works
+                    // on its latest text, and loaded again if it changed
+                    self.save_note()?;
+                    let changed = self.vault.replace_in_notes(&finder, replacement, &paths)?;
+                    debug!("Replaced text in {} notes", changed.len());
+                    let current_path = self.save_manager.get_path();
+                    if let Some(path) = current_path.filter(|path| changed.contains(path)) {
+                        self.load_note_path(&Some(path))?;
+                    }
+                    self.modal_manager.close_modal();
+                    self.request_focus = true;
+                }
+                EditorMessage::Pin(pin) => {
+                    self.pin_action(PinAction::Pin(pin))?;
+                }
+                EditorMessage::Unpin(pin) => {
+                    self.pin_action(PinAction::Unpin(pin))?;
+                }
+                EditorMessage::SaveSearch { name, query } => {
+                    // The saved searches are in the vault config, the
+                    // modals get the vault with the new one
+                    if let Err(e) = self.vault.save_search(&name, &query) {
+                        warn!("Can't save the search {}: {}", name, e);
+                    }
+                    self.modal_manager.vault_changed(self.vault.clone());
+                }
+                EditorMessage::DeleteSavedSearch { name } => {
+                    if let Err(e) = self.vault.delete_saved_search(&name) {
+                        warn!("Can't delete the saved search {}: {}", name, e);
+                    }
+                    self.modal_manager.vault_changed(self.vault.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn task_action(&mut self, action: TaskAction) -> anyhow::Result<()> {
+        match action {
+            TaskAction::Open(path) => {
+                self.load_note_path(&Some(path))?;
+                self.request_focus = true;
+            }
+            TaskAction::SetDone { path, task, done } => {
+                // The open note is saved first and loaded again after the
+                // change, like when replacing in the vault
+                self.save_note()?;
+                match self.vault.set_task_done(&path, &task, done) {
+                    Ok(_) => {
+                        let current_path = self.save_manager.get_path();
+                        if current_path.is_some_and(|current| current.eq(&path)) {
+                            self.load_note_path(&Some(path))?;
+                        }
+                    }
+                    Err(e) => warn!("Can't change the task: {}", e),
+                }
+                self.tasks.tasks_changed();
+            }
+        }
+        Ok(())
+    }
+
+    fn tree_action(&mut self, action: TreeAction) -> anyhow::Result<()> {
+        match action {
+            TreeAction::Open(path) => {
+                self.load_note_path(&Some(path))?;
+                self.request_focus = true;
+            }
+            TreeAction::NewNote(path) => {
+                self.new_note(&path);
+                // Saved now, so the tree lists it
+                self.save_note()?;
+                self.file_tree.vault_changed();
+                self.request_focus = true;
+            }
+            TreeAction::Rename { from, to } => {
+                // The note is saved first, the links to it are changed on disk
+                self.save_note()?;
+                match self.vault.rename_note(&from, &to) {
+                    Ok(changed) => {
+                        self.update_pin(&from, Some(&to));
+                        let current_path = self.save_manager.get_path();
+                        if current_path.as_ref() == Some(&from) {
+                            self.load_note_path(&Some(to))?;
+                        } else if let Some(path) =
+                            current_path.filter(|path| changed.contains(path))
+                        {
+                            self.load_note_path(&Some(path))?;
+                        }
+                    }
+                    Err(e) => warn!("Can't rename {}: {}", from, e),
+                }
+                self.file_tree.vault_changed();
+            }
+            TreeAction::Delete(path) => {
+                if self.save_manager.get_path().as_ref() == Some(&path) {
+                    self.save_manager.close();
+                    self.text.clear();
+                    self.set_view(ViewerType::Nothing);
+                }
+                match self.vault.delete_note(&path) {
+                    Ok(_) => self.update_pin(&path, None),
+                    Err(e) => warn!("Can't delete {}: {}", path, e),
+                }
+                self.file_tree.vault_changed();
+            }
+            TreeAction::NewFolder(path) => {
+                if let Err(e) = self.vault.create_directory(&path) {
+                    warn!("Can't create {}: {}", path, e);
+                }
+                self.file_tree.vault_changed();
+            }
+            TreeAction::RenameFolder { from, to } => {
+                self.save_note()?;
+                match self.vault.rename_directory(&from, &to) {
+                    Ok(changed) => {
+                        let moved =
+                            |path: &VaultPath| to.append(&VaultPath::from(path.relative_to(&from)));
+                        for path in self.pinned_notes_in(&from) {
+                            self.update_pin(&path, Some(&moved(&path)));
+                        }
+                        let current_path = self.save_manager.get_path();
+                        if let Some(path) =
+                            current_path.as_ref().filter(|path| self.is_in(path, &from))
+                        {
+                            self.load_note_path(&Some(moved(path)))?;
+                        } else if let Some(path) =
+                            current_path.filter(|path| changed.contains(path))
+                        {
+                            self.load_note_path(&Some(path))?;
+                        }
+                    }
+                    Err(e) => warn!("Can't rename {}: {}", from, e),
+                }
+                self.file_tree.vault_changed();
+            }
+            TreeAction::DeleteFolder(path) => {
+                if let Some(current_path) = self.save_manager.get_path() {
+                    if self.is_in(&current_path, &path) {
+                        self.save_manager.close();
+                        self.text.clear();
+                        self.set_view(ViewerType::Nothing);
+                    }
+                }
+                match self.vault.delete_directory(&path) {
+                    Ok(_) => {
+                        for note in self.pinned_notes_in(&path) {
+                            self.update_pin(&note, None);
+                        }
+                    }
+                    Err(e) => warn!("Can't delete {}: {}", path, e),
+                }
+                self.file_tree.vault_changed();
+            }
+            TreeAction::Leave => {
+                self.request_focus = true;
             }
         }
         Ok(())
     }
 
+    fn is_in(&self, path: &VaultPath, directory: &VaultPath) -> bool {
+        path.is_in(directory, self.vault.get_config().path_case)
+    }
+
+    /// The pinned notes inside the directory, to follow it when moved
+    fn pinned_notes_in(&self, directory: &VaultPath) -> Vec<VaultPath> {
+        self.vault
+            .get_pins()
+            .iter()
+            .filter_map(|pin| match pin {
+                Pin::Note { path } if self.is_in(path, directory) => Some(path.to_owned()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Keeps the pin of the note in its place after renaming it, or
+    /// removes it if the note was deleted
+    fn update_pin(&mut self, from: &VaultPath, to: Option<&VaultPath>) {
+        let pin = Pin::Note {
+            path: from.to_owned(),
+        };
+        let Some(position) = self
+            .vault
+            .get_pins()
+            .iter()
+            .position(|pinned| pinned == &pin)
+        else {
+            return;
+        };
+        let result = match to {
+            Some(to) => {
+                let renamed = Pin::Note {
+                    path: to.to_owned(),
+                };
+                self.vault
+                    .pin(renamed.clone())
+                    .and_then(|_| self.vault.move_pin(&renamed, position))
+                    .and_then(|_| self.vault.unpin(&pin))
+            }
+            None => self.vault.unpin(&pin),
+        };
+        if let Err(e) = result {
+            warn!("Can't change the pins: {}", e);
+        }
+        self.modal_manager.vault_changed(self.vault.clone());
+    }
+
+    fn properties_action(&mut self, action: PropertiesAction) -> anyhow::Result<()> {
+        match action {
+            PropertiesAction::Open(path) => {
+                self.load_note_path(&Some(path))?;
+                self.request_focus = true;
+            }
+            PropertiesAction::Refresh => {
+                self.save_note()?;
+                self.properties.note_changed();
+            }
+        }
+        Ok(())
+    }
+
+    fn pin_action(&mut self, action: PinAction) -> anyhow::Result<()> {
+        // The pins are in the vault config, like the saved searches
+        let result = match action {
+            PinAction::Open(Pin::Note { path }) => {
+                self.load_note_path(&Some(path))?;
+                self.request_focus = true;
+                return Ok(());
+            }
+            PinAction::Open(Pin::Search { name }) => {
+                self.modal_manager.set_modal(Modals::SavedSearch(name));
+                return Ok(());
+            }
+            PinAction::Pin(pin) => self.vault.pin(pin),
+            PinAction::Unpin(pin) => self.vault.unpin(&pin),
+            PinAction::Move { pin, position } => self.vault.move_pin(&pin, position),
+        };
+        if let Err(e) = result {
+            warn!("Can't change the pins: {}", e);
+        }
+        self.modal_manager.vault_changed(self.vault.clone());
+        Ok(())
+    }
+
     fn change_viewer(&mut self, viewer: ViewerType) -> anyhow::Result<()> {
         self.save_note()?;
         self.set_view(viewer);
@@ -201,11 +598,36 @@ impl Drop for Editor {
 impl MainView for Editor {
     fn update(&mut self, ui: &mut egui::Ui) -> anyhow::Result<Option<WindowSwitch>> {
         self.modal_manager.view(ui)?;
+        if let Some(action) = self
+            .file_tree
+            .view(ui, &self.vault, self.save_manager.get_path())
+        {
+            self.tree_action(action)?;
+        }
+        if let Some(offset) = self.outline.view(ui, &self.text) {
+            self.viewer.go_to(offset);
+        }
+        if let Some(action) = self.tasks.view(ui, &self.vault) {
+            self.task_action(action)?;
+        }
+        if let Some(action) = self
+            .pins
+            .view(ui, &self.vault, self.save_manager.get_path())
+        {
+            self.pin_action(action)?;
+        }
+        if let Some(action) = self
+            .properties
+            .view(ui, &self.vault, self.save_manager.get_path())
+        {
+            self.properties_action(action)?;
+        }
         egui::ScrollArea::vertical()
             .show(ui, |ui| match self.viewer.view(&mut self.text, ui) {
                 Ok(changed) => {
                     if changed {
                         self.save_manager.update_text(&self.text);
+                        self.outline.content_changed();
                     }
                     Ok(())
                 }
@@ -232,6 +654,24 @@ pub(crate) enum EditorMessage {
     NewNote(VaultPath),
     SwitchNoteViewer(ViewerType),
     NewJournal,
-    Save,
     OpenSettings,
+    PickLink,
+    InsertLink {
+        path: VaultPath,
+        title: String,
+    },
+    ReplaceInVault {
+        finder: TextFinder,
+        replacement: String,
+        paths: Vec<VaultPath>,
+    },
+    Pin(Pin),
+    Unpin(Pin),
+    SaveSearch {
+        name: String,
+        query: String,
+    },
+    DeleteSavedSearch {
+        name: String,
+    },
 }
@@ -48,7 +48,9 @@ where
     fn init(&self) -> P;
     fn filter<S: AsRef<str>>(&self, filter_text: S, provider: &P) -> Vec<D>;
     fn on_entry(&self, element: &D) -> Option<FilteredListFunctionMessage<Self>>;
-    fn header_element(&self, state_data: &StateData<D>) -> Option<D>;
+    /// Shown above the list, the first one is selected with cmd+enter
+    /// and the second with cmd+shift+enter
+    fn header_elements(&self, state_data: &StateData<D>) -> Vec<D>;
 }
 
 pub enum FilteredListFunctionMessage<F> {
@@ -108,23 +110,33 @@ where
         }
     }
 
+    pub fn get_filter_text(&self) -> &str {
+        &self.state_manager.state_data.filter_text
+    }
+
+    /// Replaces the text, the list is filtered again on the next update
+    pub fn set_filter_text<S: AsRef<str>>(&mut self, text: S) {
+        self.state_manager.state_data.filter_text = text.as_ref().to_string();
+        self.request_focus();
+    }
+
     pub fn get_selection(&self) -> Option<D> {
         self.state_manager.state_data.get_selection()
     }
 
-    fn get_header(&self) -> Option<D> {
+    fn get_headers(&self) -> Vec<D> {
         self.state_manager
             .functions
-            .header_element(&self.state_manager.state_data)
+            .header_elements(&self.state_manager.state_data)
     }
 
     fn get_table(&mut self, ui: &mut egui::Ui, selected_element: &mut Option<D>) {
-        let header = self.get_header();
+        let headers = self.get_headers();
         let text_height = egui::TextStyle::Body
             .resolve(ui.style())
             .size
             .max(ui.spacing().interact_size.y);
-        if let Some(element) = &header {
+        for element in &headers {
             // let height = text_height * element.get_height_mult();
             let header_resp = ui.add_sized(
                 [
@@ -136,6 +148,8 @@ where
             if header_resp.clicked() {
                 *selected_element = Some(element.clone());
             }
+        }
+        if !headers.is_empty() {
             ui.separator();
         }
 
@@ -261,11 +275,20 @@ where
             self.requested_scroll = true;
         }
 
-        if ui
+        if ui.ctx().input_mut(|input| {
+            input.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::Enter,
+            )
+        }) {
+            if let Some(header) = self.get_headers().into_iter().nth(1) {
+                selected_element = Some(header);
+            }
+        } else if ui
             .ctx()
             .input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter))
         {
-            if let Some(header) = self.get_header() {
+            if let Some(header) = self.get_headers().into_iter().next() {
                 selected_element = Some(header);
             }
         } else if ui.ctx().input(|input| input.key_pressed(egui::Key::Enter)) {
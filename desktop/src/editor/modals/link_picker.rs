@@ -0,0 +1,70 @@
+use kimun_core::{nfs::VaultPath, NoteVault, SearchResult};
+use log::{debug, error};
+
+use super::{
+    filtered_list::{FilteredListFunctionMessage, FilteredListFunctions, StateData},
+    vault_browse::{SelectorEntry, SelectorEntryType},
+    EditorMessage,
+};
+
+/// Lists all the notes in the vault from the index to pick one
+/// and insert a link to it in the note being edited
+#[derive(Clone)]
+pub struct LinkPickerFunctions {
+    vault: NoteVault,
+}
+
+impl LinkPickerFunctions {
+    pub fn new(vault: NoteVault) -> Self {
+        Self { vault }
+    }
+}
+
+impl FilteredListFunctions<Vec<SelectorEntry>, SelectorEntry> for LinkPickerFunctions {
+    fn init(&self) -> Vec<SelectorEntry> {
+        debug!("Retrieving notes to link");
+        match self.vault.get_notes(&VaultPath::root(), true) {
+            Ok(notes) => notes
+                .into_iter()
+                .map(|note| SearchResult::Note(note).into())
+                .collect(),
+            Err(e) => {
+                error!("Error getting the notes to link: {}", e);
+                vec![]
+            }
+        }
+    }
+
+    fn filter<S: AsRef<str>>(
+        &self,
+        filter_text: S,
+        data: &Vec<SelectorEntry>,
+    ) -> Vec<SelectorEntry> {
+        let mut matcher = nucleo::Matcher::new(nucleo::Config::DEFAULT.match_paths());
+        nucleo::pattern::Pattern::parse(
+            filter_text.as_ref(),
+            nucleo::pattern::CaseMatching::Ignore,
+            nucleo::pattern::Normalization::Smart,
+        )
+        .match_list(data, &mut matcher)
+        .iter()
+        .map(|e| e.0.to_owned())
+        .collect::<Vec<SelectorEntry>>()
+    }
+
+    fn on_entry(&self, element: &SelectorEntry) -> Option<FilteredListFunctionMessage<Self>> {
+        match &element.entry_type {
+            SelectorEntryType::Note { title } => Some(FilteredListFunctionMessage::ToEditor(
+                EditorMessage::InsertLink {
+                    path: element.path.clone(),
+                    title: title.to_owned(),
+                },
+            )),
+            _ => None,
+        }
+    }
+
+    fn header_elements(&self, _state_data: &StateData<SelectorEntry>) -> Vec<SelectorEntry> {
+        vec![]
+    }
+}
@@ -1,14 +1,26 @@
 mod filtered_list;
+mod link_picker;
 mod preview_list;
+mod quick_switcher;
 mod vault_browse;
+mod vault_export;
+mod vault_import;
+mod vault_replace;
+mod vault_search;
 
 use crossbeam_channel::Sender;
 use eframe::egui;
 use filtered_list::FilteredList;
-use log::debug;
 use kimun_core::{nfs::VaultPath, NoteVault};
+use link_picker::LinkPickerFunctions;
+use log::debug;
 use preview_list::PreviewList;
-use vault_browse::{VaultBrowseFunctions, VaultSearchFunctions};
+use quick_switcher::QuickSwitcherFunctions;
+use vault_browse::VaultBrowseFunctions;
+use vault_export::VaultExport;
+use vault_import::VaultImport;
+use vault_replace::VaultReplace;
+use vault_search::VaultSearch;
 
 use super::EditorMessage;
 
@@ -21,6 +33,15 @@ pub struct ModalManager {
 pub enum Modals {
     VaultBrowse(VaultPath),
     VaultSearch,
+    /// The notes found by the saved search
+    SavedSearch(String),
+    /// Goes to any note, ranking higher the recent ones, the most recent last
+    QuickSwitcher(Vec<VaultPath>),
+    LinkPicker,
+    VaultReplace,
+    /// Exports the open note, if any, or the whole vault
+    VaultExport(Option<VaultPath>),
+    VaultImport,
 }
 
 impl ModalManager {
@@ -61,18 +82,69 @@ impl ModalManager {
             }
             Modals::VaultSearch => {
                 debug!("show searcher");
+                let content = VaultSearch::new(self.vault.clone(), self.message_sender.clone());
+                self.current_modal = Some(Box::new(content));
+            }
+            Modals::SavedSearch(name) => {
+                debug!("show saved search");
+                let content = PreviewList::new(
+                    self.vault.clone(),
+                    FilteredList::new(
+                        VaultBrowseFunctions::saved_search(name, self.vault.clone()),
+                        self.message_sender.clone(),
+                    ),
+                );
+                self.current_modal = Some(Box::new(content));
+            }
+            Modals::QuickSwitcher(recent) => {
+                debug!("show quick switcher");
+                let content = PreviewList::new(
+                    self.vault.clone(),
+                    FilteredList::new(
+                        QuickSwitcherFunctions::new(self.vault.clone(), recent),
+                        self.message_sender.clone(),
+                    ),
+                );
+                self.current_modal = Some(Box::new(content));
+            }
+            Modals::LinkPicker => {
+                debug!("show link picker");
                 let content = PreviewList::new(
                     self.vault.clone(),
                     FilteredList::new(
-                        VaultSearchFunctions::new(self.vault.clone()),
+                        LinkPickerFunctions::new(self.vault.clone()),
                         self.message_sender.clone(),
                     ),
                 );
                 self.current_modal = Some(Box::new(content));
             }
+            Modals::VaultReplace => {
+                debug!("show vault replace");
+                let content = VaultReplace::new(self.vault.clone(), self.message_sender.clone());
+                self.current_modal = Some(Box::new(content));
+            }
+            Modals::VaultExport(note_path) => {
+                debug!("show vault export");
+                let content = VaultExport::new(self.vault.clone(), note_path);
+                self.current_modal = Some(Box::new(content));
+            }
+            Modals::VaultImport => {
+                debug!("show vault import");
+                let content = VaultImport::new(self.vault.clone());
+                self.current_modal = Some(Box::new(content));
+            }
         };
     }
 
+    /// Takes the vault after its config changed, for the modals opened
+    /// from now on and the one open
+    pub fn vault_changed(&mut self, vault: NoteVault) {
+        if let Some(current_modal) = self.current_modal.as_mut() {
+            current_modal.vault_changed(&vault);
+        }
+        self.vault = vault;
+    }
+
     pub fn close_modal(&mut self) {
         self.current_modal = None;
     }
@@ -80,4 +152,5 @@ impl ModalManager {
 
 pub trait EditorModal {
     fn update(&mut self, ui: &mut egui::Ui);
+    fn vault_changed(&mut self, _vault: &NoteVault) {}
 }
@@ -50,6 +50,14 @@ where
         }
     }
 
+    pub fn get_list(&self) -> &FilteredList<F, P, D> {
+        &self.list
+    }
+
+    pub fn get_list_mut(&mut self) -> &mut FilteredList<F, P, D> {
+        &mut self.list
+    }
+
     fn update_state(&mut self) {
         while let Ok(state) = self.state_receiver.try_recv() {
             self.state = state;
@@ -57,7 +65,7 @@ where
 
         let selected_path = self.list.get_selection().and_then(|selection| {
             let selection_path = selection.get_path();
-            if selection_path.is_note() {
+            if self.vault.is_note(&selection_path) {
                 Some(selection_path)
             } else {
                 None
@@ -89,11 +97,11 @@ where
 
     fn load_preview(&mut self, path: VaultPath) {
         self.state = PreviewState::LoadingPreview;
-        if path.is_note() {
+        if self.vault.is_note(&path) {
             let vault = self.vault.clone();
             let tx = self.state_sender.clone();
             std::thread::spawn(move || {
-                let text = vault.load_note(&path).unwrap_or_default();
+                let text = vault.get_note_text(&path).unwrap_or_default();
                 if let Err(e) = tx.send(PreviewState::PreviewNote { path, text }) {
                     error!("Failed to send a preview load status: {}", e);
                 }
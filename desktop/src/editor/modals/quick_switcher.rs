@@ -0,0 +1,179 @@
+use std::cmp::Reverse;
+
+use kimun_core::{nfs::VaultPath, NoteVault};
+use log::{debug, error};
+use nucleo::{
+    pattern::{CaseMatching, Normalization, Pattern},
+    Matcher, Utf32Str,
+};
+
+use super::{
+    filtered_list::{FilteredListFunctionMessage, FilteredListFunctions, StateData},
+    vault_browse::{SelectorEntry, SelectorEntryType},
+    EditorMessage,
+};
+
+/// Score added for each position a note has in the history of
+/// opened notes, so the recent ones rank higher on similar matches
+const RECENCY_WEIGHT: u32 = 4;
+
+/// Jumps to any note in the vault by fuzzy matching its title, aliases
+/// or path, or creates a new one if nothing matches
+#[derive(Clone)]
+pub struct QuickSwitcherFunctions {
+    vault: NoteVault,
+    /// The last opened notes, the most recent one last
+    recent: Vec<VaultPath>,
+}
+
+impl QuickSwitcherFunctions {
+    pub fn new(vault: NoteVault, recent: Vec<VaultPath>) -> Self {
+        Self { vault, recent }
+    }
+}
+
+#[derive(Clone)]
+pub struct SwitcherNote {
+    path: VaultPath,
+    path_str: String,
+    title: String,
+    aliases: Vec<String>,
+    /// Higher for the most recently opened notes, 0 if not opened recently
+    recency: u32,
+}
+
+impl SwitcherNote {
+    fn entry(&self, alias: Option<&String>) -> SelectorEntry {
+        let title = match alias {
+            Some(alias) => format!("{} ({})", self.title, alias),
+            None => self.title.clone(),
+        };
+        SelectorEntry {
+            path: self.path.clone(),
+            path_str: self.path_str.clone(),
+            search_str: self.title.clone(),
+            entry_type: SelectorEntryType::Note { title },
+        }
+    }
+}
+
+impl FilteredListFunctions<Vec<SwitcherNote>, SelectorEntry> for QuickSwitcherFunctions {
+    fn init(&self) -> Vec<SwitcherNote> {
+        debug!("Retrieving note names for the quick switcher");
+        match self.vault.get_note_names() {
+            Ok(notes) => notes
+                .into_iter()
+                .map(|note| {
+                    let recency = self
+                        .recent
+                        .iter()
+                        .position(|path| path.eq(&note.path))
+                        .map_or(0, |position| position as u32 + 1);
+                    SwitcherNote {
+                        path_str: note.path.to_string(),
+                        title: note.get_title(),
+                        aliases: note.data.aliases,
+                        path: note.path,
+                        recency,
+                    }
+                })
+                .collect(),
+            Err(e) => {
+                error!("Error getting the notes for the quick switcher: {}", e);
+                vec![]
+            }
+        }
+    }
+
+    fn filter<S: AsRef<str>>(
+        &self,
+        filter_text: S,
+        notes: &Vec<SwitcherNote>,
+    ) -> Vec<SelectorEntry> {
+        let filter_text = filter_text.as_ref();
+        if filter_text.trim().is_empty() {
+            let mut notes = notes.iter().collect::<Vec<&SwitcherNote>>();
+            notes.sort_by(|a, b| {
+                b.recency
+                    .cmp(&a.recency)
+                    .then_with(|| a.path_str.cmp(&b.path_str))
+            });
+            return notes.iter().map(|note| note.entry(None)).collect();
+        }
+
+        let mut matcher = Matcher::new(nucleo::Config::DEFAULT.match_paths());
+        let pattern = Pattern::parse(filter_text, CaseMatching::Ignore, Normalization::Smart);
+        let mut buf = vec![];
+        let mut scored = notes
+            .iter()
+            .filter_map(|note| {
+                let mut score =
+                    |text: &str| pattern.score(Utf32Str::new(text, &mut buf), &mut matcher);
+                // The title and aliases are preferred over the path on ties
+                let mut best = score(&note.path_str).map(|score| (score, None));
+                for (alias, text) in note
+                    .aliases
+                    .iter()
+                    .map(|alias| (Some(alias), alias))
+                    .chain([(None, &note.title)])
+                {
+                    if let Some(text_score) = score(text) {
+                        if best.is_none_or(|(best_score, _)| text_score >= best_score) {
+                            best = Some((text_score, alias));
+                        }
+                    }
+                }
+                best.map(|(score, alias)| {
+                    (score + note.recency * RECENCY_WEIGHT, note.entry(alias))
+                })
+            })
+            .collect::<Vec<(u32, SelectorEntry)>>();
+        scored.sort_by(|a, b| {
+            Reverse(a.0)
+                .cmp(&Reverse(b.0))
+                .then_with(|| a.1.path_str.cmp(&b.1.path_str))
+        });
+
+        let mut filtered = scored
+            .into_iter()
+            .map(|(_score, entry)| entry)
+            .collect::<Vec<SelectorEntry>>();
+        if filtered.is_empty() {
+            // Enter creates the note when there's nothing to open
+            filtered.push(SelectorEntry::new_note(&VaultPath::root(), filter_text));
+        }
+        debug!("filtered {} values", filtered.len());
+        filtered
+    }
+
+    fn on_entry(&self, element: &SelectorEntry) -> Option<FilteredListFunctionMessage<Self>> {
+        match element.entry_type {
+            SelectorEntryType::Note { title: _ } => Some(FilteredListFunctionMessage::ToEditor(
+                EditorMessage::OpenNote(element.path.clone()),
+            )),
+            SelectorEntryType::NewNote => Some(FilteredListFunctionMessage::ToEditor(
+                EditorMessage::NewNote(element.path.clone()),
+            )),
+            SelectorEntryType::Directory
+            | SelectorEntryType::NewFolder
+            | SelectorEntryType::Attachment
+            | SelectorEntryType::SavedSearch { query: _ }
+            | SelectorEntryType::Pinned { .. } => None,
+        }
+    }
+
+    fn header_elements(&self, state_data: &StateData<SelectorEntry>) -> Vec<SelectorEntry> {
+        let listed = state_data
+            .elements
+            .iter()
+            .any(|entry| matches!(entry.entry_type, SelectorEntryType::NewNote));
+        if !state_data.filter_text.is_empty() && !listed {
+            vec![SelectorEntry::new_note(
+                &VaultPath::root(),
+                &state_data.filter_text,
+            )]
+        } else {
+            vec![]
+        }
+    }
+}
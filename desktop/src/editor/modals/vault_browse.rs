@@ -1,9 +1,13 @@
 use eframe::egui;
-use kimun_core::{nfs::VaultPath, NoteDetails, NoteVault, SearchResult, VaultBrowseOptionsBuilder};
+use kimun_core::{
+    config::{Pin, SavedSearch},
+    nfs::VaultPath,
+    NoteDetails, NoteVault, SearchResult, VaultBrowseOptionsBuilder,
+};
 use log::{debug, error};
 use rayon::slice::ParallelSliceMut;
 
-use crate::{fonts, helpers};
+use crate::{editor::pins::pin_shortcut, fonts, helpers};
 
 use super::{
     filtered_list::{FilteredListFunctionMessage, FilteredListFunctions, ListElement, StateData},
@@ -13,18 +17,51 @@ use super::{
 #[derive(Clone)]
 pub struct VaultBrowseFunctions {
     path: VaultPath, // add code here
+    /// The saved search listed instead of the path
+    saved_search: Option<String>,
     vault: NoteVault,
 }
 
 impl VaultBrowseFunctions {
     pub fn new(path: VaultPath, vault: NoteVault) -> Self {
-        Self { path, vault }
+        Self {
+            path,
+            saved_search: None,
+            vault,
+        }
+    }
+
+    /// Lists the notes found by the saved search
+    pub fn saved_search(name: String, vault: NoteVault) -> Self {
+        Self {
+            path: VaultPath::root(),
+            saved_search: Some(name),
+            vault,
+        }
+    }
+
+    /// The notes matching the saved search, listed as if they were in a
+    /// folder inside the path
+    fn saved_search_results(&self, name: &str) -> Vec<SelectorEntry> {
+        match self.vault.run_saved_search(name) {
+            Ok(notes) => notes
+                .into_iter()
+                .map(|note| SearchResult::Note(note).into())
+                .collect(),
+            Err(e) => {
+                error!("Error running the saved search {}: {}", name, e);
+                vec![]
+            }
+        }
     }
 }
 
 impl FilteredListFunctions<Vec<SelectorEntry>, SelectorEntry> for VaultBrowseFunctions {
     fn init(&self) -> Vec<SelectorEntry> {
-        let search_path = if self.path.is_note() {
+        if let Some(name) = &self.saved_search {
+            return self.saved_search_results(name);
+        }
+        let search_path = if self.vault.is_note(&self.path) {
             self.path.get_parent_path().0
         } else {
             self.path.to_owned()
@@ -48,6 +85,14 @@ impl FilteredListFunctions<Vec<SelectorEntry>, SelectorEntry> for VaultBrowseFun
                 SearchResult::Attachment(_note_path) => {}
             }
         }
+        if search_path == VaultPath::root() {
+            for search in self.vault.get_saved_searches() {
+                results.push(SelectorEntry::saved_search(search));
+            }
+        }
+        for (position, pin) in self.vault.get_pins().iter().enumerate() {
+            results.push(SelectorEntry::pinned(pin, position));
+        }
         debug!("Retrieved {} elements", results.len());
         results
     }
@@ -67,7 +112,9 @@ impl FilteredListFunctions<Vec<SelectorEntry>, SelectorEntry> for VaultBrowseFun
         .iter()
         .map(|e| e.0.to_owned())
         .collect::<Vec<SelectorEntry>>();
-        if self.path != VaultPath::root() {
+        if self.saved_search.is_some() {
+            filtered.push(SelectorEntry::up_to(&self.path));
+        } else if self.path != VaultPath::root() {
             filtered.push(SelectorEntry::up_dir(&self.path));
         }
         filtered.par_sort_by(|a, b| a.get_sort_string().cmp(&b.get_sort_string()));
@@ -77,7 +124,7 @@ impl FilteredListFunctions<Vec<SelectorEntry>, SelectorEntry> for VaultBrowseFun
     }
 
     fn on_entry(&self, element: &SelectorEntry) -> Option<FilteredListFunctionMessage<Self>> {
-        match element.entry_type {
+        match &element.entry_type {
             SelectorEntryType::Note { title: _ } => Some(FilteredListFunctionMessage::ToEditor(
                 EditorMessage::OpenNote(element.path.clone()),
             )),
@@ -86,23 +133,62 @@ impl FilteredListFunctions<Vec<SelectorEntry>, SelectorEntry> for VaultBrowseFun
                 debug!("new path: {}", directory);
                 let new_one = Self {
                     path: directory,
+                    saved_search: None,
                     vault: self.vault.clone(),
                 };
                 // self.path = directory;
                 Some(FilteredListFunctionMessage::ResetState(new_one))
             }
+            SelectorEntryType::SavedSearch { query: _ } => {
+                debug!("saved search: {}", element.path_str);
+                let new_one = Self {
+                    path: self.path.clone(),
+                    saved_search: Some(element.path_str.clone()),
+                    vault: self.vault.clone(),
+                };
+                Some(FilteredListFunctionMessage::ResetState(new_one))
+            }
+            SelectorEntryType::Pinned { pin, position: _ } => match pin {
+                Pin::Note { path } => Some(FilteredListFunctionMessage::ToEditor(
+                    EditorMessage::OpenNote(path.clone()),
+                )),
+                Pin::Search { name } => {
+                    debug!("pinned search: {}", name);
+                    let new_one = Self {
+                        path: self.path.clone(),
+                        saved_search: Some(name.clone()),
+                        vault: self.vault.clone(),
+                    };
+                    Some(FilteredListFunctionMessage::ResetState(new_one))
+                }
+            },
             SelectorEntryType::Attachment => None,
             SelectorEntryType::NewNote => Some(FilteredListFunctionMessage::ToEditor(
                 EditorMessage::NewNote(element.path.clone()),
             )),
+            SelectorEntryType::NewFolder => {
+                if let Err(e) = self.vault.create_directory(&element.path) {
+                    error!("Can't create the folder {}: {}", element.path, e);
+                    return None;
+                }
+                let new_one = Self {
+                    path: element.path.clone(),
+                    saved_search: None,
+                    vault: self.vault.clone(),
+                };
+                Some(FilteredListFunctionMessage::ResetState(new_one))
+            }
         }
     }
 
-    fn header_element(&self, state_data: &StateData<SelectorEntry>) -> Option<SelectorEntry> {
-        if !state_data.filter_text.is_empty() {
-            Some(SelectorEntry::new_note(&self.path, &state_data.filter_text))
+    fn header_elements(&self, state_data: &StateData<SelectorEntry>) -> Vec<SelectorEntry> {
+        if !state_data.filter_text.is_empty() && self.saved_search.is_none() {
+            vec![
+                SelectorEntry::new_note(&self.path, &state_data.filter_text),
+                SelectorEntry::new_folder(&self.path, &state_data.filter_text),
+            ]
         } else {
-            None
+            vec![]
         }
     }
 }
@@ -141,8 +227,8 @@ impl FilteredListFunctions<(), NoteDetails> for VaultSearchFunctions {
         ))
     }
 
-    fn header_element(&self, _state_data: &StateData<NoteDetails>) -> Option<NoteDetails> {
-        None
+    fn header_elements(&self, _state_data: &StateData<NoteDetails>) -> Vec<NoteDetails> {
+        vec![]
     }
 }
 
@@ -171,10 +257,22 @@ pub struct SelectorEntry {
 
 #[derive(Clone, Debug)]
 pub enum SelectorEntryType {
-    Note { title: String },
+    Note {
+        title: String,
+    },
     Directory,
     Attachment,
     NewNote,
+    NewFolder,
+    /// A folder with the notes found by the saved search
+    SavedSearch {
+        query: String,
+    },
+    /// Listed first, the position is the one of its shortcut
+    Pinned {
+        pin: Pin,
+        position: usize,
+    },
 }
 
 impl From<SearchResult> for SelectorEntry {
@@ -226,6 +324,9 @@ impl ListElement for SelectorEntry {
             SelectorEntryType::Directory => 1.0,
             SelectorEntryType::Attachment => 1.0,
             SelectorEntryType::NewNote => 2.0,
+            SelectorEntryType::NewFolder => 2.0,
+            SelectorEntryType::SavedSearch { query: _ } => 2.0,
+            SelectorEntryType::Pinned { .. } => 2.0,
         }
     }
 
@@ -237,6 +338,11 @@ impl ListElement for SelectorEntry {
             SelectorEntryType::NewNote => {
                 format!("{}+enter", helpers::cmd_ctrl())
             }
+            SelectorEntryType::NewFolder => {
+                format!("{}+shift+enter", helpers::cmd_ctrl())
+            }
+            SelectorEntryType::SavedSearch { query: _ } => fonts::SAVED_SEARCH.to_string(),
+            SelectorEntryType::Pinned { pin: _, position } => pin_shortcut(*position),
         }
     }
 
@@ -258,6 +364,17 @@ impl ListElement for SelectorEntry {
                 let path = self.path_str.to_owned();
                 format!("Create new note at:\n`{}`", path)
             }
+            SelectorEntryType::NewFolder => {
+                let path = self.path_str.to_owned();
+                format!("Create new folder at:\n`{}`", path)
+            }
+            SelectorEntryType::SavedSearch { query } => {
+                format!("{}\n{}", self.path_str, query)
+            }
+            SelectorEntryType::Pinned { pin, position: _ } => match pin {
+                Pin::Note { path } => format!("{}\n{}", self.path_str, path),
+                Pin::Search { name: _ } => format!("{}\nSaved search", self.path_str),
+            },
         }
     }
 }
@@ -273,7 +390,44 @@ impl SelectorEntry {
         }
     }
 
-    fn new_note(base_path: &VaultPath, note_text: &str) -> Self {
+    /// Goes back from a saved search to the folder it was opened from
+    fn up_to(path: &VaultPath) -> Self {
+        Self {
+            path: path.to_owned(),
+            path_str: "..".to_string(),
+            search_str: ".. up".to_string(),
+            entry_type: SelectorEntryType::Directory,
+        }
+    }
+
+    fn saved_search(search: &SavedSearch) -> Self {
+        Self {
+            path: VaultPath::root(),
+            path_str: search.name.clone(),
+            search_str: search.name.clone(),
+            entry_type: SelectorEntryType::SavedSearch {
+                query: search.query.clone(),
+            },
+        }
+    }
+
+    fn pinned(pin: &Pin, position: usize) -> Self {
+        let (path, name) = match pin {
+            Pin::Note { path } => (path.clone(), helpers::note_name(path)),
+            Pin::Search { name } => (VaultPath::root(), name.clone()),
+        };
+        Self {
+            path,
+            search_str: name.clone(),
+            path_str: name,
+            entry_type: SelectorEntryType::Pinned {
+                pin: pin.clone(),
+                position,
+            },
+        }
+    }
+
+    pub(super) fn new_note(base_path: &VaultPath, note_text: &str) -> Self {
         let file_name = VaultPath::file_from(note_text);
         let path = base_path.append(&file_name);
 
@@ -285,12 +439,25 @@ impl SelectorEntry {
         }
     }
 
+    fn new_folder(base_path: &VaultPath, folder_text: &str) -> Self {
+        let path = base_path.append(&VaultPath::from(folder_text));
+
+        Self {
+            path_str: path.to_string(),
+            path,
+            search_str: "New Folder".to_string(),
+            entry_type: SelectorEntryType::NewFolder,
+        }
+    }
+
     fn get_sort_string(&self) -> String {
         match &self.entry_type {
-            SelectorEntryType::Note { title: _ } => format!("2{}", self.path),
-            SelectorEntryType::Directory => format!("1{}", self.path),
-            SelectorEntryType::Attachment => format!("3{}", self.path),
-            SelectorEntryType::NewNote => "0".to_string(),
+            SelectorEntryType::Note { title: _ } => format!("4{}", self.path),
+            SelectorEntryType::Directory => format!("3{}", self.path),
+            SelectorEntryType::Attachment => format!("5{}", self.path),
+            SelectorEntryType::NewNote | SelectorEntryType::NewFolder => "0".to_string(),
+            SelectorEntryType::Pinned { pin: _, position } => format!("1{:04}", position),
+            SelectorEntryType::SavedSearch { query: _ } => format!("2{}", self.path_str),
         }
     }
 }
@@ -0,0 +1,177 @@
+use std::path::PathBuf;
+
+use crossbeam_channel::{Receiver, Sender};
+use eframe::egui;
+use kimun_core::{export::ExportReport, nfs::VaultPath, NoteVault};
+use log::error;
+
+use super::EditorModal;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ExportScope {
+    Note,
+    Directory,
+    Vault,
+}
+
+enum ExportState {
+    Choosing,
+    Exporting,
+    Done {
+        destination: PathBuf,
+        result: Result<ExportReport, String>,
+    },
+}
+
+/// Exports the open note, its directory or the whole vault to HTML
+pub struct VaultExport {
+    vault: NoteVault,
+    note_path: Option<VaultPath>,
+    scope: ExportScope,
+    state: ExportState,
+    state_sender: Sender<ExportState>,
+    state_receiver: Receiver<ExportState>,
+}
+
+impl VaultExport {
+    pub fn new(vault: NoteVault, note_path: Option<VaultPath>) -> Self {
+        let (state_sender, state_receiver) = crossbeam_channel::unbounded();
+        let scope = if note_path.is_some() {
+            ExportScope::Note
+        } else {
+            ExportScope::Vault
+        };
+        Self {
+            vault,
+            note_path,
+            scope,
+            state: ExportState::Choosing,
+            state_sender,
+            state_receiver,
+        }
+    }
+
+    fn export_path(&self) -> VaultPath {
+        match (self.scope, &self.note_path) {
+            (ExportScope::Note, Some(path)) => path.to_owned(),
+            (ExportScope::Directory, Some(path)) => path.get_parent_path().0,
+            _ => VaultPath::root(),
+        }
+    }
+
+    fn start(&mut self) {
+        let Some(destination) = rfd::FileDialog::new()
+            .set_title("Choose where to export the notes")
+            .pick_folder()
+        else {
+            return;
+        };
+        self.state = ExportState::Exporting;
+        let vault = self.vault.clone();
+        let path = self.export_path();
+        let sender = self.state_sender.clone();
+        std::thread::spawn(move || {
+            let result = vault
+                .export_html(&path, &destination)
+                .map_err(|e| e.to_string());
+            if let Err(e) = sender.send(ExportState::Done {
+                destination,
+                result,
+            }) {
+                error!("Error sending the export result: {}", e);
+            }
+        });
+    }
+
+    fn show_choices(&mut self, ui: &mut egui::Ui) {
+        if let Some(path) = &self.note_path {
+            ui.radio_value(
+                &mut self.scope,
+                ExportScope::Note,
+                format!("This note: {}", path),
+            );
+            ui.radio_value(
+                &mut self.scope,
+                ExportScope::Directory,
+                format!("This note's folder: {}", path.get_parent_path().0),
+            );
+        }
+        ui.radio_value(&mut self.scope, ExportScope::Vault, "The whole vault");
+        ui.weak("Folders get an index page and a page for each tag");
+        ui.add_space(8.0);
+        if ui.button("Choose Folder and Export").clicked() {
+            self.start();
+        }
+    }
+}
+
+impl EditorModal for VaultExport {
+    fn update(&mut self, ui: &mut egui::Ui) {
+        while let Ok(state) = self.state_receiver.try_recv() {
+            self.state = state;
+        }
+        ui.heading("Export to HTML");
+        ui.add_space(8.0);
+        match &self.state {
+            ExportState::Choosing => self.show_choices(ui),
+            ExportState::Exporting => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Exporting");
+                });
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_millis(100));
+            }
+            ExportState::Done {
+                destination,
+                result,
+            } => match result {
+                Ok(report) => show_report(ui, destination, report),
+                Err(e) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+            },
+        }
+    }
+}
+
+fn show_report(ui: &mut egui::Ui, destination: &std::path::Path, report: &ExportReport) {
+    ui.label(format!(
+        "Exported {} notes, {} attachments and {} tags to {}",
+        report.notes,
+        report.attachments,
+        report.tags,
+        destination.to_string_lossy()
+    ));
+    if report.broken_links.is_empty() && report.skipped.is_empty() {
+        return;
+    }
+    ui.add_space(8.0);
+    ui.colored_label(
+        ui.visuals().warn_fg_color,
+        format!(
+            "{} links point to notes that were not exported, {} files couldn't be read",
+            report.broken_links.len(),
+            report.skipped.len()
+        ),
+    );
+    egui::ScrollArea::vertical()
+        .max_height(200.0)
+        .show(ui, |ui| {
+            egui::Grid::new("Export Issues")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    for link in &report.broken_links {
+                        ui.label(link.note.to_string());
+                        ui.weak(&link.link);
+                        ui.end_row();
+                    }
+                    for file in &report.skipped {
+                        ui.label(file.path.to_string());
+                        ui.weak(&file.reason);
+                        ui.end_row();
+                    }
+                });
+        });
+}
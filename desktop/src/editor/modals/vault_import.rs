@@ -0,0 +1,166 @@
+use std::path::PathBuf;
+
+use crossbeam_channel::{Receiver, Sender};
+use eframe::egui;
+use kimun_core::{
+    import::{ImportReport, ImportSource},
+    nfs::VaultPath,
+    NoteVault,
+};
+use log::error;
+
+use super::EditorModal;
+
+const DEFAULT_DIRECTORY: &str = "imported";
+
+enum ImportState {
+    Choosing,
+    Importing,
+    Done {
+        from: PathBuf,
+        result: Result<ImportReport, String>,
+    },
+}
+
+/// Imports the notes exported from Obsidian, Notion or Evernote
+pub struct VaultImport {
+    vault: NoteVault,
+    source: ImportSource,
+    directory: String,
+    state: ImportState,
+    state_sender: Sender<ImportState>,
+    state_receiver: Receiver<ImportState>,
+}
+
+impl VaultImport {
+    pub fn new(vault: NoteVault) -> Self {
+        let (state_sender, state_receiver) = crossbeam_channel::unbounded();
+        Self {
+            vault,
+            source: ImportSource::Obsidian,
+            directory: DEFAULT_DIRECTORY.to_string(),
+            state: ImportState::Choosing,
+            state_sender,
+            state_receiver,
+        }
+    }
+
+    fn start(&mut self) {
+        let dialog = rfd::FileDialog::new();
+        let from = if self.source.is_file() {
+            dialog
+                .set_title("Choose the Evernote export")
+                .add_filter("Evernote export", &["enex"])
+                .pick_file()
+        } else {
+            dialog
+                .set_title(format!("Choose the {} export folder", self.source))
+                .pick_folder()
+        };
+        let Some(from) = from else {
+            return;
+        };
+        self.state = ImportState::Importing;
+        let vault = self.vault.clone();
+        let source = self.source;
+        let into = VaultPath::from(self.directory.trim());
+        let sender = self.state_sender.clone();
+        std::thread::spawn(move || {
+            let result = vault
+                .import_notes(source, &from, &into)
+                .map_err(|e| e.to_string());
+            if let Err(e) = sender.send(ImportState::Done { from, result }) {
+                error!("Error sending the import result: {}", e);
+            }
+        });
+    }
+
+    fn show_choices(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            for source in ImportSource::all() {
+                ui.radio_value(&mut self.source, source, source.to_string());
+            }
+        });
+        ui.weak(match self.source {
+            ImportSource::Obsidian => "The folder of the Obsidian vault",
+            ImportSource::Notion => "The unzipped folder of a Markdown & CSV export",
+            ImportSource::Evernote => "An .enex export file",
+        });
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            ui.label("Into the folder");
+            ui.text_edit_singleline(&mut self.directory);
+        });
+        ui.weak("Notes already in the vault are not overwritten");
+        ui.add_space(8.0);
+        let label = if self.source.is_file() {
+            "Choose File and Import"
+        } else {
+            "Choose Folder and Import"
+        };
+        if ui.button(label).clicked() {
+            self.start();
+        }
+    }
+}
+
+impl EditorModal for VaultImport {
+    fn update(&mut self, ui: &mut egui::Ui) {
+        while let Ok(state) = self.state_receiver.try_recv() {
+            self.state = state;
+        }
+        ui.heading("Import Notes");
+        ui.add_space(8.0);
+        match &self.state {
+            ImportState::Choosing => self.show_choices(ui),
+            ImportState::Importing => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Importing");
+                });
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_millis(100));
+            }
+            ImportState::Done { from, result } => match result {
+                Ok(report) => show_report(ui, from, report),
+                Err(e) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+            },
+        }
+    }
+}
+
+fn show_report(ui: &mut egui::Ui, from: &std::path::Path, report: &ImportReport) {
+    ui.label(format!(
+        "Imported {} notes and {} attachments from {}",
+        report.notes,
+        report.attachments,
+        from.to_string_lossy()
+    ));
+    if report.issues.is_empty() {
+        return;
+    }
+    ui.add_space(8.0);
+    ui.colored_label(
+        ui.visuals().warn_fg_color,
+        format!(
+            "{} things couldn't be imported as they were",
+            report.issues.len()
+        ),
+    );
+    egui::ScrollArea::vertical()
+        .max_height(200.0)
+        .show(ui, |ui| {
+            egui::Grid::new("Import Issues")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    for issue in &report.issues {
+                        ui.label(&issue.source);
+                        ui.weak(&issue.reason);
+                        ui.end_row();
+                    }
+                });
+        });
+}
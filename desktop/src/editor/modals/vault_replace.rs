@@ -0,0 +1,243 @@
+use std::collections::HashSet;
+
+use crossbeam_channel::{Receiver, Sender};
+use eframe::egui;
+use kimun_core::{
+    find_replace::{FindOptions, NoteReplacement, TextFinder},
+    indexer::SkippedFile,
+    nfs::VaultPath,
+    NoteVault,
+};
+use log::error;
+
+use super::{EditorMessage, EditorModal};
+
+const ID_REPLACE_QUERY: &str = "Replace Query";
+
+enum PreviewState {
+    Empty,
+    Loading,
+    Ready {
+        finder: TextFinder,
+        replacement: String,
+        notes: Vec<NoteReplacement>,
+        /// Notes that couldn't be read
+        skipped: Vec<SkippedFile>,
+    },
+    Error(String),
+}
+
+/// Finds a text in all the notes of the vault and replaces it,
+/// previewing every change before applying them
+pub struct VaultReplace {
+    vault: NoteVault,
+    message_sender: Sender<EditorMessage>,
+    query: String,
+    replacement: String,
+    options: FindOptions,
+    state: PreviewState,
+    /// Notes unchecked in the preview, they are not changed
+    excluded: HashSet<VaultPath>,
+    state_sender: Sender<PreviewState>,
+    state_receiver: Receiver<PreviewState>,
+    request_focus: bool,
+}
+
+impl VaultReplace {
+    pub fn new(vault: NoteVault, message_sender: Sender<EditorMessage>) -> Self {
+        let (state_sender, state_receiver) = crossbeam_channel::unbounded();
+        Self {
+            vault,
+            message_sender,
+            query: String::new(),
+            replacement: String::new(),
+            options: FindOptions::default(),
+            state: PreviewState::Empty,
+            excluded: HashSet::new(),
+            state_sender,
+            state_receiver,
+            request_focus: true,
+        }
+    }
+
+    fn load_preview(&mut self) {
+        let finder = match TextFinder::new(&self.query, self.options.clone()) {
+            Ok(finder) => finder,
+            Err(e) => {
+                self.state = PreviewState::Error(e.to_string());
+                return;
+            }
+        };
+        self.state = PreviewState::Loading;
+        self.excluded.clear();
+        let vault = self.vault.clone();
+        let replacement = self.replacement.clone();
+        let sender = self.state_sender.clone();
+        std::thread::spawn(move || {
+            let state = match vault.preview_replace(&finder, &replacement) {
+                Ok(preview) => PreviewState::Ready {
+                    finder,
+                    replacement,
+                    notes: preview.notes,
+                    skipped: preview.skipped,
+                },
+                Err(e) => PreviewState::Error(e.to_string()),
+            };
+            if let Err(e) = sender.send(state) {
+                error!("Error sending the replace preview: {}", e);
+            }
+        });
+    }
+
+    fn apply(&self) {
+        if let PreviewState::Ready {
+            finder,
+            replacement,
+            notes,
+            ..
+        } = &self.state
+        {
+            let paths = notes
+                .iter()
+                .map(|note| note.path.clone())
+                .filter(|path| !self.excluded.contains(path))
+                .collect::<Vec<VaultPath>>();
+            let message = EditorMessage::ReplaceInVault {
+                finder: finder.clone(),
+                replacement: replacement.to_owned(),
+                paths,
+            };
+            if let Err(e) = self.message_sender.send(message) {
+                error!("Error sending the replace message: {}", e);
+            }
+        }
+    }
+
+    fn show_preview(&mut self, ui: &mut egui::Ui) {
+        let PreviewState::Ready { notes, skipped, .. } = &self.state else {
+            return;
+        };
+        let total = notes.iter().map(|note| note.changes.len()).sum::<usize>();
+        ui.label(format!("{} matches in {} notes", total, notes.len()));
+        if !skipped.is_empty() {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!("{} notes couldn't be read", skipped.len()),
+            )
+            .on_hover_text(
+                skipped
+                    .iter()
+                    .map(|file| file.to_string())
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            );
+        }
+        let removed = ui.visuals().error_fg_color;
+        let added = egui::Color32::from_rgb(0x40, 0xa0, 0x40);
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .show(ui, |ui| {
+                for note in notes {
+                    let mut included = !self.excluded.contains(&note.path);
+                    ui.horizontal(|ui| {
+                        if ui.checkbox(&mut included, note.path.to_string()).changed() {
+                            if included {
+                                self.excluded.remove(&note.path);
+                            } else {
+                                self.excluded.insert(note.path.clone());
+                            }
+                        }
+                        ui.weak(format!("({})", note.changes.len()));
+                    });
+                    ui.indent(note.path.to_string(), |ui| {
+                        for change in &note.changes {
+                            ui.horizontal(|ui| {
+                                ui.weak(format!("{:>4}", change.line));
+                                ui.vertical(|ui| {
+                                    ui.colored_label(removed, format!("- {}", change.before));
+                                    ui.colored_label(added, format!("+ {}", change.after));
+                                });
+                            });
+                        }
+                    });
+                }
+            });
+    }
+}
+
+impl EditorModal for VaultReplace {
+    fn update(&mut self, ui: &mut egui::Ui) {
+        while let Ok(state) = self.state_receiver.try_recv() {
+            self.state = state;
+        }
+
+        ui.heading("Replace in vault");
+        let mut search = false;
+        egui::Grid::new("replace_fields")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Find");
+                let query = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .desired_width(f32::INFINITY)
+                        .id(ID_REPLACE_QUERY.into()),
+                );
+                if self.request_focus {
+                    query.request_focus();
+                    self.request_focus = false;
+                }
+                search |= query.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.end_row();
+
+                ui.label("Replace");
+                let replacement = ui.add(
+                    egui::TextEdit::singleline(&mut self.replacement).desired_width(f32::INFINITY),
+                );
+                search |= replacement.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.end_row();
+            });
+        ui.horizontal(|ui| {
+            ui.toggle_value(&mut self.options.case_sensitive, "Aa")
+                .on_hover_text("Match case");
+            ui.toggle_value(&mut self.options.whole_word, "W")
+                .on_hover_text("Whole word");
+            ui.toggle_value(&mut self.options.regex, ".*")
+                .on_hover_text("Regular expression");
+            search |= ui.button("Preview").clicked();
+        });
+        if search && !self.query.is_empty() {
+            self.load_preview();
+        }
+        ui.separator();
+
+        let selected = match &self.state {
+            PreviewState::Empty => None,
+            PreviewState::Loading => {
+                ui.spinner();
+                None
+            }
+            PreviewState::Error(error) => {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+                None
+            }
+            PreviewState::Ready { notes, .. } if notes.is_empty() => {
+                ui.label("No matches found");
+                None
+            }
+            PreviewState::Ready { notes, .. } => Some(
+                notes
+                    .iter()
+                    .filter(|note| !self.excluded.contains(&note.path))
+                    .count(),
+            ),
+        };
+        if let Some(selected) = selected {
+            self.show_preview(ui);
+            ui.separator();
+            let button = egui::Button::new(format!("Replace in {} notes", selected));
+            if ui.add_enabled(selected > 0, button).clicked() {
+                self.apply();
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crossbeam_channel::Sender;
+use eframe::egui;
+use kimun_core::{config::Pin, NoteDetails, NoteVault};
+use log::error;
+
+use super::{
+    filtered_list::FilteredList, preview_list::PreviewList, vault_browse::VaultSearchFunctions,
+    EditorMessage, EditorModal,
+};
+
+/// The search box, with the saved searches to load, save or delete
+/// the query written
+pub struct VaultSearch {
+    search: PreviewList<VaultSearchFunctions, (), NoteDetails>,
+    /// Has the saved searches and pins, updated when they change
+    vault: NoteVault,
+    name: String,
+    message_sender: Sender<EditorMessage>,
+}
+
+impl VaultSearch {
+    pub fn new(vault: NoteVault, message_sender: Sender<EditorMessage>) -> Self {
+        let search = PreviewList::new(
+            vault.clone(),
+            FilteredList::new(
+                VaultSearchFunctions::new(vault.clone()),
+                message_sender.clone(),
+            ),
+        );
+        Self {
+            search,
+            vault,
+            name: String::new(),
+            message_sender,
+        }
+    }
+
+    fn is_saved(&self) -> bool {
+        self.vault
+            .get_saved_searches()
+            .iter()
+            .any(|search| search.name.eq_ignore_ascii_case(self.name.trim()))
+    }
+
+    fn send(&self, message: EditorMessage) {
+        if let Err(e) = self.message_sender.send(message) {
+            error!("Can't send the message to editor, Err: {}", e)
+        }
+    }
+
+    fn saved_searches_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut selected = None;
+            egui::ComboBox::from_id_salt("saved_searches")
+                .selected_text("Saved searches")
+                .show_ui(ui, |ui| {
+                    for search in self.vault.get_saved_searches() {
+                        if ui.selectable_label(false, &search.name).clicked() {
+                            selected = Some(search.clone());
+                        }
+                    }
+                });
+            if let Some(search) = selected {
+                self.name = search.name;
+                self.search.get_list_mut().set_filter_text(search.query);
+            }
+
+            ui.add(
+                egui::TextEdit::singleline(&mut self.name)
+                    .hint_text("Name")
+                    .desired_width(150.0),
+            );
+            let query = self.search.get_list().get_filter_text().to_string();
+            let can_save = !self.name.trim().is_empty() && !query.trim().is_empty();
+            if ui
+                .add_enabled(can_save, egui::Button::new("Save"))
+                .clicked()
+            {
+                self.send(EditorMessage::SaveSearch {
+                    name: self.name.trim().to_string(),
+                    query,
+                });
+            }
+            if ui
+                .add_enabled(self.is_saved(), egui::Button::new("Delete"))
+                .clicked()
+            {
+                self.send(EditorMessage::DeleteSavedSearch {
+                    name: self.name.trim().to_string(),
+                });
+                self.name.clear();
+            }
+            let pin = Pin::Search {
+                name: self.name.trim().to_string(),
+            };
+            let pinned = self.vault.is_pinned(&pin);
+            let pin_button = egui::Button::new(if pinned { "Unpin" } else { "Pin" });
+            if ui.add_enabled(self.is_saved(), pin_button).clicked() {
+                self.send(if pinned {
+                    EditorMessage::Unpin(pin)
+                } else {
+                    EditorMessage::Pin(pin)
+                });
+            }
+        });
+        ui.separator();
+    }
+}
+
+impl EditorModal for VaultSearch {
+    fn update(&mut self, ui: &mut egui::Ui) {
+        self.saved_searches_bar(ui);
+        self.search.update(ui);
+    }
+
+    fn vault_changed(&mut self, vault: &NoteVault) {
+        self.vault = vault.to_owned();
+    }
+}
@@ -0,0 +1,69 @@
+use eframe::egui;
+use kimun_core::{NoteVault, OutlineHeading};
+
+const INDENT_PER_LEVEL: f32 = 12.0;
+
+/// Side panel with the headings of the current note,
+/// clicking one of them jumps to it in the viewer
+pub struct OutlinePanel {
+    visible: bool,
+    outline: Vec<OutlineHeading>,
+    outdated: bool,
+}
+
+impl OutlinePanel {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            outline: vec![],
+            outdated: true,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Marks the outline to be recalculated the next time it's shown
+    pub fn content_changed(&mut self) {
+        self.outdated = true;
+    }
+
+    /// Shows the panel if visible, returns the offset of the
+    /// heading clicked, if any
+    pub fn view(&mut self, ui: &mut egui::Ui, text: &str) -> Option<usize> {
+        if self.visible && self.outdated {
+            self.outline = NoteVault::get_outline(text);
+            self.outdated = false;
+        }
+        let mut clicked = None;
+        egui::SidePanel::right("outline")
+            .resizable(true)
+            .default_width(200.0)
+            .show_animated_inside(ui, self.visible, |ui| {
+                ui.heading("Outline");
+                ui.separator();
+                if self.outline.is_empty() {
+                    ui.weak("No headings");
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for heading in &self.outline {
+                        ui.horizontal(|ui| {
+                            ui.add_space(INDENT_PER_LEVEL * (heading.level - 1) as f32);
+                            let label = egui::Label::new(&heading.text)
+                                .truncate()
+                                .sense(egui::Sense::click());
+                            if ui
+                                .add(label)
+                                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                clicked = Some(heading.offset);
+                            }
+                        });
+                    }
+                });
+            });
+        clicked
+    }
+}
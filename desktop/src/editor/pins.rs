@@ -0,0 +1,111 @@
+use eframe::egui;
+use kimun_core::{config::Pin, nfs::VaultPath, NoteVault};
+
+use crate::{fonts, helpers};
+
+/// What was done with a pin in the panel
+pub enum PinAction {
+    Open(Pin),
+    Pin(Pin),
+    Unpin(Pin),
+    Move { pin: Pin, position: usize },
+}
+
+/// Side panel with the pinned notes and searches, in the order of
+/// their shortcuts
+pub struct PinsPanel {
+    visible: bool,
+}
+
+impl PinsPanel {
+    pub fn new() -> Self {
+        Self { visible: false }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Shows the panel if visible, returns what to do with the pin
+    /// clicked, if any
+    pub fn view(
+        &mut self,
+        ui: &mut egui::Ui,
+        vault: &NoteVault,
+        current_note: Option<VaultPath>,
+    ) -> Option<PinAction> {
+        let mut action = None;
+        egui::SidePanel::right("pins")
+            .resizable(true)
+            .default_width(200.0)
+            .show_animated_inside(ui, self.visible, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Pinned");
+                    if let Some(path) = current_note {
+                        let pin = Pin::Note { path };
+                        if vault.is_pinned(&pin) {
+                            if ui.small_button("Unpin note").clicked() {
+                                action = Some(PinAction::Unpin(pin));
+                            }
+                        } else if ui.small_button("Pin note").clicked() {
+                            action = Some(PinAction::Pin(pin));
+                        }
+                    }
+                });
+                ui.separator();
+                let pins = vault.get_pins();
+                if pins.is_empty() {
+                    ui.weak("Nothing pinned");
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (position, pin) in pins.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.weak(pin_shortcut(position));
+                            let label = match pin {
+                                Pin::Note { path } => helpers::note_name(path),
+                                Pin::Search { name } => {
+                                    format!("{} {}", fonts::SAVED_SEARCH, name)
+                                }
+                            };
+                            let label = egui::Label::new(label)
+                                .truncate()
+                                .sense(egui::Sense::click());
+                            if ui
+                                .add(label)
+                                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                action = Some(PinAction::Open(pin.to_owned()));
+                            }
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    if ui.small_button("x").on_hover_text("Unpin").clicked() {
+                                        action = Some(PinAction::Unpin(pin.to_owned()));
+                                    }
+                                    if position > 0
+                                        && ui.small_button("^").on_hover_text("Move up").clicked()
+                                    {
+                                        action = Some(PinAction::Move {
+                                            pin: pin.to_owned(),
+                                            position: position - 1,
+                                        });
+                                    }
+                                },
+                            );
+                        });
+                    }
+                });
+            });
+        action
+    }
+}
+
+/// The shortcut to open the pin, only the first nine have one
+pub fn pin_shortcut(position: usize) -> String {
+    if position < 9 {
+        format!("{}+{}", helpers::cmd_ctrl(), position + 1)
+    } else {
+        fonts::PIN.to_string()
+    }
+}
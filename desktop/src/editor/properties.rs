@@ -0,0 +1,133 @@
+use chrono::{DateTime, Local};
+use eframe::egui;
+use kimun_core::{nfs::VaultPath, NoteProperties, NoteVault};
+use log::error;
+
+use crate::helpers;
+
+/// What was done in the panel
+pub enum PropertiesAction {
+    Open(VaultPath),
+    /// The counts are taken from the index, the note has to be saved
+    Refresh,
+}
+
+/// Side panel with the dates and counts of the current note, and the
+/// notes linking to it
+pub struct PropertiesPanel {
+    visible: bool,
+    properties: Option<NoteProperties>,
+    outdated: bool,
+}
+
+impl PropertiesPanel {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            properties: None,
+            outdated: true,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.outdated = true;
+    }
+
+    /// Marks the properties to be loaded again the next time they are shown
+    pub fn note_changed(&mut self) {
+        self.outdated = true;
+    }
+
+    fn load(&mut self, vault: &NoteVault, current_note: Option<VaultPath>) {
+        self.properties = current_note.and_then(|path| {
+            vault
+                .get_note_properties(&path)
+                .inspect_err(|e| error!("Error loading the properties of {}: {}", path, e))
+                .ok()
+        });
+        self.outdated = false;
+    }
+
+    /// Shows the panel if visible, returns what to do after the
+    /// user's action, if anything
+    pub fn view(
+        &mut self,
+        ui: &mut egui::Ui,
+        vault: &NoteVault,
+        current_note: Option<VaultPath>,
+    ) -> Option<PropertiesAction> {
+        if self.visible && self.outdated {
+            self.load(vault, current_note);
+        }
+        let mut action = None;
+        egui::SidePanel::right("properties")
+            .resizable(true)
+            .default_width(200.0)
+            .show_animated_inside(ui, self.visible, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Properties");
+                    if ui.small_button("Refresh").clicked() {
+                        action = Some(PropertiesAction::Refresh);
+                    }
+                });
+                ui.separator();
+                let Some(properties) = &self.properties else {
+                    ui.weak("No note");
+                    return;
+                };
+                let stats = &properties.stats;
+                egui::Grid::new("properties_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let rows = [
+                            ("Created", format_date(properties.created_secs)),
+                            ("Modified", format_date(properties.modified_secs)),
+                            ("Words", stats.words.to_string()),
+                            ("Characters", stats.characters.to_string()),
+                            ("Reading time", format!("{} min", stats.reading_minutes())),
+                            ("Headings", stats.headings.to_string()),
+                            ("Links", properties.links.to_string()),
+                            ("Backlinks", properties.backlinks.len().to_string()),
+                        ];
+                        for (name, value) in rows {
+                            ui.weak(name);
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                    });
+                if properties.backlinks.is_empty() {
+                    return;
+                }
+                ui.separator();
+                ui.strong("Linked from");
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for source in &properties.backlinks {
+                        let label = egui::Label::new(helpers::note_name(source))
+                            .truncate()
+                            .sense(egui::Sense::click());
+                        if ui
+                            .add(label)
+                            .on_hover_text(source.to_string())
+                            .on_hover_cursor(egui::CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            action = Some(PropertiesAction::Open(source.to_owned()));
+                        }
+                    }
+                });
+            });
+        action
+    }
+}
+
+fn format_date(secs: u64) -> String {
+    DateTime::from_timestamp(secs as i64, 0).map_or_else(
+        || "-".to_string(),
+        |date| {
+            date.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        },
+    )
+}
@@ -66,6 +66,14 @@ impl SaveManager {
             .store(true, std::sync::atomic::Ordering::Relaxed);
     }
 
+    /// Forgets the note, so it's not saved again
+    pub fn close(&self) {
+        *self.text.lock().unwrap() = String::new();
+        *self.path.lock().unwrap() = None;
+        self.is_saved
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
     pub fn save(&self) -> anyhow::Result<()> {
         if let Some(path) = &*self.path.lock().unwrap() {
             self.vault.save_note(path, &*self.text.lock().unwrap())?;
@@ -0,0 +1,131 @@
+use chrono::Local;
+use eframe::egui;
+use kimun_core::{
+    nfs::VaultPath,
+    tasks::{NoteTask, TaskFilter},
+    NoteVault,
+};
+use log::error;
+
+/// What was done with a task in the panel
+pub enum TaskAction {
+    Open(VaultPath),
+    SetDone {
+        path: VaultPath,
+        task: NoteTask,
+        done: bool,
+    },
+}
+
+/// Side panel with the tasks of the whole vault, under the note they are in
+pub struct TasksPanel {
+    visible: bool,
+    show_done: bool,
+    tasks: Vec<(VaultPath, NoteTask)>,
+    outdated: bool,
+}
+
+impl TasksPanel {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            show_done: false,
+            tasks: vec![],
+            outdated: true,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.outdated = true;
+    }
+
+    /// Marks the tasks to be loaded again the next time they are shown
+    pub fn tasks_changed(&mut self) {
+        self.outdated = true;
+    }
+
+    fn load(&mut self, vault: &NoteVault) {
+        let filter = TaskFilter {
+            done: if self.show_done { None } else { Some(false) },
+            ..Default::default()
+        };
+        self.tasks = vault.query_tasks(&filter).unwrap_or_else(|e| {
+            error!("Error loading the tasks: {}", e);
+            vec![]
+        });
+        self.outdated = false;
+    }
+
+    /// Shows the panel if visible, returns what to do with the
+    /// task clicked, if any
+    pub fn view(&mut self, ui: &mut egui::Ui, vault: &NoteVault) -> Option<TaskAction> {
+        if self.visible && self.outdated {
+            self.load(vault);
+        }
+        let mut action = None;
+        egui::SidePanel::right("tasks")
+            .resizable(true)
+            .default_width(250.0)
+            .show_animated_inside(ui, self.visible, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Tasks");
+                    if ui.checkbox(&mut self.show_done, "Show done").changed() {
+                        self.outdated = true;
+                    }
+                    if ui.small_button("Reload").clicked() {
+                        self.outdated = true;
+                    }
+                });
+                ui.separator();
+                if self.tasks.is_empty() {
+                    ui.weak("No tasks");
+                }
+                let today = Local::now().date_naive();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    // Tasks are sorted by due date, so the same note may show more than once
+                    let mut current_note: Option<&VaultPath> = None;
+                    for (path, task) in &self.tasks {
+                        if current_note != Some(path) {
+                            ui.add_space(4.0);
+                            let label =
+                                egui::Label::new(egui::RichText::new(path.to_string()).strong())
+                                    .truncate()
+                                    .sense(egui::Sense::click());
+                            if ui
+                                .add(label)
+                                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                action = Some(TaskAction::Open(path.to_owned()));
+                            }
+                            current_note = Some(path);
+                        }
+                        ui.horizontal(|ui| {
+                            let mut done = task.done;
+                            if ui.checkbox(&mut done, "").changed() {
+                                action = Some(TaskAction::SetDone {
+                                    path: path.to_owned(),
+                                    task: task.to_owned(),
+                                    done,
+                                });
+                            }
+                            let text = egui::RichText::new(&task.text);
+                            let text = match task.due {
+                                Some(due) if !task.done && due < today => {
+                                    text.color(ui.visuals().error_fg_color)
+                                }
+                                _ => text,
+                            };
+                            let label = egui::Label::new(text).truncate();
+                            let response = ui.add(label);
+                            if !task.breadcrumb.is_empty() {
+                                response.on_hover_text(task.breadcrumb.join(" > "));
+                            }
+                        });
+                    }
+                });
+            });
+        action
+    }
+}
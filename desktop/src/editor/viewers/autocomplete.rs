@@ -0,0 +1,388 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use eframe::egui;
+use kimun_core::{nfs::VaultPath, NoteVault};
+use log::{debug, error};
+use regex::Regex;
+
+use super::{edit_commands::EditCommand, ID_VIEWER};
+
+const MAX_SUGGESTIONS: usize = 10;
+/// A tag being typed at the cursor, with at least one character
+const TAG_QUERY_REGEX: &str = r"(?:^|\s)#([\p{L}\p{N}_\-/]+)$";
+
+/// What is being typed before the cursor that can be completed
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Trigger {
+    /// After `[[`
+    Note { query: String },
+    /// After `[[Note#`
+    Heading { note: String, query: String },
+    /// After `#`
+    Tag { query: String },
+}
+
+impl Trigger {
+    /// Looks for a trigger in the current line, before the cursor
+    fn find(line_before_cursor: &str) -> Option<Self> {
+        if let Some(open) = line_before_cursor.rfind("[[") {
+            let inner = &line_before_cursor[open + 2..];
+            if !inner.contains(['|', ']']) {
+                return Some(match inner.split_once('#') {
+                    Some((note, query)) => Trigger::Heading {
+                        note: note.to_string(),
+                        query: query.to_string(),
+                    },
+                    None => Trigger::Note {
+                        query: inner.to_string(),
+                    },
+                });
+            }
+        }
+        tag_query_regex()
+            .captures(line_before_cursor)
+            .map(|caps| Trigger::Tag {
+                query: caps[1].to_string(),
+            })
+    }
+
+    fn query(&self) -> &str {
+        match self {
+            Trigger::Note { query } => query,
+            Trigger::Heading { note: _, query } => query,
+            Trigger::Tag { query } => query,
+        }
+    }
+
+    fn closes_link(&self) -> bool {
+        !matches!(self, Trigger::Tag { query: _ })
+    }
+}
+
+fn tag_query_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(TAG_QUERY_REGEX).unwrap())
+}
+
+#[derive(Debug, Clone)]
+struct Suggestion {
+    label: String,
+    detail: String,
+    completion: String,
+    search_text: String,
+}
+
+impl AsRef<str> for Suggestion {
+    fn as_ref(&self) -> &str {
+        &self.search_text
+    }
+}
+
+#[derive(Debug, Clone)]
+struct NoteReference {
+    path: VaultPath,
+    title: String,
+    /// How the note is referenced in a wikilink, the path from
+    /// the vault root without the extension
+    link: String,
+}
+
+/// The data from the vault to complete from, loaded in the background
+#[derive(Default)]
+struct CompletionData {
+    notes: Vec<NoteReference>,
+    tags: Vec<String>,
+    headings: HashMap<VaultPath, Vec<String>>,
+}
+
+/// Suggests notes, headings and tags while typing in the editor.
+/// Typing `[[` suggests notes, `[[Note#` the headings of that note
+/// and `#` the tags already used in the vault.
+pub struct Autocomplete {
+    vault: NoteVault,
+    data: Arc<Mutex<CompletionData>>,
+    trigger: Option<Trigger>,
+    suggestions: Vec<Suggestion>,
+    selected: usize,
+    /// The trigger the user closed the suggestions for, so they are not
+    /// shown again until something else is typed
+    dismissed: Option<Trigger>,
+}
+
+impl Autocomplete {
+    pub fn new(vault: &NoteVault) -> Self {
+        let autocomplete = Self {
+            vault: vault.to_owned(),
+            data: Arc::new(Mutex::new(CompletionData::default())),
+            trigger: None,
+            suggestions: vec![],
+            selected: 0,
+            dismissed: None,
+        };
+        autocomplete.load_data();
+        autocomplete
+    }
+
+    fn load_data(&self) {
+        let vault = self.vault.clone();
+        let data = self.data.clone();
+        std::thread::spawn(move || {
+            debug!("Loading autocomplete data");
+            let notes = match vault.get_notes(&VaultPath::root(), true) {
+                Ok(notes) => notes
+                    .into_iter()
+                    .map(|note| {
+                        let link = note.path.to_string();
+                        let link = link.trim_start_matches('/');
+                        let link = link.strip_suffix(".md").unwrap_or(link).to_string();
+                        NoteReference {
+                            title: note.get_title(),
+                            path: note.path,
+                            link,
+                        }
+                    })
+                    .collect(),
+                Err(e) => {
+                    error!("Error loading the notes to autocomplete: {}", e);
+                    vec![]
+                }
+            };
+            let tags = match vault.get_tags() {
+                Ok(tags) => tags.into_iter().map(|(tag, _uses)| tag).collect(),
+                Err(e) => {
+                    error!("Error loading the tags to autocomplete: {}", e);
+                    vec![]
+                }
+            };
+            let mut data = data.lock().unwrap();
+            data.notes = notes;
+            data.tags = tags;
+        });
+    }
+
+    fn load_headings(&self, path: &VaultPath) {
+        let vault = self.vault.clone();
+        let data = self.data.clone();
+        let path = path.to_owned();
+        // We add an empty entry so we don't load it more than once
+        data.lock().unwrap().headings.insert(path.clone(), vec![]);
+        std::thread::spawn(move || match vault.get_headings(&path) {
+            Ok(headings) => {
+                data.lock().unwrap().headings.insert(path, headings);
+            }
+            Err(e) => error!("Error loading the headings of {}: {}", path, e),
+        });
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.suggestions.is_empty()
+    }
+
+    /// Updates the suggestions with the text before the cursor
+    pub fn update(&mut self, ctx: &egui::Context, text: &str) {
+        let trigger = cursor_line(ctx, text).and_then(|line| Trigger::find(&line));
+        if trigger.is_none() || trigger != self.dismissed {
+            self.dismissed = None;
+        }
+        if trigger != self.trigger {
+            self.selected = 0;
+        }
+        self.suggestions = match &trigger {
+            Some(trigger) if self.dismissed.is_none() => self.suggest(trigger),
+            _ => vec![],
+        };
+        self.selected = self.selected.min(self.suggestions.len().saturating_sub(1));
+        self.trigger = trigger;
+    }
+
+    fn suggest(&self, trigger: &Trigger) -> Vec<Suggestion> {
+        let candidates: Vec<Suggestion> = match trigger {
+            Trigger::Note { query: _ } => {
+                let data = self.data.lock().unwrap();
+                data.notes
+                    .iter()
+                    .map(|note| Suggestion {
+                        label: note.title.clone(),
+                        detail: note.link.clone(),
+                        completion: note.link.clone(),
+                        search_text: format!("{} {}", note.title, note.link),
+                    })
+                    .collect()
+            }
+            Trigger::Heading { note, query: _ } => {
+                let path = self.find_note(note);
+                let Some(path) = path else {
+                    return vec![];
+                };
+                let headings = self.data.lock().unwrap().headings.get(&path).cloned();
+                let Some(headings) = headings else {
+                    self.load_headings(&path);
+                    return vec![];
+                };
+                headings
+                    .into_iter()
+                    .map(|heading| Suggestion {
+                        label: heading.clone(),
+                        detail: String::new(),
+                        completion: heading.clone(),
+                        search_text: heading,
+                    })
+                    .collect()
+            }
+            Trigger::Tag { query: _ } => {
+                let data = self.data.lock().unwrap();
+                data.tags
+                    .iter()
+                    .map(|tag| Suggestion {
+                        label: format!("#{}", tag),
+                        detail: String::new(),
+                        completion: tag.clone(),
+                        search_text: tag.clone(),
+                    })
+                    .collect()
+            }
+        };
+
+        let query = trigger.query();
+        if query.is_empty() {
+            return candidates.into_iter().take(MAX_SUGGESTIONS).collect();
+        }
+        let mut matcher = nucleo::Matcher::new(nucleo::Config::DEFAULT.match_paths());
+        nucleo::pattern::Pattern::parse(
+            query,
+            nucleo::pattern::CaseMatching::Ignore,
+            nucleo::pattern::Normalization::Smart,
+        )
+        .match_list(candidates, &mut matcher)
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(suggestion, _score)| suggestion)
+        .collect()
+    }
+
+    /// Finds the note referenced in a wikilink, either by its
+    /// path or, if there is no path, by its name
+    fn find_note(&self, link: &str) -> Option<VaultPath> {
+        let data = self.data.lock().unwrap();
+        let link = link.trim().trim_start_matches('/');
+        data.notes
+            .iter()
+            .find(|note| note.link == link)
+            .or_else(|| {
+                let name = VaultPath::file_from(link).get_name();
+                data.notes.iter().find(|note| note.path.get_name() == name)
+            })
+            .map(|note| note.path.clone())
+    }
+
+    /// Handles the keys to navigate the suggestions before the text editor
+    /// gets them, returns the command to complete the text if one is selected
+    pub fn manage_keys(&mut self, ctx: &egui::Context, text: &str) -> Option<EditCommand> {
+        if !self.is_open() {
+            return None;
+        }
+        let consume =
+            |key: egui::Key| ctx.input_mut(|input| input.consume_key(egui::Modifiers::NONE, key));
+        if consume(egui::Key::ArrowDown) {
+            self.selected = (self.selected + 1) % self.suggestions.len();
+        } else if consume(egui::Key::ArrowUp) {
+            self.selected = self
+                .selected
+                .checked_sub(1)
+                .unwrap_or(self.suggestions.len() - 1);
+        } else if consume(egui::Key::Escape) {
+            self.close();
+        } else if consume(egui::Key::Enter) || consume(egui::Key::Tab) {
+            return self.complete(ctx, text, self.selected);
+        }
+        None
+    }
+
+    pub fn close(&mut self) {
+        self.dismissed = self.trigger.clone();
+        self.suggestions.clear();
+    }
+
+    fn complete(&mut self, ctx: &egui::Context, text: &str, index: usize) -> Option<EditCommand> {
+        let trigger = self.trigger.clone()?;
+        let suggestion = self.suggestions.get(index)?;
+        let mut completion = suggestion.completion.clone();
+        if trigger.closes_link() && !text_after_cursor(ctx, text).starts_with("]]") {
+            completion.push_str("]]");
+        }
+        let command = EditCommand::ReplaceBeforeCursor {
+            chars: trigger.query().chars().count(),
+            text: completion,
+        };
+        self.close();
+        Some(command)
+    }
+
+    /// Shows the suggestions under the cursor, returns the command to
+    /// complete the text if one is clicked
+    pub fn show(
+        &mut self,
+        ui: &egui::Ui,
+        text: &str,
+        cursor_rect: egui::Rect,
+    ) -> Option<EditCommand> {
+        if !self.is_open() {
+            return None;
+        }
+        let mut clicked = None;
+        egui::Area::new(egui::Id::new("autocomplete"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(cursor_rect.left_bottom())
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(400.0);
+                    for (index, suggestion) in self.suggestions.iter().enumerate() {
+                        let label = if suggestion.detail.is_empty() {
+                            suggestion.label.clone()
+                        } else {
+                            format!("{}  ({})", suggestion.label, suggestion.detail)
+                        };
+                        let response = ui.selectable_label(index == self.selected, label);
+                        if index == self.selected {
+                            response.scroll_to_me(None);
+                        }
+                        if response.clicked() {
+                            clicked = Some(index);
+                        }
+                    }
+                });
+            });
+        let command = clicked.and_then(|index| self.complete(ui.ctx(), text, index));
+        if command.is_some() {
+            ui.ctx()
+                .memory_mut(|mem| mem.request_focus(ID_VIEWER.into()));
+        }
+        command
+    }
+}
+
+fn cursor_position(ctx: &egui::Context, text: &str) -> Option<usize> {
+    let state = egui::TextEdit::load_state(ctx, ID_VIEWER.into())?;
+    let range = state.cursor.char_range()?;
+    if range.primary != range.secondary {
+        return None;
+    }
+    Some(
+        text.char_indices()
+            .nth(range.primary.index)
+            .map_or_else(|| text.len(), |(byte_index, _c)| byte_index),
+    )
+}
+
+fn cursor_line(ctx: &egui::Context, text: &str) -> Option<String> {
+    let cursor = cursor_position(ctx, text)?;
+    let start = text[..cursor].rfind('\n').map_or_else(|| 0, |i| i + 1);
+    Some(text[start..cursor].to_string())
+}
+
+fn text_after_cursor<'a>(ctx: &egui::Context, text: &'a str) -> &'a str {
+    cursor_position(ctx, text).map_or_else(|| "", |cursor| &text[cursor..])
+}
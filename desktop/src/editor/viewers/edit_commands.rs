@@ -0,0 +1,547 @@
+use std::{ops::Range, sync::OnceLock};
+
+use regex::Regex;
+
+/// Matches the prefix of a list item or a quote, with its indentation,
+/// marker and optional task checkbox
+const LIST_PREFIX_REGEX: &str = r"^(?P<indent>[ \t]*)(?P<marker>[-*+]|(?P<number>[0-9]{1,9})(?P<delimiter>[.)])|>)(?P<space>[ \t]+|$)(?P<task>\[[ xX]\][ \t]+)?";
+const LIST_INDENT: &str = "    ";
+const MAX_HEADING_LEVEL: usize = 6;
+
+/// Editing commands applied to the note's text on the current selection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditCommand {
+    ToggleBold,
+    ToggleItalic,
+    ToggleCode,
+    ToggleStrikethrough,
+    InsertLink {
+        title: String,
+        destination: String,
+    },
+    CycleHeading,
+    ToggleTask,
+    Indent,
+    Outdent,
+    ContinueList,
+    /// Replaces the given number of chars before the cursor with the text,
+    /// used to complete what is being typed
+    ReplaceBeforeCursor {
+        chars: usize,
+        text: String,
+    },
+}
+
+impl EditCommand {
+    /// Applies the command to the text on the selection, both the selection
+    /// received and the returned one are in chars, as egui uses them.
+    /// Returns `None` if the command doesn't apply, so the key that
+    /// triggered it can be left to the text editor
+    pub fn apply(&self, text: &mut String, selection: Range<usize>) -> Option<Range<usize>> {
+        let selection = char_to_byte(text, selection.start)..char_to_byte(text, selection.end);
+        let new_selection = match self {
+            EditCommand::ToggleBold => Some(toggle_wrap(text, selection, "**")),
+            EditCommand::ToggleItalic => Some(toggle_wrap(text, selection, "_")),
+            EditCommand::ToggleCode => Some(toggle_wrap(text, selection, "`")),
+            EditCommand::ToggleStrikethrough => Some(toggle_wrap(text, selection, "~~")),
+            EditCommand::InsertLink { title, destination } => {
+                Some(insert_link(text, selection, title, destination))
+            }
+            EditCommand::CycleHeading => Some(cycle_heading(text, selection)),
+            EditCommand::ToggleTask => Some(toggle_task(text, selection)),
+            EditCommand::Indent => indent(text, selection),
+            EditCommand::Outdent => outdent(text, selection),
+            EditCommand::ContinueList => continue_list(text, selection),
+            EditCommand::ReplaceBeforeCursor {
+                chars,
+                text: new_text,
+            } => replace_before_cursor(text, selection, *chars, new_text),
+        }?;
+        Some(byte_to_char(text, new_selection.start)..byte_to_char(text, new_selection.end))
+    }
+}
+
+fn char_to_byte(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or_else(|| text.len(), |(byte_index, _c)| byte_index)
+}
+
+fn byte_to_char(text: &str, byte_index: usize) -> usize {
+    text[..byte_index].chars().count()
+}
+
+fn list_prefix_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(LIST_PREFIX_REGEX).unwrap())
+}
+
+fn line_start(text: &str, position: usize) -> usize {
+    text[..position].rfind('\n').map_or_else(|| 0, |i| i + 1)
+}
+
+fn line_end(text: &str, position: usize) -> usize {
+    text[position..]
+        .find('\n')
+        .map_or_else(|| text.len(), |i| position + i)
+}
+
+/// Returns the start of every line touched by the selection
+fn selected_line_starts(text: &str, selection: &Range<usize>) -> Vec<usize> {
+    let mut starts = vec![line_start(text, selection.start)];
+    let mut position = selection.start;
+    while let Some(i) = text[position..selection.end].find('\n') {
+        position += i + 1;
+        starts.push(position);
+    }
+    starts
+}
+
+/// Applies an edit to each selected line, from the last one so the offsets
+/// of the previous lines are still valid, and moves the selection with it.
+/// The function receives the line start and returns the replaced range and
+/// the new text for that range.
+fn edit_lines<F>(text: &mut String, selection: Range<usize>, mut edit: F) -> Range<usize>
+where
+    F: FnMut(&str, usize) -> Option<(Range<usize>, String)>,
+{
+    let mut new_selection = selection.clone();
+    for start in selected_line_starts(text, &selection).into_iter().rev() {
+        if let Some((range, replacement)) = edit(text, start) {
+            new_selection.start = shift(new_selection.start, &range, &replacement);
+            new_selection.end = shift(new_selection.end, &range, &replacement);
+            text.replace_range(range, &replacement);
+        }
+    }
+    new_selection
+}
+
+/// Where a position ends up after replacing a range with a new text
+fn shift(position: usize, range: &Range<usize>, replacement: &str) -> usize {
+    if position <= range.start {
+        position
+    } else if position >= range.end {
+        position + replacement.len() - range.len()
+    } else {
+        range.start + replacement.len().min(position - range.start)
+    }
+}
+
+fn toggle_wrap(text: &mut String, selection: Range<usize>, marker: &str) -> Range<usize> {
+    let len = marker.len();
+    let selected = &text[selection.clone()];
+    let wrapped_outside =
+        text[..selection.start].ends_with(marker) && text[selection.end..].starts_with(marker);
+    if wrapped_outside {
+        text.replace_range(selection.end..selection.end + len, "");
+        text.replace_range(selection.start - len..selection.start, "");
+        selection.start - len..selection.end - len
+    } else if selected.len() >= len * 2
+        && selected.starts_with(marker)
+        && selected.ends_with(marker)
+    {
+        text.replace_range(selection.end - len..selection.end, "");
+        text.replace_range(selection.start..selection.start + len, "");
+        selection.start..selection.end - len * 2
+    } else {
+        text.insert_str(selection.end, marker);
+        text.insert_str(selection.start, marker);
+        selection.start + len..selection.end + len
+    }
+}
+
+fn insert_link(
+    text: &mut String,
+    selection: Range<usize>,
+    title: &str,
+    destination: &str,
+) -> Range<usize> {
+    let selected = &text[selection.clone()];
+    let link_text = if selected.is_empty() { title } else { selected };
+    let destination = if destination.contains([' ', '(', ')']) {
+        format!("<{}>", destination)
+    } else {
+        destination.to_string()
+    };
+    let link = format!("[{}]({})", link_text, destination);
+    text.replace_range(selection.clone(), &link);
+    let end = selection.start + link.len();
+    end..end
+}
+
+fn cycle_heading(text: &mut String, selection: Range<usize>) -> Range<usize> {
+    edit_lines(text, selection, |text, start| {
+        let line = &text[start..line_end(text, start)];
+        let level = line.chars().take_while(|c| *c == '#').count();
+        let is_heading = level > 0 && line[level..].starts_with(' ');
+        let (prefix_len, new_prefix) = if !is_heading {
+            (0, "# ".to_string())
+        } else if level >= MAX_HEADING_LEVEL {
+            (level + 1, String::new())
+        } else {
+            (level + 1, format!("{} ", "#".repeat(level + 1)))
+        };
+        Some((start..start + prefix_len, new_prefix))
+    })
+}
+
+fn toggle_task(text: &mut String, selection: Range<usize>) -> Range<usize> {
+    edit_lines(text, selection, |text, start| {
+        let line = &text[start..line_end(text, start)];
+        let captures = list_prefix_regex().captures(line);
+        match captures {
+            Some(caps) if caps.name("task").is_some() => {
+                let task = caps.name("task").unwrap();
+                let check = start + task.start() + 1;
+                let new_check = if &text[check..check + 1] == " " {
+                    "x"
+                } else {
+                    " "
+                };
+                Some((check..check + 1, new_check.to_string()))
+            }
+            Some(caps) if &caps["marker"] != ">" => {
+                let end = start + caps.get(0).unwrap().end();
+                let separator = if caps["space"].is_empty() { " " } else { "" };
+                Some((end..end, format!("{}[ ] ", separator)))
+            }
+            _ => {
+                let indent = line.len() - line.trim_start().len();
+                Some((start + indent..start + indent, "- [ ] ".to_string()))
+            }
+        }
+    })
+}
+
+fn is_list_item(text: &str, start: usize) -> bool {
+    let line = &text[start..line_end(text, start)];
+    list_prefix_regex()
+        .captures(line)
+        .is_some_and(|caps| &caps["marker"] != ">")
+}
+
+fn indent(text: &mut String, selection: Range<usize>) -> Option<Range<usize>> {
+    let starts = selected_line_starts(text, &selection);
+    if starts.len() == 1 && !is_list_item(text, starts[0]) {
+        return None;
+    }
+    Some(edit_lines(text, selection, |text, start| {
+        if start == line_end(text, start) {
+            None
+        } else {
+            Some((start..start, LIST_INDENT.to_string()))
+        }
+    }))
+}
+
+fn outdent(text: &mut String, selection: Range<usize>) -> Option<Range<usize>> {
+    let starts = selected_line_starts(text, &selection);
+    if starts.len() == 1 && !is_list_item(text, starts[0]) {
+        return None;
+    }
+    Some(edit_lines(text, selection, |text, start| {
+        let line = &text[start..line_end(text, start)];
+        let remove = if line.starts_with('\t') {
+            1
+        } else {
+            line.chars()
+                .take(LIST_INDENT.len())
+                .take_while(|c| *c == ' ')
+                .count()
+        };
+        if remove > 0 {
+            Some((start..start + remove, String::new()))
+        } else {
+            None
+        }
+    }))
+}
+
+/// When pressing enter in a list item, a new item is started with the same
+/// marker, the next number or an unchecked task. If the current item is
+/// empty, the marker is removed instead, ending the list.
+fn continue_list(text: &mut String, selection: Range<usize>) -> Option<Range<usize>> {
+    if !selection.is_empty() {
+        return None;
+    }
+    let cursor = selection.start;
+    let start = line_start(text, cursor);
+    let line = &text[start..line_end(text, cursor)];
+    let caps = list_prefix_regex().captures(line)?;
+    let prefix = caps.get(0).unwrap();
+    if cursor < start + prefix.end() {
+        return None;
+    }
+
+    if line[prefix.end()..].trim().is_empty() {
+        // Empty item, we end the list
+        let end = line_end(text, cursor);
+        text.replace_range(start..end, "");
+        return Some(start..start);
+    }
+
+    let marker = match caps.name("number") {
+        Some(number) => {
+            let next = number.as_str().parse::<u64>().unwrap_or_default() + 1;
+            format!("{}{}", next, &caps["delimiter"])
+        }
+        None => caps["marker"].to_string(),
+    };
+    let space = if caps["space"].is_empty() {
+        " "
+    } else {
+        &caps["space"]
+    };
+    let task = if caps.name("task").is_some() {
+        "[ ] "
+    } else {
+        ""
+    };
+    let new_item = format!("\n{}{}{}{}", &caps["indent"], marker, space, task);
+    text.insert_str(cursor, &new_item);
+    let new_cursor = cursor + new_item.len();
+    Some(new_cursor..new_cursor)
+}
+
+fn replace_before_cursor(
+    text: &mut String,
+    selection: Range<usize>,
+    chars: usize,
+    new_text: &str,
+) -> Option<Range<usize>> {
+    if !selection.is_empty() {
+        return None;
+    }
+    let cursor = selection.start;
+    let start = if chars == 0 {
+        cursor
+    } else {
+        text[..cursor]
+            .char_indices()
+            .rev()
+            .nth(chars - 1)
+            .map_or(0, |(i, _c)| i)
+    };
+    text.replace_range(start..cursor, new_text);
+    let new_cursor = start + new_text.len();
+    Some(new_cursor..new_cursor)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use super::EditCommand;
+
+    /// Applies the command and returns the new text and selection
+    fn apply(
+        command: EditCommand,
+        text: &str,
+        selection: Range<usize>,
+    ) -> (String, Option<Range<usize>>) {
+        let mut text = text.to_string();
+        let selection = command.apply(&mut text, selection);
+        (text, selection)
+    }
+
+    #[test]
+    fn toggle_wrap_selection() {
+        assert_eq!(
+            ("**hello** world".to_string(), Some(2..7)),
+            apply(EditCommand::ToggleBold, "hello world", 0..5)
+        );
+        // Markers around the selection
+        assert_eq!(
+            ("hello world".to_string(), Some(0..5)),
+            apply(EditCommand::ToggleBold, "**hello** world", 2..7)
+        );
+        // Markers inside the selection
+        assert_eq!(
+            ("hello world".to_string(), Some(0..5)),
+            apply(EditCommand::ToggleBold, "**hello** world", 0..9)
+        );
+        // The selection is in chars
+        assert_eq!(
+            ("~~café~~ au".to_string(), Some(2..6)),
+            apply(EditCommand::ToggleStrikethrough, "café au", 0..4)
+        );
+    }
+
+    #[test]
+    fn toggle_wrap_cursor() {
+        assert_eq!(
+            ("a__b".to_string(), Some(2..2)),
+            apply(EditCommand::ToggleItalic, "ab", 1..1)
+        );
+        assert_eq!(
+            ("ab".to_string(), Some(1..1)),
+            apply(EditCommand::ToggleItalic, "a__b", 2..2)
+        );
+        assert_eq!(
+            ("``".to_string(), Some(1..1)),
+            apply(EditCommand::ToggleCode, "", 0..0)
+        );
+    }
+
+    #[test]
+    fn cycle_heading() {
+        assert_eq!(
+            ("# Title".to_string(), Some(0..0)),
+            apply(EditCommand::CycleHeading, "Title", 0..0)
+        );
+        assert_eq!(
+            ("## Title".to_string(), Some(8..8)),
+            apply(EditCommand::CycleHeading, "# Title", 7..7)
+        );
+        assert_eq!(
+            ("Title".to_string(), Some(0..0)),
+            apply(EditCommand::CycleHeading, "###### Title", 3..3)
+        );
+        // A tag is not a heading
+        assert_eq!(
+            ("# #tag".to_string(), Some(0..0)),
+            apply(EditCommand::CycleHeading, "#tag", 0..0)
+        );
+        assert_eq!(
+            ("# ".to_string(), Some(0..0)),
+            apply(EditCommand::CycleHeading, "", 0..0)
+        );
+        assert_eq!(
+            ("# a\n## b".to_string(), Some(0..8)),
+            apply(EditCommand::CycleHeading, "a\n# b", 0..5)
+        );
+    }
+
+    #[test]
+    fn toggle_task() {
+        assert_eq!(
+            ("- [ ] buy milk".to_string(), Some(10..10)),
+            apply(EditCommand::ToggleTask, "buy milk", 4..4)
+        );
+        assert_eq!(
+            ("- [ ] item".to_string(), Some(10..10)),
+            apply(EditCommand::ToggleTask, "- item", 6..6)
+        );
+        assert_eq!(
+            ("1. [ ] one".to_string(), Some(10..10)),
+            apply(EditCommand::ToggleTask, "1. one", 6..6)
+        );
+        assert_eq!(
+            ("- [x] item".to_string(), Some(10..10)),
+            apply(EditCommand::ToggleTask, "- [ ] item", 10..10)
+        );
+        assert_eq!(
+            ("    - [ ] sub".to_string(), Some(4..4)),
+            apply(EditCommand::ToggleTask, "    - [X] sub", 4..4)
+        );
+        assert_eq!(
+            ("- [ ] ".to_string(), Some(0..0)),
+            apply(EditCommand::ToggleTask, "-", 0..0)
+        );
+        assert_eq!(
+            ("- [ ] ".to_string(), Some(0..0)),
+            apply(EditCommand::ToggleTask, "", 0..0)
+        );
+        assert_eq!(
+            ("- [ ] a\n- [ ] b".to_string(), Some(0..15)),
+            apply(EditCommand::ToggleTask, "- a\n- [x] b", 0..11)
+        );
+    }
+
+    #[test]
+    fn indent_and_outdent() {
+        assert_eq!(
+            ("text".to_string(), None),
+            apply(EditCommand::Indent, "text", 2..2)
+        );
+        assert_eq!(
+            ("    - item".to_string(), Some(6..6)),
+            apply(EditCommand::Indent, "- item", 2..2)
+        );
+        // Empty lines are not indented
+        assert_eq!(
+            ("    - a\n\n    - b".to_string(), Some(0..16)),
+            apply(EditCommand::Indent, "- a\n\n- b", 0..8)
+        );
+
+        assert_eq!(
+            ("    text".to_string(), None),
+            apply(EditCommand::Outdent, "    text", 6..6)
+        );
+        assert_eq!(
+            ("- a".to_string(), Some(3..3)),
+            apply(EditCommand::Outdent, "    - a", 7..7)
+        );
+        assert_eq!(
+            ("- a".to_string(), Some(0..0)),
+            apply(EditCommand::Outdent, "    - a", 2..2)
+        );
+        assert_eq!(
+            ("- a".to_string(), Some(1..1)),
+            apply(EditCommand::Outdent, "\t- a", 2..2)
+        );
+        assert_eq!(
+            ("- a\n- b\n    - c".to_string(), Some(0..15)),
+            apply(EditCommand::Outdent, "- a\n  - b\n        - c", 0..21)
+        );
+    }
+
+    #[test]
+    fn continue_list() {
+        assert_eq!(
+            ("- item\n- ".to_string(), Some(9..9)),
+            apply(EditCommand::ContinueList, "- item", 6..6)
+        );
+        assert_eq!(
+            ("9. nine\n10. ".to_string(), Some(12..12)),
+            apply(EditCommand::ContinueList, "9. nine", 7..7)
+        );
+        assert_eq!(
+            ("1) a\n2) ".to_string(), Some(8..8)),
+            apply(EditCommand::ContinueList, "1) a", 4..4)
+        );
+        assert_eq!(
+            ("- [x] done\n- [ ] ".to_string(), Some(17..17)),
+            apply(EditCommand::ContinueList, "- [x] done", 10..10)
+        );
+        assert_eq!(
+            ("- a\n    - b\n    - ".to_string(), Some(18..18)),
+            apply(EditCommand::ContinueList, "- a\n    - b", 11..11)
+        );
+        assert_eq!(
+            ("> quote\n> ".to_string(), Some(10..10)),
+            apply(EditCommand::ContinueList, "> quote", 7..7)
+        );
+        // In the middle of the item, the rest goes to the new one
+        assert_eq!(
+            ("- it\n- em".to_string(), Some(7..7)),
+            apply(EditCommand::ContinueList, "- item", 4..4)
+        );
+    }
+
+    #[test]
+    fn continue_list_ends_or_skips() {
+        // An empty item ends the list
+        assert_eq!(
+            ("- a\n".to_string(), Some(4..4)),
+            apply(EditCommand::ContinueList, "- a\n- ", 6..6)
+        );
+        assert_eq!(
+            ("    ".to_string(), None),
+            apply(EditCommand::ContinueList, "    ", 4..4)
+        );
+        assert_eq!(
+            ("text".to_string(), None),
+            apply(EditCommand::ContinueList, "text", 4..4)
+        );
+        assert_eq!(
+            ("- item".to_string(), None),
+            apply(EditCommand::ContinueList, "- item", 2..4)
+        );
+        // The cursor is in the marker
+        assert_eq!(
+            ("- item".to_string(), None),
+            apply(EditCommand::ContinueList, "- item", 1..1)
+        );
+    }
+}
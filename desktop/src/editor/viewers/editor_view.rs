@@ -1,4 +1,5 @@
 use std::{
+    ops::Range,
     sync::{Arc, Mutex},
     time::{Duration, SystemTime},
 };
@@ -10,10 +11,55 @@ use log::{debug, error};
 
 use crate::editor::NoteViewer;
 
-use super::{highlighter::MemoizedNoteHighlighter, EditorMessage, ID_VIEWER};
+use super::{
+    autocomplete::Autocomplete,
+    edit_commands::EditCommand,
+    find_bar::FindBar,
+    highlighter::{mark_ranges, MemoizedNoteHighlighter},
+    EditorMessage, ID_VIEWER,
+};
 
 const UPDATE_TITLE_EVERY_MS: u64 = 500;
 
+fn editing_shortcuts() -> Vec<(egui::Modifiers, egui::Key, EditCommand)> {
+    let command_shift = egui::Modifiers::COMMAND | egui::Modifiers::SHIFT;
+    vec![
+        (
+            egui::Modifiers::COMMAND,
+            egui::Key::B,
+            EditCommand::ToggleBold,
+        ),
+        (
+            egui::Modifiers::COMMAND,
+            egui::Key::I,
+            EditCommand::ToggleItalic,
+        ),
+        (
+            egui::Modifiers::COMMAND,
+            egui::Key::E,
+            EditCommand::ToggleCode,
+        ),
+        (
+            command_shift,
+            egui::Key::X,
+            EditCommand::ToggleStrikethrough,
+        ),
+        (command_shift, egui::Key::H, EditCommand::CycleHeading),
+        (
+            egui::Modifiers::COMMAND,
+            egui::Key::L,
+            EditCommand::ToggleTask,
+        ),
+        (egui::Modifiers::NONE, egui::Key::Tab, EditCommand::Indent),
+        (egui::Modifiers::SHIFT, egui::Key::Tab, EditCommand::Outdent),
+        (
+            egui::Modifiers::NONE,
+            egui::Key::Enter,
+            EditCommand::ContinueList,
+        ),
+    ]
+}
+
 pub struct EditorView {
     highlighter: MemoizedNoteHighlighter,
     title: Arc<Mutex<String>>,
@@ -21,10 +67,14 @@ pub struct EditorView {
     title_update: Sender<String>,
     last_title_update: SystemTime,
     pending_title_update: bool,
+    pending_commands: Vec<EditCommand>,
+    autocomplete: Autocomplete,
+    go_to_offset: Option<usize>,
+    find_bar: FindBar,
 }
 
 impl EditorView {
-    pub(super) fn new(path: &VaultPath) -> Self {
+    pub(super) fn new(path: &VaultPath, vault: &NoteVault) -> Self {
         let highlighter = MemoizedNoteHighlighter::default();
         let title = Arc::new(Mutex::new(String::new()));
         let (title_update, receiver) = crossbeam_channel::unbounded::<String>();
@@ -35,6 +85,10 @@ impl EditorView {
             title_update,
             last_title_update: SystemTime::UNIX_EPOCH,
             pending_title_update: true,
+            pending_commands: vec![],
+            autocomplete: Autocomplete::new(vault),
+            go_to_offset: None,
+            find_bar: FindBar::new(),
         };
         editor_view.title_update_loop(receiver);
         editor_view
@@ -50,15 +104,110 @@ impl EditorView {
             }
         });
     }
+
+    /// Applies the editing commands from the shortcuts pressed and the ones
+    /// queued from the editor, returns true if the text changed.
+    /// Shortcuts are only consumed if their command applies, so keys like
+    /// enter or tab keep working as usual outside lists.
+    fn apply_commands(&mut self, ctx: &egui::Context, text: &mut String) -> bool {
+        let mut changed = false;
+        for command in std::mem::take(&mut self.pending_commands) {
+            changed |= apply_command(ctx, text, &command);
+        }
+
+        if !ctx.memory(|mem| mem.has_focus(ID_VIEWER.into())) {
+            return changed;
+        }
+        for (modifiers, key, command) in editing_shortcuts() {
+            let is_shortcut = |event: &egui::Event| {
+                matches!(
+                    event,
+                    egui::Event::Key {
+                        key: event_key,
+                        modifiers: event_modifiers,
+                        pressed: true,
+                        ..
+                    } if *event_key == key && event_modifiers.matches_exact(modifiers)
+                )
+            };
+            // egui-winit also sends a cut for any command+X, that would cut
+            // the text that was just wrapped
+            let is_cut = |event: &egui::Event| {
+                key == egui::Key::X && modifiers.command && matches!(event, egui::Event::Cut)
+            };
+            if ctx.input(|input| input.events.iter().any(is_shortcut))
+                && apply_command(ctx, text, &command)
+            {
+                ctx.input_mut(|input| {
+                    input
+                        .events
+                        .retain(|event| !is_shortcut(event) && !is_cut(event))
+                });
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Selects the byte range of the text, or puts the cursor there if empty
+fn select_text(ctx: &egui::Context, text: &str, range: Range<usize>) {
+    let id = ID_VIEWER.into();
+    let (Some(before), Some(selected)) = (text.get(..range.start), text.get(range)) else {
+        return;
+    };
+    let start = before.chars().count();
+    let end = start + selected.chars().count();
+    let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+    state
+        .cursor
+        .set_char_range(Some(egui::text::CCursorRange::two(
+            egui::text::CCursor::new(start),
+            egui::text::CCursor::new(end),
+        )));
+    state.store(ctx, id);
+}
+
+fn apply_command(ctx: &egui::Context, text: &mut String, command: &EditCommand) -> bool {
+    let id = ID_VIEWER.into();
+    let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+    let selection = state.cursor.char_range().map_or_else(
+        || {
+            let end = text.chars().count();
+            end..end
+        },
+        |range| {
+            let [start, end] = range.sorted();
+            start.index..end.index
+        },
+    );
+    if let Some(new_selection) = command.apply(text, selection) {
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::two(
+                egui::text::CCursor::new(new_selection.start),
+                egui::text::CCursor::new(new_selection.end),
+            )));
+        state.store(ctx, id);
+        true
+    } else {
+        false
+    }
 }
 
 impl NoteViewer for EditorView {
     fn view(&mut self, text: &mut String, ui: &mut eframe::egui::Ui) -> anyhow::Result<bool> {
-        let mut layouter = |ui: &egui::Ui, easymark: &str, wrap_width: f32| {
-            let mut layout_job = self.highlighter.highlight(ui.style(), easymark);
-            layout_job.wrap.max_width = wrap_width;
-            ui.fonts(|f| f.layout_job(layout_job))
-        };
+        if let Some(command) = self.autocomplete.manage_keys(ui.ctx(), text) {
+            self.pending_commands.push(command);
+        }
+        let mut commands_changed = self.apply_commands(ui.ctx(), text);
+        let mut scroll_to_cursor = false;
+        if let Some(offset) = self.go_to_offset.take() {
+            select_text(ui.ctx(), text, offset..offset);
+            ui.ctx()
+                .memory_mut(|mem| mem.request_focus(ID_VIEWER.into()));
+            scroll_to_cursor = true;
+        }
 
         let title = self.title.lock().unwrap().clone();
         egui::TopBottomPanel::top("title")
@@ -70,6 +219,37 @@ impl NoteViewer for EditorView {
                     ui.label(self.path.to_string());
                 })
             });
+        let find_output = self.find_bar.view(ui, text);
+        commands_changed |= find_output.changed;
+        if let Some(range) = find_output.select {
+            select_text(ui.ctx(), text, range);
+            scroll_to_cursor = true;
+        }
+        if find_output.closed {
+            ui.ctx()
+                .memory_mut(|mem| mem.request_focus(ID_VIEWER.into()));
+        }
+
+        let marks = self.find_bar.marks(ui);
+        let marked_len = text.len();
+        let mut layouter = |ui: &egui::Ui, easymark: &str, wrap_width: f32| {
+            let mut layout_job = self.highlighter.highlight(ui.style(), easymark);
+            // The marks are only valid for the text they were found in,
+            // if it was edited on this frame they are updated on the next one
+            if easymark.len() == marked_len {
+                let marks = marks
+                    .iter()
+                    .filter(|(range, _color)| {
+                        easymark.is_char_boundary(range.start)
+                            && easymark.is_char_boundary(range.end)
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                mark_ranges(&mut layout_job, &marks);
+            }
+            layout_job.wrap.max_width = wrap_width;
+            ui.fonts(|f| f.layout_job(layout_job))
+        };
         let output = egui::TextEdit::multiline(text)
             .font(egui::TextStyle::Monospace) // for cursor height
             .code_editor()
@@ -78,13 +258,29 @@ impl NoteViewer for EditorView {
             .desired_width(f32::INFINITY)
             .layouter(&mut layouter)
             .id(ID_VIEWER.into());
-        let response = ui.add_sized(ui.available_size(), output);
+        let output = output.min_size(ui.available_size()).show(ui);
 
-        let text_edit_id = response.id;
-        if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), text_edit_id) {
-            if let Some(range) = state.cursor.char_range() {};
-        };
-        let changed = if response.changed() {
+        if output.response.has_focus() {
+            self.autocomplete.update(ui.ctx(), text);
+        } else if ui.ctx().memory(|mem| mem.focused().is_some()) {
+            // Clicking a suggestion removes the focus from the editor,
+            // so we only close them when another widget takes it
+            self.autocomplete.close();
+        }
+        if let Some(cursor_range) = output.cursor_range {
+            let cursor_rect = output
+                .galley
+                .pos_from_cursor(&cursor_range.primary)
+                .translate(output.galley_pos.to_vec2());
+            if scroll_to_cursor {
+                ui.scroll_to_rect(cursor_rect, Some(egui::Align::TOP));
+            }
+            if let Some(command) = self.autocomplete.show(ui, text, cursor_rect) {
+                commands_changed |= apply_command(ui.ctx(), text, &command);
+            }
+        }
+
+        let changed = if output.response.changed() || commands_changed {
             self.pending_title_update = true;
             true
         } else {
@@ -110,7 +306,12 @@ impl NoteViewer for EditorView {
     }
 
     fn manage_keys(&mut self, ctx: &egui::Context) -> Option<EditorMessage> {
-        if ctx.input_mut(|input| {
+        if ctx.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::F)) {
+            self.find_bar.open();
+            None
+        } else if ctx.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::K)) {
+            Some(EditorMessage::PickLink)
+        } else if ctx.input_mut(|input| {
             input.consume_key(
                 egui::Modifiers {
                     command: true,
@@ -128,13 +329,25 @@ impl NoteViewer for EditorView {
         }
     }
 
+    fn queue_command(&mut self, command: EditCommand) {
+        self.pending_commands.push(command);
+    }
+
+    fn go_to(&mut self, offset: usize) {
+        self.go_to_offset = Some(offset);
+    }
+
     fn init(&mut self, text: String) {
         if let Err(e) = self.title_update.send(text) {
             error!("Error sending an init message for setting the title: {}", e);
         }
     }
 
-    fn view_change_on_content(&self, vault_path: &VaultPath) -> Box<dyn NoteViewer> {
-        Box::new(EditorView::new(vault_path))
+    fn view_change_on_content(
+        &self,
+        vault_path: &VaultPath,
+        vault: &NoteVault,
+    ) -> Box<dyn NoteViewer> {
+        Box::new(EditorView::new(vault_path, vault))
     }
 }
@@ -0,0 +1,219 @@
+use std::ops::Range;
+
+use eframe::egui;
+use kimun_core::find_replace::{FindOptions, TextFinder};
+
+const ID_FIND_QUERY: &str = "Find Query";
+
+/// What the find bar did to the text on the last frame
+#[derive(Default)]
+pub struct FindOutput {
+    pub changed: bool,
+    /// The match to select in the editor, in bytes
+    pub select: Option<Range<usize>>,
+    /// The find bar was closed and the editor should get the focus back
+    pub closed: bool,
+}
+
+/// Bar to find and replace text in the current note
+pub struct FindBar {
+    visible: bool,
+    query: String,
+    replacement: String,
+    options: FindOptions,
+    finder: Option<TextFinder>,
+    error: Option<String>,
+    matches: Vec<Range<usize>>,
+    current: usize,
+    request_focus: bool,
+}
+
+impl FindBar {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            query: String::new(),
+            replacement: String::new(),
+            options: FindOptions::default(),
+            finder: None,
+            error: None,
+            matches: vec![],
+            current: 0,
+            request_focus: false,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.visible = true;
+        self.request_focus = true;
+    }
+
+    /// The matches in the text with the current one marked, for highlighting
+    pub fn marks(&self, ui: &egui::Ui) -> Vec<(Range<usize>, egui::Color32)> {
+        if !self.visible {
+            return vec![];
+        }
+        let visuals = &ui.style().visuals;
+        let match_color = visuals.selection.bg_fill.gamma_multiply(0.4);
+        self.matches
+            .iter()
+            .enumerate()
+            .map(|(index, range)| {
+                let color = if index == self.current {
+                    visuals.warn_fg_color.gamma_multiply(0.5)
+                } else {
+                    match_color
+                };
+                (range.clone(), color)
+            })
+            .collect()
+    }
+
+    fn update_finder(&mut self) {
+        let finder_changed = self.finder.as_ref().is_none_or(|finder| {
+            finder.get_pattern() != self.query || finder.get_options() != &self.options
+        });
+        if finder_changed {
+            self.current = 0;
+            match TextFinder::new(&self.query, self.options.clone()) {
+                Ok(finder) => {
+                    self.finder = Some(finder);
+                    self.error = None;
+                }
+                Err(e) => {
+                    self.finder = None;
+                    self.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    fn select_current(&self) -> Option<Range<usize>> {
+        self.matches.get(self.current).cloned()
+    }
+
+    pub fn view(&mut self, ui: &mut egui::Ui, text: &mut String) -> FindOutput {
+        let mut output = FindOutput::default();
+        if !self.visible {
+            return output;
+        }
+        let mut query_changed = false;
+        let mut step: Option<bool> = None;
+        let mut replace = false;
+        let mut replace_all = false;
+
+        egui::TopBottomPanel::top("find_bar")
+            .resizable(false)
+            .show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let query = ui.add(
+                        egui::TextEdit::singleline(&mut self.query)
+                            .hint_text("Find")
+                            .desired_width(240.0)
+                            .id(ID_FIND_QUERY.into()),
+                    );
+                    if self.request_focus {
+                        query.request_focus();
+                        self.request_focus = false;
+                    }
+                    query_changed |= query.changed();
+                    if query.lost_focus() {
+                        if ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                            step = Some(!ui.input(|input| input.modifiers.shift));
+                            query.request_focus();
+                        } else if ui.input(|input| input.key_pressed(egui::Key::Escape)) {
+                            self.visible = false;
+                        }
+                    }
+                    query_changed |= ui
+                        .toggle_value(&mut self.options.case_sensitive, "Aa")
+                        .on_hover_text("Match case")
+                        .changed();
+                    query_changed |= ui
+                        .toggle_value(&mut self.options.whole_word, "W")
+                        .on_hover_text("Whole word")
+                        .changed();
+                    query_changed |= ui
+                        .toggle_value(&mut self.options.regex, ".*")
+                        .on_hover_text("Regular expression")
+                        .changed();
+                    if ui.button("⬆").on_hover_text("Previous").clicked() {
+                        step = Some(false);
+                    }
+                    if ui.button("⬇").on_hover_text("Next").clicked() {
+                        step = Some(true);
+                    }
+                    if let Some(error) = &self.error {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error);
+                    } else if !self.query.is_empty() {
+                        if self.matches.is_empty() {
+                            ui.label("No results");
+                        } else {
+                            ui.label(format!("{} of {}", self.current + 1, self.matches.len()));
+                        }
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("✖").on_hover_text("Close").clicked() {
+                            self.visible = false;
+                        }
+                    });
+                });
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.replacement)
+                            .hint_text("Replace")
+                            .desired_width(240.0),
+                    );
+                    replace = ui.button("Replace").clicked();
+                    replace_all = ui.button("Replace all").clicked();
+                });
+            });
+
+        if !self.visible {
+            self.matches.clear();
+            output.closed = true;
+            return output;
+        }
+
+        self.update_finder();
+        let Some(finder) = &self.finder else {
+            self.matches.clear();
+            return output;
+        };
+        // The text may have been edited since the last frame
+        self.matches = finder.find_all(text);
+        self.current = self.current.min(self.matches.len().saturating_sub(1));
+        if replace_all {
+            let (new_text, count) = finder.replace_all(text, &self.replacement);
+            if count > 0 {
+                *text = new_text;
+                output.changed = true;
+            }
+        } else if replace {
+            if let Some(range) = self.matches.get(self.current).cloned() {
+                let new_text = finder.replacement_for(text, &range, &self.replacement);
+                text.replace_range(range, &new_text);
+                output.changed = true;
+            }
+        }
+
+        if output.changed {
+            self.matches = finder.find_all(text);
+        }
+        if let Some(forward) = step {
+            if !self.matches.is_empty() {
+                let len = self.matches.len();
+                self.current = if forward {
+                    (self.current + 1) % len
+                } else {
+                    (self.current + len - 1) % len
+                };
+            }
+        }
+        self.current = self.current.min(self.matches.len().saturating_sub(1));
+        if query_changed || step.is_some() || replace {
+            output.select = self.select_current();
+        }
+        output
+    }
+}
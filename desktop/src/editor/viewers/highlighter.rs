@@ -1,214 +1,727 @@
-use eframe::egui;
+use std::{
+    collections::HashMap,
+    ops::Range,
+    sync::{Arc, OnceLock},
+};
+
+use eframe::egui::{self, Color32};
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Theme, ThemeSet},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+/// Notes bigger than this are split in blocks that are highlighted
+/// independently, so editing only re-highlights the blocks that changed
+const LARGE_NOTE_BYTES: usize = 16 * 1024;
+const DARK_THEME: &str = "base16-ocean.dark";
+const LIGHT_THEME: &str = "InspiredGitHub";
 
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub struct Style {
-    /// # heading (large text)
-    pub heading: bool,
-    /// > quoted (slightly dimmer color or other font style)
+    /// # heading, with its level from 1 to 6
+    pub heading: Option<u8>,
+    /// > quoted
     pub quoted: bool,
-    /// `code` (monospace, some other color)
+    /// `code`, code blocks and math
     pub code: bool,
-    /// self.strong* (emphasized, e.g. bold)
+    /// <b>inline html</b> and html blocks
+    pub html: bool,
+    /// **strong**
     pub strong: bool,
-    /// _underline_
-    pub underline: bool,
-    /// ~strikethrough~
-    pub strikethrough: bool,
-    /// /italics/
+    /// *emphasis*
     pub italics: bool,
-    /// $small$
-    pub small: bool,
-    /// ^raised^
-    pub raised: bool,
+    /// ~~strikethrough~~
+    pub strikethrough: bool,
+    /// [links](url), ![images](url), [[wikilinks]] and footnote references
+    pub link: bool,
+    /// The Markdown syntax itself, like `**`, `# ` or `](url)`
+    pub markup: bool,
+    /// List bullets, numbers and task checkboxes
+    pub list_marker: bool,
+    /// Front matter
+    pub metadata: bool,
+    /// The header row of a table
+    pub table_head: bool,
+    /// Color set by the syntax highlighter of a fenced code block
+    pub syntax_color: Option<Color32>,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum SpanKind {
+    Heading(u8),
+    Quote,
+    Code,
+    Html,
+    Strong,
+    Emphasis,
+    Strikethrough,
+    Link,
+    Markup,
+    ListMarker,
+    Metadata,
+    TableHead,
+    Syntax(Color32),
+}
+
+impl SpanKind {
+    fn apply(&self, style: &mut Style) {
+        match self {
+            SpanKind::Heading(level) => style.heading = Some(*level),
+            SpanKind::Quote => style.quoted = true,
+            SpanKind::Code => style.code = true,
+            SpanKind::Html => style.html = true,
+            SpanKind::Strong => style.strong = true,
+            SpanKind::Emphasis => style.italics = true,
+            SpanKind::Strikethrough => style.strikethrough = true,
+            SpanKind::Link => style.link = true,
+            SpanKind::Markup => style.markup = true,
+            SpanKind::ListMarker => style.list_marker = true,
+            SpanKind::Metadata => style.metadata = true,
+            SpanKind::TableHead => style.table_head = true,
+            SpanKind::Syntax(color) => style.syntax_color = Some(*color),
+        }
+    }
 }
 
-/// Highlight easymark, memoizing previous output to save CPU.
-/// In practice, the highlighter is fast enough not to need any caching.
-///
-/// Taken from egui's examples at:
-/// https://github.com/emilk/egui/blob/master/crates/egui_demo_lib/src/easy_mark/easy_mark_highlighter.rs
+type StyledBlock = Arc<Vec<(Range<usize>, Style)>>;
+
+/// Highlights Markdown, memoizing previous output to save CPU.
+/// The note is split in blocks when large enough, and each block is cached
+/// by its text, so only the edited blocks are parsed again.
 #[derive(Default)]
 pub struct MemoizedNoteHighlighter {
     style: egui::Style,
     code: String,
     output: egui::text::LayoutJob,
+    dark_mode: bool,
+    blocks: HashMap<String, StyledBlock>,
 }
 
 impl MemoizedNoteHighlighter {
     pub fn highlight(&mut self, egui_style: &egui::Style, code: &str) -> egui::text::LayoutJob {
         if (&self.style, self.code.as_str()) != (egui_style, code) {
+            // The syntax colors depend on the theme
+            if self.dark_mode != egui_style.visuals.dark_mode {
+                self.dark_mode = egui_style.visuals.dark_mode;
+                self.blocks.clear();
+            }
             self.style = egui_style.clone();
             code.clone_into(&mut self.code);
-            self.output = highlight_note(egui_style, code);
+            self.output = self.highlight_blocks(egui_style, code);
         }
         self.output.clone()
     }
-}
 
-pub fn highlight_note(egui_style: &egui::Style, mut text: &str) -> egui::text::LayoutJob {
-    let mut job = egui::text::LayoutJob::default();
-    let mut style = Style::default();
-    let mut start_of_line = true;
-
-    while !text.is_empty() {
-        if start_of_line && text.starts_with("```") {
-            let end = text.find("\n```").map_or_else(|| text.len(), |i| i + 4);
-            job.append(
-                &text[..end],
-                0.0,
-                format_from_style(
-                    egui_style,
-                    &Style {
-                        code: true,
-                        ..Default::default()
-                    },
-                ),
-            );
-            text = &text[end..];
-            style = Default::default();
-            continue;
+    fn highlight_blocks(&mut self, egui_style: &egui::Style, text: &str) -> egui::text::LayoutJob {
+        let mut job = egui::text::LayoutJob::default();
+        let mut blocks = HashMap::new();
+        for block_range in split_blocks(text) {
+            let block_text = &text[block_range.clone()];
+            let styled = self.blocks.get(block_text).cloned().unwrap_or_else(|| {
+                Arc::new(styled_segments(block_text, egui_style.visuals.dark_mode))
+            });
+            for (range, style) in styled.iter() {
+                job.append(
+                    &block_text[range.clone()],
+                    0.0,
+                    format_from_style(egui_style, style),
+                );
+            }
+            blocks.insert(block_text.to_string(), styled);
         }
+        // We only keep the blocks still present in the note
+        self.blocks = blocks;
+        job
+    }
+}
 
-        if text.starts_with('`') {
-            style.code = true;
-            let end = text[1..]
-                .find(&['`', '\n'][..])
-                .map_or_else(|| text.len(), |i| i + 2);
-            job.append(&text[..end], 0.0, format_from_style(egui_style, &style));
-            text = &text[end..];
-            style.code = false;
-            continue;
+/// Sets a background color to the text in the ranges, like the matches of
+/// a search, splitting the sections of the job where needed.
+/// The ranges must be sorted and not overlap.
+pub fn mark_ranges(job: &mut egui::text::LayoutJob, marks: &[(Range<usize>, egui::Color32)]) {
+    if marks.is_empty() {
+        return;
+    }
+    let mut sections = Vec::with_capacity(job.sections.len() + marks.len() * 2);
+    let mut next_mark = 0;
+    for section in job.sections.drain(..) {
+        let end = section.byte_range.end;
+        let mut start = section.byte_range.start;
+        let mut leading_space = section.leading_space;
+        while start < end {
+            while next_mark < marks.len() && marks[next_mark].0.end <= start {
+                next_mark += 1;
+            }
+            let mut format = section.format.clone();
+            let piece_end = match marks.get(next_mark) {
+                Some((range, color)) if range.start <= start => {
+                    format.background = *color;
+                    range.end.min(end)
+                }
+                Some((range, _color)) if range.start < end => range.start,
+                _ => end,
+            };
+            sections.push(egui::text::LayoutSection {
+                leading_space,
+                byte_range: start..piece_end,
+                format,
+            });
+            leading_space = 0.0;
+            start = piece_end;
         }
+    }
+    job.sections = sections;
+}
+
+/// Splits a note in blocks separated by blank lines, small notes are
+/// returned as a single block. Fenced code, front matter and indented
+/// content following a blank line are never split, so each block parses
+/// the same way it does as part of the whole note, except for reference
+/// links whose definition lives in another block.
+fn split_blocks(text: &str) -> Vec<Range<usize>> {
+    let whole_note = 0..text.len();
+    if text.len() <= LARGE_NOTE_BYTES {
+        return vec![whole_note];
+    }
 
-        let mut skip;
-
-        if text.starts_with('\\') && text.len() >= 2 {
-            skip = 2;
-        } else if start_of_line && text.starts_with(' ') {
-            // we don't preview indentation, because it is confusing
-            skip = 1;
-        } else if start_of_line && text.starts_with("# ") {
-            style.heading = true;
-            skip = 2;
-        } else if start_of_line && text.starts_with("> ") {
-            style.quoted = true;
-            skip = 2;
-            // we don't preview indentation, because it is confusing
-        } else if start_of_line && text.starts_with("- ") {
-            skip = 2;
-            // we don't preview indentation, because it is confusing
-        } else if text.starts_with('*') {
-            skip = 1;
-            if style.strong {
-                // Include the character that is ending this style:
-                job.append(&text[..skip], 0.0, format_from_style(egui_style, &style));
-                text = &text[skip..];
-                skip = 0;
-            }
-            style.strong ^= true;
-        } else if text.starts_with('$') {
-            skip = 1;
-            if style.small {
-                // Include the character that is ending this style:
-                job.append(&text[..skip], 0.0, format_from_style(egui_style, &style));
-                text = &text[skip..];
-                skip = 0;
-            }
-            style.small ^= true;
-        } else if text.starts_with('^') {
-            skip = 1;
-            if style.raised {
-                // Include the character that is ending this style:
-                job.append(&text[..skip], 0.0, format_from_style(egui_style, &style));
-                text = &text[skip..];
-                skip = 0;
-            }
-            style.raised ^= true;
+    let mut blocks = vec![];
+    let mut block_start = 0;
+    let mut offset = 0;
+    let mut fence: Option<String> = None;
+    let mut previous_blank = false;
+    for (line_number, line) in LinesWithEndings::from(text).enumerate() {
+        let trimmed = line.trim_end_matches(['\n', '\r']);
+        let is_blank = trimmed.trim().is_empty();
+        if line_number == 0 && (trimmed == "---" || trimmed == "+++") {
+            fence = Some(trimmed.to_string());
+        } else if let Some(open_fence) = &fence {
+            let closing = trimmed.trim();
+            if closing.starts_with(open_fence.as_str())
+                && closing
+                    .trim_end_matches(open_fence.chars().next().unwrap_or('`'))
+                    .is_empty()
+            {
+                fence = None;
+            }
         } else {
-            skip = 0;
+            let starts_block =
+                previous_blank && !is_blank && !line.starts_with(' ') && !line.starts_with('\t');
+            if starts_block && offset > block_start {
+                blocks.push(block_start..offset);
+                block_start = offset;
+            }
+            let open = trimmed.trim_start();
+            let fence_len = open
+                .chars()
+                .take_while(|c| *c == '`')
+                .count()
+                .max(open.chars().take_while(|c| *c == '~').count());
+            if fence_len >= 3 {
+                fence = Some(open[..fence_len].to_string());
+            }
+        }
+        previous_blank = is_blank;
+        offset += line.len();
+    }
+    if block_start < text.len() || blocks.is_empty() {
+        blocks.push(block_start..text.len());
+    }
+    blocks
+}
+
+struct OpenTag {
+    range: Range<usize>,
+    kind: Option<SpanKind>,
+    gap_kind: SpanKind,
+    code_language: Option<String>,
+    children: Vec<Range<usize>>,
+}
+
+/// Parses the text with pulldown-cmark and returns contiguous segments
+/// covering the whole text with the style each one should be rendered with
+fn styled_segments(text: &str, dark_mode: bool) -> Vec<(Range<usize>, Style)> {
+    let spans = collect_spans(text, dark_mode);
+
+    let mut boundaries = Vec::with_capacity(spans.len() * 2 + 2);
+    boundaries.push(0);
+    boundaries.push(text.len());
+    for (range, _kind) in &spans {
+        boundaries.push(range.start);
+        boundaries.push(range.end);
+    }
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    // Spans sorted by start so we can sweep over the boundaries
+    let mut starts = (0..spans.len()).collect::<Vec<usize>>();
+    starts.sort_by_key(|i| spans[*i].0.start);
+    let mut next_start = 0;
+    let mut active: Vec<usize> = vec![];
+
+    let mut segments: Vec<(Range<usize>, Style)> = vec![];
+    for window in boundaries.windows(2) {
+        let (from, to) = (window[0], window[1]);
+        active.retain(|i| spans[*i].0.end > from);
+        while next_start < starts.len() && spans[starts[next_start]].0.start <= from {
+            let span_index = starts[next_start];
+            if spans[span_index].0.end > from {
+                active.push(span_index);
+            }
+            next_start += 1;
+        }
+        active.sort_unstable();
+        let mut style = Style::default();
+        for i in &active {
+            spans[*i].1.apply(&mut style);
+        }
+        // We merge with the previous segment if they look the same
+        match segments.last_mut() {
+            Some((last_range, last_style)) if *last_style == style => last_range.end = to,
+            _ => segments.push((from..to, style)),
+        }
+    }
+    segments
+}
+
+fn parser_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS
+        | Options::ENABLE_MATH
+        | Options::ENABLE_GFM
+}
+
+/// Walks the Markdown events and collects the ranges of every construct.
+/// The parts of a tag not covered by its children are the syntax characters
+/// (`**`, `[`, `](url)`, `#`, list bullets, table pipes...), so they are
+/// marked as markup.
+fn collect_spans(text: &str, dark_mode: bool) -> Vec<(Range<usize>, SpanKind)> {
+    let mut spans = vec![];
+    let mut stack: Vec<OpenTag> = vec![];
+
+    for (event, range) in Parser::new_ext(text, parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(tag) => {
+                let (kind, gap_kind) = match &tag {
+                    Tag::Heading { level, .. } => {
+                        (Some(SpanKind::Heading(*level as u8)), SpanKind::Markup)
+                    }
+                    Tag::BlockQuote(_) => (Some(SpanKind::Quote), SpanKind::Markup),
+                    Tag::CodeBlock(_) => (Some(SpanKind::Code), SpanKind::Markup),
+                    Tag::HtmlBlock => (Some(SpanKind::Html), SpanKind::Html),
+                    Tag::Item => (None, SpanKind::ListMarker),
+                    Tag::TableHead => (Some(SpanKind::TableHead), SpanKind::Markup),
+                    Tag::Emphasis => (Some(SpanKind::Emphasis), SpanKind::Markup),
+                    Tag::Strong => (Some(SpanKind::Strong), SpanKind::Markup),
+                    Tag::Strikethrough => (Some(SpanKind::Strikethrough), SpanKind::Markup),
+                    Tag::Link { .. } | Tag::Image { .. } => {
+                        (Some(SpanKind::Link), SpanKind::Markup)
+                    }
+                    Tag::MetadataBlock(_) => (Some(SpanKind::Metadata), SpanKind::Markup),
+                    Tag::Paragraph
+                    | Tag::List(_)
+                    | Tag::FootnoteDefinition(_)
+                    | Tag::DefinitionList
+                    | Tag::DefinitionListTitle
+                    | Tag::DefinitionListDefinition
+                    | Tag::Table(_)
+                    | Tag::TableRow
+                    | Tag::TableCell => (None, SpanKind::Markup),
+                };
+                let code_language = match &tag {
+                    Tag::CodeBlock(CodeBlockKind::Fenced(info)) => info
+                        .split_whitespace()
+                        .next()
+                        .map(|language| language.to_string()),
+                    _ => None,
+                };
+                stack.push(OpenTag {
+                    range,
+                    kind,
+                    gap_kind,
+                    code_language,
+                    children: vec![],
+                });
+            }
+            Event::End(_tag_end) => {
+                if let Some(open_tag) = stack.pop() {
+                    close_tag(text, &open_tag, &mut spans, dark_mode);
+                    if let Some(parent) = stack.last_mut() {
+                        parent.children.push(open_tag.range);
+                    }
+                }
+            }
+            Event::Text(_cow_str) => {
+                add_child(&mut stack, range);
+            }
+            Event::Code(_cow_str) => {
+                let code = &text[range.clone()];
+                let ticks = code.chars().take_while(|c| *c == '`').count();
+                let closing_ticks = code.chars().rev().take_while(|c| *c == '`').count();
+                spans.push((range.clone(), SpanKind::Code));
+                if ticks + closing_ticks <= code.len() {
+                    spans.push((range.start..range.start + ticks, SpanKind::Markup));
+                    spans.push((range.end - closing_ticks..range.end, SpanKind::Markup));
+                }
+                add_child(&mut stack, range);
+            }
+            Event::InlineMath(_cow_str) | Event::DisplayMath(_cow_str) => {
+                spans.push((range.clone(), SpanKind::Code));
+                add_child(&mut stack, range);
+            }
+            Event::Html(_cow_str) | Event::InlineHtml(_cow_str) => {
+                spans.push((range.clone(), SpanKind::Html));
+                add_child(&mut stack, range);
+            }
+            Event::FootnoteReference(_cow_str) => {
+                spans.push((range.clone(), SpanKind::Link));
+                add_child(&mut stack, range);
+            }
+            Event::TaskListMarker(_checked) => {
+                spans.push((range.clone(), SpanKind::ListMarker));
+                add_child(&mut stack, range);
+            }
+            Event::Rule => {
+                spans.push((range.clone(), SpanKind::Markup));
+                add_child(&mut stack, range);
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                add_child(&mut stack, range);
+            }
+        }
+    }
+    wikilink_spans(text, &mut spans);
+    spans
+}
+
+fn add_child(stack: &mut [OpenTag], range: Range<usize>) {
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(range);
+    }
+}
+
+fn close_tag(
+    text: &str,
+    open_tag: &OpenTag,
+    spans: &mut Vec<(Range<usize>, SpanKind)>,
+    dark_mode: bool,
+) {
+    if let Some(kind) = open_tag.kind {
+        spans.push((open_tag.range.clone(), kind));
+    }
+
+    let mut position = open_tag.range.start;
+    let gaps = open_tag
+        .children
+        .iter()
+        .map(|child| {
+            let gap = position..child.start.max(position);
+            position = child.end.max(position);
+            gap
+        })
+        .collect::<Vec<Range<usize>>>();
+    let last_gap = position..open_tag.range.end.max(position);
+    for gap in gaps.into_iter().chain([last_gap]) {
+        if !text[gap.clone()].trim().is_empty() {
+            spans.push((gap, open_tag.gap_kind));
+        }
+    }
+
+    if let (Some(language), Some(first), Some(last)) = (
+        &open_tag.code_language,
+        open_tag.children.first(),
+        open_tag.children.last(),
+    ) {
+        code_spans(text, first.start..last.end, language, spans, dark_mode);
+    }
+}
+
+/// Highlights the content of a fenced code block with syntect
+fn code_spans(
+    text: &str,
+    code_range: Range<usize>,
+    language: &str,
+    spans: &mut Vec<(Range<usize>, SpanKind)>,
+    dark_mode: bool,
+) {
+    let syntax_set = syntax_set();
+    let Some(syntax) = syntax_set.find_syntax_by_token(language) else {
+        return;
+    };
+    let mut highlighter = HighlightLines::new(syntax, theme(dark_mode));
+    let mut position = code_range.start;
+    for line in LinesWithEndings::from(&text[code_range]) {
+        match highlighter.highlight_line(line, syntax_set) {
+            Ok(regions) => {
+                for (style, region) in regions {
+                    let foreground = style.foreground;
+                    spans.push((
+                        position..position + region.len(),
+                        SpanKind::Syntax(Color32::from_rgb(
+                            foreground.r,
+                            foreground.g,
+                            foreground.b,
+                        )),
+                    ));
+                    position += region.len();
+                }
+            }
+            Err(_e) => {
+                position += line.len();
+            }
         }
-        // Note: we don't preview underline, strikethrough and italics because it confuses things.
-
-        // Swallow everything up to the next special character:
-        let line_end = text[skip..]
-            .find('\n')
-            .map_or_else(|| text.len(), |i| (skip + i + 1));
-        let end = text[skip..]
-            .find(&['*', '`', '~', '_', '/', '$', '^', '\\', '<', '['][..])
-            .map_or_else(|| text.len(), |i| (skip + i).max(1));
-
-        if line_end <= end {
-            job.append(
-                &text[..line_end],
-                0.0,
-                format_from_style(egui_style, &style),
-            );
-            text = &text[line_end..];
-            start_of_line = true;
-            style = Default::default();
+    }
+}
+
+/// Wikilinks are not CommonMark, pulldown-cmark gives them to us as
+/// plain text split around the brackets, so we look for them in the raw
+/// text, skipping the ones inside code, html or front matter
+fn wikilink_spans(text: &str, spans: &mut Vec<(Range<usize>, SpanKind)>) {
+    let literal_ranges = spans
+        .iter()
+        .filter(|(_range, kind)| {
+            matches!(kind, SpanKind::Code | SpanKind::Html | SpanKind::Metadata)
+        })
+        .map(|(range, _kind)| range.clone())
+        .collect::<Vec<Range<usize>>>();
+    let mut search_from = 0;
+    while let Some(open) = text[search_from..].find("[[") {
+        let start = search_from + open;
+        let Some(close) = text[start + 2..].find("]]") else {
+            break;
+        };
+        let end = start + 2 + close + 2;
+        let link = start..end;
+        let is_literal = literal_ranges
+            .iter()
+            .any(|range| range.start < link.end && link.start < range.end);
+        if text[link.clone()].contains('\n') {
+            // Not closed in the same line, we look again from the next bracket
+            search_from = start + 2;
         } else {
-            job.append(&text[..end], 0.0, format_from_style(egui_style, &style));
-            text = &text[end..];
-            start_of_line = false;
+            if !is_literal {
+                spans.push((link.clone(), SpanKind::Link));
+                spans.push((link.start..link.start + 2, SpanKind::Markup));
+                spans.push((link.end - 2..link.end, SpanKind::Markup));
+            }
+            search_from = end;
         }
     }
+}
 
-    job
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
 }
 
-fn format_from_style(egui_style: &egui::Style, emark_style: &Style) -> egui::text::TextFormat {
-    use egui::{Align, Color32, Stroke, TextStyle};
+fn theme(dark_mode: bool) -> &'static Theme {
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+    let theme_set = THEME_SET.get_or_init(ThemeSet::load_defaults);
+    let name = if dark_mode { DARK_THEME } else { LIGHT_THEME };
+    &theme_set.themes[name]
+}
 
-    let color = if emark_style.strong || emark_style.heading {
+fn format_from_style(egui_style: &egui::Style, note_style: &Style) -> egui::text::TextFormat {
+    use egui::{Align, FontId, Stroke, TextStyle};
+
+    let color = if note_style.markup {
+        egui_style.visuals.weak_text_color()
+    } else if let Some(color) = note_style.syntax_color {
+        color
+    } else if note_style.link {
+        egui_style.visuals.hyperlink_color
+    } else if note_style.list_marker {
+        egui_style.visuals.selection.stroke.color
+    } else if note_style.strong || note_style.heading.is_some() || note_style.table_head {
         egui_style.visuals.strong_text_color()
-    } else if emark_style.quoted {
+    } else if note_style.quoted || note_style.metadata || note_style.html {
         egui_style.visuals.weak_text_color()
     } else {
         egui_style.visuals.text_color()
     };
 
-    let text_style = if emark_style.heading {
-        TextStyle::Heading
-    } else if emark_style.code {
-        TextStyle::Monospace
-    } else if emark_style.small | emark_style.raised {
-        TextStyle::Small
+    let font_id = if let Some(level) = note_style.heading {
+        let heading_size = TextStyle::Heading.resolve(egui_style).size;
+        let body_size = TextStyle::Body.resolve(egui_style).size;
+        // From the heading size for H1 down to the body size for H6
+        let step = (heading_size - body_size).max(0.0) / 5.0;
+        FontId::proportional(heading_size - step * (level.saturating_sub(1)) as f32)
+    } else if note_style.code || note_style.metadata || note_style.html {
+        TextStyle::Monospace.resolve(egui_style)
     } else {
-        TextStyle::Body
+        TextStyle::Body.resolve(egui_style)
     };
 
-    let background = if emark_style.code {
+    let background = if note_style.code {
         egui_style.visuals.code_bg_color
     } else {
         Color32::TRANSPARENT
     };
 
-    let underline = if emark_style.underline {
+    let underline = if note_style.link && !note_style.markup {
         Stroke::new(1.0, color)
     } else {
         Stroke::NONE
     };
 
-    let strikethrough = if emark_style.strikethrough {
+    let strikethrough = if note_style.strikethrough {
         Stroke::new(1.0, color)
     } else {
         Stroke::NONE
     };
 
-    let valign = if emark_style.raised {
-        Align::TOP
-    } else {
-        Align::BOTTOM
-    };
-
     egui::text::TextFormat {
-        font_id: text_style.resolve(egui_style),
+        font_id,
         color,
         background,
-        italics: emark_style.italics,
+        italics: note_style.italics,
         underline,
         strikethrough,
-        valign,
+        valign: Align::BOTTOM,
         ..Default::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use eframe::egui::{self, Color32};
+
+    use super::{mark_ranges, split_blocks, styled_segments, MemoizedNoteHighlighter, Style};
+
+    /// A note big enough to be split, with a block per paragraph
+    fn large_note() -> String {
+        (0..600)
+            .map(|i| format!("Paragraph {} with **bold** text\n\n", i))
+            .collect()
+    }
+
+    /// The style of the first segment containing the text
+    fn style_of(text: &str, segments: &[(Range<usize>, Style)], needle: &str) -> Style {
+        let start = text.find(needle).unwrap();
+        segments
+            .iter()
+            .find(|(range, _style)| range.contains(&start))
+            .map(|(_range, style)| *style)
+            .unwrap()
+    }
+
+    #[test]
+    fn small_note_is_one_block() {
+        let text = "# Title\n\nSome text\n\nMore text";
+        assert_eq!(vec![0..text.len()], split_blocks(text));
+    }
+
+    #[test]
+    fn large_note_is_split_in_paragraphs() {
+        let note = large_note();
+        let blocks = split_blocks(&note);
+        assert_eq!(600, blocks.len());
+        assert_eq!(
+            "Paragraph 0 with **bold** text\n\n",
+            &note[blocks[0].clone()]
+        );
+        // The blocks cover the whole note
+        for pair in blocks.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert_eq!(note.len(), blocks.last().unwrap().end);
+    }
+
+    #[test]
+    fn fenced_code_is_not_split() {
+        let code = "```rust\nfn main() {\n\n    println!();\n}\n```\n\n";
+        let note = format!("{}{}{}", large_note(), code, large_note());
+        let code_start = note.find("```").unwrap();
+        let blocks = split_blocks(&note);
+        let code_block = blocks
+            .iter()
+            .find(|block| block.contains(&code_start))
+            .unwrap();
+        assert_eq!(code, &note[code_block.clone()]);
+    }
+
+    #[test]
+    fn segments_styles() {
+        let text = "# Title **bold**\n\nSee [[note]] and `[[code]]`\n\n- [ ] task";
+        let segments = styled_segments(text, true);
+        assert_eq!(0, segments[0].0.start);
+        assert_eq!(text.len(), segments.last().unwrap().0.end);
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].0.end, pair[1].0.start);
+        }
+
+        let heading = style_of(text, &segments, "Title");
+        assert_eq!(Some(1), heading.heading);
+        assert!(!heading.markup);
+        assert!(style_of(text, &segments, "#").markup);
+        let bold = style_of(text, &segments, "bold");
+        assert!(bold.strong && !bold.markup);
+        assert!(style_of(text, &segments, "**").markup);
+        assert!(style_of(text, &segments, "note").link);
+        let code = style_of(text, &segments, "[[code");
+        assert!(code.code && !code.link);
+        assert!(style_of(text, &segments, "[ ]").list_marker);
+        assert_eq!(Style::default(), style_of(text, &segments, "See"));
+    }
+
+    #[test]
+    fn blocks_are_cached_by_text() {
+        let egui_style = egui::Style::default();
+        let mut highlighter = MemoizedNoteHighlighter::default();
+        let note = large_note();
+        let job = highlighter.highlight(&egui_style, &note);
+        assert_eq!(note, job.text);
+        assert_eq!(600, highlighter.blocks.len());
+
+        let edited = note.replacen("Paragraph 0 ", "# Paragraph 0 ", 1);
+        let job = highlighter.highlight(&egui_style, &edited);
+        assert_eq!(edited, job.text);
+        assert_eq!(600, highlighter.blocks.len());
+        assert!(highlighter
+            .blocks
+            .contains_key("# Paragraph 0 with **bold** text\n\n"));
+        assert!(!highlighter
+            .blocks
+            .contains_key("Paragraph 0 with **bold** text\n\n"));
+        let heading = &highlighter.blocks["# Paragraph 0 with **bold** text\n\n"];
+        assert!(heading
+            .iter()
+            .any(|(_range, style)| style.heading == Some(1)));
+        let paragraph = &highlighter.blocks["Paragraph 1 with **bold** text\n\n"];
+        assert!(paragraph
+            .iter()
+            .all(|(_range, style)| style.heading.is_none()));
+    }
+
+    #[test]
+    fn mark_ranges_splits_sections() {
+        let mut job = egui::text::LayoutJob::default();
+        job.append("0123456789", 0.0, egui::text::TextFormat::default());
+        mark_ranges(&mut job, &[(2..4, Color32::RED), (8..12, Color32::BLUE)]);
+
+        let sections = job
+            .sections
+            .iter()
+            .map(|section| (section.byte_range.clone(), section.format.background))
+            .collect::<Vec<(Range<usize>, Color32)>>();
+        let none = egui::text::TextFormat::default().background;
+        assert_eq!(
+            vec![
+                (0..2, none),
+                (2..4, Color32::RED),
+                (4..8, none),
+                (8..10, Color32::BLUE)
+            ],
+            sections
+        );
+    }
+}
@@ -1,11 +1,15 @@
+use edit_commands::EditCommand;
 use editor_view::EditorView;
 use eframe::egui;
-use kimun_core::nfs::VaultPath;
+use kimun_core::{nfs::VaultPath, NoteVault};
 use rendered_view::RenderedView;
 
 use super::EditorMessage;
 
+mod autocomplete;
+pub mod edit_commands;
 mod editor_view;
+mod find_bar;
 mod highlighter;
 mod rendered_view;
 
@@ -19,10 +23,10 @@ pub enum ViewerType {
 }
 
 impl ViewerType {
-    pub fn get_view(&self) -> Box<dyn NoteViewer> {
+    pub fn get_view(&self, vault: &NoteVault) -> Box<dyn NoteViewer> {
         match self {
             ViewerType::Nothing => Box::new(NoView::new()),
-            ViewerType::Editor(vault_path) => Box::new(EditorView::new(vault_path)),
+            ViewerType::Editor(vault_path) => Box::new(EditorView::new(vault_path, vault)),
             ViewerType::Rendered(vault_path) => Box::new(RenderedView::new(vault_path)),
         }
     }
@@ -32,7 +36,14 @@ pub trait NoteViewer {
     fn view(&mut self, text: &mut String, ui: &mut egui::Ui) -> anyhow::Result<bool>;
     fn init(&mut self, text: String);
     fn manage_keys(&mut self, ctx: &egui::Context) -> Option<EditorMessage>;
-    fn view_change_on_content(&self, vault_path: &VaultPath) -> Box<dyn NoteViewer>;
+    fn queue_command(&mut self, command: EditCommand);
+    /// Moves the view to the byte offset in the note's text
+    fn go_to(&mut self, offset: usize);
+    fn view_change_on_content(
+        &self,
+        vault_path: &VaultPath,
+        vault: &NoteVault,
+    ) -> Box<dyn NoteViewer>;
 }
 
 pub struct NoView {}
@@ -55,9 +66,17 @@ impl NoteViewer for NoView {
         None
     }
 
+    fn queue_command(&mut self, _command: EditCommand) {}
+
+    fn go_to(&mut self, _offset: usize) {}
+
     fn init(&mut self, _text: String) {}
 
-    fn view_change_on_content(&self, vault_path: &VaultPath) -> Box<dyn NoteViewer> {
-        Box::new(EditorView::new(vault_path))
+    fn view_change_on_content(
+        &self,
+        vault_path: &VaultPath,
+        vault: &NoteVault,
+    ) -> Box<dyn NoteViewer> {
+        Box::new(EditorView::new(vault_path, vault))
     }
 }
@@ -1,14 +1,17 @@
-use eframe::egui;
+use eframe::egui::{self, layers::ShapeIdx};
 use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
-use kimun_core::nfs::VaultPath;
+use kimun_core::{nfs::VaultPath, NoteVault};
 
 use crate::editor::NoteViewer;
 
-use super::EditorMessage;
+use super::{edit_commands::EditCommand, EditorMessage};
 
 pub struct RenderedView {
     path: VaultPath,
     cache: CommonMarkCache,
+    /// Where each heading starts in the text, in the order they are shown
+    heading_offsets: Vec<usize>,
+    go_to_heading: Option<usize>,
 }
 
 impl RenderedView {
@@ -17,8 +20,70 @@ impl RenderedView {
         Self {
             path: path.to_owned(),
             cache,
+            heading_offsets: vec![],
+            go_to_heading: None,
         }
     }
+
+    /// Renders the note, and returns where the headings were shown.
+    /// Only what is in view is painted, so to find every heading the
+    /// clipping is lifted while rendering, and set back on what was painted.
+    fn show_with_headings(&mut self, text: &str, ui: &mut egui::Ui) -> Vec<egui::Rect> {
+        let clip_rect = ui.clip_rect();
+        let layer = ui.layer_id();
+        let first_shape = ui
+            .ctx()
+            .graphics(|graphics| graphics.get(layer).map_or(0, |list| list.next_idx().0));
+        ui.scope(|ui| {
+            ui.set_clip_rect(egui::Rect::EVERYTHING);
+            CommonMarkViewer::new().show(ui, &mut self.cache, text);
+        });
+        let body_size = egui::TextStyle::Body.resolve(ui.style()).size;
+        ui.ctx().graphics_mut(|graphics| {
+            let list = graphics.entry(layer);
+            let mut headings = vec![];
+            // The last heading found, and its size, while no other text follows
+            let mut last_heading: Option<(egui::Rect, f32)> = None;
+            for index in first_shape..list.next_idx().0 {
+                list.mutate_shape(ShapeIdx(index), |clipped| {
+                    clipped.clip_rect = clipped.clip_rect.intersect(clip_rect);
+                    let egui::Shape::Text(text_shape) = &clipped.shape else {
+                        return;
+                    };
+                    if text_shape.galley.text().trim().is_empty() {
+                        return;
+                    }
+                    let rect = text_shape.galley.rect.translate(text_shape.pos.to_vec2());
+                    let size = text_shape
+                        .galley
+                        .job
+                        .sections
+                        .first()
+                        .map_or(body_size, |section| section.format.font_id.size);
+                    if size <= body_size {
+                        last_heading = None;
+                        return;
+                    }
+                    // A heading with formatting is shown in pieces in the same row
+                    match last_heading.as_mut() {
+                        Some((last_rect, last_size))
+                            if *last_size == size && rect.top() < last_rect.bottom() =>
+                        {
+                            *last_rect = last_rect.union(rect);
+                            if let Some(heading) = headings.last_mut() {
+                                *heading = *last_rect;
+                            }
+                        }
+                        _ => {
+                            headings.push(rect);
+                            last_heading = Some((rect, size));
+                        }
+                    }
+                });
+            }
+            headings
+        })
+    }
 }
 
 impl NoteViewer for RenderedView {
@@ -31,9 +96,19 @@ impl NoteViewer for RenderedView {
                     ui.heading(self.path.to_string());
                 })
             });
-        let _common_mark_viewer = CommonMarkViewer::new()
-            .show(ui, &mut self.cache, text)
-            .response;
+        // The note is rendered as a whole, so reference links and
+        // footnotes defined in another section still resolve
+        match self.go_to_heading.take() {
+            Some(index) => {
+                let headings = self.show_with_headings(text, ui);
+                if let Some(rect) = headings.get(index) {
+                    ui.scroll_to_rect(*rect, Some(egui::Align::TOP));
+                }
+            }
+            None => {
+                CommonMarkViewer::new().show(ui, &mut self.cache, text);
+            }
+        }
         Ok(false)
     }
 
@@ -56,9 +131,70 @@ impl NoteViewer for RenderedView {
         }
     }
 
-    fn init(&mut self, _text: String) {}
+    fn queue_command(&mut self, _command: EditCommand) {}
+
+    fn go_to(&mut self, offset: usize) {
+        self.go_to_heading = self
+            .heading_offsets
+            .iter()
+            .position(|heading_offset| *heading_offset == offset);
+    }
+
+    fn init(&mut self, text: String) {
+        self.heading_offsets = NoteVault::get_outline(text)
+            .into_iter()
+            .map(|heading| heading.offset)
+            .collect();
+    }
 
-    fn view_change_on_content(&self, vault_path: &VaultPath) -> Box<dyn NoteViewer> {
+    fn view_change_on_content(
+        &self,
+        vault_path: &VaultPath,
+        _vault: &NoteVault,
+    ) -> Box<dyn NoteViewer> {
         Box::new(RenderedView::new(vault_path))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui;
+    use kimun_core::nfs::VaultPath;
+
+    use super::RenderedView;
+
+    #[test]
+    fn headings_out_of_view_are_found() {
+        let paragraphs = "Some text in a paragraph\n\n".repeat(40);
+        let text = format!(
+            "# One\n\nSee [the docs][docs] and a note[^1]\n\n{}## Two **bold**\n\n{}### Three\n\n[docs]: https://example.com\n[^1]: A footnote\n",
+            paragraphs, paragraphs
+        );
+        let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(400.0, 300.0));
+        let input = egui::RawInput {
+            screen_rect: Some(screen),
+            ..Default::default()
+        };
+        let mut view = RenderedView::new(&VaultPath::from("note.md"));
+        let mut headings = vec![];
+
+        let ctx = egui::Context::default();
+        let output = ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    headings = view.show_with_headings(&text, ui);
+                });
+            });
+        });
+
+        assert_eq!(3, headings.len());
+        assert!(headings[0].top() < headings[1].top());
+        assert!(headings[1].top() < headings[2].top());
+        assert!(headings[2].top() > screen.bottom());
+        // What is out of view is clipped again
+        assert!(output
+            .shapes
+            .iter()
+            .all(|shape| shape.clip_rect.bottom() <= screen.bottom()));
+    }
+}
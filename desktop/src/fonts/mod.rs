@@ -45,3 +45,6 @@ pub fn set_fonts(ctx: &egui::Context) {
 pub const NOTE: &str = "\u{E800}";
 pub const DIRECTORY: &str = "\u{E802}";
 pub const ATTACHMENT: &str = "\u{E803}";
+/// From the emoji font that egui includes
+pub const SAVED_SEARCH: &str = "\u{1F50D}";
+pub const PIN: &str = "\u{1F4CC}";
@@ -1,4 +1,5 @@
 use eframe::egui;
+use kimun_core::nfs::VaultPath;
 
 #[cfg(not(target_os = "macos"))]
 pub fn cmd_ctrl() -> String {
@@ -21,3 +22,12 @@ pub fn info_pill<S: AsRef<str>>(ui: &mut egui::Ui, label: S) {
             ui.add(egui::Label::new(label.as_ref()));
         });
 }
+
+/// The file name of the note without the extension, for the lists that
+/// don't load the title from the index
+pub fn note_name(path: &VaultPath) -> String {
+    let name = path.get_name();
+    path.get_extension()
+        .and_then(|extension| name.strip_suffix(&format!(".{}", extension)))
+        .map_or_else(|| name.clone(), str::to_string)
+}
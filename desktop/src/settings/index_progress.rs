@@ -0,0 +1,187 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crossbeam_channel::Receiver;
+use eframe::egui;
+use kimun_core::{
+    error::VaultError,
+    indexer::{IndexProgress, IndexReport, IndexStage},
+    NoteVault, NotesValidation,
+};
+use log::error;
+
+pub enum IndexTask {
+    Reindex,
+    Rebuild,
+}
+
+enum IndexUpdate {
+    Progress(IndexProgress),
+    Finished(Result<IndexReport, VaultError>),
+}
+
+/// Runs the indexing in the background, showing its progress in a modal
+/// that allows cancelling it
+pub struct IndexProgressModal {
+    receiver: Receiver<IndexUpdate>,
+    cancel: Arc<AtomicBool>,
+    progress: Option<IndexProgress>,
+    result: Option<Result<IndexReport, VaultError>>,
+}
+
+impl IndexProgressModal {
+    pub fn start(vault: NoteVault, task: IndexTask) -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancel_flag = cancel.clone();
+        std::thread::spawn(move || {
+            let progress_sender = sender.clone();
+            let on_progress = move |progress: &IndexProgress| {
+                let _ = progress_sender.send(IndexUpdate::Progress(progress.to_owned()));
+                !cancel_flag.load(Ordering::Relaxed)
+            };
+            let result = match task {
+                IndexTask::Reindex => {
+                    vault.index_notes_with_progress(NotesValidation::Full, on_progress)
+                }
+                IndexTask::Rebuild => vault.recreate_index_with_progress(on_progress),
+            };
+            if let Err(e) = sender.send(IndexUpdate::Finished(result)) {
+                error!("Error sending the index result: {}", e);
+            }
+        });
+        Self {
+            receiver,
+            cancel,
+            progress: None,
+            result: None,
+        }
+    }
+
+    /// Shows the modal, returns true once it's closed
+    pub fn view(&mut self, ui: &mut egui::Ui) -> bool {
+        while let Ok(update) = self.receiver.try_recv() {
+            match update {
+                IndexUpdate::Progress(progress) => self.progress = Some(progress),
+                IndexUpdate::Finished(result) => self.result = Some(result),
+            }
+        }
+        let mut close = false;
+        egui::Modal::new(egui::Id::new("Index Progress")).show(ui.ctx(), |ui| {
+            ui.set_width(400.0);
+            ui.heading("Indexing notes");
+            ui.add_space(8.0);
+            match &self.result {
+                None => {
+                    self.show_progress(ui);
+                    // The progress comes from another thread
+                    ui.ctx().request_repaint_after(Duration::from_millis(100));
+                    ui.add_space(8.0);
+                    let cancelling = self.cancel.load(Ordering::Relaxed);
+                    if ui
+                        .add_enabled(!cancelling, egui::Button::new("Cancel"))
+                        .clicked()
+                    {
+                        self.cancel.store(true, Ordering::Relaxed);
+                    }
+                }
+                Some(result) => {
+                    match result {
+                        Ok(report) => {
+                            let notes = self.progress.as_ref().map_or(0, |p| p.seen);
+                            ui.label(format!("Indexed {} notes", notes));
+                            ui.weak(format!(
+                                "Added: {}, updated: {}, removed: {}",
+                                report.added, report.updated, report.removed
+                            ));
+                            show_skipped(ui, report);
+                        }
+                        Err(VaultError::IndexCancelled) => {
+                            ui.label("Indexing cancelled");
+                        }
+                        Err(e) => {
+                            ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+                        }
+                    }
+                    ui.add_space(8.0);
+                    close = ui.button("Close").clicked();
+                }
+            }
+        });
+        close
+    }
+
+    fn show_progress(&self, ui: &mut egui::Ui) {
+        let Some(progress) = &self.progress else {
+            ui.spinner();
+            return;
+        };
+        let (label, fraction) = match progress.stage {
+            IndexStage::Scanning => (format!("Scanning, {} notes found", progress.seen), None),
+            IndexStage::Parsing => (
+                format!("Reading notes {}/{}", progress.parsed, progress.to_parse),
+                Some(ratio(progress.parsed, progress.to_parse)),
+            ),
+            IndexStage::Writing => (
+                format!("Saving changes {}/{}", progress.written, progress.to_write),
+                Some(ratio(progress.written, progress.to_write)),
+            ),
+            IndexStage::Done => ("Done".to_string(), Some(1.0)),
+        };
+        ui.label(label);
+        match fraction {
+            Some(fraction) => {
+                ui.add(egui::ProgressBar::new(fraction).show_percentage());
+            }
+            None => {
+                ui.spinner();
+            }
+        }
+        let eta = progress
+            .eta()
+            .map_or_else(|| "-".to_string(), |eta| format!("{}s", eta.as_secs()));
+        ui.weak(format!(
+            "Elapsed: {}s, remaining: {}",
+            progress.elapsed.as_secs(),
+            eta
+        ));
+    }
+}
+
+fn show_skipped(ui: &mut egui::Ui, report: &IndexReport) {
+    if report.skipped.is_empty() {
+        return;
+    }
+    ui.add_space(8.0);
+    ui.colored_label(
+        ui.visuals().warn_fg_color,
+        format!("{} files couldn't be indexed", report.skipped.len()),
+    );
+    egui::ScrollArea::vertical()
+        .max_height(200.0)
+        .show(ui, |ui| {
+            egui::Grid::new("Skipped Files")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    for file in &report.skipped {
+                        ui.label(file.path.to_string());
+                        ui.weak(&file.reason);
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+fn ratio(done: usize, total: usize) -> f32 {
+    if total == 0 {
+        1.0
+    } else {
+        done as f32 / total as f32
+    }
+}
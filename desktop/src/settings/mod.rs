@@ -1,29 +1,25 @@
+mod index_progress;
 pub mod view;
 
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use std::fs::File;
 
 use anyhow::bail;
-use kimun_core::nfs::VaultPath;
+use kimun_core::{error::VaultError, nfs::VaultPath, NoteVault};
 
 const BASE_CONFIG_FILE: &str = ".note.toml";
-const LAST_PATH_HISTORY_SIZE: usize = 5;
+const LAST_PATH_HISTORY_SIZE: usize = 20;
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct Settings {
     pub last_paths: Vec<VaultPath>,
     pub workspace_dir: Option<PathBuf>,
-}
-
-impl Default for Settings {
-    fn default() -> Self {
-        Self {
-            last_paths: vec![],
-            workspace_dir: None,
-        }
-    }
+    /// Where to store the index DB, by default it's in the cache directory
+    pub db_path: Option<PathBuf>,
+    #[serde(default)]
+    pub hide_file_tree: bool,
 }
 
 impl Settings {
@@ -74,17 +70,26 @@ impl Settings {
         self.workspace_dir = Some(workspace_path.to_owned());
     }
 
+    /// Opens the vault at the workspace path, with the index DB in
+    /// the configured location
+    pub fn open_vault<P: AsRef<Path>>(&self, workspace_path: P) -> Result<NoteVault, VaultError> {
+        match &self.db_path {
+            Some(db_path) => NoteVault::with_db_path(workspace_path, db_path),
+            None => NoteVault::new(workspace_path),
+        }
+    }
+
+    /// Adds a note to the history, the vault knows which paths are notes
+    /// so the caller has to check it
     pub fn add_path_history(&mut self, note_path: &VaultPath) {
-        if note_path.is_note() {
-            // If the path already is in the history, we remove it
-            self.last_paths.retain(|path| !path.eq(note_path));
-            // Maximum size of the path list
-            // removing an element at a position is not very efficient
-            // but since is a short list, shouldn't be a major problem
-            while self.last_paths.len() >= LAST_PATH_HISTORY_SIZE {
-                self.last_paths.remove(0);
-            }
-            self.last_paths.push(note_path.to_owned());
+        // If the path already is in the history, we remove it
+        self.last_paths.retain(|path| !path.eq(note_path));
+        // Maximum size of the path list
+        // removing an element at a position is not very efficient
+        // but since is a short list, shouldn't be a major problem
+        while self.last_paths.len() >= LAST_PATH_HISTORY_SIZE {
+            self.last_paths.remove(0);
         }
+        self.last_paths.push(note_path.to_owned());
     }
 }
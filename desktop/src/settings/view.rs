@@ -1,21 +1,137 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use eframe::egui::{self, CollapsingHeader};
+use kimun_core::{
+    nfs::{ignore_rules::IGNORE_FILE, PathCase, VaultPath},
+    utilities::path_to_string,
+    NoteVault,
+};
 use log::{error, info};
-use kimun_core::{error::VaultError, utilities::path_to_string, NoteVault, NotesValidation};
 
 use crate::{MainView, WindowSwitch};
 
-use super::Settings;
+use super::{
+    index_progress::{IndexProgressModal, IndexTask},
+    Settings,
+};
 
 pub struct SettingsView {
     settings: Settings,
+    indexing: Option<IndexProgressModal>,
+    /// The vault of the workspace, to edit its config
+    vault: Option<NoteVault>,
+    /// The index needs to be validated when going back to the editor
+    workspace_changed: bool,
 }
 
 impl SettingsView {
     pub fn new(settings: &Settings) -> Self {
-        Self {
+        let mut view = Self {
             settings: settings.to_owned(),
+            indexing: None,
+            vault: None,
+            workspace_changed: false,
+        };
+        view.load_vault();
+        view
+    }
+
+    fn load_vault(&mut self) {
+        self.vault = self
+            .settings
+            .workspace_dir
+            .as_ref()
+            .and_then(|workspace_path| {
+                self.settings
+                    .open_vault(workspace_path)
+                    .inspect_err(|e| error!("Error opening the vault: {}", e))
+                    .ok()
+            });
+    }
+
+    fn ignore_rules(&mut self, ui: &mut egui::Ui) {
+        let Some(vault) = self.vault.as_mut() else {
+            ui.weak("Choose a workspace first");
+            return;
+        };
+        let mut config = vault.get_config().to_owned();
+        let rules = &mut config.ignore;
+        let mut changed = ui
+            .checkbox(&mut rules.skip_hidden, "Skip hidden files and folders")
+            .changed();
+        changed |= ui
+            .checkbox(&mut rules.use_gitignore, "Skip files in .gitignore")
+            .changed();
+        ui.weak(format!(
+            "Add a {} file with glob patterns to skip other files",
+            IGNORE_FILE
+        ));
+        ui.label(egui::RichText::new("Excluded Folders:").strong());
+        let mut removed = None;
+        for (index, folder) in rules.excluded_folders.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                    removed = Some(index);
+                }
+                ui.label(folder.to_string());
+            });
+        }
+        if let Some(index) = removed {
+            rules.excluded_folders.remove(index);
+            changed = true;
+        }
+        if ui.button("Add Folder").clicked() {
+            if let Ok(path) = pick_folder_in(&vault.workspace_path) {
+                match VaultPath::from_path(&vault.workspace_path, &path) {
+                    Ok(folder) if !rules.excluded_folders.contains(&folder) => {
+                        rules.excluded_folders.push(folder);
+                        changed = true;
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("The folder must be inside the workspace: {}", e),
+                }
+            }
+        }
+        if changed {
+            match vault.set_config(config) {
+                // The index has to be updated with the new rules
+                Ok(()) => self.workspace_changed = true,
+                Err(e) => error!("Error saving the vault config: {}", e),
+            }
+        }
+    }
+
+    fn note_names(&mut self, ui: &mut egui::Ui) {
+        let Some(vault) = self.vault.as_mut() else {
+            ui.weak("Choose a workspace first");
+            return;
+        };
+        let mut config = vault.get_config().to_owned();
+        let mut ignore_case = config.path_case == PathCase::Insensitive;
+        if ui
+            .checkbox(&mut ignore_case, "Ignore case in note names")
+            .on_hover_text("Notes with the same name in another case are reported when indexing")
+            .changed()
+        {
+            config.path_case = if ignore_case {
+                PathCase::Insensitive
+            } else {
+                PathCase::Sensitive
+            };
+            match vault.set_config(config) {
+                // Checks again for notes with the same name
+                Ok(()) => self.workspace_changed = true,
+                Err(e) => error!("Error saving the vault config: {}", e),
+            }
+        }
+    }
+
+    fn start_indexing(&mut self, task: IndexTask) {
+        if let Some(workspace_path) = &self.settings.workspace_dir {
+            match self.settings.open_vault(workspace_path) {
+                Ok(vault) => self.indexing = Some(IndexProgressModal::start(vault, task)),
+                Err(e) => error!("Error opening the vault: {}", e),
+            }
         }
     }
 
@@ -35,7 +151,6 @@ impl SettingsView {
 impl MainView for SettingsView {
     fn update(&mut self, ui: &mut eframe::egui::Ui) -> anyhow::Result<Option<WindowSwitch>> {
         let mut should_close = false;
-        let mut workspace_changed = false;
         egui::TopBottomPanel::bottom("Settings buttons")
             .resizable(false)
             .min_height(0.0)
@@ -79,59 +194,90 @@ impl MainView for SettingsView {
                                             || true,
                                             |workspace_dir| &path != workspace_dir,
                                         ) {
-                                            workspace_changed = true;
+                                            self.workspace_changed = true;
                                         }
                                         self.settings.set_workspace(&path);
                                         if let Err(e) = self.settings.save_to_disk() {
                                             error!("Error setting the workspace: {}", e);
                                         }
+                                        self.load_vault();
                                     }
                                 }
                             });
                             ui.label(egui::RichText::new("Vault DB:").strong());
+                            let db_label = match &self.settings.db_path {
+                                Some(db_path) => path_to_string(db_path),
+                                None => "<Cache Directory>".to_string(),
+                            };
+                            ui.horizontal(|ui| {
+                                egui::Frame::default()
+                                    .fill(ui.visuals().noninteractive().weak_bg_fill)
+                                    .rounding(ui.visuals().widgets.noninteractive.rounding)
+                                    .show(ui, |ui| {
+                                        ui.label(db_label);
+                                    });
+                                let mut db_changed = false;
+                                if ui.button("Browse").clicked() {
+                                    if let Ok(path) = pick_db_file() {
+                                        self.settings.db_path = Some(path);
+                                        db_changed = true;
+                                    }
+                                }
+                                if ui
+                                    .add_enabled(
+                                        self.settings.db_path.is_some(),
+                                        egui::Button::new("Use Default"),
+                                    )
+                                    .clicked()
+                                {
+                                    self.settings.db_path = None;
+                                    db_changed = true;
+                                }
+                                if db_changed {
+                                    // The new DB needs to be initialized
+                                    self.workspace_changed = true;
+                                    self.load_vault();
+                                    if let Err(e) = self.settings.save_to_disk() {
+                                        error!("Error setting the DB path: {}", e);
+                                    }
+                                }
+                            });
                             ui.horizontal(|ui| {
                                 if self
                                     .add_worspace_button(ui, egui::Button::new("Re-Index"))
                                     .clicked()
                                 {
-                                    if let Some(workspace_path) = &self.settings.workspace_dir {
-                                        let res: Result<_, VaultError> =
-                                            match NoteVault::new(workspace_path) {
-                                                // TODO: Show a modal while executing
-                                                Ok(vault) => {
-                                                    vault.index_notes(NotesValidation::Full)
-                                                }
-                                                Err(e) => Err(e),
-                                            };
-                                        if let Err(e) = res {
-                                            error!("Error reindexing the DB: {}", e);
-                                        }
-                                    };
+                                    self.start_indexing(IndexTask::Reindex);
                                 }
                                 if self
                                     .add_worspace_button(ui, egui::Button::new("Rebuild DB"))
                                     .clicked()
                                 {
-                                    if let Some(workspace_path) = &self.settings.workspace_dir {
-                                        let res: Result<_, VaultError> =
-                                            match NoteVault::new(workspace_path) {
-                                                // TODO: Show a modal while executing
-                                                Ok(vault) => vault.recreate_index(),
-                                                Err(e) => Err(e),
-                                            };
-                                        if let Err(e) = res {
-                                            error!("Error reindexing the DB: {}", e);
-                                        }
-                                    };
+                                    self.start_indexing(IndexTask::Rebuild);
                                 }
                             })
                         });
-                    })
+                    });
+                CollapsingHeader::new("Ignored Files")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.group(|ui| self.ignore_rules(ui));
+                    });
+                CollapsingHeader::new("Note Names")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.group(|ui| self.note_names(ui));
+                    });
             });
         });
+        if let Some(indexing) = self.indexing.as_mut() {
+            if indexing.view(ui) {
+                self.indexing = None;
+            }
+        }
         if should_close {
             Ok(Some(WindowSwitch::Editor {
-                recreate_index: workspace_changed,
+                recreate_index: self.workspace_changed,
             }))
         } else {
             Ok(None)
@@ -147,3 +293,23 @@ fn pick_workspace() -> anyhow::Result<PathBuf> {
 
     Ok(handle.to_path_buf())
 }
+
+fn pick_folder_in(directory: &Path) -> anyhow::Result<PathBuf> {
+    let handle = rfd::FileDialog::new()
+        .set_title("Choose a Folder to Exclude")
+        .set_directory(directory)
+        .pick_folder()
+        .ok_or(anyhow::anyhow!("Dialog Closed"))?;
+
+    Ok(handle.to_path_buf())
+}
+
+fn pick_db_file() -> anyhow::Result<PathBuf> {
+    let handle = rfd::FileDialog::new()
+        .set_title("Choose where to store the index DB")
+        .set_file_name("notes.sqlite")
+        .save_file()
+        .ok_or(anyhow::anyhow!("Dialog Closed"))?;
+
+    Ok(handle.to_path_buf())
+}
@@ -0,0 +1,21 @@
+//! Language server for the notes of a vault, so they can be edited from
+//! any editor with the same index as the desktop app. It talks LSP over
+//! stdin and stdout, the vault is the workspace opened by the editor.
+
+mod server;
+mod text;
+
+use lsp_server::Connection;
+
+fn main() -> anyhow::Result<()> {
+    // stdout is for the protocol, the logs go to stderr
+    env_logger::Builder::new()
+        .filter(Some("kimun_"), log::LevelFilter::Info)
+        .parse_default_env()
+        .init();
+
+    let (connection, io_threads) = Connection::stdio();
+    server::run(connection)?;
+    io_threads.join()?;
+    Ok(())
+}
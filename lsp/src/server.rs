@@ -0,0 +1,706 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::anyhow;
+use kimun_core::{
+    links::{self, LinkKind, NoteLink},
+    nfs::VaultPath,
+    NoteVault, NotesValidation,
+};
+use log::{error, info};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidCreateFiles, DidDeleteFiles,
+        DidOpenTextDocument, DidRenameFiles, DidSaveTextDocument, Notification as LspNotification,
+        PublishDiagnostics,
+    },
+    request::{
+        Completion, GotoDefinition, References, Rename, Request as LspRequest,
+        WorkspaceSymbolRequest,
+    },
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    CompletionTextEdit, Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentChangeOperation,
+    DocumentChanges, FileOperationFilter, FileOperationPattern, FileOperationRegistrationOptions,
+    GotoDefinitionParams, GotoDefinitionResponse, InitializeParams, Location, OneOf,
+    OptionalVersionedTextDocumentIdentifier, Position, PublishDiagnosticsParams, Range,
+    ReferenceParams, RenameFile, RenameParams, ResourceOp, ServerCapabilities, SymbolInformation,
+    SymbolKind, TextDocumentEdit, TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url,
+    WorkspaceEdit, WorkspaceFileOperationsServerCapabilities, WorkspaceServerCapabilities,
+    WorkspaceSymbolParams, WorkspaceSymbolResponse,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::text::{self, CompletionContext};
+
+const MAX_SYMBOLS: usize = 200;
+const DIAGNOSTICS_SOURCE: &str = "kimun";
+
+/// Starts the server with the vault in the editor's workspace, returns
+/// when the editor asks to exit
+pub fn run(connection: Connection) -> anyhow::Result<()> {
+    serve(connection, |workspace| Ok(NoteVault::new(workspace)?))
+}
+
+fn serve<F>(connection: Connection, open_vault: F) -> anyhow::Result<()>
+where
+    F: FnOnce(&Path) -> anyhow::Result<NoteVault>,
+{
+    let capabilities = serde_json::to_value(capabilities())?;
+    let params: InitializeParams = serde_json::from_value(connection.initialize(capabilities)?)?;
+    let workspace = workspace_path(&params)
+        .ok_or_else(|| anyhow!("The editor has no workspace folder to use as the vault"))?;
+    let vault = open_vault(&workspace)?;
+    let report = vault.init_and_validate()?;
+    info!(
+        "Vault at {} indexed, {} notes added, {} updated and {} removed",
+        workspace.to_string_lossy(),
+        report.added,
+        report.updated,
+        report.removed
+    );
+    let mut server = Server {
+        connection: &connection,
+        vault,
+        documents: HashMap::new(),
+    };
+    server.main_loop()
+}
+
+fn capabilities() -> ServerCapabilities {
+    let files = FileOperationRegistrationOptions {
+        filters: vec![FileOperationFilter {
+            scheme: Some("file".to_string()),
+            pattern: FileOperationPattern {
+                glob: "**/*".to_string(),
+                matches: None,
+                options: None,
+            },
+        }],
+    };
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["[".to_string(), "#".to_string()]),
+            ..Default::default()
+        }),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Left(true)),
+        workspace_symbol_provider: Some(OneOf::Left(true)),
+        workspace: Some(WorkspaceServerCapabilities {
+            workspace_folders: None,
+            file_operations: Some(WorkspaceFileOperationsServerCapabilities {
+                did_create: Some(files.clone()),
+                did_rename: Some(files.clone()),
+                did_delete: Some(files),
+                ..Default::default()
+            }),
+        }),
+        ..Default::default()
+    }
+}
+
+#[allow(deprecated)]
+fn workspace_path(params: &InitializeParams) -> Option<std::path::PathBuf> {
+    params
+        .workspace_folders
+        .as_ref()
+        .and_then(|folders| folders.first())
+        .map(|folder| &folder.uri)
+        .or(params.root_uri.as_ref())
+        .and_then(|uri| uri.to_file_path().ok())
+}
+
+fn params<P: DeserializeOwned>(params: Value) -> anyhow::Result<P> {
+    Ok(serde_json::from_value(params)?)
+}
+
+/// Headings are linked by their text or by the slug of it
+fn heading_offset(text: &str, heading: &str) -> Option<usize> {
+    let normalize = |heading: &str| heading.trim().to_lowercase().replace('-', " ");
+    let heading = normalize(heading);
+    NoteVault::get_outline(text)
+        .into_iter()
+        .find(|outline| normalize(&outline.text) == heading)
+        .map(|outline| outline.offset)
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    vault: NoteVault,
+    /// The text of the notes open in the editor, by uri
+    documents: HashMap<Url, String>,
+}
+
+impl Server<'_> {
+    fn main_loop(&mut self) -> anyhow::Result<()> {
+        for message in &self.connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.answer(request);
+                    self.connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => {
+                    let method = notification.method.clone();
+                    if let Err(e) = self.notify(notification) {
+                        error!("Error handling {}: {}", method, e);
+                    }
+                }
+                Message::Response(_response) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn answer(&mut self, request: Request) -> Response {
+        let result = match request.method.as_str() {
+            Completion::METHOD => params(request.params)
+                .and_then(|params| self.completion(params))
+                .and_then(|result| Ok(serde_json::to_value(result)?)),
+            GotoDefinition::METHOD => params(request.params)
+                .and_then(|params| self.definition(params))
+                .and_then(|result| Ok(serde_json::to_value(result)?)),
+            References::METHOD => params(request.params)
+                .and_then(|params| self.references(params))
+                .and_then(|result| Ok(serde_json::to_value(result)?)),
+            Rename::METHOD => params(request.params)
+                .and_then(|params| self.rename(params))
+                .and_then(|result| Ok(serde_json::to_value(result)?)),
+            WorkspaceSymbolRequest::METHOD => params(request.params)
+                .and_then(|params| self.symbols(params))
+                .and_then(|result| Ok(serde_json::to_value(result)?)),
+            method => {
+                return Response::new_err(
+                    request.id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("Unsupported request {}", method),
+                )
+            }
+        };
+        match result {
+            Ok(result) => Response::new_ok(request.id, result),
+            Err(e) => Response::new_err(request.id, ErrorCode::RequestFailed as i32, e.to_string()),
+        }
+    }
+
+    fn notify(&mut self, notification: Notification) -> anyhow::Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams = params(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                self.publish_diagnostics(&uri)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let mut params: DidChangeTextDocumentParams = params(notification.params)?;
+                // The whole text is sent on each change
+                if let Some(change) = params.content_changes.pop() {
+                    let uri = params.text_document.uri;
+                    self.documents.insert(uri.clone(), change.text);
+                    self.publish_diagnostics(&uri)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams = params(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+            }
+            DidSaveTextDocument::METHOD
+            | DidCreateFiles::METHOD
+            | DidRenameFiles::METHOD
+            | DidDeleteFiles::METHOD => {
+                self.vault.index_notes(NotesValidation::Fast)?;
+                // Links in the open notes may go somewhere else now
+                let uris = self.documents.keys().cloned().collect::<Vec<Url>>();
+                for uri in uris {
+                    self.publish_diagnostics(&uri)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn path_of(&self, uri: &Url) -> anyhow::Result<VaultPath> {
+        let file = uri
+            .to_file_path()
+            .map_err(|_e| anyhow!("{} is not a file", uri))?;
+        Ok(VaultPath::from_path(&self.vault.workspace_path, file)?)
+    }
+
+    fn uri_of(&self, path: &VaultPath) -> anyhow::Result<Url> {
+        Url::from_file_path(path.to_pathbuf(&self.vault.workspace_path))
+            .map_err(|_e| anyhow!("{} has no uri", path))
+    }
+
+    /// The text in the editor if the note is open, or the one on disk
+    fn text_of(&self, path: &VaultPath) -> anyhow::Result<String> {
+        let open = self
+            .uri_of(path)
+            .ok()
+            .and_then(|uri| self.documents.get(&uri));
+        match open {
+            Some(text) => Ok(text.clone()),
+            None => Ok(self.vault.get_note_text(path)?),
+        }
+    }
+
+    /// The link at the position, with the note it is in
+    fn link_at(
+        &self,
+        uri: &Url,
+        position: Position,
+    ) -> anyhow::Result<Option<(VaultPath, NoteLink)>> {
+        let path = self.path_of(uri)?;
+        let text = self.text_of(&path)?;
+        let offset = text::offset(&text, position);
+        let link = links::extract_links(&text)
+            .into_iter()
+            .find(|link| link.range.start <= offset && offset < link.range.end);
+        Ok(link.map(|link| (path, link)))
+    }
+
+    /// The note linked at the position, or the note itself if there's
+    /// no link there
+    fn note_at(&self, uri: &Url, position: Position) -> anyhow::Result<Option<VaultPath>> {
+        match self.link_at(uri, position)? {
+            Some((path, link)) => Ok(self.vault.resolve_link(&path, &link)?),
+            None => Ok(Some(self.path_of(uri)?)),
+        }
+    }
+
+    fn publish_diagnostics(&self, uri: &Url) -> anyhow::Result<()> {
+        let (Some(text), Ok(path)) = (self.documents.get(uri), self.path_of(uri)) else {
+            return Ok(());
+        };
+        if !self.vault.is_note(&path) {
+            return Ok(());
+        }
+        let diagnostics = self
+            .vault
+            .get_broken_links(&path, text)?
+            .into_iter()
+            .map(|link| Diagnostic {
+                range: text::range(text, &link.range),
+                severity: Some(DiagnosticSeverity::WARNING),
+                source: Some(DIAGNOSTICS_SOURCE.to_string()),
+                message: format!("There's no note at {}", link.target),
+                ..Default::default()
+            })
+            .collect();
+        let params = PublishDiagnosticsParams::new(uri.clone(), diagnostics, None);
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))?;
+        Ok(())
+    }
+
+    fn completion(&self, params: CompletionParams) -> anyhow::Result<Option<CompletionResponse>> {
+        let position = params.text_document_position.position;
+        let uri = params.text_document_position.text_document.uri;
+        let Some(text) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+        let cursor = text::offset(text, position);
+        let line_start = text[..cursor].rfind('\n').map_or(0, |newline| newline + 1);
+        let Some(context) = CompletionContext::find(&text[line_start..cursor]) else {
+            return Ok(None);
+        };
+        // What's typed is replaced by the completion
+        let replace = Range::new(
+            text::position(text, cursor - context.typed().len()),
+            position,
+        );
+        let item = |label: String, detail: String, kind: CompletionItemKind| CompletionItem {
+            filter_text: Some(format!("{} {}", label, detail)),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                replace,
+                label.clone(),
+            ))),
+            label,
+            detail: Some(detail),
+            kind: Some(kind),
+            ..Default::default()
+        };
+
+        let items = match &context {
+            CompletionContext::Note { .. } => {
+                let notes = self.vault.get_note_names()?;
+                let mut names: HashMap<String, usize> = HashMap::new();
+                for note in &notes {
+                    *names.entry(note_link_name(&note.path)).or_default() += 1;
+                }
+                notes
+                    .iter()
+                    .map(|note| {
+                        let name = note_link_name(&note.path);
+                        // The path tells apart the notes with the same name
+                        let label = if names.get(&name).is_some_and(|count| *count > 1) {
+                            strip_extension(note.path.to_string().trim_start_matches('/'))
+                        } else {
+                            strip_extension(&note.path.get_name())
+                        };
+                        item(label, note.get_title(), CompletionItemKind::FILE)
+                    })
+                    .collect::<Vec<CompletionItem>>()
+            }
+            CompletionContext::Heading { note, .. } => {
+                let path = self.path_of(&uri)?;
+                let headings = if note.is_empty() {
+                    NoteVault::get_outline(text)
+                        .into_iter()
+                        .map(|heading| heading.text)
+                        .collect()
+                } else {
+                    let link = NoteLink {
+                        kind: LinkKind::Wiki,
+                        target: note.to_owned(),
+                        heading: None,
+                        range: 0..0,
+                        target_range: 0..0,
+                    };
+                    match self.vault.resolve_link(&path, &link)? {
+                        Some(linked) => self.vault.get_headings(&linked)?,
+                        None => vec![],
+                    }
+                };
+                headings
+                    .into_iter()
+                    .map(|heading| item(heading, note.to_owned(), CompletionItemKind::REFERENCE))
+                    .collect()
+            }
+            CompletionContext::Tag { .. } => self
+                .vault
+                .get_tags()?
+                .into_iter()
+                .map(|(tag, count)| {
+                    item(tag, format!("{} notes", count), CompletionItemKind::KEYWORD)
+                })
+                .collect(),
+        };
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+
+    fn definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> anyhow::Result<Option<GotoDefinitionResponse>> {
+        let position = params.text_document_position_params;
+        let Some((path, link)) = self.link_at(&position.text_document.uri, position.position)?
+        else {
+            return Ok(None);
+        };
+        let Some(linked) = self.vault.resolve_link(&path, &link)? else {
+            return Ok(None);
+        };
+        let text = self.text_of(&linked)?;
+        let offset = link
+            .heading
+            .as_ref()
+            .and_then(|heading| heading_offset(&text, heading))
+            .unwrap_or_default();
+        let location = Location::new(self.uri_of(&linked)?, text::range(&text, &(offset..offset)));
+        Ok(Some(GotoDefinitionResponse::Scalar(location)))
+    }
+
+    fn references(&self, params: ReferenceParams) -> anyhow::Result<Option<Vec<Location>>> {
+        let position = params.text_document_position;
+        let Some(note) = self.note_at(&position.text_document.uri, position.position)? else {
+            return Ok(None);
+        };
+        let mut texts: HashMap<VaultPath, String> = HashMap::new();
+        let mut locations = vec![];
+        for (source, link) in self.vault.get_backlinks(&note)? {
+            if !texts.contains_key(&source) {
+                texts.insert(source.clone(), self.text_of(&source)?);
+            }
+            let text = &texts[&source];
+            locations.push(Location::new(
+                self.uri_of(&source)?,
+                text::range(text, &link.range),
+            ));
+        }
+        Ok(Some(locations))
+    }
+
+    /// Renames the note at the position, or the one linked there, and
+    /// changes the links to it
+    fn rename(&self, params: RenameParams) -> anyhow::Result<Option<WorkspaceEdit>> {
+        let position = params.text_document_position;
+        let Some(note) = self.note_at(&position.text_document.uri, position.position)? else {
+            return Ok(None);
+        };
+        let new_name = params.new_name.trim();
+        let renamed = if new_name.contains('/') {
+            VaultPath::from(new_name)
+        } else {
+            note.get_parent_path().0.append(&VaultPath::from(new_name))
+        };
+        // The same extension if the new name has none
+        let renamed = match note.get_extension() {
+            Some(extension) if !self.vault.is_note(&renamed) => {
+                VaultPath::from(format!("{}.{}", renamed, extension))
+            }
+            _ => renamed,
+        };
+        if self.vault.exists(&renamed).is_some() {
+            return Err(anyhow!("There's already something at {}", renamed));
+        }
+
+        let mut operations = vec![];
+        for (source, edits) in self.vault.get_rename_edits(&note, &renamed)? {
+            let text = self.text_of(&source)?;
+            let edits = edits
+                .into_iter()
+                .map(|edit| OneOf::Left(TextEdit::new(text::range(&text, &edit.range), edit.text)))
+                .collect();
+            operations.push(DocumentChangeOperation::Edit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    uri: self.uri_of(&source)?,
+                    version: None,
+                },
+                edits,
+            }));
+        }
+        // The edits are to the note before moving it
+        operations.push(DocumentChangeOperation::Op(ResourceOp::Rename(
+            RenameFile {
+                old_uri: self.uri_of(&note)?,
+                new_uri: self.uri_of(&renamed)?,
+                options: None,
+                annotation_id: None,
+            },
+        )));
+        Ok(Some(WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Operations(operations)),
+            ..Default::default()
+        }))
+    }
+
+    #[allow(deprecated)]
+    fn symbols(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> anyhow::Result<Option<WorkspaceSymbolResponse>> {
+        let query = params.query.to_lowercase();
+        let mut texts: HashMap<VaultPath, String> = HashMap::new();
+        let mut symbols = vec![];
+        for (path, mut breadcrumb) in self.vault.search_headings(&query, MAX_SYMBOLS)? {
+            // The text may be in one of the parents only
+            let Some(heading) = breadcrumb.pop() else {
+                continue;
+            };
+            if !heading.to_lowercase().contains(&query) {
+                continue;
+            }
+            if !texts.contains_key(&path) {
+                texts.insert(path.clone(), self.text_of(&path)?);
+            }
+            let text = &texts[&path];
+            let offset = heading_offset(text, &heading).unwrap_or_default();
+            breadcrumb.insert(0, path.to_string());
+            symbols.push(SymbolInformation {
+                name: heading,
+                kind: SymbolKind::STRING,
+                tags: None,
+                deprecated: None,
+                location: Location::new(self.uri_of(&path)?, text::range(text, &(offset..offset))),
+                container_name: Some(breadcrumb.join(" > ")),
+            });
+        }
+        Ok(Some(WorkspaceSymbolResponse::Flat(symbols)))
+    }
+}
+
+/// The name of a note as written in a wiki link, to tell when two have
+/// the same one
+fn note_link_name(path: &VaultPath) -> String {
+    strip_extension(&path.get_name()).to_lowercase()
+}
+
+fn strip_extension(name: &str) -> String {
+    name.rsplit_once('.')
+        .filter(|(stem, _extension)| !stem.is_empty())
+        .map_or(name, |(stem, _extension)| stem)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_server::{Connection, Message, Notification, Request, RequestId};
+    use lsp_types::{
+        notification::{
+            DidOpenTextDocument, Exit, Initialized, Notification as LspNotification,
+            PublishDiagnostics,
+        },
+        request::{
+            Completion, GotoDefinition, Initialize, References, Rename, Request as LspRequest,
+            Shutdown,
+        },
+        CompletionResponse, GotoDefinitionResponse, Location, PublishDiagnosticsParams, Url,
+        WorkspaceEdit,
+    };
+    use serde_json::{json, Value};
+
+    use kimun_core::{
+        test_utils::{db_path, vault_files, workspace},
+        NoteVault,
+    };
+
+    use super::serve;
+
+    struct Client {
+        connection: Connection,
+        next_id: i32,
+    }
+
+    impl Client {
+        fn request(&mut self, method: &str, params: Value) -> Value {
+            self.next_id += 1;
+            let id = RequestId::from(self.next_id);
+            let request = Request::new(id.clone(), method.to_string(), params);
+            self.connection
+                .sender
+                .send(Message::Request(request))
+                .unwrap();
+            loop {
+                match self.connection.receiver.recv().unwrap() {
+                    Message::Response(response) if response.id == id => {
+                        assert!(response.error.is_none(), "{:?}", response.error);
+                        return response.result.unwrap_or_default();
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        fn notify(&self, method: &str, params: Value) {
+            let notification = Notification::new(method.to_string(), params);
+            self.connection
+                .sender
+                .send(Message::Notification(notification))
+                .unwrap();
+        }
+
+        fn diagnostics(&self) -> PublishDiagnosticsParams {
+            loop {
+                if let Message::Notification(notification) =
+                    self.connection.receiver.recv().unwrap()
+                {
+                    if notification.method == PublishDiagnostics::METHOD {
+                        return serde_json::from_value(notification.params).unwrap();
+                    }
+                }
+            }
+        }
+    }
+
+    fn position(uri: &Url, line: u32, character: u32) -> Value {
+        json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character },
+        })
+    }
+
+    #[test]
+    fn answer_the_editor() {
+        let home_text = "# Home\n\n[[plan#Goals]] and [[Missing]]\n";
+        let dir = vault_files(&[
+            ("projects/plan.md", "# Plan\n\n## Goals\n\nSome #work"),
+            ("home.md", home_text),
+        ]);
+        let workspace = workspace(&dir);
+        let db_path = db_path(&dir);
+
+        let (server, client) = Connection::memory();
+        let handle = std::thread::spawn(move || {
+            serve(server, |workspace| {
+                Ok(NoteVault::with_db_path(workspace, db_path)?)
+            })
+        });
+        let mut client = Client {
+            connection: client,
+            next_id: 0,
+        };
+        let root = Url::from_file_path(&workspace).unwrap();
+        client.request(
+            Initialize::METHOD,
+            json!({ "capabilities": {}, "rootUri": root }),
+        );
+        client.notify(Initialized::METHOD, json!({}));
+
+        let home = Url::from_file_path(workspace.join("home.md")).unwrap();
+        client.notify(
+            DidOpenTextDocument::METHOD,
+            json!({ "textDocument": {
+                "uri": home, "languageId": "markdown", "version": 1, "text": home_text
+            }}),
+        );
+        let diagnostics = client.diagnostics();
+        assert_eq!(1, diagnostics.diagnostics.len());
+        assert_eq!(2, diagnostics.diagnostics[0].range.start.line);
+        assert_eq!(19, diagnostics.diagnostics[0].range.start.character);
+
+        let definition: GotoDefinitionResponse =
+            serde_json::from_value(client.request(GotoDefinition::METHOD, position(&home, 2, 3)))
+                .unwrap();
+        let plan = Url::from_file_path(workspace.join("projects/plan.md")).unwrap();
+        let GotoDefinitionResponse::Scalar(location) = definition else {
+            panic!("One location expected");
+        };
+        assert_eq!(plan, location.uri);
+        // At the heading
+        assert_eq!(2, location.range.start.line);
+
+        let references = client.request(
+            References::METHOD,
+            json!({
+                "textDocument": { "uri": plan },
+                "position": { "line": 0, "character": 0 },
+                "context": { "includeDeclaration": false },
+            }),
+        );
+        let references: Vec<Location> = serde_json::from_value(references).unwrap();
+        assert_eq!(1, references.len());
+        assert_eq!(home, references[0].uri);
+
+        let completion: CompletionResponse =
+            serde_json::from_value(client.request(Completion::METHOD, position(&home, 2, 2)))
+                .unwrap();
+        let CompletionResponse::Array(items) = completion else {
+            panic!("A list of items expected");
+        };
+        let mut labels = items
+            .iter()
+            .map(|item| item.label.as_str())
+            .collect::<Vec<&str>>();
+        labels.sort();
+        assert_eq!(vec!["home", "plan"], labels);
+
+        let rename = client.request(
+            Rename::METHOD,
+            json!({
+                "textDocument": { "uri": home },
+                "position": { "line": 2, "character": 3 },
+                "newName": "Roadmap",
+            }),
+        );
+        let rename: WorkspaceEdit = serde_json::from_value(rename).unwrap();
+        let rename = serde_json::to_value(rename.document_changes).unwrap();
+        assert_eq!("Roadmap", rename[0]["edits"][0]["newText"]);
+        assert_eq!(
+            Url::from_file_path(workspace.join("projects/Roadmap.md")).unwrap(),
+            Url::parse(rename[1]["newUri"].as_str().unwrap()).unwrap()
+        );
+
+        client.request(Shutdown::METHOD, Value::Null);
+        client.notify(Exit::METHOD, Value::Null);
+        handle.join().unwrap().unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use lsp_types::{Position, Range};
+
+/// What is being written at the cursor that can be completed, with the
+/// text typed so far
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompletionContext {
+    /// After `[[`
+    Note { typed: String },
+    /// After `[[Note#`, the note is empty for a heading in the same note
+    Heading { note: String, typed: String },
+    /// After `#`
+    Tag { typed: String },
+}
+
+impl CompletionContext {
+    /// Looks at the line up to the cursor
+    pub fn find(line: &str) -> Option<Self> {
+        if let Some(open) = line.rfind("[[") {
+            let link = &line[open + 2..];
+            if !link.contains("]]") {
+                if link.contains('|') {
+                    return None;
+                }
+                return Some(match link.split_once('#') {
+                    Some((note, typed)) => Self::Heading {
+                        note: note.trim().to_string(),
+                        typed: typed.to_string(),
+                    },
+                    None => Self::Note {
+                        typed: link.to_string(),
+                    },
+                });
+            }
+        }
+        let word_start = line
+            .rfind(|c: char| c.is_whitespace())
+            .map_or(0, |space| space + 1);
+        let typed = line[word_start..].strip_prefix('#')?;
+        // A `#` at the start of the line is a heading
+        let is_heading = word_start == 0 && typed.is_empty();
+        if is_heading || typed.contains(['#', '[', ']', '(', ')']) {
+            return None;
+        }
+        Some(Self::Tag {
+            typed: typed.to_string(),
+        })
+    }
+
+    pub fn typed(&self) -> &str {
+        match self {
+            Self::Note { typed } | Self::Heading { typed, .. } | Self::Tag { typed } => typed,
+        }
+    }
+}
+
+/// The position in the text, LSP counts the columns in UTF-16 units
+pub fn position(text: &str, offset: usize) -> Position {
+    let offset = offset.min(text.len());
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+pub fn range(text: &str, range: &std::ops::Range<usize>) -> Range {
+    Range {
+        start: position(text, range.start),
+        end: position(text, range.end),
+    }
+}
+
+/// The byte offset of the position, the end of the line or the text if
+/// it is past them
+pub fn offset(text: &str, position: Position) -> usize {
+    let line_start = if position.line == 0 {
+        0
+    } else {
+        match text.match_indices('\n').nth(position.line as usize - 1) {
+            Some((newline, _)) => newline + 1,
+            None => return text.len(),
+        }
+    };
+    let line = text[line_start..].split('\n').next().unwrap_or_default();
+    let mut units = 0;
+    for (index, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + index;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Position;
+
+    use super::{offset, position, CompletionContext};
+
+    #[test]
+    fn positions_in_utf16() {
+        let text = "# Kimün\n\n😀 [[Note]]";
+        let link = text.find("[[").unwrap();
+        let position_of_link = Position {
+            line: 2,
+            character: 3,
+        };
+        assert_eq!(position_of_link, position(text, link));
+        assert_eq!(link, offset(text, position_of_link));
+        assert_eq!(text.find('\n').unwrap(), offset(text, Position::new(0, 50)));
+        assert_eq!(text.len(), offset(text, Position::new(10, 0)));
+    }
+
+    #[test]
+    fn completion_context() {
+        assert_eq!(
+            Some(CompletionContext::Note {
+                typed: "pro".to_string()
+            }),
+            CompletionContext::find("See [[pro")
+        );
+        assert_eq!(
+            Some(CompletionContext::Heading {
+                note: "Plan".to_string(),
+                typed: "Go".to_string()
+            }),
+            CompletionContext::find("See [[Plan#Go")
+        );
+        assert_eq!(
+            Some(CompletionContext::Tag {
+                typed: "wo".to_string()
+            }),
+            CompletionContext::find("Some #wo")
+        );
+        assert_eq!(None, CompletionContext::find("#"));
+        assert_eq!(None, CompletionContext::find("[[Plan]] and"));
+        assert_eq!(None, CompletionContext::find("[[Plan|lab"));
+    }
+}